@@ -64,31 +64,40 @@ impl BlockEntity {
     }
 
     pub fn from_nbt(nbt: &NbtCompound) -> Self {
-        let nbt_map = NbtMap::from_quartz_nbt(nbt);
-        let id = nbt_map.get("Id")
+        Self::from_nbt_map(&NbtMap::from_quartz_nbt(nbt))
+    }
+
+    /// Like [`BlockEntity::from_nbt`], keeping the element type of empty lists.
+    pub fn from_nbt_map(nbt: &NbtMap) -> Self {
+        let id = nbt.get("Id")
             .and_then(|v| v.as_string())
             .cloned()
             .unwrap_or_else(|| "unknown".to_string());
-        let position = nbt_map.get("Pos")
+        let position = nbt.get("Pos")
             .and_then(|v| v.as_int_array())
             .map(|v| (v[0], v[1], v[2]))
             .unwrap_or_else(|| (0, 0, 0));
-        BlockEntity { nbt: nbt_map, id, position }
+        BlockEntity { nbt: nbt.clone(), id, position }
     }
 
     pub fn to_nbt(&self) -> NbtCompound {
-        let mut nbt = NbtCompound::new();
+        self.to_nbt_map().to_quartz_nbt()
+    }
+
+    /// Like [`BlockEntity::to_nbt`], keeping the element type of empty lists.
+    pub fn to_nbt_map(&self) -> NbtMap {
+        let mut nbt = NbtMap::new();
         // Store the core BlockEntity fields
-        nbt.insert("Id", NbtValue::String(self.id.clone()).to_quartz_nbt());
+        nbt.insert("Id", NbtValue::String(self.id.clone()));
         nbt.insert("Pos", NbtValue::IntArray(vec![
             self.position.0,
             self.position.1,
             self.position.2
-        ]).to_quartz_nbt());
+        ]));
 
         // Store the rest of the NBT data
         for (key, value) in &self.nbt {
-            nbt.insert(key, value.to_quartz_nbt());
+            nbt.insert(key.clone(), value.clone());
         }
         nbt
     }
@@ -3,7 +3,7 @@ use serde::{Serialize, Deserialize};
 use wasm_bindgen::prelude::*;
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct BlockPosition {
     pub x: i32,
     pub y: i32,
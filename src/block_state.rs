@@ -1,13 +1,15 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fmt;
-use std::hash::{Hash, Hasher};
 use quartz_nbt::{NbtCompound, NbtTag};
 use serde::{Deserialize, Serialize};
+use crate::NucleationError;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// A block name with its properties. Properties are kept sorted by key, so equal states hash
+/// the same and format to the same canonical string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct BlockState {
     pub name: String,
-    pub properties: HashMap<String, String>,
+    pub properties: BTreeMap<String, String>,
 }
 
 impl fmt::Display for BlockState {
@@ -27,21 +29,11 @@ impl fmt::Display for BlockState {
     }
 }
 
-impl Hash for BlockState {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.name.hash(state);
-        for (k, v) in &self.properties {
-            k.hash(state);
-            v.hash(state);
-        }
-    }
-}
-
 impl BlockState {
     pub fn new(name: String) -> Self {
         BlockState {
             name,
-            properties: HashMap::new(),
+            properties: BTreeMap::new(),
         }
     }
 
@@ -54,8 +46,8 @@ impl BlockState {
         self
     }
 
-    pub fn with_properties(mut self, properties: HashMap<String, String>) -> Self {
-        self.properties = properties;
+    pub fn with_properties(mut self, properties: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.properties = properties.into_iter().collect();
         self
     }
 
@@ -85,13 +77,10 @@ impl BlockState {
         NbtTag::Compound(compound)
     }
 
-    pub fn from_nbt(compound: &NbtCompound) -> Result<Self, String> {
-        let name = compound
-            .get::<_, &String>("Name")
-            .map_err(|e| format!("Failed to get Name: {}", e))?
-            .clone();
+    pub fn from_nbt(compound: &NbtCompound) -> Result<Self, NucleationError> {
+        let name = compound.get::<_, &String>("Name")?.clone();
 
-        let mut properties = HashMap::new();
+        let mut properties = BTreeMap::new();
         if let Ok(props) = compound.get::<_, &NbtCompound>("Properties") {
             for (key, value) in props.inner() {
                 if let NbtTag::String(value_str) = value {
@@ -121,4 +110,28 @@ mod tests {
         assert_eq!(block.name, "minecraft:stone");
         assert_eq!(block.properties.get("variant"), Some(&"granite".to_string()));
     }
+
+    #[test]
+    fn test_block_state_canonical_form() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let a = BlockState::new("minecraft:repeater".to_string())
+            .with_property("powered".to_string(), "false".to_string())
+            .with_property("delay".to_string(), "2".to_string())
+            .with_property("facing".to_string(), "north".to_string());
+        let b = BlockState::new("minecraft:repeater".to_string())
+            .with_property("facing".to_string(), "north".to_string())
+            .with_property("delay".to_string(), "2".to_string())
+            .with_property("powered".to_string(), "false".to_string());
+
+        let hash = |state: &BlockState| {
+            let mut hasher = DefaultHasher::new();
+            state.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_eq!(a.to_string(), "minecraft:repeater[delay=2,facing=north,powered=false]");
+    }
 }
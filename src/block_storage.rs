@@ -0,0 +1,533 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Palette indices of a region, stored in the narrowest integer width that fits the largest
+/// index so far. Setting a larger index widens the storage.
+#[derive(Debug, Clone)]
+pub enum BlockStorage {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    /// 16³ sections of a sparse region, see [`SectionedStorage`]
+    Sectioned(SectionedStorage),
+}
+
+impl BlockStorage {
+    /// `len` entries of palette index 0.
+    pub fn new(len: usize) -> Self {
+        BlockStorage::U8(vec![0; len])
+    }
+
+    /// Sparse storage for a region with the given minimum corner and dimensions.
+    pub fn sectioned(min: (i32, i32, i32), size: (i32, i32, i32)) -> Self {
+        BlockStorage::Sectioned(SectionedStorage::new(min, size))
+    }
+
+    pub fn is_sectioned(&self) -> bool {
+        matches!(self, BlockStorage::Sectioned(_))
+    }
+
+    /// `len` entries of the given palette index.
+    pub fn filled(len: usize, value: usize) -> Self {
+        let mut storage = BlockStorage::new(0);
+        storage.widen_for(value);
+        match &mut storage {
+            BlockStorage::U8(values) => values.resize(len, value as u8),
+            BlockStorage::U16(values) => values.resize(len, value as u16),
+            BlockStorage::U32(values) => values.resize(len, value as u32),
+            BlockStorage::Sectioned(_) => unreachable!(),
+        }
+        storage
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            BlockStorage::U8(values) => values.len(),
+            BlockStorage::U16(values) => values.len(),
+            BlockStorage::U32(values) => values.len(),
+            BlockStorage::Sectioned(storage) => storage.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes used per entry, of the widest section for sectioned storage.
+    pub fn width(&self) -> usize {
+        match self {
+            BlockStorage::U8(_) => 1,
+            BlockStorage::U16(_) => 2,
+            BlockStorage::U32(_) => 4,
+            BlockStorage::Sectioned(storage) => storage.sections.values()
+                .map(|section| section.blocks.width())
+                .max()
+                .unwrap_or(1),
+        }
+    }
+
+    /// Panics if `index` is out of bounds, like slice indexing.
+    #[inline]
+    pub fn get(&self, index: usize) -> usize {
+        match self {
+            BlockStorage::U8(values) => values[index] as usize,
+            BlockStorage::U16(values) => values[index] as usize,
+            BlockStorage::U32(values) => values[index] as usize,
+            BlockStorage::Sectioned(storage) => storage.get(index),
+        }
+    }
+
+    /// Panics if `index` is out of bounds, like slice indexing.
+    #[inline]
+    pub fn set(&mut self, index: usize, value: usize) {
+        self.widen_for(value);
+        match self {
+            BlockStorage::U8(values) => values[index] = value as u8,
+            BlockStorage::U16(values) => values[index] = value as u16,
+            BlockStorage::U32(values) => values[index] = value as u32,
+            BlockStorage::Sectioned(storage) => storage.set(index, value),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { storage: self, index: 0, section: None }
+    }
+
+    /// `(index, value)` of the entries that are not 0, without visiting empty sections.
+    pub fn iter_non_zero(&self) -> Box<dyn Iterator<Item = (usize, usize)> + '_> {
+        match self {
+            BlockStorage::Sectioned(storage) => Box::new(storage.iter_non_zero()),
+            _ => Box::new(self.iter().enumerate().filter(|&(_, value)| value != 0)),
+        }
+    }
+
+    pub fn fill(&mut self, value: usize) {
+        match self {
+            BlockStorage::Sectioned(storage) if value == 0 => storage.sections.clear(),
+            BlockStorage::Sectioned(_) => (0..self.len()).for_each(|index| self.set(index, value)),
+            _ => *self = BlockStorage::filled(self.len(), value),
+        }
+    }
+
+    /// Replaces every entry with `f(entry)`.
+    pub fn map_in_place(&mut self, mut f: impl FnMut(usize) -> usize) {
+        // Empty sections stay empty as long as 0 maps to 0, so only section palettes change
+        if let BlockStorage::Sectioned(storage) = self {
+            if f(0) == 0 {
+                storage.map_palettes(f);
+                return;
+            }
+        }
+        for index in 0..self.len() {
+            let value = f(self.get(index));
+            self.set(index, value);
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<usize> {
+        self.iter().collect()
+    }
+
+    fn push(&mut self, value: usize) {
+        self.widen_for(value);
+        match self {
+            BlockStorage::U8(values) => values.push(value as u8),
+            BlockStorage::U16(values) => values.push(value as u16),
+            BlockStorage::U32(values) => values.push(value as u32),
+            BlockStorage::Sectioned(_) => unreachable!("Sectioned storage has a fixed size"),
+        }
+    }
+
+    fn widen_for(&mut self, value: usize) {
+        let fits = match self {
+            BlockStorage::U8(_) => value <= u8::MAX as usize,
+            BlockStorage::U16(_) => value <= u16::MAX as usize,
+            BlockStorage::U32(_) | BlockStorage::Sectioned(_) => true,
+        };
+        if fits {
+            return;
+        }
+        *self = if value <= u16::MAX as usize {
+            BlockStorage::U16(self.iter().map(|v| v as u16).collect())
+        } else {
+            assert!(value <= u32::MAX as usize, "Palette index {} does not fit in block storage", value);
+            BlockStorage::U32(self.iter().map(|v| v as u32).collect())
+        };
+    }
+}
+
+impl PartialEq for BlockStorage {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for BlockStorage {}
+
+impl From<Vec<usize>> for BlockStorage {
+    fn from(values: Vec<usize>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl FromIterator<usize> for BlockStorage {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut storage = BlockStorage::U8(Vec::with_capacity(iter.size_hint().0));
+        for value in iter {
+            storage.push(value);
+        }
+        storage
+    }
+}
+
+impl<'a> IntoIterator for &'a BlockStorage {
+    type Item = usize;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+type SectionKey = (i32, i32, i32);
+
+pub struct Iter<'a> {
+    storage: &'a BlockStorage,
+    index: usize,
+    // Section of the previous entry, x runs along sections for 16 entries at a time
+    section: Option<(SectionKey, Option<&'a Section>)>,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        if self.index >= self.storage.len() {
+            return None;
+        }
+        let value = match self.storage {
+            BlockStorage::Sectioned(storage) => {
+                let (key, offset) = storage.locate(self.index);
+                let section = match self.section {
+                    Some((cached_key, section)) if cached_key == key => section,
+                    _ => {
+                        let section = storage.sections.get(&key);
+                        self.section = Some((key, section));
+                        section
+                    }
+                };
+                section.map_or(0, |section| section.get(offset))
+            }
+            storage => storage.get(self.index),
+        };
+        self.index += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.storage.len() - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// Storage for sparse regions: 16³ sections aligned to the world grid, each with its own palette
+/// of region palette indices. Sections without any non-zero entry are not stored, and since
+/// sections do not depend on the region bounds, growing the region moves no data.
+#[derive(Debug, Clone)]
+pub struct SectionedStorage {
+    min: (i32, i32, i32),
+    size: (i32, i32, i32),
+    sections: HashMap<SectionKey, Section>,
+}
+
+#[derive(Debug, Clone)]
+struct Section {
+    /// Region palette indices, local index 0 is always region index 0
+    palette: Vec<usize>,
+    blocks: BlockStorage,
+    non_zero: usize,
+}
+
+const SECTION_VOLUME: usize = 16 * 16 * 16;
+
+impl Section {
+    fn new() -> Self {
+        Section { palette: vec![0], blocks: BlockStorage::new(SECTION_VOLUME), non_zero: 0 }
+    }
+
+    #[inline]
+    fn get(&self, offset: usize) -> usize {
+        self.palette[self.blocks.get(offset)]
+    }
+
+    fn set(&mut self, offset: usize, value: usize) {
+        let local = match self.palette.iter().position(|&entry| entry == value) {
+            Some(local) => local,
+            None => {
+                self.palette.push(value);
+                self.palette.len() - 1
+            }
+        };
+        let previous = self.blocks.get(offset);
+        match (previous != 0, local != 0) {
+            (false, true) => self.non_zero += 1,
+            (true, false) => self.non_zero -= 1,
+            _ => {}
+        }
+        self.blocks.set(offset, local);
+    }
+}
+
+impl SectionedStorage {
+    fn new(min: (i32, i32, i32), size: (i32, i32, i32)) -> Self {
+        SectionedStorage { min, size, sections: HashMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size.0 as usize * self.size.1 as usize * self.size.2 as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of sections holding blocks.
+    pub fn section_count(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// Changes the bounds the linear indices refer to. Entries outside the new bounds are
+    /// dropped with the sections that lie completely outside of them.
+    pub fn reshape(&mut self, min: (i32, i32, i32), size: (i32, i32, i32)) {
+        self.min = min;
+        self.size = size;
+        let max = (min.0 + size.0 - 1, min.1 + size.1 - 1, min.2 + size.2 - 1);
+        self.sections.retain(|&(sx, sy, sz), _| {
+            sx >= min.0 >> 4 && sx <= max.0 >> 4
+                && sy >= min.1 >> 4 && sy <= max.1 >> 4
+                && sz >= min.2 >> 4 && sz <= max.2 >> 4
+        });
+    }
+
+    /// Moves the bounds and their entries by `offset`. Moves by whole sections only re-key them.
+    pub fn translate(&mut self, offset: (i32, i32, i32)) {
+        let min = (self.min.0 + offset.0, self.min.1 + offset.1, self.min.2 + offset.2);
+        if offset.0 % 16 == 0 && offset.1 % 16 == 0 && offset.2 % 16 == 0 {
+            let shift = (offset.0 >> 4, offset.1 >> 4, offset.2 >> 4);
+            self.sections = std::mem::take(&mut self.sections)
+                .into_iter()
+                .map(|((x, y, z), section)| ((x + shift.0, y + shift.1, z + shift.2), section))
+                .collect();
+            self.min = min;
+            return;
+        }
+        let mut moved = SectionedStorage::new(min, self.size);
+        for (index, value) in self.iter_non_zero() {
+            moved.set(index, value);
+        }
+        *self = moved;
+    }
+
+    // Same layout as `BoundingBox::index_to_coords`
+    #[inline]
+    fn coords(&self, index: usize) -> (i32, i32, i32) {
+        let (width, length) = (self.size.0 as usize, self.size.2 as usize);
+        let x = (index % width) as i32 + self.min.0;
+        let z = ((index / width) % length) as i32 + self.min.2;
+        let y = (index / (width * length)) as i32 + self.min.1;
+        (x, y, z)
+    }
+
+    #[inline]
+    fn locate(&self, index: usize) -> (SectionKey, usize) {
+        let (x, y, z) = self.coords(index);
+        let offset = (x & 15) as usize | ((z & 15) as usize) << 4 | ((y & 15) as usize) << 8;
+        ((x >> 4, y >> 4, z >> 4), offset)
+    }
+
+    fn get(&self, index: usize) -> usize {
+        assert!(index < self.len(), "Index {} out of bounds for {} entries", index, self.len());
+        let (key, offset) = self.locate(index);
+        self.sections.get(&key).map_or(0, |section| section.get(offset))
+    }
+
+    fn set(&mut self, index: usize, value: usize) {
+        assert!(index < self.len(), "Index {} out of bounds for {} entries", index, self.len());
+        let (key, offset) = self.locate(index);
+        if value == 0 {
+            if let Some(section) = self.sections.get_mut(&key) {
+                section.set(offset, 0);
+                if section.non_zero == 0 {
+                    self.sections.remove(&key);
+                }
+            }
+            return;
+        }
+        self.sections.entry(key).or_insert_with(Section::new).set(offset, value);
+    }
+
+    fn map_palettes(&mut self, mut f: impl FnMut(usize) -> usize) {
+        for section in self.sections.values_mut() {
+            for entry in section.palette.iter_mut() {
+                *entry = f(*entry);
+            }
+            // Entries mapped to 0 no longer count, rebuild the section to keep local 0 unique
+            if section.palette[1..].contains(&0) {
+                let mut rebuilt = Section::new();
+                for offset in 0..SECTION_VOLUME {
+                    rebuilt.set(offset, section.get(offset));
+                }
+                *section = rebuilt;
+            }
+        }
+        self.sections.retain(|_, section| section.non_zero > 0);
+    }
+
+    fn iter_non_zero(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (width, length) = (self.size.0 as i64, self.size.2 as i64);
+        let max = (self.min.0 + self.size.0, self.min.1 + self.size.1, self.min.2 + self.size.2);
+        self.sections.iter().flat_map(move |(&(sx, sy, sz), section)| {
+            section.blocks.iter().enumerate()
+                .filter(|&(_, local)| local != 0)
+                .filter_map(move |(offset, local)| {
+                    let x = (sx << 4) + (offset & 15) as i32;
+                    let z = (sz << 4) + ((offset >> 4) & 15) as i32;
+                    let y = (sy << 4) + (offset >> 8) as i32;
+                    if x < self.min.0 || y < self.min.1 || z < self.min.2 || x >= max.0 || y >= max.1 || z >= max.2 {
+                        return None;
+                    }
+                    let (dx, dy, dz) = ((x - self.min.0) as i64, (y - self.min.1) as i64, (z - self.min.2) as i64);
+                    Some(((dx + dz * width + dy * width * length) as usize, section.palette[local]))
+                })
+        })
+    }
+}
+
+// Serialized as a plain list of indices, as when regions stored a `Vec<usize>`. Sectioned
+// storage keeps its sections instead, so sparse regions stay small
+impl Serialize for BlockStorage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let BlockStorage::Sectioned(storage) = self else {
+            return serializer.collect_seq(self.iter());
+        };
+        let mut keys: Vec<&SectionKey> = storage.sections.keys().collect();
+        keys.sort();
+        let sections = keys.into_iter()
+            .map(|key| {
+                let section = &storage.sections[key];
+                SerializedSection { key: *key, palette: section.palette.clone(), blocks: section.blocks.clone() }
+            })
+            .collect();
+        SerializedStorage::Sectioned { min: storage.min, size: storage.size, sections }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockStorage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (min, size, serialized) = match SerializedStorage::deserialize(deserializer)? {
+            SerializedStorage::Dense(values) => return Ok(values.into()),
+            SerializedStorage::Sectioned { min, size, sections } => (min, size, sections),
+        };
+        let mut storage = SectionedStorage::new(min, size);
+        for SerializedSection { key, palette, blocks } in serialized {
+            if palette.first() != Some(&0) || blocks.len() != SECTION_VOLUME || blocks.iter().any(|local| local >= palette.len()) {
+                return Err(serde::de::Error::custom(format!("Invalid section at {:?}", key)));
+            }
+            let non_zero = blocks.iter().filter(|&local| local != 0).count();
+            if non_zero > 0 {
+                storage.sections.insert(key, Section { palette, blocks, non_zero });
+            }
+        }
+        Ok(BlockStorage::Sectioned(storage))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SerializedStorage {
+    Dense(Vec<usize>),
+    Sectioned { min: (i32, i32, i32), size: (i32, i32, i32), sections: Vec<SerializedSection> },
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedSection {
+    key: SectionKey,
+    palette: Vec<usize>,
+    blocks: BlockStorage,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_widens_as_palette_grows() {
+        let mut storage = BlockStorage::new(4);
+        assert_eq!(storage.width(), 1);
+
+        storage.set(1, 200);
+        assert_eq!(storage.width(), 1);
+        storage.set(2, 300);
+        assert_eq!(storage.width(), 2);
+        storage.set(3, 70_000);
+        assert_eq!(storage.width(), 4);
+
+        assert_eq!(storage.to_vec(), vec![0, 200, 300, 70_000]);
+        assert_eq!(storage, BlockStorage::from(vec![0, 200, 300, 70_000]));
+    }
+
+    #[test]
+    fn test_serializes_as_index_list() {
+        let storage = BlockStorage::from(vec![0, 1, 2, 1]);
+        let json = serde_json::to_string(&storage).unwrap();
+        assert_eq!(json, "[0,1,2,1]");
+        assert_eq!(serde_json::from_str::<BlockStorage>(&json).unwrap(), storage);
+    }
+
+    #[test]
+    fn test_sectioned_storage_only_keeps_used_sections() {
+        let mut storage = BlockStorage::sectioned((-8, 0, -8), (64, 16, 64));
+        assert_eq!(storage.len(), 64 * 16 * 64);
+
+        storage.set(0, 3);
+        storage.set(storage.len() - 1, 5);
+        let BlockStorage::Sectioned(sections) = &storage else { unreachable!() };
+        assert_eq!(sections.section_count(), 2);
+        assert_eq!(storage.get(0), 3);
+        assert_eq!(storage.get(1), 0);
+        assert_eq!(storage.iter_non_zero().collect::<Vec<_>>().len(), 2);
+        assert_eq!(storage.iter().filter(|&value| value != 0).count(), 2);
+
+        storage.set(0, 0);
+        storage.map_in_place(|value| if value == 5 { 0 } else { value });
+        let BlockStorage::Sectioned(sections) = &storage else { unreachable!() };
+        assert_eq!(sections.section_count(), 0);
+    }
+
+    #[test]
+    fn test_sectioned_storage_serializes_its_sections() {
+        let size = (1 << 12, 256, 1 << 12);
+        let mut storage = BlockStorage::sectioned((-2048, -64, -2048), size);
+        storage.set(0, 3);
+        storage.set(storage.len() - 1, 5);
+        storage.set(1 << 20, 3);
+
+        let json = serde_json::to_string(&storage).unwrap();
+        assert!(json.len() < 100_000);
+        let read = serde_json::from_str::<BlockStorage>(&json).unwrap();
+        let BlockStorage::Sectioned(sections) = &read else { panic!("Not sectioned") };
+        assert_eq!(sections.section_count(), 3);
+        assert_eq!(read.len(), storage.len());
+        let blocks = |storage: &BlockStorage| {
+            let mut blocks: Vec<(usize, usize)> = storage.iter_non_zero().collect();
+            blocks.sort();
+            blocks
+        };
+        assert_eq!(blocks(&read), vec![(0, 3), (1 << 20, 3), (storage.len() - 1, 5)]);
+        assert_eq!(blocks(&read), blocks(&storage));
+    }
+}
@@ -18,6 +18,14 @@ impl BoundingBox {
         BoundingBox { min, max }
     }
 
+    /// The box between two opposite corners given in any order.
+    pub fn from_corners(a: (i32, i32, i32), b: (i32, i32, i32)) -> Self {
+        BoundingBox {
+            min: (a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)),
+            max: (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)),
+        }
+    }
+
     pub fn contains(&self, point: (i32, i32, i32)) -> bool {
         point.0 >= self.min.0 && point.0 <= self.max.0 &&
             point.1 >= self.min.1 && point.1 <= self.max.1 &&
@@ -51,6 +59,24 @@ impl BoundingBox {
         }
     }
 
+    pub fn intersection(&self, other: &BoundingBox) -> Option<BoundingBox> {
+        if !self.intersects(other) {
+            return None;
+        }
+        Some(BoundingBox {
+            min: (
+                self.min.0.max(other.min.0),
+                self.min.1.max(other.min.1),
+                self.min.2.max(other.min.2),
+            ),
+            max: (
+                self.max.0.min(other.max.0),
+                self.max.1.min(other.max.1),
+                self.max.2.min(other.max.2),
+            ),
+        })
+    }
+
     pub fn coords_to_index(&self, x: i32, y: i32, z: i32) -> usize {
         let (width, _, length) = self.get_dimensions();
         let dx = x - self.min.0;
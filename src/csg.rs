@@ -0,0 +1,120 @@
+//! Boolean operations between regions and schematics, see [`Region::combine`](crate::Region::combine).
+
+use crate::{BlockState, NucleationError};
+
+/// How two sets of blocks are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BooleanOperation {
+    /// Blocks of either side.
+    Union,
+    /// Blocks where both sides have one.
+    Intersection,
+    /// Blocks of the first side where the second has none.
+    Difference,
+    /// Blocks where exactly one side has one.
+    Xor,
+}
+
+/// Which block stays where both sides have one, for unions and intersections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ConflictPolicy {
+    /// The first side's block.
+    Keep,
+    /// The second side's block.
+    #[default]
+    Replace,
+    /// Fail when the two blocks differ.
+    Fail,
+}
+
+impl ConflictPolicy {
+    /// `keep`, `replace` or `fail`.
+    pub fn parse(name: &str) -> Result<Self, NucleationError> {
+        match name {
+            "keep" => Ok(ConflictPolicy::Keep),
+            "replace" => Ok(ConflictPolicy::Replace),
+            "fail" => Ok(ConflictPolicy::Fail),
+            _ => Err(format!("Unknown conflict policy: {}", name).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Side {
+    Mine,
+    Theirs,
+}
+
+impl BooleanOperation {
+    /// `union`, `intersection`, `difference` or `xor`.
+    pub fn parse(name: &str) -> Result<Self, NucleationError> {
+        match name {
+            "union" => Ok(BooleanOperation::Union),
+            "intersection" => Ok(BooleanOperation::Intersection),
+            "difference" => Ok(BooleanOperation::Difference),
+            "xor" => Ok(BooleanOperation::Xor),
+            _ => Err(format!("Unknown boolean operation: {}", name).into()),
+        }
+    }
+
+    /// Side whose block ends up at `position`, `None` for air.
+    pub(crate) fn pick(
+        self,
+        policy: ConflictPolicy,
+        position: (i32, i32, i32),
+        mine: Option<&BlockState>,
+        theirs: Option<&BlockState>,
+    ) -> Result<Option<Side>, NucleationError> {
+        use BooleanOperation::*;
+        Ok(match (mine, theirs) {
+            (None, None) => None,
+            (Some(_), None) => (self != Intersection).then_some(Side::Mine),
+            (None, Some(_)) => matches!(self, Union | Xor).then_some(Side::Theirs),
+            (Some(_), Some(_)) if matches!(self, Difference | Xor) => None,
+            (Some(mine), Some(theirs)) => match policy {
+                ConflictPolicy::Keep => Some(Side::Mine),
+                ConflictPolicy::Replace => Some(Side::Theirs),
+                ConflictPolicy::Fail if mine == theirs => Some(Side::Mine),
+                ConflictPolicy::Fail => {
+                    return Err(format!(
+                        "Conflicting blocks at {:?}: {} and {}",
+                        position, mine, theirs
+                    )
+                    .into())
+                }
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick() {
+        let stone = BlockState::new("minecraft:stone".to_string());
+        let dirt = BlockState::new("minecraft:dirt".to_string());
+        let pick = |operation: BooleanOperation, policy, mine, theirs| {
+            operation.pick(policy, (0, 0, 0), mine, theirs).unwrap()
+        };
+        use BooleanOperation::*;
+        use ConflictPolicy::*;
+
+        assert_eq!(pick(Union, Keep, Some(&stone), Some(&dirt)), Some(Side::Mine));
+        assert_eq!(pick(Union, Replace, Some(&stone), Some(&dirt)), Some(Side::Theirs));
+        assert_eq!(pick(Union, Keep, None, Some(&dirt)), Some(Side::Theirs));
+        assert_eq!(pick(Intersection, Keep, Some(&stone), None), None);
+        assert_eq!(pick(Intersection, Replace, Some(&stone), Some(&dirt)), Some(Side::Theirs));
+        assert_eq!(pick(Difference, Keep, Some(&stone), Some(&dirt)), None);
+        assert_eq!(pick(Difference, Keep, None, Some(&dirt)), None);
+        assert_eq!(pick(Xor, Keep, None, Some(&dirt)), Some(Side::Theirs));
+        assert_eq!(pick(Xor, Keep, Some(&stone), Some(&dirt)), None);
+        assert_eq!(pick(Union, Fail, Some(&stone), Some(&stone)), Some(Side::Mine));
+        assert!(Union.pick(Fail, (1, 2, 3), Some(&stone), Some(&dirt)).is_err());
+
+        assert_eq!(BooleanOperation::parse("xor").unwrap(), Xor);
+        assert_eq!(ConflictPolicy::parse("keep").unwrap(), Keep);
+        assert!(ConflictPolicy::parse("overwrite").is_err());
+    }
+}
@@ -0,0 +1,468 @@
+//! Structural diffs between schematics that can be stored and applied as patches.
+
+use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use crate::block_entity::BlockEntity;
+use crate::block_position::BlockPosition;
+use crate::entity::Entity;
+use crate::formats::{read_gzip_nbt, write_gzip_nbt};
+use crate::region::Region;
+use crate::utils::{NbtMap, NbtValue};
+use crate::{BlockState, NucleationError, UniversalSchematic};
+
+const PATCH_VERSION: i32 = 1;
+
+/// A block that differs, air where a schematic has none.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockChange {
+    pub position: BlockPosition,
+    pub old: BlockState,
+    pub new: BlockState,
+}
+
+/// A block entity that was added (`old` is `None`), removed (`new` is `None`) or whose NBT changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockEntityChange {
+    pub position: BlockPosition,
+    pub old: Option<BlockEntity>,
+    pub new: Option<BlockEntity>,
+}
+
+/// The changes turning one schematic into another. Stored as JSON with [`SchematicDiff::to_json`]
+/// or as gzipped NBT with [`SchematicDiff::to_bytes`], and applied with [`SchematicDiff::apply`].
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SchematicDiff {
+    /// Block state properties left out of the comparison.
+    pub ignored_properties: Vec<String>,
+    pub blocks: Vec<BlockChange>,
+    pub block_entities: Vec<BlockEntityChange>,
+    pub removed_entities: Vec<Entity>,
+    pub added_entities: Vec<Entity>,
+}
+
+/// The changes from `a` to `b`.
+pub fn diff(a: &UniversalSchematic, b: &UniversalSchematic) -> SchematicDiff {
+    diff_with(a, b, &[])
+}
+
+/// Like [`diff`], blocks that only differ in `ignored_properties` count as unchanged.
+pub fn diff_with(a: &UniversalSchematic, b: &UniversalSchematic, ignored_properties: &[&str]) -> SchematicDiff {
+    let ignored_properties: Vec<String> = ignored_properties.iter().map(|property| property.to_string()).collect();
+
+    let mut positions: Vec<BlockPosition> = non_air_positions(a)
+        .chain(non_air_positions(b))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    positions.sort_unstable_by_key(|position| (position.y, position.z, position.x));
+    let blocks = positions
+        .into_iter()
+        .filter_map(|position| {
+            let old = block_at(a, position);
+            let new = block_at(b, position);
+            (!same_block(&old, &new, &ignored_properties)).then_some(BlockChange { position, old, new })
+        })
+        .collect();
+
+    let mut positions: Vec<BlockPosition> = a
+        .get_block_entities_as_list()
+        .into_iter()
+        .chain(b.get_block_entities_as_list())
+        .map(|block_entity| BlockPosition::from_tuple(block_entity.position))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    positions.sort_unstable_by_key(|position| (position.y, position.z, position.x));
+    let block_entities = positions
+        .into_iter()
+        .filter_map(|position| {
+            let old = a.get_block_entity(position);
+            let new = b.get_block_entity(position);
+            (old != new).then(|| BlockEntityChange { position, old: old.cloned(), new: new.cloned() })
+        })
+        .collect();
+
+    let mut added_entities = b.get_entities_as_list();
+    let mut removed_entities = Vec::new();
+    for entity in a.get_entities_as_list() {
+        match added_entities.iter().position(|added| *added == entity) {
+            Some(index) => {
+                added_entities.remove(index);
+            }
+            None => removed_entities.push(entity),
+        }
+    }
+
+    SchematicDiff { ignored_properties, blocks, block_entities, removed_entities, added_entities }
+}
+
+impl SchematicDiff {
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+            && self.block_entities.is_empty()
+            && self.removed_entities.is_empty()
+            && self.added_entities.is_empty()
+    }
+
+    /// Applies the changes to `schematic`, which has to hold what the diff was made from (up to
+    /// the ignored properties). Changed blocks keep their own values of the ignored properties.
+    /// Nothing changes when it does not.
+    pub fn apply(&self, schematic: &mut UniversalSchematic) -> Result<(), NucleationError> {
+        self.check(schematic)?;
+
+        for change in &self.blocks {
+            let BlockPosition { x, y, z } = change.position;
+            let mut block = change.new.clone();
+            let current = block_at(schematic, change.position);
+            for key in &self.ignored_properties {
+                if let (Some(value), Some(current)) = (block.properties.get_mut(key), current.properties.get(key)) {
+                    value.clone_from(current);
+                }
+            }
+            match region_at(schematic, change.position) {
+                Some(region) => region.set_block(x, y, z, block),
+                None => schematic.set_block(x, y, z, block),
+            };
+        }
+        for change in &self.block_entities {
+            match (region_at(schematic, change.position), &change.new) {
+                (Some(region), Some(block_entity)) => region.add_block_entity(block_entity.clone()),
+                (Some(region), None) => {
+                    region.remove_block_entity(change.position.to_tuple());
+                }
+                (None, Some(block_entity)) => {
+                    schematic.add_block_entity(block_entity.clone());
+                }
+                (None, None) => {}
+            }
+        }
+        for entity in &self.removed_entities {
+            for region in regions_mut(schematic) {
+                if let Some(index) = region.entities.iter().position(|existing| existing == entity) {
+                    region.entities.remove(index);
+                    break;
+                }
+            }
+        }
+        for entity in &self.added_entities {
+            schematic.add_entity(entity.clone());
+        }
+        Ok(())
+    }
+
+    fn check(&self, schematic: &UniversalSchematic) -> Result<(), NucleationError> {
+        for change in &self.blocks {
+            let found = block_at(schematic, change.position);
+            if !same_block(&found, &change.old, &self.ignored_properties) {
+                return Err(format!(
+                    "Patch does not apply at {:?}: expected {} but found {}",
+                    change.position.to_tuple(), change.old, found
+                )
+                .into());
+            }
+        }
+        for change in &self.block_entities {
+            if schematic.get_block_entity(change.position) != change.old.as_ref() {
+                return Err(format!(
+                    "Patch does not apply at {:?}: the block entity differs",
+                    change.position.to_tuple()
+                )
+                .into());
+            }
+        }
+        let mut entities = schematic.get_entities_as_list();
+        for entity in &self.removed_entities {
+            match entities.iter().position(|existing| existing == entity) {
+                Some(index) => {
+                    entities.remove(index);
+                }
+                None => {
+                    return Err(format!(
+                        "Patch does not apply: missing entity {} at {:?}",
+                        entity.id, entity.position
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Result<String, NucleationError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, NucleationError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Gzipped NBT.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, NucleationError> {
+        write_gzip_nbt(&self.to_nbt(), "")
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, NucleationError> {
+        Self::from_nbt(&read_gzip_nbt(data)?)
+    }
+
+    fn to_nbt(&self) -> NbtMap {
+        let mut root = NbtMap::new();
+        root.insert("Version", NbtValue::Int(PATCH_VERSION));
+        let ignored_properties = self.ignored_properties.iter().cloned().map(NbtValue::String).collect();
+        root.insert("IgnoredProperties", NbtValue::List(ignored_properties));
+
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|change| {
+                let mut tag = NbtMap::new();
+                tag.insert("Pos", position_to_nbt(change.position));
+                tag.insert("Old", NbtValue::from_quartz_nbt(&change.old.to_nbt()));
+                tag.insert("New", NbtValue::from_quartz_nbt(&change.new.to_nbt()));
+                NbtValue::Compound(tag)
+            })
+            .collect();
+        root.insert("Blocks", NbtValue::List(blocks));
+
+        let block_entities = self
+            .block_entities
+            .iter()
+            .map(|change| {
+                let mut tag = NbtMap::new();
+                tag.insert("Pos", position_to_nbt(change.position));
+                if let Some(old) = &change.old {
+                    tag.insert("Old", block_entity_to_nbt(old));
+                }
+                if let Some(new) = &change.new {
+                    tag.insert("New", block_entity_to_nbt(new));
+                }
+                NbtValue::Compound(tag)
+            })
+            .collect();
+        root.insert("BlockEntities", NbtValue::List(block_entities));
+
+        root.insert("RemovedEntities", NbtValue::List(self.removed_entities.iter().map(entity_to_nbt).collect()));
+        root.insert("AddedEntities", NbtValue::List(self.added_entities.iter().map(entity_to_nbt).collect()));
+        root
+    }
+
+    fn from_nbt(root: &NbtMap) -> Result<Self, NucleationError> {
+        let version = root.get_as::<i32>("Version")?;
+        if version != PATCH_VERSION {
+            return Err(NucleationError::UnsupportedVersion { format: "patch", version });
+        }
+
+        let mut ignored_properties = Vec::new();
+        for tag in root.get_as::<&[NbtValue]>("IgnoredProperties")? {
+            match tag {
+                NbtValue::String(property) => ignored_properties.push(property.clone()),
+                _ => return Err("Ignored properties must be strings".into()),
+            }
+        }
+
+        let mut blocks = Vec::new();
+        for tag in compounds(root, "Blocks")? {
+            blocks.push(BlockChange {
+                position: position_from_nbt(tag)?,
+                old: BlockState::from_nbt(&tag.get_as::<&NbtMap>("Old")?.to_quartz_nbt())?,
+                new: BlockState::from_nbt(&tag.get_as::<&NbtMap>("New")?.to_quartz_nbt())?,
+            });
+        }
+
+        let mut block_entities = Vec::new();
+        for tag in compounds(root, "BlockEntities")? {
+            let block_entity = |key: &str| match tag.get_as::<&NbtMap>(key) {
+                Ok(block_entity) => block_entity_from_nbt(block_entity).map(Some),
+                Err(_) => Ok(None),
+            };
+            block_entities.push(BlockEntityChange {
+                position: position_from_nbt(tag)?,
+                old: block_entity("Old")?,
+                new: block_entity("New")?,
+            });
+        }
+
+        let removed_entities = compounds(root, "RemovedEntities")?.into_iter().map(entity_from_nbt).collect::<Result<_, _>>()?;
+        let added_entities = compounds(root, "AddedEntities")?.into_iter().map(entity_from_nbt).collect::<Result<_, _>>()?;
+
+        Ok(SchematicDiff { ignored_properties, blocks, block_entities, removed_entities, added_entities })
+    }
+}
+
+fn non_air_positions(schematic: &UniversalSchematic) -> impl Iterator<Item = BlockPosition> + '_ {
+    std::iter::once(&schematic.default_region)
+        .chain(schematic.other_regions.values())
+        .flat_map(|region| {
+            region.non_air_blocks().map(move |(index, _)| BlockPosition::from_tuple(region.index_to_coords(index)))
+        })
+}
+
+fn block_at(schematic: &UniversalSchematic, position: BlockPosition) -> BlockState {
+    schematic
+        .get_block(position.x, position.y, position.z)
+        .cloned()
+        .unwrap_or_else(|| BlockState::new("minecraft:air".to_string()))
+}
+
+fn same_block(a: &BlockState, b: &BlockState, ignored_properties: &[String]) -> bool {
+    let compared = |block: &BlockState| {
+        block
+            .properties
+            .iter()
+            .filter(|(key, _)| !ignored_properties.contains(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<_>>()
+    };
+    a.name == b.name && compared(a) == compared(b)
+}
+
+fn regions_mut(schematic: &mut UniversalSchematic) -> impl Iterator<Item = &mut Region> {
+    std::iter::once(&mut schematic.default_region).chain(schematic.other_regions.values_mut())
+}
+
+/// The region `UniversalSchematic::get_block` reads `position` from.
+fn region_at(schematic: &mut UniversalSchematic, position: BlockPosition) -> Option<&mut Region> {
+    regions_mut(schematic).find(|region| region.get_bounding_box().contains(position.to_tuple()))
+}
+
+fn compounds<'a>(root: &'a NbtMap, key: &str) -> Result<Vec<&'a NbtMap>, NucleationError> {
+    root.get_as::<&[NbtValue]>(key)?
+        .iter()
+        .map(|tag| match tag {
+            NbtValue::Compound(compound) => Ok(compound),
+            _ => Err(format!("{} must hold compounds", key).into()),
+        })
+        .collect()
+}
+
+fn position_to_nbt(position: BlockPosition) -> NbtValue {
+    NbtValue::IntArray(vec![position.x, position.y, position.z])
+}
+
+fn position_from_nbt(tag: &NbtMap) -> Result<BlockPosition, NucleationError> {
+    match tag.get_as::<&[i32]>("Pos")? {
+        &[x, y, z] => Ok(BlockPosition { x, y, z }),
+        _ => Err("Invalid position data".into()),
+    }
+}
+
+// `BlockEntity::to_nbt` and `Entity::to_nbt` write the game's layout, which does not read back
+// into the same value. Patches compare them exactly, so they keep the fields apart.
+fn block_entity_to_nbt(block_entity: &BlockEntity) -> NbtValue {
+    let mut tag = NbtMap::new();
+    tag.insert("Id", NbtValue::String(block_entity.id.clone()));
+    tag.insert("Pos", NbtValue::IntArray(vec![block_entity.position.0, block_entity.position.1, block_entity.position.2]));
+    tag.insert("NBT", NbtValue::Compound(block_entity.nbt.clone()));
+    NbtValue::Compound(tag)
+}
+
+fn block_entity_from_nbt(tag: &NbtMap) -> Result<BlockEntity, NucleationError> {
+    Ok(BlockEntity {
+        id: tag.get_as::<&str>("Id")?.to_string(),
+        position: position_from_nbt(tag)?.to_tuple(),
+        nbt: tag.get_as::<&NbtMap>("NBT")?.clone(),
+    })
+}
+
+fn entity_to_nbt(entity: &Entity) -> NbtValue {
+    let mut tag = NbtMap::new();
+    tag.insert("Id", NbtValue::String(entity.id.clone()));
+    let (x, y, z) = entity.position;
+    tag.insert("Pos", NbtValue::List(vec![NbtValue::Double(x), NbtValue::Double(y), NbtValue::Double(z)]));
+    tag.insert("NBT", NbtValue::Compound(entity.nbt.clone()));
+    NbtValue::Compound(tag)
+}
+
+fn entity_from_nbt(tag: &NbtMap) -> Result<Entity, NucleationError> {
+    let position = match tag.get_as::<&[NbtValue]>("Pos")? {
+        [NbtValue::Double(x), NbtValue::Double(y), NbtValue::Double(z)] => (*x, *y, *z),
+        _ => return Err("Invalid position data".into()),
+    };
+    Ok(Entity {
+        id: tag.get_as::<&str>("Id")?.to_string(),
+        position,
+        nbt: tag.get_as::<&NbtMap>("NBT")?.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn before() -> UniversalSchematic {
+        let mut schematic = UniversalSchematic::new("Before".to_string());
+        schematic.set_block_str(0, 0, 0, "minecraft:stone");
+        schematic.set_block_from_string(1, 0, 0, "minecraft:oak_stairs[facing=east,waterlogged=false]").unwrap();
+        schematic.set_block_from_string(2, 0, 0, "minecraft:chest[facing=north]").unwrap();
+        schematic.set_block_entity(
+            BlockPosition { x: 2, y: 0, z: 0 },
+            BlockEntity::new("minecraft:chest".to_string(), (2, 0, 0)),
+        );
+        schematic.add_entity(Entity::new("minecraft:pig".to_string(), (0.5, 1.0, 0.5)));
+        schematic
+    }
+
+    fn after() -> UniversalSchematic {
+        let mut schematic = before();
+        schematic.set_block_str(0, 0, 0, "minecraft:dirt");
+        schematic.set_block_from_string(1, 0, 0, "minecraft:oak_stairs[facing=east,waterlogged=true]").unwrap();
+        schematic.set_block_str(3, 0, 0, "minecraft:glass");
+        let chest = BlockEntity::new("minecraft:chest".to_string(), (2, 0, 0))
+            .with_nbt_data("CustomName".to_string(), NbtValue::String("Loot".to_string()))
+            .with_nbt_data("Items".to_string(), NbtValue::EmptyList(10));
+        schematic.set_block_entity(BlockPosition { x: 2, y: 0, z: 0 }, chest);
+        schematic.remove_entity(0);
+        schematic.add_entity(Entity::new("minecraft:cow".to_string(), (1.5, 1.0, 0.5)));
+        schematic
+    }
+
+    #[test]
+    fn test_diff() {
+        let changes = diff(&before(), &after());
+        let changed: Vec<(i32, &str)> = changes.blocks.iter().map(|change| (change.position.x, change.new.name.as_str())).collect();
+        assert_eq!(changed, vec![(0, "minecraft:dirt"), (1, "minecraft:oak_stairs"), (3, "minecraft:glass")]);
+        assert_eq!(changes.blocks[2].old.name, "minecraft:air");
+        assert_eq!(changes.block_entities.len(), 1);
+        assert!(changes.block_entities[0].new.as_ref().unwrap().nbt.contains_key("CustomName"));
+        assert_eq!(changes.removed_entities[0].id, "minecraft:pig");
+        assert_eq!(changes.added_entities[0].id, "minecraft:cow");
+
+        let changes = diff_with(&before(), &after(), &["waterlogged"]);
+        assert_eq!(changes.blocks.len(), 2);
+        assert!(diff(&after(), &after()).is_empty());
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        let changes = diff(&before(), &after());
+        for patch in [
+            SchematicDiff::from_json(&changes.to_json().unwrap()).unwrap(),
+            SchematicDiff::from_bytes(&changes.to_bytes().unwrap()).unwrap(),
+        ] {
+            assert_eq!(patch, changes);
+            let mut schematic = before();
+            patch.apply(&mut schematic).unwrap();
+            assert!(diff(&schematic, &after()).is_empty());
+
+            // Already applied
+            assert!(patch.apply(&mut schematic).is_err());
+            assert!(diff(&schematic, &after()).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_apply_patch_keeps_ignored_properties() {
+        let mut old = before();
+        let mut new = after();
+        old.set_block_from_string(1, 0, 0, "minecraft:oak_stairs[facing=east,waterlogged=true]").unwrap();
+        new.set_block_from_string(1, 0, 0, "minecraft:oak_stairs[facing=west,waterlogged=true]").unwrap();
+        let patch = diff_with(&old, &new, &["waterlogged"]);
+
+        // The target's stairs are not waterlogged
+        let mut schematic = before();
+        patch.apply(&mut schematic).unwrap();
+        let stairs = schematic.get_block(1, 0, 0).unwrap();
+        assert_eq!(stairs.get_property("facing").map(String::as_str), Some("west"));
+        assert_eq!(stairs.get_property("waterlogged").map(String::as_str), Some("false"));
+    }
+}
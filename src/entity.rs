@@ -1,29 +1,13 @@
-use std::collections::HashMap;
-use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use quartz_nbt::{NbtCompound, NbtTag};
 use serde::{Deserialize, Serialize};
-
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub enum NbtValue {
-    String(String),
-    Int(i32),
-    Long(i64),
-    Float(f32),
-    Double(f64),
-    Byte(i8),
-    Short(i16),
-    Boolean(bool),
-    IntArray(Vec<i32>),
-    LongArray(Vec<i64>),
-    ByteArray(Vec<i8>),
-    List(Vec<NbtValue>),
-    Compound(HashMap<String, NbtValue>),
-}
+use crate::utils::{NbtMap, NbtValue};
+use crate::NucleationError;
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Entity {
     pub id: String,
     pub position: (f64, f64, f64),
-    pub nbt: HashMap<String, NbtValue>,
+    pub nbt: NbtMap,
 }
 
 impl Entity {
@@ -31,7 +15,7 @@ impl Entity {
         Entity {
             id,
             position,
-            nbt: HashMap::new(),
+            nbt: NbtMap::new(),
         }
     }
 
@@ -40,65 +24,13 @@ impl Entity {
         self
     }
 
-    fn nbt_tag_to_value(tag: &NbtTag) -> NbtValue {
-        match tag {
-            NbtTag::String(s) => NbtValue::String(s.clone()),
-            NbtTag::Int(i) => NbtValue::Int(*i),
-            NbtTag::Long(l) => NbtValue::Long(*l),
-            NbtTag::Float(f) => NbtValue::Float(*f),
-            NbtTag::Double(d) => NbtValue::Double(*d),
-            NbtTag::Byte(b) => NbtValue::Byte(*b),
-            NbtTag::Short(s) => NbtValue::Short(*s),
-            NbtTag::IntArray(arr) => NbtValue::IntArray(arr.clone()),
-            NbtTag::LongArray(arr) => NbtValue::LongArray(arr.clone()),
-            NbtTag::ByteArray(arr) => NbtValue::ByteArray(arr.clone()),
-            NbtTag::List(list) => {
-                let values: Vec<NbtValue> = list.iter()
-                    .map(|tag| Self::nbt_tag_to_value(tag))
-                    .collect();
-                NbtValue::List(values)
-            }
-            NbtTag::Compound(compound) => {
-                let mut map = HashMap::new();
-                for (key, value) in compound.inner() {
-                    map.insert(key.clone(), Self::nbt_tag_to_value(value));
-                }
-                NbtValue::Compound(map)
-            }
-        }
-    }
-
-    fn value_to_nbt_tag(value: &NbtValue) -> NbtTag {
-        match value {
-            NbtValue::String(s) => NbtTag::String(s.clone()),
-            NbtValue::Int(i) => NbtTag::Int(*i),
-            NbtValue::Long(l) => NbtTag::Long(*l),
-            NbtValue::Float(f) => NbtTag::Float(*f),
-            NbtValue::Double(d) => NbtTag::Double(*d),
-            NbtValue::Byte(b) => NbtTag::Byte(*b),
-            NbtValue::Short(s) => NbtTag::Short(*s),
-            NbtValue::Boolean(b) => NbtTag::Byte(if *b { 1 } else { 0 }),
-            NbtValue::IntArray(arr) => NbtTag::IntArray(arr.clone()),
-            NbtValue::LongArray(arr) => NbtTag::LongArray(arr.clone()),
-            NbtValue::ByteArray(arr) => NbtTag::ByteArray(arr.clone()),
-            NbtValue::List(list) => {
-                let tags: Vec<NbtTag> = list.iter()
-                    .map(|value| Self::value_to_nbt_tag(value))
-                    .collect();
-                NbtTag::List(NbtList::from(tags))
-            }
-            NbtValue::Compound(map) => {
-                let mut compound = NbtCompound::new();
-                for (key, value) in map {
-                    compound.insert(key, Self::value_to_nbt_tag(value));
-                }
-                NbtTag::Compound(compound)
-            }
-        }
+    pub fn to_nbt(&self) -> NbtTag {
+        NbtValue::Compound(self.to_nbt_map()).to_quartz_nbt()
     }
 
-    pub fn to_nbt(&self) -> NbtTag {
-        let mut compound = NbtCompound::new();
+    /// Like [`Entity::to_nbt`], keeping the element type of empty lists.
+    pub fn to_nbt_map(&self) -> NbtMap {
+        let mut compound = NbtMap::new();
 
         // Always store the full minecraft:id format
         let full_id = if self.id.starts_with("minecraft:") {
@@ -106,38 +38,32 @@ impl Entity {
         } else {
             format!("minecraft:{}", self.id)
         };
-        compound.insert("id", NbtTag::String(full_id));
+        compound.insert("id", NbtValue::String(full_id));
 
         // Add position
-        let pos_list = NbtList::from(vec![
-            NbtTag::Double(self.position.0),
-            NbtTag::Double(self.position.1),
-            NbtTag::Double(self.position.2)
-        ]);
-        compound.insert("Pos", NbtTag::List(pos_list));
+        compound.insert("Pos", NbtValue::List(vec![
+            NbtValue::Double(self.position.0),
+            NbtValue::Double(self.position.1),
+            NbtValue::Double(self.position.2)
+        ]));
 
-        // Convert HashMap<String, NbtValue> to NbtCompound
         if !self.nbt.is_empty() {
-            let mut nbt_compound = NbtCompound::new();
-            for (key, value) in &self.nbt {
-                nbt_compound.insert(key, Self::value_to_nbt_tag(value));
-            }
-            compound.insert("NBT", NbtTag::Compound(nbt_compound));
+            compound.insert("NBT", NbtValue::Compound(self.nbt.clone()));
         }
 
-        NbtTag::Compound(compound)
+        compound
     }
 
-    pub fn from_nbt(nbt: &NbtCompound) -> Result<Self, String> {
+    pub fn from_nbt(nbt: &NbtCompound) -> Result<Self, NucleationError> {
+        Self::from_nbt_map(&NbtMap::from_quartz_nbt(nbt))
+    }
+
+    /// Like [`Entity::from_nbt`], keeping the element type of empty lists.
+    pub fn from_nbt_map(nbt: &NbtMap) -> Result<Self, NucleationError> {
         // Handle both id cases, but preserve the minecraft: prefix
-        let id = match nbt.get::<_, &str>("id") {
+        let id = match nbt.get_as::<&str>("id") {
             Ok(id) => id.to_string(),
-            Err(_) => {
-                match nbt.get::<_, &str>("Id") {
-                    Ok(id) => id.to_string(),
-                    Err(e) => return Err(format!("Failed to get Entity id: {}", e)),
-                }
-            }
+            Err(_) => nbt.get_as::<&str>("Id")?.to_string(),
         };
 
         // Don't strip the minecraft: prefix anymore
@@ -147,25 +73,15 @@ impl Entity {
             format!("minecraft:{}", id)
         };
 
-        let position = nbt.get::<_, &NbtList>("Pos")
-            .map_err(|e| format!("Failed to get Entity position: {}", e))?;
-        let position = if position.len() == 3 {
-            (
-                position.get::<f64>(0).map_err(|e| format!("Failed to get X position: {}", e))?,
-                position.get::<f64>(1).map_err(|e| format!("Failed to get Y position: {}", e))?,
-                position.get::<f64>(2).map_err(|e| format!("Failed to get Z position: {}", e))?,
-            )
-        } else {
-            return Err("Invalid position data".to_string());
+        let position = match nbt.get_as::<&[NbtValue]>("Pos")? {
+            [NbtValue::Double(x), NbtValue::Double(y), NbtValue::Double(z)] => (*x, *y, *z),
+            _ => return Err("Invalid position data".into()),
         };
 
-        // Get NBT data if it exists and convert it to HashMap<String, NbtValue>
-        let mut nbt_map = HashMap::new();
-        if let Ok(entity_nbt) = nbt.get::<_, &NbtCompound>("NBT") {
-            for (key, value) in entity_nbt.inner() {
-                nbt_map.insert(key.clone(), Self::nbt_tag_to_value(value));
-            }
-        }
+        let nbt_map = match nbt.get_as::<&NbtMap>("NBT") {
+            Ok(entity_nbt) => entity_nbt.clone(),
+            Err(_) => NbtMap::new(),
+        };
 
         Ok(Entity {
             id,
@@ -178,6 +94,7 @@ impl Entity {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use quartz_nbt::NbtList;
 
     #[test]
     fn test_new_entity() {
@@ -258,7 +175,7 @@ mod tests {
         entity.nbt.insert("ByteArray".to_string(), NbtValue::ByteArray(vec![1, 2, 3]));
 
         // Test nested compound
-        let mut nested_map = HashMap::new();
+        let mut nested_map = NbtMap::new();
         nested_map.insert("NestedString".to_string(), NbtValue::String("test".to_string()));
         entity.nbt.insert("Compound".to_string(), NbtValue::Compound(nested_map));
 
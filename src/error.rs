@@ -0,0 +1,134 @@
+use quartz_nbt::io::NbtIoError;
+use quartz_nbt::{NbtReprError, NbtStructureError};
+use thiserror::Error;
+
+/// Errors returned by the schematic model and the format readers and writers.
+#[derive(Debug, Error)]
+pub enum NucleationError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("NBT error: {0}")]
+    Nbt(#[from] NbtIoError),
+
+    #[error("Unsupported format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Unsupported {format} version {version}")]
+    UnsupportedVersion { format: &'static str, version: i32 },
+
+    #[error("Missing tag \"{path}\"")]
+    MissingTag { path: String },
+
+    #[error("Palette index {index} is out of range for a palette of {palette_len} entries")]
+    InvalidPaletteIndex { index: usize, palette_len: usize },
+
+    #[error("Invalid block string at position {pos}: {message}")]
+    BlockStringSyntax { pos: usize, message: String },
+
+    #[error("Invalid SNBT at line {line}, column {column}: {message}")]
+    Snbt { line: usize, column: usize, message: String },
+
+    #[error("Region '{0}' not found")]
+    RegionNotFound(String),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    InvalidData(String),
+}
+
+pub type Result<T> = std::result::Result<T, NucleationError>;
+
+impl NucleationError {
+    pub fn missing_tag(path: impl Into<String>) -> Self {
+        NucleationError::MissingTag { path: path.into() }
+    }
+
+    /// Stable identifier for the variant, used as the error code in the bindings.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NucleationError::Io(_) => "Io",
+            NucleationError::Nbt(_) => "Nbt",
+            NucleationError::UnsupportedFormat(_) => "UnsupportedFormat",
+            NucleationError::UnsupportedVersion { .. } => "UnsupportedVersion",
+            NucleationError::MissingTag { .. } => "MissingTag",
+            NucleationError::InvalidPaletteIndex { .. } => "InvalidPaletteIndex",
+            NucleationError::BlockStringSyntax { .. } => "BlockStringSyntax",
+            NucleationError::Snbt { .. } => "Snbt",
+            NucleationError::RegionNotFound(_) => "RegionNotFound",
+            NucleationError::Json(_) => "Json",
+            NucleationError::InvalidData(_) => "InvalidData",
+        }
+    }
+
+    /// Stable numeric code for the variant. The C API returns its negation.
+    pub fn code(&self) -> i32 {
+        match self {
+            NucleationError::InvalidData(_) => 2,
+            NucleationError::UnsupportedFormat(_) => 3,
+            NucleationError::Io(_) => 4,
+            NucleationError::Nbt(_) => 5,
+            NucleationError::UnsupportedVersion { .. } => 6,
+            NucleationError::MissingTag { .. } => 7,
+            NucleationError::InvalidPaletteIndex { .. } => 8,
+            NucleationError::BlockStringSyntax { .. } => 9,
+            NucleationError::RegionNotFound(_) => 10,
+            NucleationError::Json(_) => 11,
+            NucleationError::Snbt { .. } => 12,
+        }
+    }
+}
+
+// quartz_nbt keeps the structure error kinds private, so missing tags are
+// recognised from the message it formats for them.
+impl From<NbtStructureError> for NucleationError {
+    fn from(error: NbtStructureError) -> Self {
+        let message = error.to_string();
+        match message.strip_prefix("Missing tag \"").and_then(|rest| rest.strip_suffix('"')) {
+            Some(path) => NucleationError::missing_tag(path),
+            None => NucleationError::InvalidData(message),
+        }
+    }
+}
+
+impl From<NbtReprError> for NucleationError {
+    fn from(error: NbtReprError) -> Self {
+        match error {
+            NbtReprError::Structure(error) => (*error).into(),
+            NbtReprError::Custom(error) => NucleationError::InvalidData(error.to_string()),
+        }
+    }
+}
+
+impl From<String> for NucleationError {
+    fn from(message: String) -> Self {
+        NucleationError::InvalidData(message)
+    }
+}
+
+impl From<&str> for NucleationError {
+    fn from(message: &str) -> Self {
+        NucleationError::InvalidData(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quartz_nbt::NbtCompound;
+
+    #[test]
+    fn test_missing_tag_from_nbt_lookup() {
+        let compound = NbtCompound::new();
+        let error: NucleationError = compound.get::<_, i32>("Version").unwrap_err().into();
+        assert!(matches!(error, NucleationError::MissingTag { ref path } if path == "Version"));
+        assert_eq!(error.kind(), "MissingTag");
+
+        let mut compound = NbtCompound::new();
+        compound.insert("Version", "three");
+        let error: NucleationError = compound.get::<_, i32>("Version").unwrap_err().into();
+        assert!(matches!(error, NucleationError::InvalidData(_)));
+    }
+}
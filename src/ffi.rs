@@ -2,12 +2,20 @@
 #![cfg(feature = "ffi")]
 use std::os::raw::{c_char, c_uchar, c_int, c_float};
 use std::ffi::{CStr, CString};
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::ptr;
 use crate::{
     UniversalSchematic,
     BlockState,
-    formats::{litematic, schematic},
+    NucleationError,
+    BooleanOperation,
+    ConflictPolicy,
+    Mask,
+    Pattern,
+    SchematicDiff,
+    diff_with,
+    formats::{litematic, schematic, structure, mcstructure, axiom, vox},
     print_utils::{format_schematic, format_json_schematic},
     block_position::BlockPosition,
     bounding_box::BoundingBox,
@@ -92,6 +100,40 @@ pub struct CChunkArray {
 }
 
 
+// --- Error Codes ---
+// Negated `NucleationError::code`, plus -1 for NULL arguments.
+
+pub const NUCLEATION_ERR_NULL_POINTER: c_int = -1;
+pub const NUCLEATION_ERR_INVALID_DATA: c_int = -2;
+pub const NUCLEATION_ERR_UNSUPPORTED_FORMAT: c_int = -3;
+pub const NUCLEATION_ERR_IO: c_int = -4;
+pub const NUCLEATION_ERR_NBT: c_int = -5;
+pub const NUCLEATION_ERR_UNSUPPORTED_VERSION: c_int = -6;
+pub const NUCLEATION_ERR_MISSING_TAG: c_int = -7;
+pub const NUCLEATION_ERR_INVALID_PALETTE_INDEX: c_int = -8;
+pub const NUCLEATION_ERR_BLOCK_STRING_SYNTAX: c_int = -9;
+pub const NUCLEATION_ERR_REGION_NOT_FOUND: c_int = -10;
+pub const NUCLEATION_ERR_JSON: c_int = -11;
+pub const NUCLEATION_ERR_SNBT: c_int = -12;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+// Records the error message for `last_error_message` and returns the matching error code.
+fn error_code(error: NucleationError) -> c_int {
+    let code = -error.code();
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(error.to_string()).ok());
+    code
+}
+
+/// Returns the message of the last error raised on this thread, or NULL if there was none.
+/// The returned C string must be freed with `free_string`.
+#[no_mangle]
+pub extern "C" fn last_error_message() -> *mut c_char {
+    LAST_ERROR.with(|last| last.borrow().clone().map_or(ptr::null_mut(), CString::into_raw))
+}
+
 // --- Wrapper Structs with Opaque Pointers ---
 
 pub struct SchematicWrapper(*mut UniversalSchematic);
@@ -239,42 +281,62 @@ pub extern "C" fn schematic_free(schematic: *mut SchematicWrapper) {
 // --- Data I/O ---
 
 /// Populates a schematic from raw byte data, auto-detecting the format.
-/// Returns 0 on success, negative on error.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
 #[no_mangle]
 pub extern "C" fn schematic_from_data(
     schematic: *mut SchematicWrapper,
     data: *const c_uchar,
     data_len: usize,
 ) -> c_int {
-    if schematic.is_null() || data.is_null() { return -1; }
+    if schematic.is_null() || data.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
     let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
     let s = unsafe { &mut *(*schematic).0 };
 
     if litematic::is_litematic(data_slice) {
         match litematic::from_litematic(data_slice) {
             Ok(res) => { *s = res; 0 }
-            Err(_) => -2,
+            Err(e) => error_code(e),
         }
     } else if schematic::is_schematic(data_slice) {
         match schematic::from_schematic(data_slice) {
             Ok(res) => { *s = res; 0 }
-            Err(_) => -2,
+            Err(e) => error_code(e),
+        }
+    } else if structure::is_structure(data_slice) {
+        match structure::from_structure(data_slice) {
+            Ok(res) => { *s = res; 0 }
+            Err(e) => error_code(e),
+        }
+    } else if mcstructure::is_mcstructure(data_slice) {
+        match mcstructure::from_mcstructure(data_slice) {
+            Ok(res) => { *s = res; 0 }
+            Err(e) => error_code(e),
+        }
+    } else if axiom::is_axiom(data_slice) {
+        match axiom::from_axiom(data_slice) {
+            Ok(res) => { *s = res; 0 }
+            Err(e) => error_code(e),
+        }
+    } else if vox::is_vox(data_slice) {
+        match vox::from_vox(data_slice) {
+            Ok(res) => { *s = res; 0 }
+            Err(e) => error_code(e),
         }
     } else {
-        -3 // Unknown format
+        error_code(NucleationError::UnsupportedFormat("unknown or unsupported schematic format".to_string()))
     }
 }
 
 /// Populates a schematic from Litematic data.
-/// Returns 0 on success, negative on error.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
 #[no_mangle]
 pub extern "C" fn schematic_from_litematic(schematic: *mut SchematicWrapper, data: *const c_uchar, data_len: usize) -> c_int {
-    if schematic.is_null() || data.is_null() { return -1; }
+    if schematic.is_null() || data.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
     let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
     let s = unsafe { &mut *(*schematic).0 };
     match litematic::from_litematic(data_slice) {
         Ok(res) => { *s = res; 0 },
-        Err(_) => -2,
+        Err(e) => error_code(e),
     }
 }
 
@@ -292,20 +354,20 @@ pub extern "C" fn schematic_to_litematic(schematic: *const SchematicWrapper) ->
             std::mem::forget(data);
             ByteArray { data: ptr, len }
         }
-        Err(_) => ByteArray { data: ptr::null_mut(), len: 0 },
+        Err(e) => { error_code(e); ByteArray { data: ptr::null_mut(), len: 0 } }
     }
 }
 
 /// Populates a schematic from classic `.schematic` data.
-/// Returns 0 on success, negative on error.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
 #[no_mangle]
 pub extern "C" fn schematic_from_schematic(schematic: *mut SchematicWrapper, data: *const c_uchar, data_len: usize) -> c_int {
-    if schematic.is_null() || data.is_null() { return -1; }
+    if schematic.is_null() || data.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
     let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
     let s = unsafe { &mut *(*schematic).0 };
     match schematic::from_schematic(data_slice) {
         Ok(res) => { *s = res; 0 },
-        Err(_) => -2,
+        Err(e) => error_code(e),
     }
 }
 
@@ -323,21 +385,145 @@ pub extern "C" fn schematic_to_schematic(schematic: *const SchematicWrapper) ->
             std::mem::forget(data);
             ByteArray { data: ptr, len }
         }
-        Err(_) => ByteArray { data: ptr::null_mut(), len: 0 },
+        Err(e) => { error_code(e); ByteArray { data: ptr::null_mut(), len: 0 } }
+    }
+}
+
+/// Populates a schematic from vanilla structure `.nbt` data.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_from_structure(schematic: *mut SchematicWrapper, data: *const c_uchar, data_len: usize) -> c_int {
+    if schematic.is_null() || data.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
+    let s = unsafe { &mut *(*schematic).0 };
+    match structure::from_structure(data_slice) {
+        Ok(res) => { *s = res; 0 },
+        Err(e) => error_code(e),
+    }
+}
+
+/// Converts the schematic to vanilla structure `.nbt` format.
+/// The returned ByteArray must be freed with `free_byte_array`.
+#[no_mangle]
+pub extern "C" fn schematic_to_structure(schematic: *const SchematicWrapper) -> ByteArray {
+    if schematic.is_null() { return ByteArray { data: ptr::null_mut(), len: 0 }; }
+    let s = unsafe { &*(*schematic).0 };
+    match structure::to_structure(s) {
+        Ok(data) => {
+            let mut data = data;
+            let ptr = data.as_mut_ptr();
+            let len = data.len();
+            std::mem::forget(data);
+            ByteArray { data: ptr, len }
+        }
+        Err(e) => { error_code(e); ByteArray { data: ptr::null_mut(), len: 0 } }
+    }
+}
+
+/// Populates a schematic from Bedrock `.mcstructure` data.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_from_mcstructure(schematic: *mut SchematicWrapper, data: *const c_uchar, data_len: usize) -> c_int {
+    if schematic.is_null() || data.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
+    let s = unsafe { &mut *(*schematic).0 };
+    match mcstructure::from_mcstructure(data_slice) {
+        Ok(res) => { *s = res; 0 },
+        Err(e) => error_code(e),
+    }
+}
+
+/// Converts the schematic to Bedrock `.mcstructure` format.
+/// The returned ByteArray must be freed with `free_byte_array`.
+#[no_mangle]
+pub extern "C" fn schematic_to_mcstructure(schematic: *const SchematicWrapper) -> ByteArray {
+    if schematic.is_null() { return ByteArray { data: ptr::null_mut(), len: 0 }; }
+    let s = unsafe { &*(*schematic).0 };
+    match mcstructure::to_mcstructure(s) {
+        Ok(data) => {
+            let mut data = data;
+            let ptr = data.as_mut_ptr();
+            let len = data.len();
+            std::mem::forget(data);
+            ByteArray { data: ptr, len }
+        }
+        Err(e) => { error_code(e); ByteArray { data: ptr::null_mut(), len: 0 } }
+    }
+}
+
+/// Populates a schematic from Axiom blueprint (`.bp`) data.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_from_axiom(schematic: *mut SchematicWrapper, data: *const c_uchar, data_len: usize) -> c_int {
+    if schematic.is_null() || data.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
+    let s = unsafe { &mut *(*schematic).0 };
+    match axiom::from_axiom(data_slice) {
+        Ok(res) => { *s = res; 0 },
+        Err(e) => error_code(e),
+    }
+}
+
+/// Converts the schematic to Axiom blueprint format.
+/// The returned ByteArray must be freed with `free_byte_array`.
+#[no_mangle]
+pub extern "C" fn schematic_to_axiom(schematic: *const SchematicWrapper) -> ByteArray {
+    if schematic.is_null() { return ByteArray { data: ptr::null_mut(), len: 0 }; }
+    let s = unsafe { &*(*schematic).0 };
+    match axiom::to_axiom(s) {
+        Ok(data) => {
+            let mut data = data;
+            let ptr = data.as_mut_ptr();
+            let len = data.len();
+            std::mem::forget(data);
+            ByteArray { data: ptr, len }
+        }
+        Err(e) => { error_code(e); ByteArray { data: ptr::null_mut(), len: 0 } }
+    }
+}
+
+/// Populates a schematic from MagicaVoxel `.vox` data.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_from_vox(schematic: *mut SchematicWrapper, data: *const c_uchar, data_len: usize) -> c_int {
+    if schematic.is_null() || data.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
+    let s = unsafe { &mut *(*schematic).0 };
+    match vox::from_vox(data_slice) {
+        Ok(res) => { *s = res; 0 },
+        Err(e) => error_code(e),
+    }
+}
+
+/// Converts the schematic to MagicaVoxel `.vox` format.
+/// The returned ByteArray must be freed with `free_byte_array`.
+#[no_mangle]
+pub extern "C" fn schematic_to_vox(schematic: *const SchematicWrapper) -> ByteArray {
+    if schematic.is_null() { return ByteArray { data: ptr::null_mut(), len: 0 }; }
+    let s = unsafe { &*(*schematic).0 };
+    match vox::to_vox(s) {
+        Ok(data) => {
+            let mut data = data;
+            let ptr = data.as_mut_ptr();
+            let len = data.len();
+            std::mem::forget(data);
+            ByteArray { data: ptr, len }
+        }
+        Err(e) => { error_code(e); ByteArray { data: ptr::null_mut(), len: 0 } }
     }
 }
 
 // --- Block Manipulation ---
 
 /// Sets a block at a given position with just a block name (no properties).
-/// Returns 0 on success, negative on error.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
 #[no_mangle]
 pub extern "C" fn schematic_set_block(
     schematic: *mut SchematicWrapper,
     x: c_int, y: c_int, z: c_int,
     block_name: *const c_char,
 ) -> c_int {
-    if schematic.is_null() || block_name.is_null() { return -1; }
+    if schematic.is_null() || block_name.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
     let s = unsafe { &mut *(*schematic).0 };
     let block_name_str = unsafe { CStr::from_ptr(block_name).to_string_lossy().into_owned() };
 
@@ -348,7 +534,7 @@ pub extern "C" fn schematic_set_block(
 
 /// Sets a block at a given position with properties.
 /// The properties array is a list of key-value pairs.
-/// Returns 0 on success, negative on error.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
 #[no_mangle]
 pub extern "C" fn schematic_set_block_with_properties(
     schematic: *mut SchematicWrapper,
@@ -357,11 +543,11 @@ pub extern "C" fn schematic_set_block_with_properties(
     properties: *const CProperty,
     properties_len: usize,
 ) -> c_int {
-    if schematic.is_null() || block_name.is_null() { return -1; }
+    if schematic.is_null() || block_name.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
     let s = unsafe { &mut *(*schematic).0 };
     let block_name_str = unsafe { CStr::from_ptr(block_name).to_string_lossy().into_owned() };
 
-    let mut props = HashMap::new();
+    let mut props = BTreeMap::new();
     if !properties.is_null() {
         let props_slice = unsafe { std::slice::from_raw_parts(properties, properties_len) };
         for prop in props_slice {
@@ -377,24 +563,24 @@ pub extern "C" fn schematic_set_block_with_properties(
 }
 
 /// Sets a block from a full block string, e.g., "minecraft:chest[facing=north]{Items:[...]}".
-/// Returns 0 on success, negative on error.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
 #[no_mangle]
 pub extern "C" fn schematic_set_block_from_string(
     schematic: *mut SchematicWrapper,
     x: c_int, y: c_int, z: c_int,
     block_string: *const c_char,
 ) -> c_int {
-    if schematic.is_null() || block_string.is_null() { return -1; }
+    if schematic.is_null() || block_string.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
     let s = unsafe { &mut *(*schematic).0 };
     let block_str = unsafe { CStr::from_ptr(block_string).to_string_lossy() };
     match s.set_block_from_string(x, y, z, &block_str) {
         Ok(_) => 0,
-        Err(_) => -2,
+        Err(e) => error_code(e),
     }
 }
 
 /// Copies a region from a source schematic to a target schematic.
-/// Returns 0 on success, negative on error.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
 #[no_mangle]
 pub extern "C" fn schematic_copy_region(
     target: *mut SchematicWrapper,
@@ -405,7 +591,7 @@ pub extern "C" fn schematic_copy_region(
     excluded_blocks: *const *const c_char,
     excluded_blocks_len: usize,
 ) -> c_int {
-    if target.is_null() || source.is_null() { return -1; }
+    if target.is_null() || source.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
     let target_s = unsafe { &mut *(*target).0 };
     let source_s = unsafe { &*(*source).0 };
     let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
@@ -417,14 +603,248 @@ pub extern "C" fn schematic_copy_region(
             let block_str = unsafe { CStr::from_ptr(block_ptr).to_string_lossy() };
             match UniversalSchematic::parse_block_string(&block_str) {
                 Ok((bs, _)) => excluded.push(bs),
-                Err(_) => return -3,
+                Err(e) => return error_code(e),
             }
         }
     }
 
     match target_s.copy_region(source_s, &bounds, (target_x, target_y, target_z), &excluded) {
         Ok(_) => 0,
-        Err(_) => -2,
+        Err(e) => error_code(e),
+    }
+}
+
+// --- Edit Operations ---
+
+fn c_pattern(pattern: *const c_char) -> Result<Pattern, NucleationError> {
+    Pattern::parse(&unsafe { CStr::from_ptr(pattern) }.to_string_lossy())
+}
+
+// Block counts saturate at INT_MAX, so they never read as negative error codes
+fn c_count(count: usize) -> c_int {
+    count.min(c_int::MAX as usize) as c_int
+}
+
+/// Sets every block of the box to the pattern expression `pattern`, e.g. `50%stone,50%andesite`.
+/// `seed` picks the blocks of random patterns, the edit functions below take it the same way.
+/// Returns the number of blocks set, at most `INT_MAX`, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_fill(
+    schematic: *mut SchematicWrapper,
+    min_x: c_int, min_y: c_int, min_z: c_int,
+    max_x: c_int, max_y: c_int, max_z: c_int,
+    pattern: *const c_char,
+    seed: u64,
+) -> c_int {
+    if schematic.is_null() || pattern.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let s = unsafe { &mut *(*schematic).0 };
+    let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+    let pattern = match c_pattern(pattern) {
+        Ok(pattern) => pattern.with_seed(seed),
+        Err(e) => return error_code(e),
+    };
+    c_count(s.fill(&bounds, &pattern))
+}
+
+/// Sets the blocks of the box matching the mask expression `mask`, e.g. `!air >grass_block`, to `pattern`.
+/// Returns the number of blocks set, at most `INT_MAX`, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_replace(
+    schematic: *mut SchematicWrapper,
+    min_x: c_int, min_y: c_int, min_z: c_int,
+    max_x: c_int, max_y: c_int, max_z: c_int,
+    mask: *const c_char,
+    pattern: *const c_char,
+    seed: u64,
+) -> c_int {
+    if schematic.is_null() || mask.is_null() || pattern.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let s = unsafe { &mut *(*schematic).0 };
+    let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+    let mask = match Mask::parse(&unsafe { CStr::from_ptr(mask) }.to_string_lossy()) {
+        Ok(mask) => mask,
+        Err(e) => return error_code(e),
+    };
+    let pattern = match c_pattern(pattern) {
+        Ok(pattern) => pattern.with_seed(seed),
+        Err(e) => return error_code(e),
+    };
+    c_count(s.replace(&bounds, &mask, &pattern))
+}
+
+/// Sets the four vertical sides of the box to `pattern`.
+/// Returns the number of blocks set, at most `INT_MAX`, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_walls(
+    schematic: *mut SchematicWrapper,
+    min_x: c_int, min_y: c_int, min_z: c_int,
+    max_x: c_int, max_y: c_int, max_z: c_int,
+    pattern: *const c_char,
+    seed: u64,
+) -> c_int {
+    if schematic.is_null() || pattern.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let s = unsafe { &mut *(*schematic).0 };
+    let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+    let pattern = match c_pattern(pattern) {
+        Ok(pattern) => pattern.with_seed(seed),
+        Err(e) => return error_code(e),
+    };
+    c_count(s.walls(&bounds, &pattern))
+}
+
+/// Sets the six faces of the box to `pattern`.
+/// Returns the number of blocks set, at most `INT_MAX`, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_outline(
+    schematic: *mut SchematicWrapper,
+    min_x: c_int, min_y: c_int, min_z: c_int,
+    max_x: c_int, max_y: c_int, max_z: c_int,
+    pattern: *const c_char,
+    seed: u64,
+) -> c_int {
+    if schematic.is_null() || pattern.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let s = unsafe { &mut *(*schematic).0 };
+    let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+    let pattern = match c_pattern(pattern) {
+        Ok(pattern) => pattern.with_seed(seed),
+        Err(e) => return error_code(e),
+    };
+    c_count(s.outline(&bounds, &pattern))
+}
+
+/// Hollows out the objects in the box, keeping a shell `thickness` blocks thick.
+/// Returns the number of blocks removed, at most `INT_MAX`, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_hollow(
+    schematic: *mut SchematicWrapper,
+    min_x: c_int, min_y: c_int, min_z: c_int,
+    max_x: c_int, max_y: c_int, max_z: c_int,
+    thickness: c_int,
+) -> c_int {
+    if schematic.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let s = unsafe { &mut *(*schematic).0 };
+    let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+    c_count(s.hollow(&bounds, thickness.max(0) as u32))
+}
+
+/// Places `pattern` on top of the highest block of every column of the box.
+/// Returns the number of blocks set, at most `INT_MAX`, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_overlay(
+    schematic: *mut SchematicWrapper,
+    min_x: c_int, min_y: c_int, min_z: c_int,
+    max_x: c_int, max_y: c_int, max_z: c_int,
+    pattern: *const c_char,
+    seed: u64,
+) -> c_int {
+    if schematic.is_null() || pattern.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let s = unsafe { &mut *(*schematic).0 };
+    let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+    let pattern = match c_pattern(pattern) {
+        Ok(pattern) => pattern.with_seed(seed),
+        Err(e) => return error_code(e),
+    };
+    c_count(s.overlay(&bounds, &pattern))
+}
+
+/// Counts the blocks matching the mask expression `mask`.
+/// Returns the count, at most `INT_MAX`, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_count_matching(schematic: *const SchematicWrapper, mask: *const c_char) -> c_int {
+    if schematic.is_null() || mask.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let s = unsafe { &*(*schematic).0 };
+    match Mask::parse(&unsafe { CStr::from_ptr(mask) }.to_string_lossy()) {
+        Ok(mask) => c_count(s.count_matching(&mask)),
+        Err(e) => error_code(e),
+    }
+}
+
+/// Finds the blocks matching the mask expression `mask`, as flat `x, y, z` triples.
+/// Returns an empty array on error, see `last_error_message`. Free with `free_int_array`.
+#[no_mangle]
+pub extern "C" fn schematic_find_blocks(schematic: *const SchematicWrapper, mask: *const c_char) -> IntArray {
+    if schematic.is_null() || mask.is_null() { return IntArray { data: ptr::null_mut(), len: 0 }; }
+    let s = unsafe { &*(*schematic).0 };
+    let mask = match Mask::parse(&unsafe { CStr::from_ptr(mask) }.to_string_lossy()) {
+        Ok(mask) => mask,
+        Err(e) => { error_code(e); return IntArray { data: ptr::null_mut(), len: 0 }; }
+    };
+    let positions: Vec<c_int> = s.find_blocks(&mask).into_iter().flat_map(|p| [p.x, p.y, p.z]).collect();
+    let mut boxed_slice = positions.into_boxed_slice();
+    let ptr = boxed_slice.as_mut_ptr();
+    let len = boxed_slice.len();
+    std::mem::forget(boxed_slice);
+    IntArray { data: ptr, len }
+}
+
+/// Combines `target` with `source` moved by `(dx, dy, dz)`. `operation` is `union`, `intersection`,
+/// `difference` or `xor`, `policy` is `keep`, `replace` or `fail` and picks the block where both have one.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_combine(
+    target: *mut SchematicWrapper,
+    source: *const SchematicWrapper,
+    dx: c_int, dy: c_int, dz: c_int,
+    operation: *const c_char,
+    policy: *const c_char,
+) -> c_int {
+    if target.is_null() || source.is_null() || operation.is_null() || policy.is_null() {
+        return NUCLEATION_ERR_NULL_POINTER;
+    }
+    let target_s = unsafe { &mut *(*target).0 };
+    let source_s = unsafe { &*(*source).0 };
+    let operation = BooleanOperation::parse(&unsafe { CStr::from_ptr(operation) }.to_string_lossy());
+    let policy = ConflictPolicy::parse(&unsafe { CStr::from_ptr(policy) }.to_string_lossy());
+    let result = operation.and_then(|operation| {
+        policy.and_then(|policy| target_s.combine(source_s, (dx, dy, dz), operation, policy))
+    });
+    match result {
+        Ok(_) => 0,
+        Err(e) => error_code(e),
+    }
+}
+
+/// Patch from `a` to `b` as gzipped NBT. Blocks only differing in the `ignored_properties` are left out.
+/// Returns an empty array on error, see `last_error_message`. Free with `free_byte_array`.
+#[no_mangle]
+pub extern "C" fn schematic_diff(
+    a: *const SchematicWrapper,
+    b: *const SchematicWrapper,
+    ignored_properties: *const *const c_char,
+    ignored_properties_len: usize,
+) -> ByteArray {
+    if a.is_null() || b.is_null() { return ByteArray { data: ptr::null_mut(), len: 0 }; }
+    let a = unsafe { &*(*a).0 };
+    let b = unsafe { &*(*b).0 };
+    let mut ignored = Vec::new();
+    if !ignored_properties.is_null() {
+        let ignored_slice = unsafe { std::slice::from_raw_parts(ignored_properties, ignored_properties_len) };
+        for &property in ignored_slice {
+            ignored.push(unsafe { CStr::from_ptr(property).to_string_lossy() }.into_owned());
+        }
+    }
+    let ignored: Vec<&str> = ignored.iter().map(String::as_str).collect();
+    match diff_with(a, b, &ignored).to_bytes() {
+        Ok(data) => {
+            let mut data = data;
+            let ptr = data.as_mut_ptr();
+            let len = data.len();
+            std::mem::forget(data);
+            ByteArray { data: ptr, len }
+        }
+        Err(e) => { error_code(e); ByteArray { data: ptr::null_mut(), len: 0 } }
+    }
+}
+
+/// Applies a patch made by `schematic_diff`. The schematic is left unchanged when the patch does not fit it.
+/// Returns 0 on success, a negative `NUCLEATION_ERR_*` code on error.
+#[no_mangle]
+pub extern "C" fn schematic_apply_patch(schematic: *mut SchematicWrapper, data: *const c_uchar, data_len: usize) -> c_int {
+    if schematic.is_null() || data.is_null() { return NUCLEATION_ERR_NULL_POINTER; }
+    let s = unsafe { &mut *(*schematic).0 };
+    let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
+    match SchematicDiff::from_bytes(data_slice).and_then(|patch| patch.apply(s)) {
+        Ok(_) => 0,
+        Err(e) => error_code(e),
     }
 }
 
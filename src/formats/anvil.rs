@@ -0,0 +1,1293 @@
+// Reads and writes blocks, block entities and entities in Anvil (.mca) region files.
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use quartz_nbt::io::{read_nbt, Flavor};
+use crate::{BlockState, NucleationError, UniversalSchematic};
+use crate::block_entity::BlockEntity;
+use crate::bounding_box::BoundingBox;
+use crate::entity::Entity;
+use crate::formats::{current_time_millis, mcedit};
+use crate::formats::structure::STRUCTURE_VOID;
+use crate::region::{pack_block_indices, Region};
+use crate::utils::{NbtMap, NbtValue};
+
+const SECTOR_SIZE: usize = 4096;
+const SECTION_VOLUME: usize = 4096;
+// The sector count of a region file entry is a single byte, larger chunks go to .mcc files
+const MAX_CHUNK_SECTORS: usize = 255;
+const EXTERNAL_CHUNK: u8 = 0x80;
+// Height of the overworld since 1.18
+const MIN_WORLD_Y: i32 = -64;
+const MAX_WORLD_Y: i32 = 319;
+
+// 1.16 (20w17a) stopped packing block state indices across long boundaries
+const NON_SPANNING_DATA_VERSION: i32 = 2529;
+
+/// Extracts the blocks inside `bounding_box` (inclusive, world coordinates) from a world or
+/// dimension directory containing `region/` and, since 1.17, `entities/` folders.
+/// The returned schematic has its origin at `bounding_box.min`.
+pub fn from_world<P: AsRef<Path>>(world_dir: P, bounding_box: &BoundingBox) -> Result<UniversalSchematic, NucleationError> {
+    let world_dir = world_dir.as_ref();
+    let region_dir = world_dir.join("region");
+    if !region_dir.is_dir() {
+        return Err(format!("{} has no region directory", world_dir.display()).into());
+    }
+    let entities_dir = world_dir.join("entities");
+
+    let mut extractor = WorldExtractor::new(bounding_box);
+    let (min_chunk_x, min_chunk_z) = (bounding_box.min.0 >> 4, bounding_box.min.2 >> 4);
+    let (max_chunk_x, max_chunk_z) = (bounding_box.max.0 >> 4, bounding_box.max.2 >> 4);
+
+    for region_x in (min_chunk_x >> 5)..=(max_chunk_x >> 5) {
+        for region_z in (min_chunk_z >> 5)..=(max_chunk_z >> 5) {
+            let file_name = format!("r.{}.{}.mca", region_x, region_z);
+            for dir in [&region_dir, &entities_dir] {
+                let path = dir.join(&file_name);
+                if !path.is_file() {
+                    continue;
+                }
+                let data = std::fs::read(&path)?;
+
+                let chunks_x = (min_chunk_x.max(region_x * 32))..=(max_chunk_x.min(region_x * 32 + 31));
+                for chunk_x in chunks_x {
+                    for chunk_z in (min_chunk_z.max(region_z * 32))..=(max_chunk_z.min(region_z * 32 + 31)) {
+                        let slot = ((chunk_x & 31) + (chunk_z & 31) * 32) as usize;
+                        if let Some(chunk) = read_chunk(&data, slot, Some((dir, chunk_x, chunk_z)))? {
+                            extractor.add_chunk(&chunk)?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(extractor.finish())
+}
+
+/// Extracts the blocks inside `bounding_box` from individual region files.
+/// Entity region files from the `entities/` folder can be passed alongside the block region files.
+pub fn from_region_files(region_files: &[&[u8]], bounding_box: &BoundingBox) -> Result<UniversalSchematic, NucleationError> {
+    let mut extractor = WorldExtractor::new(bounding_box);
+    for data in region_files {
+        for slot in 0..1024 {
+            if let Some(chunk) = read_chunk(data, slot, None)? {
+                extractor.add_chunk(&chunk)?;
+            }
+        }
+    }
+    Ok(extractor.finish())
+}
+
+// Compression type and compressed payload of a stored chunk
+type ChunkPayload<'a> = Option<(u8, &'a [u8])>;
+
+// Returns the payload stored in `slot` of a region file
+fn chunk_payload(data: &[u8], slot: usize) -> Result<ChunkPayload<'_>, NucleationError> {
+    if data.len() < 2 * SECTOR_SIZE {
+        return Err("Region file header is truncated".into());
+    }
+
+    let entry = &data[slot * 4..slot * 4 + 4];
+    let offset = u32::from_be_bytes([0, entry[0], entry[1], entry[2]]) as usize * SECTOR_SIZE;
+    if offset == 0 {
+        return Ok(None);
+    }
+    if offset + 5 > data.len() {
+        return Err(format!("Chunk {} points outside of the region file", slot).into());
+    }
+
+    let length = u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) as usize;
+    let compression = data[offset + 4];
+    if length == 0 || offset + 4 + length > data.len() {
+        return Err(format!("Chunk {} has an invalid length", slot).into());
+    }
+    Ok(Some((compression, &data[offset + 5..offset + 4 + length])))
+}
+
+// Reads the chunk stored in `slot` of a region file, `external` locates oversized .mcc chunks
+fn read_chunk(data: &[u8], slot: usize, external: Option<(&Path, i32, i32)>) -> Result<Option<NbtCompound>, NucleationError> {
+    match chunk_payload(data, slot)? {
+        Some((compression, payload)) => decode_chunk(compression, payload, external).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn decode_chunk(compression: u8, payload: &[u8], external: Option<(&Path, i32, i32)>) -> Result<NbtCompound, NucleationError> {
+    let external_data;
+    let payload = if compression & 0x80 != 0 {
+        let (dir, chunk_x, chunk_z) = external.ok_or("Chunk is stored in an external .mcc file")?;
+        external_data = std::fs::read(dir.join(format!("c.{}.{}.mcc", chunk_x, chunk_z)))?;
+        &external_data[..]
+    } else {
+        payload
+    };
+
+    let mut decompressed = Vec::new();
+    match compression & 0x7f {
+        1 => { GzDecoder::new(payload).read_to_end(&mut decompressed)?; }
+        2 => { ZlibDecoder::new(payload).read_to_end(&mut decompressed)?; }
+        3 => decompressed.extend_from_slice(payload),
+        other => return Err(format!("Unsupported chunk compression type {}", other).into()),
+    }
+
+    let (chunk, _) = read_nbt(&mut Cursor::new(decompressed), Flavor::Uncompressed)?;
+    Ok(chunk)
+}
+
+struct WorldExtractor {
+    bounding_box: BoundingBox,
+    region: Region,
+    data_version: Option<i32>,
+}
+
+impl WorldExtractor {
+    fn new(bounding_box: &BoundingBox) -> Self {
+        let region = Region::new("Main".to_string(), (0, 0, 0), bounding_box.get_dimensions());
+        WorldExtractor { bounding_box: bounding_box.clone(), region, data_version: None }
+    }
+
+    fn finish(self) -> UniversalSchematic {
+        let mut schematic = UniversalSchematic::new("Unnamed".to_string());
+        schematic.metadata.mc_version = self.data_version;
+        schematic.add_region(self.region);
+        schematic
+    }
+
+    fn to_local(&self, x: i32, y: i32, z: i32) -> Option<(i32, i32, i32)> {
+        if !self.bounding_box.contains((x, y, z)) {
+            return None;
+        }
+        let min = self.bounding_box.min;
+        Some((x - min.0, y - min.1, z - min.2))
+    }
+
+    fn add_chunk(&mut self, chunk: &NbtCompound) -> Result<(), NucleationError> {
+        let data_version = chunk.get::<_, i32>("DataVersion").ok();
+        if self.data_version.is_none() {
+            self.data_version = data_version;
+        }
+
+        // Before 1.18 everything lives in a Level compound
+        let level = chunk.get::<_, &NbtCompound>("Level").unwrap_or(chunk);
+
+        let chunk_pos = match (level.get::<_, i32>("xPos"), level.get::<_, i32>("zPos")) {
+            (Ok(x), Ok(z)) => Some((x, z)),
+            // Entity region files only carry a Position
+            _ => match level.get::<_, &[i32]>("Position") {
+                Ok(&[x, z]) => Some((x, z)),
+                _ => None,
+            },
+        };
+        let (chunk_x, chunk_z) = match chunk_pos {
+            Some(pos) => pos,
+            None => return Ok(()),
+        };
+        if !self.bounding_box.intersects_range(chunk_x * 16, i32::MIN, chunk_z * 16, chunk_x * 16 + 16, i32::MAX, chunk_z * 16 + 16) {
+            return Ok(());
+        }
+
+        let sections = level.get::<_, &NbtList>("sections")
+            .or_else(|_| level.get::<_, &NbtList>("Sections"));
+        if let Ok(sections) = sections {
+            let spanning = data_version.map(|v| v < NON_SPANNING_DATA_VERSION).unwrap_or(true);
+            for tag in sections.iter() {
+                if let NbtTag::Compound(section) = tag {
+                    self.add_section(section, chunk_x, chunk_z, spanning)?;
+                }
+            }
+        }
+
+        for key in ["block_entities", "TileEntities"] {
+            if let Ok(block_entities) = level.get::<_, &NbtList>(key) {
+                for tag in block_entities.iter() {
+                    if let NbtTag::Compound(compound) = tag {
+                        self.add_block_entity(compound);
+                    }
+                }
+            }
+        }
+
+        if let Ok(entities) = level.get::<_, &NbtList>("Entities") {
+            for tag in entities.iter() {
+                if let NbtTag::Compound(compound) = tag {
+                    self.add_entity(compound)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_section(&mut self, section: &NbtCompound, chunk_x: i32, chunk_z: i32, spanning: bool) -> Result<(), NucleationError> {
+        let section_y = match section.get::<_, &NbtTag>("Y")? {
+            NbtTag::Byte(y) => *y as i32,
+            NbtTag::Int(y) => *y,
+            _ => return Err("Invalid section Y".into()),
+        };
+        let base = (chunk_x * 16, section_y * 16, chunk_z * 16);
+        if !self.bounding_box.intersects_range(base.0, base.1, base.2, base.0 + 16, base.1 + 16, base.2 + 16) {
+            return Ok(());
+        }
+
+        let (indices, palette) = if let Ok(block_states) = section.get::<_, &NbtCompound>("block_states") {
+            // 1.18+: block_states { palette, data }
+            read_block_states(block_states)?
+        } else if let Ok(palette) = section.get::<_, &NbtList>("Palette") {
+            // 1.13 - 1.17: Palette and BlockStates next to each other
+            let palette = parse_palette(palette)?;
+            let data = section.get::<_, &[i64]>("BlockStates").unwrap_or(&[]);
+            (unpack_indices(data, spanning), palette)
+        } else if let Ok(blocks) = section.get::<_, &[i8]>("Blocks") {
+            // Pre-flattening: numeric ids with data nibbles
+            legacy_section(section, blocks)?
+        } else {
+            // Sections without block data only carry light or biomes
+            return Ok(());
+        };
+
+        let mapping: Vec<usize> = palette.into_iter()
+            .map(|block_state| self.region.get_or_insert_in_palette(block_state))
+            .collect();
+
+        // Sections are indexed y, z, x with x changing fastest
+        for (index, &palette_index) in indices.iter().enumerate() {
+            let x = base.0 + (index & 15) as i32;
+            let z = base.2 + ((index >> 4) & 15) as i32;
+            let y = base.1 + (index >> 8) as i32;
+            if let Some((lx, ly, lz)) = self.to_local(x, y, z) {
+                let block = *mapping.get(palette_index)
+                    .ok_or_else(|| format!("Invalid palette index {} at ({}, {}, {})", palette_index, x, y, z))?;
+                let region_index = self.region.coords_to_index(lx, ly, lz);
+                self.region.blocks.set(region_index, block);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_block_entity(&mut self, compound: &NbtCompound) {
+        let position = match (compound.get::<_, i32>("x"), compound.get::<_, i32>("y"), compound.get::<_, i32>("z")) {
+            (Ok(x), Ok(y), Ok(z)) => (x, y, z),
+            _ => return,
+        };
+        let local = match self.to_local(position.0, position.1, position.2) {
+            Some(local) => local,
+            None => return,
+        };
+
+        let id = compound.get::<_, &str>("id").unwrap_or("unknown");
+        let id = if id.contains(':') {
+            id.to_string()
+        } else {
+            match mcedit::modern_block_entity_id(id) {
+                Some(id) => id,
+                None => return,
+            }
+        };
+
+        let mut nbt = NbtMap::from_quartz_nbt(compound);
+        for key in ["id", "x", "y", "z", "keepPacked"] {
+            nbt.remove(key);
+        }
+        self.region.add_block_entity(BlockEntity { nbt, id, position: local });
+    }
+
+    fn add_entity(&mut self, compound: &NbtCompound) -> Result<(), NucleationError> {
+        let position = compound.get::<_, &NbtList>("Pos")?;
+        if position.len() != 3 {
+            return Err("Invalid entity position".into());
+        }
+        let (x, y, z) = (position.get::<f64>(0)?, position.get::<f64>(1)?, position.get::<f64>(2)?);
+        if self.to_local(x.floor() as i32, y.floor() as i32, z.floor() as i32).is_none() {
+            return Ok(());
+        }
+
+        let min = self.bounding_box.min;
+        let id = compound.get::<_, &str>("id")?;
+        let mut entity = Entity::new(
+            mcedit::modern_entity_id(id),
+            (x - min.0 as f64, y - min.1 as f64, z - min.2 as f64),
+        );
+        for (key, value) in compound.inner() {
+            if key == "id" || key == "Pos" {
+                continue;
+            }
+            entity.nbt.insert(key.clone(), NbtValue::from_quartz_nbt(value));
+        }
+        self.region.add_entity(entity);
+        Ok(())
+    }
+}
+
+fn parse_palette(palette: &NbtList) -> Result<Vec<BlockState>, NucleationError> {
+    let mut result = Vec::with_capacity(palette.len());
+    for tag in palette.iter() {
+        match tag {
+            NbtTag::Compound(compound) => result.push(BlockState::from_nbt(compound)?),
+            _ => return Err("Invalid palette entry".into()),
+        }
+    }
+    if result.is_empty() {
+        return Err("Section palette is empty".into());
+    }
+    Ok(result)
+}
+
+// Reads a 1.18+ `block_states` compound into the section's palette indices and palette
+pub(crate) fn read_block_states(block_states: &NbtCompound) -> Result<(Vec<usize>, Vec<BlockState>), NucleationError> {
+    let palette = parse_palette(block_states.get::<_, &NbtList>("palette")?)?;
+    let data = block_states.get::<_, &[i64]>("data").unwrap_or(&[]);
+    Ok((unpack_indices(data, false), palette))
+}
+
+// Packs section palette indices like the game does, with at least 4 bits per block and no data for a single entry palette
+pub(crate) fn write_block_states(indices: &[usize], palette: &[BlockState]) -> NbtCompound {
+    let mut block_states = NbtCompound::new();
+    block_states.insert("palette", NbtTag::List(NbtList::from(palette.iter().map(|block| block.to_nbt()).collect::<Vec<NbtTag>>())));
+    if palette.len() > 1 {
+        let bits = (usize::BITS - (palette.len() - 1).leading_zeros()).max(4) as usize;
+        block_states.insert("data", NbtTag::LongArray(pack_block_indices(indices.iter().copied(), bits, false)));
+    }
+    block_states
+}
+
+// Unpacks the 4096 palette indices of a section. Before 1.16 indices could span two longs.
+fn unpack_indices(data: &[i64], spanning: bool) -> Vec<usize> {
+    // A missing data array means the section is filled with the only palette entry
+    if data.is_empty() {
+        return vec![0; SECTION_VOLUME];
+    }
+
+    let bits = if spanning {
+        (data.len() * 64 / SECTION_VOLUME).max(1)
+    } else {
+        let per_long = SECTION_VOLUME.div_ceil(data.len());
+        (64 / per_long).max(1)
+    };
+    let mask = if bits >= 64 { u64::MAX } else { (1u64 << bits) - 1 };
+    let word = |index: usize| data.get(index).copied().unwrap_or(0) as u64;
+
+    (0..SECTION_VOLUME)
+        .map(|i| {
+            let value = if spanning {
+                let bit = i * bits;
+                let (index, offset) = (bit / 64, bit % 64);
+                let mut value = word(index) >> offset;
+                if offset + bits > 64 {
+                    value |= word(index + 1) << (64 - offset);
+                }
+                value
+            } else {
+                let per_long = 64 / bits;
+                word(i / per_long) >> ((i % per_long) * bits)
+            };
+            (value & mask) as usize
+        })
+        .collect()
+}
+
+fn legacy_section(section: &NbtCompound, blocks: &[i8]) -> Result<(Vec<usize>, Vec<BlockState>), NucleationError> {
+    if blocks.len() < SECTION_VOLUME {
+        return Err("Legacy section has too few blocks".into());
+    }
+    let data = section.get::<_, &[i8]>("Data").unwrap_or(&[]);
+    let add = section.get::<_, &[i8]>("Add").unwrap_or(&[]);
+    let nibble = |array: &[i8], index: usize| -> u8 {
+        let byte = array.get(index / 2).copied().unwrap_or(0) as u8;
+        if index.is_multiple_of(2) { byte & 0x0F } else { byte >> 4 }
+    };
+
+    let mut palette = Vec::new();
+    let mut seen: HashMap<(u16, u8), usize> = HashMap::new();
+    let indices = (0..SECTION_VOLUME)
+        .map(|index| {
+            let id = (blocks[index] as u8 as u16) | ((nibble(add, index) as u16) << 8);
+            let key = (id, nibble(data, index));
+            *seen.entry(key).or_insert_with(|| {
+                palette.push(mcedit::legacy_to_block_state(key.0, key.1));
+                palette.len() - 1
+            })
+        })
+        .collect();
+
+    Ok((indices, palette))
+}
+
+/// Pastes the schematic into a world directory so that schematic position (0, 0, 0) lands on `offset`.
+/// Region files, chunks and sections are created as needed and structure void leaves the world untouched.
+/// Only chunks in the 1.18+ format can be written to, and the schematic has to fit between y -64 and 319.
+/// Chunks too large for a region file are written to `c.<x>.<z>.mcc` files like the game does.
+pub fn paste_into_world<P: AsRef<Path>>(schematic: &UniversalSchematic, world_dir: P, offset: (i32, i32, i32)) -> Result<(), NucleationError> {
+    let world_dir = world_dir.as_ref();
+    let region_dir = world_dir.join("region");
+    let entities_dir = world_dir.join("entities");
+    std::fs::create_dir_all(&region_dir)?;
+
+    let paste = Paste::new(schematic, offset)?;
+    // Entities may lie outside the blocks, so the region files have to cover both
+    let entity_columns = paste.region.entities.iter().map(|entity| (
+        (entity.position.0 + offset.0 as f64).floor() as i32,
+        (entity.position.2 + offset.2 as f64).floor() as i32,
+    ));
+    let block_columns = paste.world_box().into_iter().flat_map(|world_box| [(world_box.min.0, world_box.min.2), (world_box.max.0, world_box.max.2)]);
+    let Some((min_x, min_z, max_x, max_z)) = block_columns.chain(entity_columns)
+        .fold(None, |bounds: Option<(i32, i32, i32, i32)>, (x, z)| Some(match bounds {
+            Some((min_x, min_z, max_x, max_z)) => (min_x.min(x), min_z.min(z), max_x.max(x), max_z.max(z)),
+            None => (x, z, x, z),
+        }))
+    else {
+        return Ok(());
+    };
+
+    for region_x in (min_x >> 9)..=(max_x >> 9) {
+        for region_z in (min_z >> 9)..=(max_z >> 9) {
+            let file_name = format!("r.{}.{}.mca", region_x, region_z);
+
+            let path = region_dir.join(&file_name);
+            let existing = if path.is_file() { Some(std::fs::read(&path)?) } else { None };
+            let updated = paste.write_blocks(existing.as_deref(), region_x, region_z, Some(&region_dir))?;
+            std::fs::write(&path, updated)?;
+
+            let path = entities_dir.join(&file_name);
+            let existing = if path.is_file() { Some(std::fs::read(&path)?) } else { None };
+            std::fs::create_dir_all(&entities_dir)?;
+            if let Some(updated) = paste.write_entities(existing.as_deref(), region_x, region_z, Some(&entities_dir))? {
+                std::fs::write(&path, updated)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Pastes the blocks and block entities of a schematic into the region file at (`region_x`, `region_z`),
+/// or into a new region file when `region_file` is `None`. Entities live in separate files since 1.17
+/// and are only written by `paste_into_world`. Fails for chunks too large for a region file, which
+/// `paste_into_world` writes to separate files.
+pub fn paste_into_region_file(region_file: Option<&[u8]>, region_x: i32, region_z: i32, schematic: &UniversalSchematic, offset: (i32, i32, i32)) -> Result<Vec<u8>, NucleationError> {
+    Paste::new(schematic, offset)?.write_blocks(region_file, region_x, region_z, None)
+}
+
+/// Writes a minimal void world containing only the schematic, placed on y 64 at the world origin.
+pub fn write_world<P: AsRef<Path>>(schematic: &UniversalSchematic, world_dir: P, level_name: &str) -> Result<(), NucleationError> {
+    let world_dir = world_dir.as_ref();
+    let region = schematic.get_merged_region();
+    let bounding_box = region.tight_bounding_box(&[STRUCTURE_VOID]).unwrap_or_else(|| region.get_bounding_box());
+    let offset = (-bounding_box.min.0, 64 - bounding_box.min.1, -bounding_box.min.2);
+    paste_into_world(schematic, world_dir, offset)?;
+
+    let (width, height, length) = bounding_box.get_dimensions();
+    let spawn = (width / 2, 64 + height, length / 2);
+    std::fs::write(world_dir.join("level.dat"), level_dat(level_name, data_version_for(schematic), spawn)?)?;
+    Ok(())
+}
+
+// 1.21.1, written when the schematic does not carry a data version we can write chunks for
+const DEFAULT_DATA_VERSION: i32 = 3955;
+// 1.18 (21w43a) moved sections out of the Level compound and introduced block_states
+const FLAT_CHUNK_DATA_VERSION: i32 = 2860;
+
+fn data_version_for(schematic: &UniversalSchematic) -> i32 {
+    schematic.metadata.mc_version
+        .filter(|&version| version >= FLAT_CHUNK_DATA_VERSION)
+        .unwrap_or(DEFAULT_DATA_VERSION)
+}
+
+type ChunkPayloads = Vec<Option<(u8, Vec<u8>)>>;
+
+fn read_region_payloads(region_file: Option<&[u8]>) -> Result<(ChunkPayloads, Vec<u32>), NucleationError> {
+    let mut payloads = vec![None; 1024];
+    let mut timestamps = vec![0u32; 1024];
+    if let Some(data) = region_file {
+        for slot in 0..1024 {
+            payloads[slot] = chunk_payload(data, slot)?.map(|(compression, payload)| (compression, payload.to_vec()));
+            let timestamp = &data[SECTOR_SIZE + slot * 4..SECTOR_SIZE + slot * 4 + 4];
+            timestamps[slot] = u32::from_be_bytes([timestamp[0], timestamp[1], timestamp[2], timestamp[3]]);
+        }
+    }
+    Ok((payloads, timestamps))
+}
+
+fn write_region_payloads(payloads: &ChunkPayloads, timestamps: &[u32]) -> Result<Vec<u8>, NucleationError> {
+    let mut out = vec![0u8; 2 * SECTOR_SIZE];
+    for (slot, payload) in payloads.iter().enumerate() {
+        let (compression, payload) = match payload {
+            Some(payload) => payload,
+            None => continue,
+        };
+
+        let sector = out.len() / SECTOR_SIZE;
+        let mut bytes = ((payload.len() + 1) as u32).to_be_bytes().to_vec();
+        bytes.push(*compression);
+        bytes.extend_from_slice(payload);
+        let sectors = bytes.len().div_ceil(SECTOR_SIZE);
+        if sectors > MAX_CHUNK_SECTORS {
+            return Err(format!("Chunk {} is too large for a region file", slot).into());
+        }
+        bytes.resize(sectors * SECTOR_SIZE, 0);
+        out.extend(bytes);
+
+        let entry = ((sector as u32) << 8) | sectors as u32;
+        out[slot * 4..slot * 4 + 4].copy_from_slice(&entry.to_be_bytes());
+        out[SECTOR_SIZE + slot * 4..SECTOR_SIZE + slot * 4 + 4].copy_from_slice(&timestamps[slot].to_be_bytes());
+    }
+    Ok(out)
+}
+
+fn encode_chunk(chunk: &NbtCompound) -> Result<(u8, Vec<u8>), NucleationError> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    quartz_nbt::io::write_nbt(&mut encoder, None, chunk, Flavor::Uncompressed)?;
+    Ok((2, encoder.finish()?))
+}
+
+// Encodes a chunk into its slot. Chunks needing more sectors than a region file entry can hold
+// go to c.<x>.<z>.mcc in `external`, leaving only the compression type with the external flag.
+fn store_chunk(payloads: &mut ChunkPayloads, chunk: &NbtCompound, chunk_x: i32, chunk_z: i32, external: Option<&Path>) -> Result<(), NucleationError> {
+    let slot = ((chunk_x & 31) + (chunk_z & 31) * 32) as usize;
+    let (compression, payload) = encode_chunk(chunk)?;
+    let path = external.map(|dir| dir.join(format!("c.{}.{}.mcc", chunk_x, chunk_z)));
+    if (payload.len() + 5).div_ceil(SECTOR_SIZE) > MAX_CHUNK_SECTORS {
+        let path = path.ok_or_else(|| format!("Chunk {}, {} is too large for a region file", chunk_x, chunk_z))?;
+        std::fs::write(path, payload)?;
+        payloads[slot] = Some((compression | EXTERNAL_CHUNK, Vec::new()));
+    } else {
+        if let Some(path) = path.filter(|path| path.is_file()) {
+            std::fs::remove_file(path)?;
+        }
+        payloads[slot] = Some((compression, payload));
+    }
+    Ok(())
+}
+
+fn section_y(section: &NbtCompound) -> Option<i32> {
+    match section.get::<_, &NbtTag>("Y").ok()? {
+        NbtTag::Byte(y) => Some(*y as i32),
+        NbtTag::Int(y) => Some(*y),
+        _ => None,
+    }
+}
+
+struct Paste {
+    region: Region,
+    // The blocks to paste, without the air regions are padded with
+    bounds: Option<BoundingBox>,
+    offset: (i32, i32, i32),
+    data_version: i32,
+    timestamp: u32,
+}
+
+impl Paste {
+    fn new(schematic: &UniversalSchematic, offset: (i32, i32, i32)) -> Result<Self, NucleationError> {
+        let region = schematic.get_merged_region();
+        let bounds = region.tight_bounding_box(&[STRUCTURE_VOID]);
+        let paste = Paste {
+            region,
+            bounds,
+            offset,
+            data_version: data_version_for(schematic),
+            timestamp: (current_time_millis() / 1000) as u32,
+        };
+
+        if let Some(blocks) = &paste.bounds {
+            let (min_y, max_y) = (blocks.min.1 + offset.1, blocks.max.1 + offset.1);
+            if min_y < MIN_WORLD_Y || max_y > MAX_WORLD_Y {
+                return Err(format!(
+                    "Blocks from y {} to {} do not fit the world height of y {} to {}",
+                    min_y, max_y, MIN_WORLD_Y, MAX_WORLD_Y
+                ).into());
+            }
+        }
+        Ok(paste)
+    }
+
+    // World positions covered by the schematic's blocks, `None` when it has none
+    fn world_box(&self) -> Option<BoundingBox> {
+        let bounds = self.bounds.as_ref()?;
+        let (dx, dy, dz) = self.offset;
+        Some(BoundingBox::new(
+            (bounds.min.0 + dx, bounds.min.1 + dy, bounds.min.2 + dz),
+            (bounds.max.0 + dx, bounds.max.1 + dy, bounds.max.2 + dz),
+        ))
+    }
+
+    // The block to place at a world position, structure void and the padding around the
+    // blocks mean keep the world's block
+    fn block_at(&self, x: i32, y: i32, z: i32) -> Option<&BlockState> {
+        let position = (x - self.offset.0, y - self.offset.1, z - self.offset.2);
+        if !self.bounds.as_ref()?.contains(position) {
+            return None;
+        }
+        let index = self.region.get_block_index(position.0, position.1, position.2)?;
+        self.region.palette.get(index).filter(|block| block.name != STRUCTURE_VOID)
+    }
+
+    fn chunk_range(&self, region_x: i32, region_z: i32) -> Option<(std::ops::RangeInclusive<i32>, std::ops::RangeInclusive<i32>)> {
+        let world_box = self.world_box()?;
+        Some((
+            (world_box.min.0 >> 4).max(region_x * 32)..=(world_box.max.0 >> 4).min(region_x * 32 + 31),
+            (world_box.min.2 >> 4).max(region_z * 32)..=(world_box.max.2 >> 4).min(region_z * 32 + 31),
+        ))
+    }
+
+    fn write_blocks(&self, region_file: Option<&[u8]>, region_x: i32, region_z: i32, external: Option<&Path>) -> Result<Vec<u8>, NucleationError> {
+        let (mut payloads, mut timestamps) = read_region_payloads(region_file)?;
+
+        let Some((chunks_x, chunks_z)) = self.chunk_range(region_x, region_z) else {
+            return write_region_payloads(&payloads, &timestamps);
+        };
+        for chunk_x in chunks_x {
+            for chunk_z in chunks_z.clone() {
+                let slot = ((chunk_x & 31) + (chunk_z & 31) * 32) as usize;
+                let mut chunk = match &payloads[slot] {
+                    Some((compression, payload)) => decode_chunk(*compression, payload, external.map(|dir| (dir, chunk_x, chunk_z)))?,
+                    None => self.new_chunk(chunk_x, chunk_z),
+                };
+                self.paste_chunk(&mut chunk, chunk_x, chunk_z)?;
+                store_chunk(&mut payloads, &chunk, chunk_x, chunk_z, external)?;
+                timestamps[slot] = self.timestamp;
+            }
+        }
+
+        write_region_payloads(&payloads, &timestamps)
+    }
+
+    fn write_entities(&self, entity_file: Option<&[u8]>, region_x: i32, region_z: i32, external: Option<&Path>) -> Result<Option<Vec<u8>>, NucleationError> {
+        let mut by_chunk: HashMap<(i32, i32), Vec<NbtTag>> = HashMap::new();
+        for entity in &self.region.entities {
+            let x = entity.position.0 + self.offset.0 as f64;
+            let y = entity.position.1 + self.offset.1 as f64;
+            let z = entity.position.2 + self.offset.2 as f64;
+            let chunk = ((x.floor() as i32) >> 4, (z.floor() as i32) >> 4);
+            if chunk.0 >> 5 != region_x || chunk.1 >> 5 != region_z {
+                continue;
+            }
+
+            let mut compound = entity.nbt.to_quartz_nbt();
+            compound.insert("id", NbtTag::String(entity.id.clone()));
+            compound.insert("Pos", NbtTag::List(NbtList::from(vec![NbtTag::Double(x), NbtTag::Double(y), NbtTag::Double(z)])));
+            by_chunk.entry(chunk).or_default().push(NbtTag::Compound(compound));
+        }
+        if by_chunk.is_empty() {
+            return Ok(None);
+        }
+
+        let (mut payloads, mut timestamps) = read_region_payloads(entity_file)?;
+        for ((chunk_x, chunk_z), entities) in by_chunk {
+            let slot = ((chunk_x & 31) + (chunk_z & 31) * 32) as usize;
+            let mut chunk = match &payloads[slot] {
+                Some((compression, payload)) => decode_chunk(*compression, payload, external.map(|dir| (dir, chunk_x, chunk_z)))?,
+                None => {
+                    let mut chunk = NbtCompound::new();
+                    chunk.insert("DataVersion", NbtTag::Int(self.data_version));
+                    chunk.insert("Position", NbtTag::IntArray(vec![chunk_x, chunk_z]));
+                    chunk
+                }
+            };
+            let mut list = chunk.get::<_, &NbtList>("Entities").cloned().unwrap_or_default();
+            list.extend(entities);
+            chunk.insert("Entities", NbtTag::List(list));
+            store_chunk(&mut payloads, &chunk, chunk_x, chunk_z, external)?;
+            timestamps[slot] = self.timestamp;
+        }
+
+        write_region_payloads(&payloads, &timestamps).map(Some)
+    }
+
+    fn new_chunk(&self, chunk_x: i32, chunk_z: i32) -> NbtCompound {
+        let mut structures = NbtCompound::new();
+        structures.insert("References", NbtTag::Compound(NbtCompound::new()));
+        structures.insert("starts", NbtTag::Compound(NbtCompound::new()));
+
+        let mut chunk = NbtCompound::new();
+        chunk.insert("DataVersion", NbtTag::Int(self.data_version));
+        chunk.insert("xPos", NbtTag::Int(chunk_x));
+        chunk.insert("zPos", NbtTag::Int(chunk_z));
+        chunk.insert("yPos", NbtTag::Int(-4));
+        chunk.insert("Status", NbtTag::String("minecraft:full".to_string()));
+        chunk.insert("LastUpdate", NbtTag::Long(0));
+        chunk.insert("InhabitedTime", NbtTag::Long(0));
+        chunk.insert("sections", NbtTag::List(NbtList::new()));
+        chunk.insert("block_entities", NbtTag::List(NbtList::new()));
+        chunk.insert("structures", NbtTag::Compound(structures));
+        chunk
+    }
+
+    fn paste_chunk(&self, chunk: &mut NbtCompound, chunk_x: i32, chunk_z: i32) -> Result<(), NucleationError> {
+        let data_version = chunk.get::<_, i32>("DataVersion").unwrap_or(0);
+        if chunk.contains_key("Level") || data_version < FLAT_CHUNK_DATA_VERSION {
+            return Err(NucleationError::UnsupportedVersion { format: "chunk", version: data_version });
+        }
+
+        let Some(world_box) = self.world_box() else {
+            return Ok(());
+        };
+        let mut sections: Vec<NbtCompound> = chunk.get::<_, &NbtList>("sections")
+            .map(|list| list.iter().filter_map(|tag| match tag {
+                NbtTag::Compound(section) => Some(section.clone()),
+                _ => None,
+            }).collect())
+            .unwrap_or_default();
+
+        for section_index in (world_box.min.1 >> 4)..=(world_box.max.1 >> 4) {
+            let position = match sections.iter().position(|section| section_y(section) == Some(section_index)) {
+                Some(position) => position,
+                None => {
+                    let mut section = NbtCompound::new();
+                    section.insert("Y", NbtTag::Byte(section_index as i8));
+                    sections.push(section);
+                    sections.len() - 1
+                }
+            };
+            self.paste_section(&mut sections[position], (chunk_x * 16, section_index * 16, chunk_z * 16))?;
+        }
+        sections.sort_by_key(|section| section_y(section).unwrap_or(0));
+        chunk.insert("sections", NbtTag::List(NbtList::from(sections.into_iter().map(NbtTag::Compound).collect::<Vec<NbtTag>>())));
+
+        // Let the game recompute light and heightmaps for the changed blocks
+        chunk.insert("isLightOn", NbtTag::Byte(0));
+        chunk.inner_mut().shift_remove("Heightmaps");
+
+        let mut block_entities = NbtList::new();
+        if let Ok(existing) = chunk.get::<_, &NbtList>("block_entities") {
+            for tag in existing.iter() {
+                if let NbtTag::Compound(compound) = tag {
+                    let position = (compound.get::<_, i32>("x"), compound.get::<_, i32>("y"), compound.get::<_, i32>("z"));
+                    if let (Ok(x), Ok(y), Ok(z)) = position {
+                        if self.block_at(x, y, z).is_some() {
+                            continue;
+                        }
+                    }
+                }
+                block_entities.push(tag.clone());
+            }
+        }
+        for block_entity in self.region.block_entities.values() {
+            let (x, y, z) = block_entity.position;
+            let (x, y, z) = (x + self.offset.0, y + self.offset.1, z + self.offset.2);
+            if x >> 4 != chunk_x || z >> 4 != chunk_z {
+                continue;
+            }
+            let mut compound = NbtCompound::new();
+            for (key, value) in &block_entity.nbt {
+                if key == "Id" || key == "Pos" {
+                    continue;
+                }
+                compound.insert(key, value.to_quartz_nbt());
+            }
+            compound.insert("id", NbtTag::String(block_entity.id.clone()));
+            compound.insert("x", NbtTag::Int(x));
+            compound.insert("y", NbtTag::Int(y));
+            compound.insert("z", NbtTag::Int(z));
+            compound.insert("keepPacked", NbtTag::Byte(0));
+            block_entities.push(NbtTag::Compound(compound));
+        }
+        chunk.insert("block_entities", NbtTag::List(block_entities));
+
+        Ok(())
+    }
+
+    fn paste_section(&self, section: &mut NbtCompound, base: (i32, i32, i32)) -> Result<(), NucleationError> {
+        let (mut indices, mut palette) = match section.get::<_, &NbtCompound>("block_states") {
+            Ok(block_states) => read_block_states(block_states)?,
+            Err(_) => (vec![0; SECTION_VOLUME], vec![BlockState::new("minecraft:air".to_string())]),
+        };
+
+        let Some(world_box) = self.world_box() else {
+            return Ok(());
+        };
+        let mut changed = false;
+        for y in world_box.min.1.max(base.1)..=world_box.max.1.min(base.1 + 15) {
+            for z in world_box.min.2.max(base.2)..=world_box.max.2.min(base.2 + 15) {
+                for x in world_box.min.0.max(base.0)..=world_box.max.0.min(base.0 + 15) {
+                    let block = match self.block_at(x, y, z) {
+                        Some(block) => block,
+                        None => continue,
+                    };
+                    let palette_index = match palette.iter().position(|entry| entry == block) {
+                        Some(palette_index) => palette_index,
+                        None => {
+                            palette.push(block.clone());
+                            palette.len() - 1
+                        }
+                    };
+                    let index = (((y - base.1) << 8) | ((z - base.2) << 4) | (x - base.0)) as usize;
+                    indices[index] = palette_index;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return Ok(());
+        }
+
+        // Drop palette entries that were entirely overwritten
+        let mut remapped: Vec<Option<usize>> = vec![None; palette.len()];
+        let mut compacted = Vec::new();
+        for index in indices.iter_mut() {
+            *index = *remapped[*index].get_or_insert_with(|| {
+                compacted.push(palette[*index].clone());
+                compacted.len() - 1
+            });
+        }
+
+        section.insert("block_states", NbtTag::Compound(write_block_states(&indices, &compacted)));
+        section.inner_mut().shift_remove("BlockLight");
+        section.inner_mut().shift_remove("SkyLight");
+        if !section.contains_key("biomes") {
+            let mut biomes = NbtCompound::new();
+            biomes.insert("palette", NbtTag::List(NbtList::from(vec![NbtTag::String("minecraft:plains".to_string())])));
+            section.insert("biomes", NbtTag::Compound(biomes));
+        }
+
+        Ok(())
+    }
+}
+
+fn level_dat(level_name: &str, data_version: i32, spawn: (i32, i32, i32)) -> Result<Vec<u8>, NucleationError> {
+    let string = |value: &str| NbtTag::String(value.to_string());
+    let compound = |entries: Vec<(&str, NbtTag)>| {
+        let mut compound = NbtCompound::new();
+        for (key, value) in entries {
+            compound.insert(key, value);
+        }
+        NbtTag::Compound(compound)
+    };
+
+    // A void superflat overworld, the other dimensions use their vanilla generators
+    let overworld = compound(vec![
+        ("type", string("minecraft:overworld")),
+        ("generator", compound(vec![
+            ("type", string("minecraft:flat")),
+            ("settings", compound(vec![
+                ("biome", string("minecraft:the_void")),
+                ("features", NbtTag::Byte(0)),
+                ("lakes", NbtTag::Byte(0)),
+                ("layers", NbtTag::List(NbtList::from(vec![compound(vec![
+                    ("block", string("minecraft:air")),
+                    ("height", NbtTag::Int(1)),
+                ])]))),
+                ("structure_overrides", NbtTag::List(NbtList::new())),
+            ])),
+        ])),
+    ]);
+    let nether = compound(vec![
+        ("type", string("minecraft:the_nether")),
+        ("generator", compound(vec![
+            ("type", string("minecraft:noise")),
+            ("settings", string("minecraft:nether")),
+            ("biome_source", compound(vec![
+                ("type", string("minecraft:multi_noise")),
+                ("preset", string("minecraft:nether")),
+            ])),
+        ])),
+    ]);
+    let end = compound(vec![
+        ("type", string("minecraft:the_end")),
+        ("generator", compound(vec![
+            ("type", string("minecraft:noise")),
+            ("settings", string("minecraft:end")),
+            ("biome_source", compound(vec![("type", string("minecraft:the_end"))])),
+        ])),
+    ]);
+
+    let mut version = vec![
+        ("Id", NbtTag::Int(data_version)),
+        ("Series", string("main")),
+        ("Snapshot", NbtTag::Byte(0)),
+    ];
+    if data_version == DEFAULT_DATA_VERSION {
+        version.push(("Name", string("1.21.1")));
+    }
+
+    let last_played = current_time_millis() as i64;
+
+    let data = compound(vec![
+        ("DataVersion", NbtTag::Int(data_version)),
+        ("version", NbtTag::Int(19133)),
+        ("Version", compound(version)),
+        ("LevelName", string(level_name)),
+        ("GameType", NbtTag::Int(1)),
+        ("Difficulty", NbtTag::Byte(0)),
+        ("hardcore", NbtTag::Byte(0)),
+        ("allowCommands", NbtTag::Byte(1)),
+        ("initialized", NbtTag::Byte(1)),
+        ("SpawnX", NbtTag::Int(spawn.0)),
+        ("SpawnY", NbtTag::Int(spawn.1)),
+        ("SpawnZ", NbtTag::Int(spawn.2)),
+        ("SpawnAngle", NbtTag::Float(0.0)),
+        ("Time", NbtTag::Long(0)),
+        ("DayTime", NbtTag::Long(6000)),
+        ("LastPlayed", NbtTag::Long(last_played)),
+        ("raining", NbtTag::Byte(0)),
+        ("thundering", NbtTag::Byte(0)),
+        ("GameRules", compound(vec![
+            ("doDaylightCycle", string("false")),
+            ("doWeatherCycle", string("false")),
+            ("doMobSpawning", string("false")),
+        ])),
+        ("DataPacks", compound(vec![
+            ("Enabled", NbtTag::List(NbtList::from(vec![string("vanilla")]))),
+            ("Disabled", NbtTag::List(NbtList::new())),
+        ])),
+        ("WorldGenSettings", compound(vec![
+            ("seed", NbtTag::Long(0)),
+            ("generate_features", NbtTag::Byte(0)),
+            ("bonus_chest", NbtTag::Byte(0)),
+            ("dimensions", compound(vec![
+                ("minecraft:overworld", overworld),
+                ("minecraft:the_nether", nether),
+                ("minecraft:the_end", end),
+            ])),
+        ])),
+    ]);
+
+    let mut root = NbtCompound::new();
+    root.insert("Data", data);
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    quartz_nbt::io::write_nbt(&mut encoder, None, &root, Flavor::Uncompressed)?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use crate::block_position::BlockPosition;
+    use crate::item::ItemStack;
+
+    fn palette(names: &[&str]) -> NbtList {
+        NbtList::from(names.iter().map(|name| BlockState::new(name.to_string()).to_nbt()).collect::<Vec<NbtTag>>())
+    }
+
+    // A section whose block at local (x, y, z) uses palette index (x + y + z) % palette_len
+    fn section_indices(palette_len: usize) -> Vec<usize> {
+        (0..SECTION_VOLUME).map(|i| ((i & 15) + (i >> 8) + ((i >> 4) & 15)) % palette_len).collect()
+    }
+
+    fn region_file(chunks: &[(usize, NbtCompound)]) -> Vec<u8> {
+        let mut data = vec![0u8; 2 * SECTOR_SIZE];
+        for (slot, chunk) in chunks {
+            let mut nbt = Vec::new();
+            quartz_nbt::io::write_nbt(&mut nbt, None, chunk, Flavor::Uncompressed).unwrap();
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&nbt).unwrap();
+            let compressed = encoder.finish().unwrap();
+
+            let sector = data.len() / SECTOR_SIZE;
+            let mut payload = ((compressed.len() + 1) as u32).to_be_bytes().to_vec();
+            payload.push(2);
+            payload.extend(compressed);
+            let sectors = payload.len().div_ceil(SECTOR_SIZE);
+            payload.resize(sectors * SECTOR_SIZE, 0);
+            data.extend(payload);
+
+            let entry = ((sector as u32) << 8) | sectors as u32;
+            data[slot * 4..slot * 4 + 4].copy_from_slice(&entry.to_be_bytes());
+        }
+        data
+    }
+
+    fn modern_chunk(chunk_x: i32, chunk_z: i32) -> NbtCompound {
+        let names = ["minecraft:stone", "minecraft:dirt", "minecraft:oak_planks", "minecraft:glass", "minecraft:sand"];
+        let mut block_states = NbtCompound::new();
+        block_states.insert("palette", NbtTag::List(palette(&names)));
+        block_states.insert("data", NbtTag::LongArray(pack_block_indices(section_indices(names.len()).into_iter(), 4, false)));
+
+        let mut section = NbtCompound::new();
+        section.insert("Y", NbtTag::Byte(-1));
+        section.insert("block_states", NbtTag::Compound(block_states));
+
+        // A section that is entirely air, stored without data
+        let mut air_states = NbtCompound::new();
+        air_states.insert("palette", NbtTag::List(palette(&["minecraft:air"])));
+        let mut air_section = NbtCompound::new();
+        air_section.insert("Y", NbtTag::Byte(0));
+        air_section.insert("block_states", NbtTag::Compound(air_states));
+
+        let mut chest = NbtCompound::new();
+        chest.insert("id", NbtTag::String("minecraft:chest".to_string()));
+        chest.insert("x", NbtTag::Int(chunk_x * 16 + 2));
+        chest.insert("y", NbtTag::Int(-14));
+        chest.insert("z", NbtTag::Int(chunk_z * 16 + 3));
+        chest.insert("CustomName", NbtTag::String("Loot".to_string()));
+
+        let mut chunk = NbtCompound::new();
+        chunk.insert("DataVersion", NbtTag::Int(3700));
+        chunk.insert("xPos", NbtTag::Int(chunk_x));
+        chunk.insert("zPos", NbtTag::Int(chunk_z));
+        chunk.insert("sections", NbtTag::List(NbtList::from(vec![NbtTag::Compound(section), NbtTag::Compound(air_section)])));
+        chunk.insert("block_entities", NbtTag::List(NbtList::from(vec![NbtTag::Compound(chest)])));
+        chunk
+    }
+
+    fn entity_chunk(chunk_x: i32, chunk_z: i32) -> NbtCompound {
+        let mut pig = NbtCompound::new();
+        pig.insert("id", NbtTag::String("minecraft:pig".to_string()));
+        pig.insert("Pos", NbtTag::List(NbtList::from(vec![
+            NbtTag::Double(chunk_x as f64 * 16.0 + 4.5),
+            NbtTag::Double(-15.0),
+            NbtTag::Double(chunk_z as f64 * 16.0 + 4.5),
+        ])));
+
+        let mut chunk = NbtCompound::new();
+        chunk.insert("DataVersion", NbtTag::Int(3700));
+        chunk.insert("Position", NbtTag::IntArray(vec![chunk_x, chunk_z]));
+        chunk.insert("Entities", NbtTag::List(NbtList::from(vec![NbtTag::Compound(pig)])));
+        chunk
+    }
+
+    #[test]
+    fn test_unpack_indices() {
+        let indices = section_indices(33);
+        assert_eq!(unpack_indices(&pack_block_indices(indices.iter().copied(), 6, false), false), indices);
+        assert_eq!(unpack_indices(&pack_block_indices(indices.iter().copied(), 6, true), true), indices);
+        assert_eq!(unpack_indices(&[], false), vec![0; SECTION_VOLUME]);
+    }
+
+    #[test]
+    fn test_from_region_files() {
+        // Chunk (-1, 0) is stored in slot 31 of r.-1.0.mca
+        let blocks = region_file(&[(31, modern_chunk(-1, 0))]);
+        let entities = region_file(&[(31, entity_chunk(-1, 0))]);
+
+        let bounding_box = BoundingBox::new((-16, -16, 0), (-1, -12, 7));
+        let schematic = from_region_files(&[&blocks, &entities], &bounding_box).unwrap();
+        assert_eq!(schematic.get_dimensions(), (16, 5, 8));
+        assert_eq!(schematic.metadata.mc_version, Some(3700));
+
+        let names = ["minecraft:stone", "minecraft:dirt", "minecraft:oak_planks", "minecraft:glass", "minecraft:sand"];
+        for (x, y, z) in [(0, 0, 0), (5, 3, 7), (15, 4, 2)] {
+            // Section y 0 of section -1 sits at world y -16
+            assert_eq!(schematic.get_block(x, y, z).unwrap().name, names[(x + y + z) as usize % names.len()]);
+        }
+
+        let chest = schematic.get_block_entity(BlockPosition { x: 2, y: 2, z: 3 }).unwrap();
+        assert_eq!(chest.id, "minecraft:chest");
+
+        let region = schematic.get_region("Main").unwrap();
+        assert_eq!(region.entities.len(), 1);
+        assert_eq!(region.entities[0].position, (4.5, 1.0, 4.5));
+    }
+
+    #[test]
+    fn test_from_world_legacy_chunks() {
+        // Pre-1.16 chunk with indices spanning longs, plus a pre-flattening section above it
+        let names = ["minecraft:stone", "minecraft:dirt", "minecraft:oak_planks", "minecraft:glass", "minecraft:sand"];
+        let mut section = NbtCompound::new();
+        section.insert("Y", NbtTag::Byte(0));
+        section.insert("Palette", NbtTag::List(palette(&names)));
+        section.insert("BlockStates", NbtTag::LongArray(pack_block_indices(section_indices(names.len()).into_iter(), 4, true)));
+
+        let mut legacy_section = NbtCompound::new();
+        legacy_section.insert("Y", NbtTag::Byte(1));
+        let mut blocks = vec![0i8; SECTION_VOLUME];
+        blocks[0] = 35; // white wool
+        let mut data = vec![0i8; SECTION_VOLUME / 2];
+        data[0] = 14; // red
+        legacy_section.insert("Blocks", NbtTag::ByteArray(blocks));
+        legacy_section.insert("Data", NbtTag::ByteArray(data));
+
+        let mut level = NbtCompound::new();
+        level.insert("xPos", NbtTag::Int(1));
+        level.insert("zPos", NbtTag::Int(0));
+        level.insert("Sections", NbtTag::List(NbtList::from(vec![NbtTag::Compound(section), NbtTag::Compound(legacy_section)])));
+        let mut chunk = NbtCompound::new();
+        chunk.insert("DataVersion", NbtTag::Int(1976));
+        chunk.insert("Level", NbtTag::Compound(level));
+
+        let world_dir = std::env::temp_dir().join(format!("nucleation_anvil_test_{}", std::process::id()));
+        std::fs::create_dir_all(world_dir.join("region")).unwrap();
+        std::fs::write(world_dir.join("region").join("r.0.0.mca"), region_file(&[(1, chunk)])).unwrap();
+
+        let schematic = from_world(&world_dir, &BoundingBox::new((16, 0, 0), (19, 16, 3)));
+        std::fs::remove_dir_all(&world_dir).unwrap();
+        let schematic = schematic.unwrap();
+
+        assert_eq!(schematic.get_block(3, 2, 1).unwrap().name, names[6 % names.len()]);
+        assert_eq!(schematic.get_block(0, 16, 0).unwrap().name, "minecraft:red_wool");
+        assert_eq!(schematic.get_block(1, 16, 0).unwrap().name, "minecraft:air");
+
+        assert!(from_world(std::env::temp_dir().join("nucleation_missing_world"), &BoundingBox::new((0, 0, 0), (1, 1, 1))).is_err());
+    }
+
+    #[test]
+    fn test_paste_into_new_region_file() {
+        let mut schematic = UniversalSchematic::new("Paste".to_string());
+        schematic.set_block(0, 0, 0, BlockState::new("minecraft:stone".to_string()));
+        schematic.set_block(17, 3, 2, BlockState::new("minecraft:gold_block".to_string()));
+        let chest = BlockEntity::create_chest((1, 0, 0), vec![ItemStack::new("minecraft:diamond", 3).with_slot(0)]);
+        schematic.set_block(1, 0, 0, BlockState::new("minecraft:chest".to_string()));
+        schematic.set_block_entity(BlockPosition { x: 1, y: 0, z: 0 }, chest);
+
+        // Schematic origin lands on (-8, 100, -8), across four chunks of r.-1.-1.mca, r.0.-1.mca and r.-1.0.mca, r.0.0.mca
+        let offset = (-8, 100, -8);
+        let first = paste_into_region_file(None, -1, -1, &schematic, offset).unwrap();
+        let second = paste_into_region_file(None, 0, -1, &schematic, offset).unwrap();
+        let third = paste_into_region_file(None, 0, 0, &schematic, offset).unwrap();
+        assert_eq!(first.len() % SECTOR_SIZE, 0);
+
+        let bounding_box = BoundingBox::new((-8, 100, -8), (9, 103, -6));
+        let read = from_region_files(&[&first, &second, &third], &bounding_box).unwrap();
+        assert_eq!(read.metadata.mc_version, Some(DEFAULT_DATA_VERSION));
+        assert_eq!(read.get_block(0, 0, 0).unwrap().name, "minecraft:stone");
+        assert_eq!(read.get_block(17, 3, 2).unwrap().name, "minecraft:gold_block");
+        assert_eq!(read.get_block(5, 1, 1).unwrap().name, "minecraft:air");
+
+        let chest = read.get_block_entity(BlockPosition { x: 1, y: 0, z: 0 }).unwrap();
+        assert_eq!(chest.id, "minecraft:chest");
+        assert!(chest.nbt.get("Items").is_some());
+    }
+
+    #[test]
+    fn test_paste_over_existing_chunk() {
+        let names = ["minecraft:stone", "minecraft:dirt", "minecraft:oak_planks", "minecraft:glass", "minecraft:sand"];
+        let existing = region_file(&[(31, modern_chunk(-1, 0))]);
+
+        // Overwrite section -1 of chunk (-1, 0) except for a structure void column, and the old chest with it
+        let mut region = Region::new("Main".to_string(), (0, 0, 0), (16, 16, 16));
+        for (x, y, z) in (0..16).flat_map(|x| (0..16).flat_map(move |y| (0..16).map(move |z| (x, y, z)))) {
+            let name = if x == 0 && z == 0 { STRUCTURE_VOID } else { "minecraft:glass" };
+            region.set_block(x, y, z, BlockState::new(name.to_string()));
+        }
+        let mut schematic = UniversalSchematic::new("Paste".to_string());
+        schematic.add_region(region);
+
+        let updated = paste_into_region_file(Some(&existing), -1, 0, &schematic, (-16, -16, 0)).unwrap();
+        let chunk = read_chunk(&updated, 31, None).unwrap().unwrap();
+        assert_eq!(chunk.get::<_, i32>("DataVersion").unwrap(), 3700);
+        assert!(chunk.get::<_, &NbtList>("block_entities").unwrap().is_empty());
+
+        let sections = chunk.get::<_, &NbtList>("sections").unwrap();
+        let section = match (**sections).first() {
+            Some(NbtTag::Compound(section)) => section,
+            _ => panic!("Missing section"),
+        };
+        assert_eq!(section_y(section), Some(-1));
+        let block_states = section.get::<_, &NbtCompound>("block_states").unwrap();
+        let palette = parse_palette(block_states.get::<_, &NbtList>("palette").unwrap()).unwrap();
+        let indices = unpack_indices(block_states.get::<_, &[i64]>("data").unwrap(), false);
+        // Only what remains of the original column survives, glass was already in the palette
+        assert_eq!(palette.len(), names.len());
+        for y in 0..16 {
+            assert_eq!(palette[indices[y << 8]].name, names[y % names.len()]);
+            assert_eq!(palette[indices[(y << 8) | 0x11]].name, "minecraft:glass");
+        }
+
+        // The untouched air section keeps its single entry palette
+        let air = match (**sections).get(1) {
+            Some(NbtTag::Compound(section)) => section,
+            _ => panic!("Missing section"),
+        };
+        assert!(!air.get::<_, &NbtCompound>("block_states").unwrap().contains_key("data"));
+
+        let mut legacy = modern_chunk(-1, 0);
+        legacy.insert("DataVersion", NbtTag::Int(1976));
+        assert!(paste_into_region_file(Some(&region_file(&[(31, legacy)])), -1, 0, &schematic, (-16, -16, 0)).is_err());
+    }
+
+    #[test]
+    fn test_paste_keeps_terrain_around_blocks() {
+        let names = ["minecraft:stone", "minecraft:dirt", "minecraft:oak_planks", "minecraft:glass", "minecraft:sand"];
+        let existing = region_file(&[(31, modern_chunk(-1, 0))]);
+
+        // The default region grows with air past the second block, which must not be pasted
+        let mut schematic = UniversalSchematic::new("Paste".to_string());
+        schematic.set_block(0, 0, 0, BlockState::new("minecraft:diamond_block".to_string()));
+        schematic.set_block(1, 0, 0, BlockState::new("minecraft:diamond_block".to_string()));
+        assert!(schematic.get_bounding_box().max.0 > 15);
+
+        let updated = paste_into_region_file(Some(&existing), -1, 0, &schematic, (-16, -16, 0)).unwrap();
+        let chunk = read_chunk(&updated, 31, None).unwrap().unwrap();
+        assert_eq!(chunk.get::<_, &NbtList>("block_entities").unwrap().len(), 1);
+        let sections = chunk.get::<_, &NbtList>("sections").unwrap();
+        let section = match (**sections).first() {
+            Some(NbtTag::Compound(section)) => section,
+            _ => panic!("Missing section"),
+        };
+        let block_states = section.get::<_, &NbtCompound>("block_states").unwrap();
+        let palette = parse_palette(block_states.get::<_, &NbtList>("palette").unwrap()).unwrap();
+        let indices = unpack_indices(block_states.get::<_, &[i64]>("data").unwrap(), false);
+        let original = section_indices(names.len());
+        assert_eq!(palette[indices[0]].name, "minecraft:diamond_block");
+        assert_eq!(palette[indices[1]].name, "minecraft:diamond_block");
+        for index in 2..4096 {
+            assert_eq!(palette[indices[index]].name, names[original[index]]);
+        }
+    }
+
+    #[test]
+    fn test_write_world() {
+        let mut schematic = UniversalSchematic::new("World".to_string());
+        schematic.set_block(-2, -1, 3, BlockState::new("minecraft:diamond_block".to_string()));
+        schematic.set_block(2, 1, 5, BlockState::new("minecraft:emerald_block".to_string()));
+        let mut region = schematic.get_region("Main").unwrap().clone();
+        region.entities.push(Entity::new("minecraft:pig".to_string(), (0.5, 2.0, 4.5)));
+        schematic.add_region(region);
+
+        let world_dir = std::env::temp_dir().join(format!("nucleation_anvil_write_test_{}", std::process::id()));
+        let result = write_world(&schematic, &world_dir, "Nucleation");
+        let level = std::fs::read(world_dir.join("level.dat"));
+        let read = from_world(&world_dir, &BoundingBox::new((0, 64, 0), (4, 67, 2)));
+        std::fs::remove_dir_all(&world_dir).unwrap();
+        result.unwrap();
+
+        let (level, _) = quartz_nbt::io::read_nbt(&mut std::io::Cursor::new(level.unwrap()), Flavor::GzCompressed).unwrap();
+        let data = level.get::<_, &NbtCompound>("Data").unwrap();
+        assert_eq!(data.get::<_, &str>("LevelName").unwrap(), "Nucleation");
+        assert_eq!(data.get::<_, i32>("DataVersion").unwrap(), DEFAULT_DATA_VERSION);
+
+        let read = read.unwrap();
+        assert_eq!(read.get_block(0, 0, 0).unwrap().name, "minecraft:diamond_block");
+        assert_eq!(read.get_block(4, 2, 2).unwrap().name, "minecraft:emerald_block");
+        let entities = &read.get_region("Main").unwrap().entities;
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].position, (2.5, 3.0, 1.5));
+    }
+
+    #[test]
+    fn test_world_height() {
+        let mut schematic = UniversalSchematic::new("Tall".to_string());
+        schematic.set_block(0, 0, 0, BlockState::new("minecraft:stone".to_string()));
+        schematic.set_block(0, 10, 0, BlockState::new("minecraft:stone".to_string()));
+        assert!(paste_into_region_file(None, 0, 0, &schematic, (0, 310, 0)).is_err());
+        assert!(paste_into_region_file(None, 0, 0, &schematic, (0, -70, 0)).is_err());
+        // The region reaches above the world, but only with air
+        assert!(schematic.get_bounding_box().max.1 + 309 > MAX_WORLD_Y);
+        assert!(paste_into_region_file(None, 0, 0, &schematic, (0, 309, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_external_chunk() {
+        use rand::{RngCore, SeedableRng};
+        // Random bytes do not compress, so the chunk needs more than 255 sectors
+        let mut noise = vec![0u8; 1_200_000];
+        rand::rngs::StdRng::seed_from_u64(7).fill_bytes(&mut noise);
+        let mut schematic = UniversalSchematic::new("Large".to_string());
+        schematic.set_block(0, 0, 0, BlockState::new("minecraft:chest".to_string()));
+        let chest = BlockEntity::new("minecraft:chest".to_string(), (0, 0, 0))
+            .with_nbt_data("Noise".to_string(), NbtValue::ByteArray(noise.iter().map(|&b| b as i8).collect()));
+        schematic.set_block_entity(BlockPosition { x: 0, y: 0, z: 0 }, chest);
+        assert!(paste_into_region_file(None, 0, 0, &schematic, (0, 64, 0)).is_err());
+
+        let world_dir = std::env::temp_dir().join(format!("nucleation_anvil_mcc_test_{}", std::process::id()));
+        let result = paste_into_world(&schematic, &world_dir, (0, 64, 0));
+        let region = std::fs::read(world_dir.join("region").join("r.0.0.mca"));
+        let external = world_dir.join("region").join("c.0.0.mcc").is_file();
+        let read = from_world(&world_dir, &BoundingBox::new((0, 64, 0), (0, 64, 0)));
+        std::fs::remove_dir_all(&world_dir).unwrap();
+        result.unwrap();
+
+        let region = region.unwrap();
+        assert!(external);
+        assert_eq!(region.len(), 3 * SECTOR_SIZE);
+        assert_eq!(region[2 * SECTOR_SIZE + 4], 2 | EXTERNAL_CHUNK);
+        let read = read.unwrap();
+        let chest = read.get_block_entity(BlockPosition { x: 0, y: 0, z: 0 }).unwrap();
+        assert_eq!(chest.nbt.get("Noise").map(|noise| matches!(noise, NbtValue::ByteArray(bytes) if bytes.len() == 1_200_000)), Some(true));
+    }
+}
@@ -0,0 +1,403 @@
+// Axiom blueprints (.bp): a magic number followed by three length prefixed parts,
+// an uncompressed NBT header, a PNG thumbnail and gzipped NBT with the blocks in 16x16x16 sections.
+use std::collections::HashMap;
+use std::io::Cursor;
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use quartz_nbt::io::{read_nbt, write_nbt, Flavor};
+use crate::{BlockState, NucleationError, UniversalSchematic};
+use crate::block_entity::BlockEntity;
+use crate::entity::Entity;
+use crate::formats::anvil::{read_block_states, write_block_states};
+use crate::formats::structure::STRUCTURE_VOID;
+use crate::region::Region;
+use crate::utils::{NbtMap, NbtValue};
+
+pub const AXIOM_MAGIC: u32 = 0x0AE5_BB36;
+const BLUEPRINT_VERSION: i64 = 1;
+const SECTION_VOLUME: usize = 4096;
+
+// Header fields that map onto `Metadata` or are recomputed on export, everything else ends up in `Metadata::extra`
+const HEADER_FIELDS: &[&str] = &["Version", "Name", "Author", "Tags", "BlockCount", "ContainsAir"];
+
+pub fn is_axiom(data: &[u8]) -> bool {
+    data.len() >= 4 && u32::from_be_bytes([data[0], data[1], data[2], data[3]]) == AXIOM_MAGIC
+}
+
+pub fn from_axiom(data: &[u8]) -> Result<UniversalSchematic, NucleationError> {
+    if !is_axiom(data) {
+        return Err(NucleationError::UnsupportedFormat("not an Axiom blueprint".to_string()));
+    }
+    let mut position = 4;
+    let header = read_part(data, &mut position)?;
+    let thumbnail = read_part(data, &mut position)?;
+    let block_data = read_part(data, &mut position)?;
+
+    let (header, _) = read_nbt(&mut Cursor::new(header), Flavor::Uncompressed)?;
+    let (root, _) = read_nbt(&mut Cursor::new(block_data), Flavor::GzCompressed)?;
+
+    let mut schematic = UniversalSchematic::new("Unnamed".to_string());
+    let metadata = &mut schematic.metadata;
+    if let Ok(name) = header.get::<_, &str>("Name") {
+        metadata.name = Some(name.to_string());
+    }
+    metadata.author = header.get::<_, &str>("Author").ok().map(|author| author.to_string());
+    if let Ok(tags) = header.get::<_, &NbtList>("Tags") {
+        metadata.tags = tags.iter().filter_map(|tag| match tag {
+            NbtTag::String(tag) => Some(tag.clone()),
+            _ => None,
+        }).collect();
+    }
+    for (key, value) in header.inner() {
+        if !HEADER_FIELDS.contains(&key.as_str()) {
+            metadata.extra.insert(key.clone(), NbtValue::from_quartz_nbt(value));
+        }
+    }
+    if !thumbnail.is_empty() {
+        metadata.thumbnail = Some(thumbnail.to_vec());
+    }
+    metadata.mc_version = root.get::<_, i32>("DataVersion").ok();
+
+    // Decode every section first, the blueprint size is only known once all sections are read
+    let mut sections = Vec::new();
+    if let Ok(block_region) = root.get::<_, &NbtList>("BlockRegion") {
+        for tag in block_region.iter() {
+            let section = match tag {
+                NbtTag::Compound(section) => section,
+                _ => continue,
+            };
+            let base = (section.get::<_, i32>("X")? * 16, section.get::<_, i32>("Y")? * 16, section.get::<_, i32>("Z")? * 16);
+            let (indices, palette) = read_block_states(section.get::<_, &NbtCompound>("BlockStates")?)?;
+            if let Some(&index) = indices.iter().find(|&&index| index >= palette.len()) {
+                return Err(NucleationError::InvalidPaletteIndex { index, palette_len: palette.len() });
+            }
+            sections.push((base, indices, palette));
+        }
+    }
+
+    let mut min = (i32::MAX, i32::MAX, i32::MAX);
+    let mut max = (i32::MIN, i32::MIN, i32::MIN);
+    for (base, indices, palette) in &sections {
+        for (index, &palette_index) in indices.iter().enumerate() {
+            if palette[palette_index].name == STRUCTURE_VOID {
+                continue;
+            }
+            let (x, y, z) = section_coords(*base, index);
+            min = (min.0.min(x), min.1.min(y), min.2.min(z));
+            max = (max.0.max(x), max.1.max(y), max.2.max(z));
+        }
+    }
+    if min.0 > max.0 {
+        return Ok(schematic);
+    }
+
+    let size = (max.0 - min.0 + 1, max.1 - min.1 + 1, max.2 - min.2 + 1);
+    let mut region = Region::new(schematic.default_region_name.clone(), min, size);
+
+    // Positions outside of every section are empty in the blueprint
+    let void_index = region.get_or_insert_in_palette(BlockState::new(STRUCTURE_VOID.to_string()));
+    region.blocks.fill(void_index);
+
+    for (base, indices, palette) in sections {
+        let mapping: Vec<usize> = palette.into_iter()
+            .map(|block_state| region.get_or_insert_in_palette(block_state))
+            .collect();
+        for (index, &palette_index) in indices.iter().enumerate() {
+            let (x, y, z) = section_coords(base, index);
+            if region.is_in_region(x, y, z) {
+                let region_index = region.coords_to_index(x, y, z);
+                region.blocks.set(region_index, mapping[palette_index]);
+            }
+        }
+    }
+
+    if let Ok(block_entities) = root.get::<_, &NbtList>("BlockEntities") {
+        for tag in block_entities.iter() {
+            if let NbtTag::Compound(compound) = tag {
+                region.add_block_entity(parse_block_entity(compound)?);
+            }
+        }
+    }
+    if let Ok(entities) = root.get::<_, &NbtList>("Entities") {
+        for tag in entities.iter() {
+            if let NbtTag::Compound(compound) = tag {
+                region.add_entity(parse_entity(compound)?);
+            }
+        }
+    }
+
+    schematic.add_region(region);
+    Ok(schematic)
+}
+
+pub fn to_axiom(schematic: &UniversalSchematic) -> Result<Vec<u8>, NucleationError> {
+    let region = schematic.get_merged_region();
+    let bounding_box = region.get_bounding_box();
+
+    let mut block_region = NbtList::new();
+    let mut block_count = 0;
+    let mut contains_air = false;
+    for section_y in (bounding_box.min.1 >> 4)..=(bounding_box.max.1 >> 4) {
+        for section_z in (bounding_box.min.2 >> 4)..=(bounding_box.max.2 >> 4) {
+            for section_x in (bounding_box.min.0 >> 4)..=(bounding_box.max.0 >> 4) {
+                let base = (section_x * 16, section_y * 16, section_z * 16);
+                let mut palette = vec![BlockState::new(STRUCTURE_VOID.to_string())];
+                let mut mapping: HashMap<usize, usize> = HashMap::new();
+                let mut indices = vec![0; SECTION_VOLUME];
+
+                for (index, section_index) in indices.iter_mut().enumerate() {
+                    let (x, y, z) = section_coords(base, index);
+                    let block_state = match region.get_block_index(x, y, z).and_then(|block| region.palette.get(block).map(|state| (block, state))) {
+                        Some((_, state)) if state.name == STRUCTURE_VOID => continue,
+                        Some(block_state) => block_state,
+                        None => continue,
+                    };
+
+                    block_count += 1;
+                    contains_air |= block_state.1.name == "minecraft:air";
+                    *section_index = *mapping.entry(block_state.0).or_insert_with(|| {
+                        palette.push(block_state.1.clone());
+                        palette.len() - 1
+                    });
+                }
+                if palette.len() == 1 {
+                    continue;
+                }
+
+                let mut section = NbtCompound::new();
+                section.insert("X", NbtTag::Int(section_x));
+                section.insert("Y", NbtTag::Int(section_y));
+                section.insert("Z", NbtTag::Int(section_z));
+                section.insert("BlockStates", NbtTag::Compound(write_block_states(&indices, &palette)));
+                block_region.push(NbtTag::Compound(section));
+            }
+        }
+    }
+
+    let mut root = NbtCompound::new();
+    root.insert("DataVersion", NbtTag::Int(schematic.metadata.mc_version.unwrap_or(3700)));
+    root.insert("BlockRegion", NbtTag::List(block_region));
+    root.insert("BlockEntities", NbtTag::List(NbtList::from(
+        region.block_entities.values().map(|block_entity| NbtTag::Compound(block_entity_to_nbt(block_entity))).collect::<Vec<NbtTag>>(),
+    )));
+    if !region.entities.is_empty() {
+        root.insert("Entities", NbtTag::List(NbtList::from(
+            region.entities.iter().map(|entity| NbtTag::Compound(entity_to_nbt(entity))).collect::<Vec<NbtTag>>(),
+        )));
+    }
+
+    let metadata = &schematic.metadata;
+    let mut header = metadata.extra.to_quartz_nbt();
+    header.insert("Version", NbtTag::Long(BLUEPRINT_VERSION));
+    header.insert("Name", NbtTag::String(metadata.name.clone().unwrap_or_default()));
+    header.insert("Author", NbtTag::String(metadata.author.clone().unwrap_or_default()));
+    header.insert("Tags", NbtTag::List(NbtList::from(
+        metadata.tags.iter().map(|tag| NbtTag::String(tag.clone())).collect::<Vec<NbtTag>>(),
+    )));
+    header.insert("BlockCount", NbtTag::Int(block_count));
+    header.insert("ContainsAir", NbtTag::Byte(contains_air as i8));
+    // Axiom's defaults for blueprints that were not created in game
+    if !header.contains_key("ThumbnailYaw") {
+        header.insert("ThumbnailYaw", NbtTag::Float(135.0));
+    }
+    if !header.contains_key("ThumbnailPitch") {
+        header.insert("ThumbnailPitch", NbtTag::Float(30.0));
+    }
+    if !header.contains_key("LockedThumbnail") {
+        header.insert("LockedThumbnail", NbtTag::Byte(0));
+    }
+
+    let mut header_bytes = Vec::new();
+    write_nbt(&mut header_bytes, None, &header, Flavor::Uncompressed)?;
+    let mut block_data = Vec::new();
+    write_nbt(&mut block_data, None, &root, Flavor::GzCompressed)?;
+    let thumbnail = metadata.thumbnail.as_deref().unwrap_or(&[]);
+
+    let mut out = AXIOM_MAGIC.to_be_bytes().to_vec();
+    for part in [header_bytes.as_slice(), thumbnail, block_data.as_slice()] {
+        out.extend_from_slice(&(part.len() as u32).to_be_bytes());
+        out.extend_from_slice(part);
+    }
+    Ok(out)
+}
+
+fn read_part<'a>(data: &'a [u8], position: &mut usize) -> Result<&'a [u8], NucleationError> {
+    let length = data.get(*position..*position + 4)
+        .ok_or("Axiom blueprint is truncated")?;
+    let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+    let start = *position + 4;
+    let part = data.get(start..start.saturating_add(length))
+        .ok_or("Axiom blueprint is truncated")?;
+    *position = start + length;
+    Ok(part)
+}
+
+// Sections are indexed y, z, x with x changing fastest
+fn section_coords(base: (i32, i32, i32), index: usize) -> (i32, i32, i32) {
+    (
+        base.0 + (index & 15) as i32,
+        base.1 + (index >> 8) as i32,
+        base.2 + ((index >> 4) & 15) as i32,
+    )
+}
+
+fn parse_block_entity(compound: &NbtCompound) -> Result<BlockEntity, NucleationError> {
+    let position = (compound.get::<_, i32>("x")?, compound.get::<_, i32>("y")?, compound.get::<_, i32>("z")?);
+    let id = compound.get::<_, &str>("id").unwrap_or("unknown").to_string();
+    let mut nbt = NbtMap::from_quartz_nbt(compound);
+    for key in ["id", "x", "y", "z"] {
+        nbt.remove(key);
+    }
+    Ok(BlockEntity { nbt, id, position })
+}
+
+fn block_entity_to_nbt(block_entity: &BlockEntity) -> NbtCompound {
+    let mut nbt = NbtCompound::new();
+    for (key, value) in &block_entity.nbt {
+        if key == "Id" || key == "Pos" {
+            continue;
+        }
+        nbt.insert(key, value.to_quartz_nbt());
+    }
+    let (x, y, z) = block_entity.position;
+    nbt.insert("id", NbtTag::String(block_entity.id.clone()));
+    nbt.insert("x", NbtTag::Int(x));
+    nbt.insert("y", NbtTag::Int(y));
+    nbt.insert("z", NbtTag::Int(z));
+    nbt
+}
+
+fn parse_entity(compound: &NbtCompound) -> Result<Entity, NucleationError> {
+    let id = compound.get::<_, &str>("id")?;
+    let position = compound.get::<_, &NbtList>("Pos")?;
+    let coordinate = |index: usize| -> Result<f64, NucleationError> {
+        match (**position).get(index) {
+            Some(NbtTag::Double(value)) => Ok(*value),
+            Some(NbtTag::Float(value)) => Ok(*value as f64),
+            _ => Err("Invalid entity position".into()),
+        }
+    };
+
+    let mut entity = Entity::new(id.to_string(), (coordinate(0)?, coordinate(1)?, coordinate(2)?));
+    for (key, value) in compound.inner() {
+        if key == "id" || key == "Pos" {
+            continue;
+        }
+        entity.nbt.insert(key.clone(), NbtValue::from_quartz_nbt(value));
+    }
+    Ok(entity)
+}
+
+fn entity_to_nbt(entity: &Entity) -> NbtCompound {
+    let mut nbt = entity.nbt.to_quartz_nbt();
+    nbt.insert("id", NbtTag::String(entity.id.clone()));
+    nbt.insert("Pos", NbtTag::List(NbtList::from(vec![
+        NbtTag::Double(entity.position.0),
+        NbtTag::Double(entity.position.1),
+        NbtTag::Double(entity.position.2),
+    ])));
+    nbt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_position::BlockPosition;
+    use crate::item::ItemStack;
+
+    #[test]
+    fn test_axiom_roundtrip() {
+        let mut schematic = UniversalSchematic::new("Tower".to_string());
+        schematic.metadata.author = Some("Builder".to_string());
+        schematic.metadata.tags = vec!["medieval".to_string(), "tower".to_string()];
+        schematic.metadata.thumbnail = Some(vec![0x89, b'P', b'N', b'G']);
+        schematic.metadata.mc_version = Some(3955);
+
+        schematic.set_block(-3, 0, 2, BlockState::new("minecraft:stone".to_string()));
+        schematic.set_block(20, 5, 2, BlockState::new("minecraft:oak_stairs".to_string())
+            .with_property("facing".to_string(), "east".to_string()));
+        schematic.set_block(0, 1, 0, BlockState::new("minecraft:chest".to_string()));
+        schematic.set_block_entity(BlockPosition { x: 0, y: 1, z: 0 },
+            BlockEntity::create_chest((0, 1, 0), vec![ItemStack::new("minecraft:apple", 5).with_slot(3)]));
+        let mut region = schematic.get_region("Main").unwrap().clone();
+        region.add_entity(Entity::new("minecraft:armor_stand".to_string(), (0.5, 2.0, 0.5)));
+        schematic.add_region(region);
+
+        let data = to_axiom(&schematic).unwrap();
+        assert!(is_axiom(&data));
+        let loaded = from_axiom(&data).unwrap();
+
+        assert_eq!(loaded.metadata.name.as_deref(), Some("Tower"));
+        assert_eq!(loaded.metadata.author.as_deref(), Some("Builder"));
+        assert_eq!(loaded.metadata.tags, schematic.metadata.tags);
+        assert_eq!(loaded.metadata.thumbnail, schematic.metadata.thumbnail);
+        assert_eq!(loaded.metadata.mc_version, Some(3955));
+        assert_eq!(loaded.metadata.extra.get("ThumbnailYaw"), Some(&NbtValue::Float(135.0)));
+
+        assert_eq!(loaded.get_block(-3, 0, 2).unwrap().name, "minecraft:stone");
+        let stairs = loaded.get_block(20, 5, 2).unwrap();
+        assert_eq!(stairs.name, "minecraft:oak_stairs");
+        assert_eq!(stairs.get_property("facing"), Some(&"east".to_string()));
+        assert_eq!(loaded.get_block(5, 3, 1).unwrap().name, "minecraft:air");
+
+        let chest = loaded.get_block_entity(BlockPosition { x: 0, y: 1, z: 0 }).unwrap();
+        assert_eq!(chest.id, "minecraft:chest");
+        assert!(chest.nbt.get("Items").is_some());
+
+        let entities = &loaded.get_region("Main").unwrap().entities;
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].position, (0.5, 2.0, 0.5));
+    }
+
+    #[test]
+    fn test_axiom_header_and_empty_space() {
+        // A blueprint with an unknown header field and a single block surrounded by structure void
+        let mut header = NbtCompound::new();
+        header.insert("Version", NbtTag::Long(1));
+        header.insert("Name", NbtTag::String("Pillar".to_string()));
+        header.insert("Author", NbtTag::String("Someone".to_string()));
+        header.insert("Tags", NbtTag::List(NbtList::new()));
+        header.insert("LockedThumbnail", NbtTag::Byte(1));
+        header.insert("Collection", NbtTag::String("castles".to_string()));
+
+        let palette = [BlockState::new(STRUCTURE_VOID.to_string()), BlockState::new("minecraft:gold_block".to_string())];
+        let mut indices = vec![0; SECTION_VOLUME];
+        indices[(2 << 8) | (1 << 4) | 3] = 1;
+        indices[(3 << 8) | (1 << 4) | 3] = 1;
+        let mut section = NbtCompound::new();
+        section.insert("X", NbtTag::Int(-1));
+        section.insert("Y", NbtTag::Int(0));
+        section.insert("Z", NbtTag::Int(0));
+        section.insert("BlockStates", NbtTag::Compound(write_block_states(&indices, &palette)));
+        let mut root = NbtCompound::new();
+        root.insert("DataVersion", NbtTag::Int(3700));
+        root.insert("BlockRegion", NbtTag::List(NbtList::from(vec![NbtTag::Compound(section)])));
+
+        let mut header_bytes = Vec::new();
+        write_nbt(&mut header_bytes, None, &header, Flavor::Uncompressed).unwrap();
+        let mut block_data = Vec::new();
+        write_nbt(&mut block_data, None, &root, Flavor::GzCompressed).unwrap();
+        let mut data = AXIOM_MAGIC.to_be_bytes().to_vec();
+        for part in [header_bytes.as_slice(), &[], block_data.as_slice()] {
+            data.extend_from_slice(&(part.len() as u32).to_be_bytes());
+            data.extend_from_slice(part);
+        }
+
+        let schematic = from_axiom(&data).unwrap();
+        assert_eq!(schematic.metadata.thumbnail, None);
+        assert_eq!(schematic.get_bounding_box().min, (-13, 2, 1));
+        assert_eq!(schematic.get_dimensions(), (1, 2, 1));
+        assert_eq!(schematic.get_block(-13, 3, 1).unwrap().name, "minecraft:gold_block");
+
+        // Unknown fields survive, counts are recomputed
+        let exported = to_axiom(&schematic).unwrap();
+        let mut position = 4;
+        let (header, _) = read_nbt(&mut Cursor::new(read_part(&exported, &mut position).unwrap()), Flavor::Uncompressed).unwrap();
+        assert_eq!(header.get::<_, &str>("Collection").unwrap(), "castles");
+        assert_eq!(header.get::<_, i8>("LockedThumbnail").unwrap(), 1);
+        assert_eq!(header.get::<_, i32>("BlockCount").unwrap(), 2);
+        assert_eq!(header.get::<_, i8>("ContainsAir").unwrap(), 0);
+
+        assert!(from_axiom(&data[..data.len() - 10]).is_err());
+        assert!(!is_axiom(b"PK\x03\x04"));
+    }
+}
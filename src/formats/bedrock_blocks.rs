@@ -0,0 +1,761 @@
+// Translation between Java block states and Bedrock block states.
+// Bedrock shares most block names with Java since 1.21, so the table only covers
+// renamed blocks, variants that Bedrock encodes in the name and state properties.
+use std::collections::HashMap;
+use quartz_nbt::{NbtCompound, NbtTag};
+use crate::BlockState;
+
+// Block state version written into palettes (1.21.0)
+pub const BEDROCK_BLOCK_VERSION: i32 = 18_153_472;
+
+// Blocks with a different name in each edition, as (java, bedrock)
+const RENAMES: &[(&str, &str)] = &[
+    ("dirt_path", "grass_path"),
+    ("note_block", "noteblock"),
+    ("powered_rail", "golden_rail"),
+    ("cobweb", "web"),
+    ("dead_bush", "deadbush"),
+    ("snow_block", "snow"),
+    ("snow", "snow_layer"),
+    ("sugar_cane", "reeds"),
+    ("nether_portal", "portal"),
+    ("jack_o_lantern", "lit_pumpkin"),
+    ("melon", "melon_block"),
+    ("lily_pad", "waterlily"),
+    ("nether_bricks", "nether_brick"),
+    ("red_nether_bricks", "red_nether_brick"),
+    ("end_stone_bricks", "end_bricks"),
+    ("terracotta", "hardened_clay"),
+    ("magma_block", "magma"),
+    ("slime_block", "slime"),
+    ("spawner", "mob_spawner"),
+    ("bricks", "brick_block"),
+    ("nether_quartz_ore", "quartz_ore"),
+    ("rooted_dirt", "dirt_with_roots"),
+    ("stonecutter", "stonecutter_block"),
+    ("shulker_box", "undyed_shulker_box"),
+    ("oak_door", "wooden_door"),
+    ("oak_trapdoor", "trapdoor"),
+    ("oak_fence_gate", "fence_gate"),
+    ("oak_button", "wooden_button"),
+    ("oak_pressure_plate", "wooden_pressure_plate"),
+    ("oak_wall_sign", "wall_sign"),
+    ("moving_piston", "moving_block"),
+    ("tripwire", "trip_wire"),
+];
+
+// Java blocks that collapse into another block on Bedrock
+const JAVA_ALIASES: &[(&str, &str)] = &[
+    ("cave_air", "air"),
+    ("void_air", "air"),
+];
+
+// Blocks that Bedrock splits into a lit_ prefixed variant
+const LIT_VARIANTS: &[&str] = &[
+    "furnace",
+    "blast_furnace",
+    "smoker",
+    "redstone_ore",
+    "deepslate_redstone_ore",
+    "redstone_lamp",
+];
+
+// Blocks that use a six-way integer facing_direction on Bedrock
+const FACING_DIRECTION_BLOCKS: &[&str] = &[
+    "dispenser",
+    "dropper",
+    "observer",
+    "piston",
+    "sticky_piston",
+    "piston_head",
+    "hopper",
+    "barrel",
+    "end_rod",
+    "lightning_rod",
+    "ladder",
+    "command_block",
+    "chain_command_block",
+    "repeating_command_block",
+];
+
+// Blocks that use a four-way integer direction on Bedrock
+const DIRECTION_BLOCKS: &[&str] = &[
+    "repeater",
+    "comparator",
+    "tripwire_hook",
+    "end_portal_frame",
+    "cocoa",
+    "beehive",
+    "bee_nest",
+    "lectern",
+    "loom",
+    "bell",
+    "grindstone",
+];
+
+const SIX_WAY: &[&str] = &["down", "up", "north", "south", "west", "east"];
+const FOUR_WAY: &[&str] = &["south", "west", "north", "east"];
+const RAIL_SHAPES: &[&str] = &[
+    "north_south", "east_west", "ascending_east", "ascending_west", "ascending_north",
+    "ascending_south", "south_east", "south_west", "north_west", "north_east",
+];
+const WALL_CONNECTIONS: &[(&str, &str)] = &[("none", "none"), ("low", "short"), ("tall", "tall")];
+
+// How a single Java property maps onto a Bedrock state
+#[derive(Clone, Copy)]
+enum Rule {
+    // Same values under a different key
+    Rename(&'static str, &'static str),
+    // "true"/"false" stored as a byte
+    Bit(&'static str, &'static str),
+    // "true"/"false" stored as an inverted byte
+    InvertedBit(&'static str, &'static str),
+    // Two-valued property stored as a byte, [off, on]
+    BitValue(&'static str, &'static str, [&'static str; 2]),
+    // Integer property, bedrock = java + offset
+    Int(&'static str, &'static str, i32),
+    // Named values stored as their index
+    IntEnum(&'static str, &'static str, &'static [&'static str]),
+    // Named values with different spellings, as (java, bedrock)
+    StrEnum(&'static str, &'static str, &'static [(&'static str, &'static str)]),
+    // No Bedrock counterpart, restored with the default when importing
+    Drop(&'static str, &'static str),
+}
+
+const WATERLOGGED: Rule = Rule::Drop("waterlogged", "false");
+const HORIZONTAL_DROPS: [Rule; 4] = [
+    Rule::Drop("north", "false"),
+    Rule::Drop("east", "false"),
+    Rule::Drop("south", "false"),
+    Rule::Drop("west", "false"),
+];
+
+const STAIRS: &[Rule] = &[
+    Rule::IntEnum("facing", "weirdo_direction", &["east", "west", "south", "north"]),
+    Rule::BitValue("half", "upside_down_bit", ["bottom", "top"]),
+    Rule::Drop("shape", "straight"),
+    WATERLOGGED,
+];
+const SLAB: &[Rule] = &[
+    Rule::StrEnum("type", "minecraft:vertical_half", &[("bottom", "bottom"), ("top", "top")]),
+    WATERLOGGED,
+];
+const DOOR: &[Rule] = &[
+    Rule::IntEnum("facing", "direction", &["east", "south", "west", "north"]),
+    Rule::BitValue("half", "upper_block_bit", ["lower", "upper"]),
+    Rule::BitValue("hinge", "door_hinge_bit", ["left", "right"]),
+    Rule::Drop("powered", "false"),
+];
+const TRAPDOOR: &[Rule] = &[
+    Rule::IntEnum("facing", "direction", &["east", "west", "south", "north"]),
+    Rule::BitValue("half", "upside_down_bit", ["bottom", "top"]),
+    Rule::Drop("powered", "false"),
+    WATERLOGGED,
+];
+const FENCE_GATE: &[Rule] = &[
+    Rule::IntEnum("facing", "direction", FOUR_WAY),
+    Rule::Drop("powered", "false"),
+];
+const FENCE: &[Rule] = &[
+    HORIZONTAL_DROPS[0],
+    HORIZONTAL_DROPS[1],
+    HORIZONTAL_DROPS[2],
+    HORIZONTAL_DROPS[3],
+    WATERLOGGED,
+];
+const WALL: &[Rule] = &[
+    Rule::StrEnum("north", "wall_connection_type_north", WALL_CONNECTIONS),
+    Rule::StrEnum("east", "wall_connection_type_east", WALL_CONNECTIONS),
+    Rule::StrEnum("south", "wall_connection_type_south", WALL_CONNECTIONS),
+    Rule::StrEnum("west", "wall_connection_type_west", WALL_CONNECTIONS),
+    Rule::Bit("up", "wall_post_bit"),
+    WATERLOGGED,
+];
+const BED: &[Rule] = &[
+    Rule::IntEnum("facing", "direction", FOUR_WAY),
+    Rule::BitValue("part", "head_piece_bit", ["foot", "head"]),
+];
+const WALL_ATTACHED: &[Rule] = &[
+    Rule::IntEnum("facing", "facing_direction", SIX_WAY),
+    WATERLOGGED,
+];
+const STANDING_SIGN: &[Rule] = &[
+    Rule::Int("rotation", "ground_sign_direction", 0),
+    WATERLOGGED,
+];
+const LEAVES: &[Rule] = &[
+    Rule::Drop("distance", "7"),
+    WATERLOGGED,
+];
+const BUTTON: &[Rule] = &[
+    Rule::Bit("powered", "button_pressed_bit"),
+];
+const PRESSURE_PLATE: &[Rule] = &[
+    Rule::Int("power", "redstone_signal", 0),
+    Rule::Drop("powered", "false"),
+];
+const CANDLE: &[Rule] = &[
+    Rule::Int("candles", "candles", -1),
+    Rule::Bit("lit", "lit"),
+    WATERLOGGED,
+];
+const RAIL: &[Rule] = &[
+    Rule::IntEnum("shape", "rail_direction", RAIL_SHAPES),
+    Rule::Bit("powered", "rail_data_bit"),
+    WATERLOGGED,
+];
+const LIQUID: &[Rule] = &[
+    Rule::Int("level", "liquid_depth", 0),
+];
+const REDSTONE_WIRE: &[Rule] = &[
+    Rule::Int("power", "redstone_signal", 0),
+    Rule::Drop("north", "none"),
+    Rule::Drop("east", "none"),
+    Rule::Drop("south", "none"),
+    Rule::Drop("west", "none"),
+];
+const REPEATER: &[Rule] = &[
+    Rule::Int("delay", "repeater_delay", -1),
+    Rule::Drop("locked", "false"),
+];
+const COMPARATOR: &[Rule] = &[
+    Rule::BitValue("mode", "output_subtract_bit", ["compare", "subtract"]),
+];
+const POWER_LEVEL: &[Rule] = &[
+    Rule::Int("power", "redstone_signal", 0),
+];
+const SNOW: &[Rule] = &[
+    Rule::Int("layers", "height", -1),
+];
+const FARMLAND: &[Rule] = &[
+    Rule::Int("moisture", "moisturized_amount", 0),
+];
+const CAKE: &[Rule] = &[
+    Rule::Int("bites", "bite_counter", 0),
+];
+const NOTE_BLOCK: &[Rule] = &[
+    Rule::Drop("instrument", "harp"),
+    Rule::Drop("note", "0"),
+    Rule::Drop("powered", "false"),
+];
+const CROP: &[Rule] = &[
+    Rule::Int("age", "growth", 0),
+];
+const END_PORTAL_FRAME: &[Rule] = &[
+    Rule::Bit("eye", "end_portal_eye_bit"),
+];
+const COMPOSTER: &[Rule] = &[
+    Rule::Int("level", "composter_fill_level", 0),
+];
+const RESPAWN_ANCHOR: &[Rule] = &[
+    Rule::Int("charges", "respawn_anchor_charge", 0),
+];
+const TNT: &[Rule] = &[
+    Rule::Bit("unstable", "explode_bit"),
+];
+const LANTERN: &[Rule] = &[
+    Rule::Bit("hanging", "hanging"),
+    WATERLOGGED,
+];
+const CAMPFIRE: &[Rule] = &[
+    Rule::InvertedBit("lit", "extinguished"),
+    Rule::Drop("signal_fire", "false"),
+    WATERLOGGED,
+];
+const CHEST: &[Rule] = &[
+    Rule::Drop("type", "single"),
+    WATERLOGGED,
+];
+const HOPPER: &[Rule] = &[
+    Rule::InvertedBit("enabled", "toggle_bit"),
+];
+const PISTON: &[Rule] = &[
+    Rule::Drop("extended", "false"),
+];
+const PISTON_HEAD: &[Rule] = &[
+    Rule::Drop("short", "false"),
+];
+const SCAFFOLDING: &[Rule] = &[
+    Rule::Int("distance", "stability", 0),
+    Rule::Bit("bottom", "stability_check"),
+    WATERLOGGED,
+];
+const SEA_PICKLE: &[Rule] = &[
+    Rule::Int("pickles", "cluster_count", -1),
+    WATERLOGGED,
+];
+const SNOWY: &[Rule] = &[
+    Rule::Drop("snowy", "false"),
+];
+const NETHER_PORTAL: &[Rule] = &[
+    Rule::Rename("axis", "portal_axis"),
+];
+const TRIPWIRE: &[Rule] = &[
+    HORIZONTAL_DROPS[0],
+    HORIZONTAL_DROPS[1],
+    HORIZONTAL_DROPS[2],
+    HORIZONTAL_DROPS[3],
+];
+const MULTIFACE: &[Rule] = &[
+    HORIZONTAL_DROPS[0],
+    HORIZONTAL_DROPS[1],
+    HORIZONTAL_DROPS[2],
+    HORIZONTAL_DROPS[3],
+    Rule::Drop("up", "false"),
+];
+
+// Properties that mean the same thing on every block
+const GLOBAL: &[Rule] = &[
+    Rule::Rename("axis", "pillar_axis"),
+    Rule::Bit("powered", "powered_bit"),
+    Rule::Bit("open", "open_bit"),
+    Rule::Bit("triggered", "triggered_bit"),
+    Rule::Bit("conditional", "conditional_bit"),
+    Rule::Bit("attached", "attached_bit"),
+    Rule::Bit("disarmed", "disarmed_bit"),
+    Rule::Bit("persistent", "persistent_bit"),
+    Rule::Bit("in_wall", "in_wall_bit"),
+    Rule::Bit("occupied", "occupied_bit"),
+    Rule::Int("age", "age", 0),
+];
+
+fn family_rules(name: &str) -> &'static [Rule] {
+    match name {
+        n if n.ends_with("_stairs") => STAIRS,
+        n if n.ends_with("_slab") => SLAB,
+        n if n.ends_with("_trapdoor") => TRAPDOOR,
+        n if n.ends_with("_door") => DOOR,
+        n if n.ends_with("_fence_gate") => FENCE_GATE,
+        n if n.ends_with("_fence") || n.ends_with("_pane") || n == "glass_pane" || n == "iron_bars" => FENCE,
+        n if n.ends_with("_wall") => WALL,
+        n if n.ends_with("_bed") => BED,
+        n if n.ends_with("_wall_sign") || n.ends_with("_wall_banner") || n == "ladder" => WALL_ATTACHED,
+        n if (n.ends_with("_sign") && !n.ends_with("_hanging_sign")) || n.ends_with("_banner") => STANDING_SIGN,
+        n if n.ends_with("_leaves") => LEAVES,
+        n if n.ends_with("_button") => BUTTON,
+        "light_weighted_pressure_plate" | "heavy_weighted_pressure_plate" => POWER_LEVEL,
+        n if n.ends_with("_pressure_plate") => PRESSURE_PLATE,
+        n if n.ends_with("candle") => CANDLE,
+        n if n.ends_with("rail") => RAIL,
+        "water" | "lava" => LIQUID,
+        "redstone_wire" => REDSTONE_WIRE,
+        "repeater" => REPEATER,
+        "comparator" => COMPARATOR,
+        "daylight_detector" => POWER_LEVEL,
+        "snow" => SNOW,
+        "farmland" => FARMLAND,
+        "cake" => CAKE,
+        "note_block" => NOTE_BLOCK,
+        "wheat" | "carrots" | "potatoes" | "melon_stem" | "pumpkin_stem" => CROP,
+        "end_portal_frame" => END_PORTAL_FRAME,
+        "composter" => COMPOSTER,
+        "respawn_anchor" => RESPAWN_ANCHOR,
+        "tnt" => TNT,
+        "lantern" | "soul_lantern" => LANTERN,
+        "campfire" | "soul_campfire" => CAMPFIRE,
+        "chest" | "trapped_chest" => CHEST,
+        "hopper" => HOPPER,
+        "piston" | "sticky_piston" => PISTON,
+        "piston_head" => PISTON_HEAD,
+        "scaffolding" => SCAFFOLDING,
+        "sea_pickle" => SEA_PICKLE,
+        "grass_block" | "podzol" | "mycelium" => SNOWY,
+        "nether_portal" => NETHER_PORTAL,
+        "tripwire" => TRIPWIRE,
+        "fire" | "vine" => MULTIFACE,
+        _ => &[],
+    }
+}
+
+fn facing_rule(name: &str) -> Rule {
+    if FACING_DIRECTION_BLOCKS.contains(&name) || name.ends_with("_glazed_terracotta") {
+        Rule::IntEnum("facing", "facing_direction", SIX_WAY)
+    } else if DIRECTION_BLOCKS.contains(&name) {
+        Rule::IntEnum("facing", "direction", FOUR_WAY)
+    } else {
+        Rule::Rename("facing", "minecraft:cardinal_direction")
+    }
+}
+
+// Family rules come first so they can claim properties before the generic ones
+fn rules_for(name: &str) -> Vec<Rule> {
+    let mut rules = family_rules(name).to_vec();
+    rules.push(facing_rule(name));
+    rules.extend_from_slice(GLOBAL);
+    rules
+}
+
+impl Rule {
+    fn to_bedrock(self, properties: &mut HashMap<String, String>, states: &mut HashMap<String, NbtTag>) {
+        let (java_key, bedrock_key) = match self {
+            Rule::Drop(java_key, _) => {
+                properties.remove(java_key);
+                return;
+            }
+            Rule::Rename(j, b) | Rule::Bit(j, b) | Rule::InvertedBit(j, b) | Rule::BitValue(j, b, _) |
+            Rule::Int(j, b, _) | Rule::IntEnum(j, b, _) | Rule::StrEnum(j, b, _) => (j, b),
+        };
+        let value = match properties.get(java_key) {
+            Some(value) => value.as_str(),
+            None => return,
+        };
+
+        let tag = match self {
+            Rule::Rename(..) => Some(NbtTag::String(value.to_string())),
+            Rule::Bit(..) => Some(NbtTag::Byte((value == "true") as i8)),
+            Rule::InvertedBit(..) => Some(NbtTag::Byte((value != "true") as i8)),
+            Rule::BitValue(_, _, [_, on]) => Some(NbtTag::Byte((value == on) as i8)),
+            Rule::Int(_, _, offset) => value.parse::<i32>().ok().map(|v| NbtTag::Int(v + offset)),
+            Rule::IntEnum(_, _, values) => values.iter().position(|&v| v == value).map(|i| NbtTag::Int(i as i32)),
+            Rule::StrEnum(_, _, pairs) => pairs.iter()
+                .find(|(java, _)| *java == value)
+                .map(|(_, bedrock)| NbtTag::String(bedrock.to_string())),
+            Rule::Drop(..) => None,
+        };
+
+        // Values we cannot translate are passed through untouched
+        if let Some(tag) = tag {
+            properties.remove(java_key);
+            states.insert(bedrock_key.to_string(), tag);
+        }
+    }
+
+    fn to_java(self, states: &mut HashMap<String, NbtTag>, properties: &mut HashMap<String, String>) {
+        let (java_key, bedrock_key) = match self {
+            Rule::Drop(java_key, default) => {
+                properties.entry(java_key.to_string()).or_insert_with(|| default.to_string());
+                return;
+            }
+            Rule::Rename(j, b) | Rule::Bit(j, b) | Rule::InvertedBit(j, b) | Rule::BitValue(j, b, _) |
+            Rule::Int(j, b, _) | Rule::IntEnum(j, b, _) | Rule::StrEnum(j, b, _) => (j, b),
+        };
+        let tag = match states.get(bedrock_key) {
+            Some(tag) => tag,
+            None => return,
+        };
+
+        let value = match self {
+            Rule::Rename(..) => Some(tag_to_string(tag)),
+            Rule::Bit(..) => tag_to_i32(tag).map(|v| (v != 0).to_string()),
+            Rule::InvertedBit(..) => tag_to_i32(tag).map(|v| (v == 0).to_string()),
+            Rule::BitValue(_, _, [off, on]) => tag_to_i32(tag).map(|v| if v != 0 { on } else { off }.to_string()),
+            Rule::Int(_, _, offset) => tag_to_i32(tag).map(|v| (v - offset).to_string()),
+            Rule::IntEnum(_, _, values) => tag_to_i32(tag)
+                .and_then(|v| values.get(v as usize))
+                .map(|v| v.to_string()),
+            Rule::StrEnum(_, _, pairs) => {
+                let value = tag_to_string(tag);
+                pairs.iter()
+                    .find(|(_, bedrock)| *bedrock == value)
+                    .map(|(java, _)| java.to_string())
+            }
+            Rule::Drop(..) => None,
+        };
+
+        if let Some(value) = value {
+            states.remove(bedrock_key);
+            properties.insert(java_key.to_string(), value);
+        }
+    }
+}
+
+fn tag_to_i32(tag: &NbtTag) -> Option<i32> {
+    match tag {
+        NbtTag::Byte(v) => Some(*v as i32),
+        NbtTag::Short(v) => Some(*v as i32),
+        NbtTag::Int(v) => Some(*v),
+        NbtTag::String(v) => v.parse().ok(),
+        _ => None,
+    }
+}
+
+fn tag_to_string(tag: &NbtTag) -> String {
+    match tag {
+        NbtTag::String(v) => v.clone(),
+        NbtTag::Byte(v) => v.to_string(),
+        NbtTag::Short(v) => v.to_string(),
+        NbtTag::Int(v) => v.to_string(),
+        NbtTag::Long(v) => v.to_string(),
+        other => other.to_snbt(),
+    }
+}
+
+fn opposite(direction: &str) -> &'static str {
+    match direction {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        "west" => "east",
+        "up" => "down",
+        _ => "up",
+    }
+}
+
+fn take_flag(properties: &mut HashMap<String, String>, key: &str, default: bool) -> bool {
+    properties.remove(key).map(|v| v == "true").unwrap_or(default)
+}
+
+// Picks the Bedrock name, consuming properties that Bedrock encodes in the name itself
+fn bedrock_name(java: &str, properties: &mut HashMap<String, String>, states: &mut HashMap<String, NbtTag>) -> String {
+    if LIT_VARIANTS.contains(&java) {
+        return if take_flag(properties, "lit", false) { format!("lit_{}", java) } else { java.to_string() };
+    }
+
+    match java {
+        "redstone_torch" | "redstone_wall_torch" | "torch" | "wall_torch" | "soul_torch" | "soul_wall_torch" => {
+            let direction = match properties.remove("facing") {
+                // Bedrock stores the direction the torch leans away from the wall
+                Some(facing) => opposite(&facing),
+                None => "top",
+            };
+            states.insert("torch_facing_direction".to_string(), NbtTag::String(direction.to_string()));
+            let standing = java.replace("wall_torch", "torch");
+            if standing == "redstone_torch" && !take_flag(properties, "lit", true) {
+                "unlit_redstone_torch".to_string()
+            } else {
+                standing
+            }
+        }
+        "repeater" | "comparator" => {
+            let prefix = if take_flag(properties, "powered", false) { "powered" } else { "unpowered" };
+            format!("{}_{}", prefix, java)
+        }
+        "daylight_detector" => {
+            if take_flag(properties, "inverted", false) { "daylight_detector_inverted".to_string() } else { java.to_string() }
+        }
+        "piston_head" => {
+            let sticky = properties.remove("type").map(|t| t == "sticky").unwrap_or(false);
+            if sticky { "sticky_piston_arm_collision".to_string() } else { "piston_arm_collision".to_string() }
+        }
+        "lever" => {
+            let face = properties.remove("face").unwrap_or_else(|| "wall".to_string());
+            let facing = properties.remove("facing").unwrap_or_else(|| "north".to_string());
+            let axis = if facing == "north" || facing == "south" { "north_south" } else { "east_west" };
+            let direction = match face.as_str() {
+                "floor" => format!("up_{}", axis),
+                "ceiling" => format!("down_{}", axis),
+                _ => facing,
+            };
+            states.insert("lever_direction".to_string(), NbtTag::String(direction));
+            java.to_string()
+        }
+        n if n.ends_with("_button") => {
+            let face = properties.remove("face").unwrap_or_else(|| "wall".to_string());
+            let facing = properties.remove("facing").unwrap_or_else(|| "north".to_string());
+            let direction = match face.as_str() {
+                "ceiling" => 0,
+                "floor" => 1,
+                _ => SIX_WAY.iter().position(|&d| d == facing).unwrap_or(2) as i32,
+            };
+            states.insert("facing_direction".to_string(), NbtTag::Int(direction));
+            rename_to_bedrock(java)
+        }
+        n if n.ends_with("_slab") && properties.get("type").map(|t| t == "double").unwrap_or(false) => {
+            properties.remove("type");
+            format!("{}_double_slab", n.trim_end_matches("_slab"))
+        }
+        "oak_sign" => "standing_sign".to_string(),
+        n if n.ends_with("_sign") && !n.ends_with("_wall_sign") && !n.ends_with("_hanging_sign") => {
+            format!("{}_standing_sign", n.trim_end_matches("_sign"))
+        }
+        _ => rename_to_bedrock(java),
+    }
+}
+
+fn rename_to_bedrock(java: &str) -> String {
+    RENAMES.iter()
+        .chain(JAVA_ALIASES.iter())
+        .find(|(j, _)| *j == java)
+        .map(|(_, bedrock)| bedrock.to_string())
+        .unwrap_or_else(|| java.to_string())
+}
+
+// Picks the Java name, turning name-encoded variants back into properties
+fn java_name(bedrock: &str, states: &mut HashMap<String, NbtTag>, properties: &mut HashMap<String, String>) -> String {
+    if let Some((java, _)) = RENAMES.iter().find(|(_, b)| *b == bedrock) {
+        return java.to_string();
+    }
+    if let Some(base) = bedrock.strip_prefix("lit_").filter(|base| LIT_VARIANTS.contains(base)) {
+        properties.insert("lit".to_string(), "true".to_string());
+        return base.to_string();
+    }
+    if LIT_VARIANTS.contains(&bedrock) {
+        properties.insert("lit".to_string(), "false".to_string());
+        return bedrock.to_string();
+    }
+
+    match bedrock {
+        "redstone_torch" | "unlit_redstone_torch" | "torch" | "soul_torch" => {
+            let standing = if bedrock == "torch" || bedrock == "soul_torch" {
+                bedrock.to_string()
+            } else {
+                properties.insert("lit".to_string(), (bedrock == "redstone_torch").to_string());
+                "redstone_torch".to_string()
+            };
+            let direction = states.remove("torch_facing_direction").map(|t| tag_to_string(&t));
+            match direction.as_deref() {
+                Some(direction @ ("north" | "south" | "east" | "west")) => {
+                    properties.insert("facing".to_string(), opposite(direction).to_string());
+                    standing.replace("torch", "wall_torch")
+                }
+                _ => standing,
+            }
+        }
+        "powered_repeater" | "unpowered_repeater" | "powered_comparator" | "unpowered_comparator" => {
+            let (prefix, base) = bedrock.split_once('_').unwrap_or(("unpowered", bedrock));
+            properties.insert("powered".to_string(), (prefix == "powered").to_string());
+            base.to_string()
+        }
+        "daylight_detector" | "daylight_detector_inverted" => {
+            properties.insert("inverted".to_string(), (bedrock == "daylight_detector_inverted").to_string());
+            "daylight_detector".to_string()
+        }
+        "piston_arm_collision" | "sticky_piston_arm_collision" => {
+            let piston_type = if bedrock.starts_with("sticky") { "sticky" } else { "normal" };
+            properties.insert("type".to_string(), piston_type.to_string());
+            "piston_head".to_string()
+        }
+        "lever" => {
+            let direction = states.remove("lever_direction").map(|t| tag_to_string(&t)).unwrap_or_else(|| "north".to_string());
+            let (face, facing) = match direction.as_str() {
+                "up_north_south" => ("floor", "north"),
+                "up_east_west" => ("floor", "east"),
+                "down_north_south" => ("ceiling", "north"),
+                "down_east_west" => ("ceiling", "east"),
+                other => ("wall", SIX_WAY.iter().copied().find(|&d| d == other).unwrap_or("north")),
+            };
+            properties.insert("face".to_string(), face.to_string());
+            properties.insert("facing".to_string(), facing.to_string());
+            bedrock.to_string()
+        }
+        n if n.ends_with("_double_slab") => {
+            states.remove("minecraft:vertical_half");
+            properties.insert("type".to_string(), "double".to_string());
+            format!("{}_slab", n.trim_end_matches("_double_slab"))
+        }
+        "standing_sign" => "oak_sign".to_string(),
+        n if n.ends_with("_standing_sign") => format!("{}_sign", n.trim_end_matches("_standing_sign")),
+        _ => bedrock.to_string(),
+    }
+}
+
+/// Converts a Java block state into a Bedrock block name and state compound.
+pub fn java_to_bedrock(block_state: &BlockState) -> (String, NbtCompound) {
+    let mut properties: HashMap<String, String> = block_state.properties.iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let mut states = HashMap::new();
+
+    let name = match block_state.name.strip_prefix("minecraft:") {
+        Some(java) => {
+            // Waterlogging is stored in the second block layer
+            properties.remove("waterlogged");
+            let bedrock = bedrock_name(java, &mut properties, &mut states);
+            for rule in rules_for(java) {
+                rule.to_bedrock(&mut properties, &mut states);
+            }
+            format!("minecraft:{}", bedrock)
+        }
+        None => block_state.name.clone(),
+    };
+
+    for (key, value) in properties {
+        states.insert(key, NbtTag::String(value));
+    }
+
+    let mut compound = NbtCompound::new();
+    for (key, tag) in states {
+        compound.insert(key, tag);
+    }
+    (name, compound)
+}
+
+/// Converts a Bedrock block name and state compound into a Java block state.
+pub fn bedrock_to_java(name: &str, states: &NbtCompound) -> BlockState {
+    let mut states: HashMap<String, NbtTag> = states.inner().iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let mut properties = HashMap::new();
+
+    let name = match name.strip_prefix("minecraft:") {
+        Some(bedrock) => {
+            let java = java_name(bedrock, &mut states, &mut properties);
+            if java.ends_with("_button") {
+                let direction = states.remove("facing_direction").and_then(|t| tag_to_i32(&t)).unwrap_or(2);
+                let (face, facing) = match direction {
+                    0 => ("ceiling", "north"),
+                    1 => ("floor", "north"),
+                    d => ("wall", SIX_WAY.get(d as usize).copied().unwrap_or("north")),
+                };
+                properties.insert("face".to_string(), face.to_string());
+                properties.insert("facing".to_string(), facing.to_string());
+            }
+            for rule in rules_for(&java) {
+                rule.to_java(&mut states, &mut properties);
+            }
+            format!("minecraft:{}", java)
+        }
+        None => name.to_string(),
+    };
+
+    for (key, tag) in states {
+        properties.entry(key).or_insert_with(|| tag_to_string(&tag));
+    }
+
+    let mut block_state = BlockState::new(name);
+    for (key, value) in properties {
+        block_state.set_property(key, value);
+    }
+    block_state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(name: &str, properties: &[(&str, &str)]) -> BlockState {
+        let mut block_state = BlockState::new(name.to_string());
+        for (key, value) in properties {
+            block_state.set_property(key.to_string(), value.to_string());
+        }
+        block_state
+    }
+
+    #[test]
+    fn test_java_bedrock_roundtrip() {
+        let cases = [
+            state("minecraft:stone", &[]),
+            state("minecraft:oak_stairs", &[("facing", "north"), ("half", "top"), ("shape", "straight"), ("waterlogged", "false")]),
+            state("minecraft:oak_log", &[("axis", "x")]),
+            state("minecraft:stone_slab", &[("type", "double"), ("waterlogged", "false")]),
+            state("minecraft:furnace", &[("facing", "east"), ("lit", "true")]),
+            state("minecraft:redstone_wall_torch", &[("facing", "west"), ("lit", "false")]),
+            state("minecraft:repeater", &[("delay", "3"), ("facing", "south"), ("locked", "false"), ("powered", "true")]),
+            state("minecraft:lever", &[("face", "floor"), ("facing", "east"), ("powered", "true")]),
+            state("minecraft:stone_button", &[("face", "wall"), ("facing", "west"), ("powered", "false")]),
+            state("minecraft:spruce_sign", &[("rotation", "7"), ("waterlogged", "false")]),
+            state("minecraft:jack_o_lantern", &[("facing", "north")]),
+            state("minecraft:water", &[("level", "0")]),
+        ];
+
+        for java in cases {
+            let (name, states) = java_to_bedrock(&java);
+            assert_eq!(bedrock_to_java(&name, &states), java, "roundtrip through {}", name);
+        }
+    }
+
+    #[test]
+    fn test_bedrock_states() {
+        let (name, states) = java_to_bedrock(&state("minecraft:oak_stairs", &[("facing", "north"), ("half", "top")]));
+        assert_eq!(name, "minecraft:oak_stairs");
+        assert_eq!(states.get::<_, i32>("weirdo_direction").unwrap(), 3);
+        assert_eq!(states.get::<_, i8>("upside_down_bit").unwrap(), 1);
+
+        let (name, states) = java_to_bedrock(&state("minecraft:smooth_stone_slab", &[("type", "double")]));
+        assert_eq!(name, "minecraft:smooth_stone_double_slab");
+        assert!(states.is_empty());
+
+        let (name, _) = java_to_bedrock(&state("minecraft:cave_air", &[]));
+        assert_eq!(name, "minecraft:air");
+    }
+}
@@ -0,0 +1,238 @@
+// Little-endian NBT as used by Bedrock Edition.
+// quartz_nbt only speaks the big-endian Java flavor, so we read and write NbtMap trees here.
+use crate::utils::{NbtMap, NbtValue};
+use crate::NucleationError;
+
+const TAG_END: u8 = 0;
+const TAG_BYTE: u8 = 1;
+const TAG_SHORT: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_LONG: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_DOUBLE: u8 = 6;
+const TAG_BYTE_ARRAY: u8 = 7;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+const TAG_INT_ARRAY: u8 = 11;
+const TAG_LONG_ARRAY: u8 = 12;
+
+// Guards against stack overflows on malicious input
+const MAX_DEPTH: usize = 512;
+
+pub(crate) fn read_le_nbt(data: &[u8]) -> Result<(NbtMap, String), NucleationError> {
+    let mut reader = LeReader { data, pos: 0 };
+    if reader.read_u8()? != TAG_COMPOUND {
+        return Err("Root tag is not a compound".into());
+    }
+    let name = reader.read_string()?;
+    let root = reader.read_compound(0)?;
+    Ok((root, name))
+}
+
+pub(crate) fn write_le_nbt(root: &NbtMap, name: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.push(TAG_COMPOUND);
+    write_string(&mut out, name);
+    write_compound(&mut out, root);
+    out
+}
+
+struct LeReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> LeReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], NucleationError> {
+        if len > self.data.len() - self.pos {
+            return Err("Unexpected end of NBT data".into());
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], NucleationError> {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, NucleationError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_i32(&mut self) -> Result<i32, NucleationError> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_i64(&mut self) -> Result<i64, NucleationError> {
+        Ok(i64::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_len(&mut self, element_size: usize) -> Result<usize, NucleationError> {
+        let len = self.read_i32()?;
+        if len < 0 || (len as usize).saturating_mul(element_size) > self.data.len() - self.pos {
+            return Err(format!("Invalid NBT array length {}", len).into());
+        }
+        Ok(len as usize)
+    }
+
+    fn read_string(&mut self) -> Result<String, NucleationError> {
+        let len = u16::from_le_bytes(self.read_array()?) as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn read_compound(&mut self, depth: usize) -> Result<NbtMap, NucleationError> {
+        let mut compound = NbtMap::new();
+        loop {
+            let tag_type = self.read_u8()?;
+            if tag_type == TAG_END {
+                return Ok(compound);
+            }
+            let name = self.read_string()?;
+            let tag = self.read_tag(tag_type, depth + 1)?;
+            compound.insert(name, tag);
+        }
+    }
+
+    fn read_tag(&mut self, tag_type: u8, depth: usize) -> Result<NbtValue, NucleationError> {
+        if depth > MAX_DEPTH {
+            return Err("NBT data is nested too deeply".into());
+        }
+
+        Ok(match tag_type {
+            TAG_BYTE => NbtValue::Byte(self.read_u8()? as i8),
+            TAG_SHORT => NbtValue::Short(i16::from_le_bytes(self.read_array()?)),
+            TAG_INT => NbtValue::Int(self.read_i32()?),
+            TAG_LONG => NbtValue::Long(self.read_i64()?),
+            TAG_FLOAT => NbtValue::Float(f32::from_le_bytes(self.read_array()?)),
+            TAG_DOUBLE => NbtValue::Double(f64::from_le_bytes(self.read_array()?)),
+            TAG_BYTE_ARRAY => {
+                let len = self.read_len(1)?;
+                NbtValue::ByteArray(self.take(len)?.iter().map(|&b| b as i8).collect())
+            }
+            TAG_STRING => NbtValue::String(self.read_string()?),
+            TAG_LIST => {
+                let element_type = self.read_u8()?;
+                let len = self.read_len(0)?;
+                if element_type == TAG_END && len > 0 {
+                    return Err("List of end tags".into());
+                }
+                if len == 0 && element_type != TAG_END {
+                    return Ok(NbtValue::EmptyList(element_type));
+                }
+                let mut list = Vec::with_capacity(len.min(self.data.len() - self.pos));
+                for _ in 0..len {
+                    list.push(self.read_tag(element_type, depth + 1)?);
+                }
+                NbtValue::List(list)
+            }
+            TAG_COMPOUND => NbtValue::Compound(self.read_compound(depth)?),
+            TAG_INT_ARRAY => {
+                let len = self.read_len(4)?;
+                let mut values = Vec::with_capacity(len);
+                for _ in 0..len {
+                    values.push(self.read_i32()?);
+                }
+                NbtValue::IntArray(values)
+            }
+            TAG_LONG_ARRAY => {
+                let len = self.read_len(8)?;
+                let mut values = Vec::with_capacity(len);
+                for _ in 0..len {
+                    values.push(self.read_i64()?);
+                }
+                NbtValue::LongArray(values)
+            }
+            other => return Err(format!("Unknown NBT tag type {}", other).into()),
+        })
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    let bytes = value.as_bytes();
+    let len = bytes.len().min(u16::MAX as usize);
+    out.extend_from_slice(&(len as u16).to_le_bytes());
+    out.extend_from_slice(&bytes[..len]);
+}
+
+fn write_compound(out: &mut Vec<u8>, compound: &NbtMap) {
+    for (name, tag) in compound {
+        out.push(tag.tag_id());
+        write_string(out, name);
+        write_tag(out, tag);
+    }
+    out.push(TAG_END);
+}
+
+fn write_tag(out: &mut Vec<u8>, tag: &NbtValue) {
+    match tag {
+        NbtValue::Byte(value) => out.push(*value as u8),
+        NbtValue::Short(value) => out.extend_from_slice(&value.to_le_bytes()),
+        NbtValue::Int(value) => out.extend_from_slice(&value.to_le_bytes()),
+        NbtValue::Long(value) => out.extend_from_slice(&value.to_le_bytes()),
+        NbtValue::Float(value) => out.extend_from_slice(&value.to_le_bytes()),
+        NbtValue::Double(value) => out.extend_from_slice(&value.to_le_bytes()),
+        NbtValue::ByteArray(values) => {
+            out.extend_from_slice(&(values.len() as i32).to_le_bytes());
+            out.extend(values.iter().map(|&b| b as u8));
+        }
+        NbtValue::String(value) => write_string(out, value),
+        NbtValue::List(list) => {
+            // Untyped empty lists are written as lists of end tags, like Bedrock does
+            let element_type = list.first().map(NbtValue::tag_id).unwrap_or(TAG_END);
+            out.push(element_type);
+            out.extend_from_slice(&(list.len() as i32).to_le_bytes());
+            for element in list.iter() {
+                write_tag(out, element);
+            }
+        }
+        NbtValue::EmptyList(element_type) => {
+            out.push(*element_type);
+            out.extend_from_slice(&0i32.to_le_bytes());
+        }
+        NbtValue::Compound(compound) => write_compound(out, compound),
+        NbtValue::IntArray(values) => {
+            out.extend_from_slice(&(values.len() as i32).to_le_bytes());
+            for value in values {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        NbtValue::LongArray(values) => {
+            out.extend_from_slice(&(values.len() as i32).to_le_bytes());
+            for value in values {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_le_roundtrip() {
+        let mut nested = NbtMap::new();
+        nested.insert("name", NbtValue::String("minecraft:stone".to_string()));
+        let mut root = NbtMap::new();
+        root.insert("byte", NbtValue::Byte(-3));
+        root.insert("int", NbtValue::Int(0x01020304));
+        root.insert("double", NbtValue::Double(1.5));
+        root.insert("ints", NbtValue::List(vec![NbtValue::Int(1), NbtValue::Int(2)]));
+        root.insert("empty", NbtValue::List(Vec::new()));
+        root.insert("empty_compounds", NbtValue::EmptyList(TAG_COMPOUND));
+        root.insert("nested", NbtValue::Compound(nested));
+
+        let data = write_le_nbt(&root, "");
+        // Tag type, empty name, then the first entry's type
+        assert_eq!(&data[..3], &[TAG_COMPOUND, 0, 0]);
+
+        let (read, name) = read_le_nbt(&data).unwrap();
+        assert_eq!(name, "");
+        assert_eq!(read, root);
+        assert!(read_le_nbt(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -1,63 +1,53 @@
-use std::io::{Cursor, Read};
-use quartz_nbt::{NbtCompound, NbtTag, NbtList};
-use std::time::{SystemTime, UNIX_EPOCH};
-use flate2::read::GzDecoder;
-use quartz_nbt::io::Flavor;
-use crate::{UniversalSchematic, BlockState};
+use std::borrow::Cow;
+use crate::{UniversalSchematic, BlockState, NucleationError};
 use crate::block_entity::BlockEntity;
 use crate::entity::Entity;
 use crate::region::Region;
+use crate::formats::{read_gzip_nbt, write_gzip_nbt, ExportOptions};
+use crate::utils::{NbtMap, NbtValue};
 
 
 pub fn is_litematic(data: &[u8]) -> bool {
-    // Decompress the data
-    let mut decoder = GzDecoder::new(data);
-    let mut decompressed = Vec::new();
-    if decoder.read_to_end(&mut decompressed).is_err() {
-        return false;
-    }
-
-    // Read the NBT data
-    let (root, _) = match quartz_nbt::io::read_nbt(&mut Cursor::new(decompressed), Flavor::Uncompressed) {
-        Ok(result) => result,
+    // Decompress and read the NBT data
+    let root = match read_gzip_nbt(data) {
+        Ok(root) => root,
         Err(_) => return false,
     };
 
     // Check for required fields as per the Litematic format
-    root.get::<_, i32>("Version").is_ok() &&
-        root.get::<_, &NbtCompound>("Metadata").is_ok() &&
-        root.get::<_, &NbtCompound>("Regions").is_ok()
+    root.get_as::<i32>("Version").is_ok() &&
+        root.get_as::<&NbtMap>("Metadata").is_ok() &&
+        root.get_as::<&NbtMap>("Regions").is_ok()
 }
-pub fn to_litematic(schematic: &UniversalSchematic) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut root = NbtCompound::new();
+pub fn to_litematic(schematic: &UniversalSchematic) -> Result<Vec<u8>, NucleationError> {
+    to_litematic_with_options(schematic, &ExportOptions::default())
+}
+
+pub fn to_litematic_with_options(schematic: &UniversalSchematic, options: &ExportOptions) -> Result<Vec<u8>, NucleationError> {
+    let schematic = &*options.prepare(schematic);
+    let mut root = NbtMap::new();
 
     // Add Version and SubVersion
-    root.insert("Version", NbtTag::Int(6));
-    root.insert("SubVersion", NbtTag::Int(1));
+    root.insert("Version", NbtValue::Int(6));
+    root.insert("SubVersion", NbtValue::Int(1));
 
     // Add MinecraftDataVersion
-    root.insert("MinecraftDataVersion", NbtTag::Int(schematic.metadata.mc_version.unwrap_or(3700)));
+    root.insert("MinecraftDataVersion", NbtValue::Int(schematic.metadata.mc_version.unwrap_or(3700)));
 
     // Add Metadata
-    let metadata = create_metadata(schematic);
-    root.insert("Metadata", NbtTag::Compound(metadata));
+    let metadata = create_metadata(schematic, options);
+    root.insert("Metadata", NbtValue::Compound(metadata));
 
     // Add Regions
-    let regions = create_regions(schematic);
-    root.insert("Regions", NbtTag::Compound(regions));
+    let regions = create_regions(schematic, options);
+    root.insert("Regions", NbtValue::Compound(regions));
 
     // Compress and return the NBT data
-    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
-    quartz_nbt::io::write_nbt(&mut encoder, None, &root, quartz_nbt::io::Flavor::Uncompressed)?;
-    Ok(encoder.finish()?)
+    write_gzip_nbt(&root, "")
 }
 
-pub fn from_litematic(data: &[u8]) -> Result<UniversalSchematic, Box<dyn std::error::Error>> {
-    let mut decoder = flate2::read::GzDecoder::new(data);
-    let mut decompressed = Vec::new();
-    std::io::Read::read_to_end(&mut decoder, &mut decompressed)?;
-
-    let (root, _) = quartz_nbt::io::read_nbt(&mut std::io::Cursor::new(decompressed), quartz_nbt::io::Flavor::Uncompressed)?;
+pub fn from_litematic(data: &[u8]) -> Result<UniversalSchematic, NucleationError> {
+    let root = read_gzip_nbt(data)?;
 
     let mut schematic = UniversalSchematic::new("Unnamed".to_string());
 
@@ -70,75 +60,65 @@ pub fn from_litematic(data: &[u8]) -> Result<UniversalSchematic, Box<dyn std::er
     Ok(schematic)
 }
 
-fn create_metadata(schematic: &UniversalSchematic) -> NbtCompound {
-    let mut metadata = NbtCompound::new();
-
-    metadata.insert("Name", NbtTag::String(schematic.metadata.name.clone().unwrap_or_default()));
-    metadata.insert("Description", NbtTag::String(schematic.metadata.description.clone().unwrap_or_default()));
-    metadata.insert("Author", NbtTag::String(schematic.metadata.author.clone().unwrap_or_default()));
-
-    // Get current time as milliseconds since epoch, safely handling both WASM and non-WASM environments
-    let now = if let Some(time) = schematic.metadata.created {
-        // Use existing timestamp if available
-        time as i64
-    } else {
-        // Generate current timestamp based on platform
-        #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
-        let current_time = js_sys::Date::now() as i64;
-
-        #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
-        let current_time = {
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_millis() as i64
-        };
+fn create_metadata(schematic: &UniversalSchematic, options: &ExportOptions) -> NbtMap {
+    let mut metadata = NbtMap::new();
 
-        current_time
-    };
+    metadata.insert("Name", NbtValue::String(schematic.metadata.name.clone().unwrap_or_default()));
+    metadata.insert("Description", NbtValue::String(schematic.metadata.description.clone().unwrap_or_default()));
+    metadata.insert("Author", NbtValue::String(schematic.metadata.author.clone().unwrap_or_default()));
+
+    // A fixed export timestamp wins, then the existing timestamp, then the current time
+    let now = options.timestamp.or(schematic.metadata.created).unwrap_or_else(|| options.timestamp_millis()) as i64;
 
     // Use existing modified timestamp or fall back to creation time
     let modified = schematic.metadata.modified.unwrap_or(now as u64) as i64;
 
-    metadata.insert("TimeCreated", NbtTag::Long(now));
-    metadata.insert("TimeModified", NbtTag::Long(modified));
+    metadata.insert("TimeCreated", NbtValue::Long(now));
+    metadata.insert("TimeModified", NbtValue::Long(modified));
 
     // Rest of the function remains the same...
     let bounding_box = schematic.get_bounding_box();
     let (width, height, length) = bounding_box.get_dimensions();
-    let mut enclosing_size = NbtCompound::new();
-    enclosing_size.insert("x", NbtTag::Int(width as i32));
-    enclosing_size.insert("y", NbtTag::Int(height as i32));
-    enclosing_size.insert("z", NbtTag::Int(length as i32));
-    metadata.insert("EnclosingSize", NbtTag::Compound(enclosing_size));
+    let mut enclosing_size = NbtMap::new();
+    enclosing_size.insert("x", NbtValue::Int(width as i32));
+    enclosing_size.insert("y", NbtValue::Int(height as i32));
+    enclosing_size.insert("z", NbtValue::Int(length as i32));
+    metadata.insert("EnclosingSize", NbtValue::Compound(enclosing_size));
 
-    metadata.insert("TotalVolume", NbtTag::Int(schematic.total_volume() as i32));
-    metadata.insert("TotalBlocks", NbtTag::Int(schematic.total_blocks() as i32));
-    metadata.insert("RegionCount", NbtTag::Int(schematic.other_regions.len() as i32 + 1));
+    metadata.insert("TotalVolume", NbtValue::Int(schematic.total_volume() as i32));
+    metadata.insert("TotalBlocks", NbtValue::Int(schematic.total_blocks() as i32));
+    metadata.insert("RegionCount", NbtValue::Int(schematic.other_regions.len() as i32 + 1));
 
-    metadata.insert("Software", NbtTag::String("UniversalSchematic".to_string()));
+    metadata.insert("Software", NbtValue::String("UniversalSchematic".to_string()));
 
     metadata
 }
-fn create_regions(schematic: &UniversalSchematic) -> NbtCompound {
-    let mut regions = NbtCompound::new();
+fn create_regions(schematic: &UniversalSchematic, options: &ExportOptions) -> NbtMap {
+    let mut regions = NbtMap::new();
 
     for (name, region) in &schematic.get_all_regions() {
-        let mut region_nbt = NbtCompound::new();
-
-        // Position
-        let mut position = NbtCompound::new();
-        position.insert("x", NbtTag::Int(region.position.0));
-        position.insert("y", NbtTag::Int(region.position.1));
-        position.insert("z", NbtTag::Int(region.position.2));
-        region_nbt.insert("Position", NbtTag::Compound(position));
-
-        // Size
-        let mut size = NbtCompound::new();
-        size.insert("x", NbtTag::Int(region.size.0));
-        size.insert("y", NbtTag::Int(region.size.1));
-        size.insert("z", NbtTag::Int(region.size.2));
-        region_nbt.insert("Size", NbtTag::Compound(size));
+        let region = if options.deterministic {
+            let mut region = (*region).clone();
+            region.compact_palette();
+            Cow::Owned(region)
+        } else {
+            Cow::Borrowed(*region)
+        };
+        let mut region_nbt = NbtMap::new();
+
+        // Position and Size, negative sizes put Position at the far corner like Litematica does
+        let (region_position, region_size) = region.get_signed_position_and_size();
+        let mut position = NbtMap::new();
+        position.insert("x", NbtValue::Int(region_position.0));
+        position.insert("y", NbtValue::Int(region_position.1));
+        position.insert("z", NbtValue::Int(region_position.2));
+        region_nbt.insert("Position", NbtValue::Compound(position));
+
+        let mut size = NbtMap::new();
+        size.insert("x", NbtValue::Int(region_size.0));
+        size.insert("y", NbtValue::Int(region_size.1));
+        size.insert("z", NbtValue::Int(region_size.2));
+        region_nbt.insert("Size", NbtValue::Compound(size));
 
         // BlockStatePalette
         // Create a reordered palette with air always at index 0
@@ -161,8 +141,8 @@ fn create_regions(schematic: &UniversalSchematic) -> NbtCompound {
         }
         
         // Create the NBT list for the reordered palette
-        let palette = NbtList::from(reordered_palette.iter().map(|block_state| block_state.to_nbt()).collect::<Vec<NbtTag>>());
-        region_nbt.insert("BlockStatePalette", NbtTag::List(palette));
+        let palette = reordered_palette.iter().map(|block_state| NbtValue::from_quartz_nbt(&block_state.to_nbt())).collect();
+        region_nbt.insert("BlockStatePalette", NbtValue::List(palette));
 
         // BlockStates
         // We need to map block indices from the original palette to the reordered palette
@@ -189,7 +169,7 @@ fn create_regions(schematic: &UniversalSchematic) -> NbtCompound {
         let mut packed_states = vec![0i64; expected_len];
         let mask = (1i64 << bits_per_block) - 1;
         
-        for (index, &block_state) in region.blocks.iter().enumerate() {
+        for (index, block_state) in region.blocks.iter().enumerate() {
             // Map the original block state index to the reordered index
             let mapped_state = index_mapping[block_state];
             
@@ -211,48 +191,48 @@ fn create_regions(schematic: &UniversalSchematic) -> NbtCompound {
         // Handle negative numbers
         packed_states.iter_mut().for_each(|x| *x = *x as u64 as i64);
         
-        region_nbt.insert("BlockStates", NbtTag::LongArray(packed_states));
+        region_nbt.insert("BlockStates", NbtValue::LongArray(packed_states));
 
         // Entities
-        let entities = NbtList::from(region.entities.iter().map(|entity| entity.to_nbt()).collect::<Vec<NbtTag>>());
-        region_nbt.insert("Entities", NbtTag::List(entities));
+        let entities = options.entities(&region).into_iter().map(|entity| NbtValue::Compound(entity.to_nbt_map())).collect();
+        region_nbt.insert("Entities", NbtValue::List(entities));
 
         // TileEntities
-        let tile_entities = NbtList::from(region.block_entities.values().map(|block_entity| {
-            NbtTag::Compound(block_entity.to_nbt())
-        }).collect::<Vec<NbtTag>>());
-        region_nbt.insert("TileEntities", NbtTag::List(tile_entities));
+        let tile_entities = options.block_entities(&region).into_iter().map(|block_entity| {
+            NbtValue::Compound(block_entity.to_nbt_map())
+        }).collect();
+        region_nbt.insert("TileEntities", NbtValue::List(tile_entities));
 
 
         // PendingBlockTicks and PendingFluidTicks (not fully supported, using empty lists)
-        region_nbt.insert("PendingBlockTicks", NbtTag::List(NbtList::new()));
-        region_nbt.insert("PendingFluidTicks", NbtTag::List(NbtList::new()));
+        region_nbt.insert("PendingBlockTicks", NbtValue::EmptyList(10));
+        region_nbt.insert("PendingFluidTicks", NbtValue::EmptyList(10));
 
-        regions.insert(name, NbtTag::Compound(region_nbt));
+        regions.insert(name.clone(), NbtValue::Compound(region_nbt));
     }
 
     regions
 }
 
 
-fn parse_metadata(root: &NbtCompound, schematic: &mut UniversalSchematic) -> Result<(), Box<dyn std::error::Error>> {
-    let metadata = root.get::<_, &NbtCompound>("Metadata")?;
+fn parse_metadata(root: &NbtMap, schematic: &mut UniversalSchematic) -> Result<(), NucleationError> {
+    let metadata = root.get_as::<&NbtMap>("Metadata")?;
 
-    schematic.metadata.name = metadata.get::<_, &str>("Name").ok().map(String::from);
-    schematic.metadata.description = metadata.get::<_, &str>("Description").ok().map(String::from);
-    schematic.metadata.author = metadata.get::<_, &str>("Author").ok().map(String::from);
-    schematic.metadata.created = metadata.get::<_, i64>("TimeCreated").ok().map(|t| t as u64);
-    schematic.metadata.modified = metadata.get::<_, i64>("TimeModified").ok().map(|t| t as u64);
+    schematic.metadata.name = metadata.get_as::<&str>("Name").ok().map(String::from);
+    schematic.metadata.description = metadata.get_as::<&str>("Description").ok().map(String::from);
+    schematic.metadata.author = metadata.get_as::<&str>("Author").ok().map(String::from);
+    schematic.metadata.created = metadata.get_as::<i64>("TimeCreated").ok().map(|t| t as u64);
+    schematic.metadata.modified = metadata.get_as::<i64>("TimeModified").ok().map(|t| t as u64);
 
     // We don't need to parse EnclosingSize, TotalVolume, TotalBlocks as they will be recalculated
 
     Ok(())
 }
 
-fn parse_regions(root: &NbtCompound, schematic: &mut UniversalSchematic) -> Result<(), Box<dyn std::error::Error>> {
-    let regions = root.get::<_, &NbtCompound>("Regions")?;
+fn parse_regions(root: &NbtMap, schematic: &mut UniversalSchematic) -> Result<(), NucleationError> {
+    let regions = root.get_as::<&NbtMap>("Regions")?;
     let mut loop_count = 0;
-    for (name, region_tag) in regions.inner() {
+    for (name, region_tag) in regions {
         //if it's the first region we want to override the default region name
         if loop_count == 0 {
             schematic.default_region_name = name.clone();
@@ -260,42 +240,42 @@ fn parse_regions(root: &NbtCompound, schematic: &mut UniversalSchematic) -> Resu
         loop_count += 1;
 
 
-        if let NbtTag::Compound(region_nbt) = region_tag {
-            let position = region_nbt.get::<_, &NbtCompound>("Position")?;
-            let size = region_nbt.get::<_, &NbtCompound>("Size")?;
+        if let NbtValue::Compound(region_nbt) = region_tag {
+            let position = region_nbt.get_as::<&NbtMap>("Position")?;
+            let size = region_nbt.get_as::<&NbtMap>("Size")?;
 
             let position = (
-                position.get::<_, i32>("x")?,
-                position.get::<_, i32>("y")?,
-                position.get::<_, i32>("z")?,
+                position.get_as::<i32>("x")?,
+                position.get_as::<i32>("y")?,
+                position.get_as::<i32>("z")?,
             );
             let size = (
-                size.get::<_, i32>("x")?,
-                size.get::<_, i32>("y")?,
-                size.get::<_, i32>("z")?,
+                size.get_as::<i32>("x")?,
+                size.get_as::<i32>("y")?,
+                size.get_as::<i32>("z")?,
             );
 
             let mut region = Region::new(name.to_string(), position, size);
 
             // Parse BlockStatePalette
-            let palette = region_nbt.get::<_, &NbtList>("BlockStatePalette")?;
+            let palette = region_nbt.get_as::<&[NbtValue]>("BlockStatePalette")?;
             region.palette = palette.iter().filter_map(|tag| {
-                if let NbtTag::Compound(compound) = tag {
-                    BlockState::from_nbt(compound).ok()
+                if let NbtValue::Compound(compound) = tag {
+                    BlockState::from_nbt(&compound.to_quartz_nbt()).ok()
                 } else {
                     None
                 }
             }).collect();
 
             // Parse BlockStates
-            let block_states = region_nbt.get::<_, &[i64]>("BlockStates")?;
+            let block_states = region_nbt.get_as::<&[i64]>("BlockStates")?;
             // region.unpack_block_states(block_states);
             region.blocks = region.unpack_block_states(block_states);
             // Parse Entities
-            if let Ok(entities_list) = region_nbt.get::<_, &NbtList>("Entities") {
+            if let Ok(entities_list) = region_nbt.get_as::<&[NbtValue]>("Entities") {
                 region.entities = entities_list.iter().filter_map(|tag| {
-                    if let NbtTag::Compound(compound) = tag {
-                        Entity::from_nbt(compound).ok()
+                    if let NbtValue::Compound(compound) = tag {
+                        Entity::from_nbt_map(compound).ok()
                     } else {
                         None
                     }
@@ -303,10 +283,10 @@ fn parse_regions(root: &NbtCompound, schematic: &mut UniversalSchematic) -> Resu
             }
 
             // Parse TileEntities
-            if let Ok(tile_entities_list) = region_nbt.get::<_, &NbtList>("TileEntities") {
+            if let Ok(tile_entities_list) = region_nbt.get_as::<&[NbtValue]>("TileEntities") {
                 for tag in tile_entities_list.iter() {
-                    if let NbtTag::Compound(compound) = tag {
-                        if let block_entity = BlockEntity::from_nbt(compound) {
+                    if let NbtValue::Compound(compound) = tag {
+                        if let block_entity = BlockEntity::from_nbt_map(compound) {
                             region.block_entities.insert(block_entity.position, block_entity);
                         }
                     }
@@ -327,6 +307,7 @@ mod tests {
     use num_complex::Complex;
     use super::*;
     use crate::{UniversalSchematic, BlockState};
+    use quartz_nbt::{NbtCompound, NbtList, NbtTag};
 
     #[test]
     fn test_create_metadata() {
@@ -336,7 +317,7 @@ mod tests {
         schematic.metadata.created = Some(1000);
         schematic.metadata.modified = Some(2000);
 
-        let metadata = create_metadata(&schematic);
+        let metadata = create_metadata(&schematic, &ExportOptions::default()).to_quartz_nbt();
 
         assert_eq!(metadata.get::<_, &str>("Name").unwrap(), "Test Schematic");
         assert_eq!(metadata.get::<_, &str>("Author").unwrap(), "Test Author");
@@ -369,7 +350,7 @@ mod tests {
 
         schematic.add_region(region);
 
-        let regions = create_regions(&schematic);
+        let regions = create_regions(&schematic, &ExportOptions::default()).to_quartz_nbt();
 
         assert!(regions.contains_key("TestRegion"));
         let region_nbt = regions.get::<_, &NbtCompound>("TestRegion").unwrap();
@@ -396,7 +377,7 @@ mod tests {
         root.insert("Metadata", NbtTag::Compound(metadata));
 
         let mut schematic = UniversalSchematic::new("".to_string());
-        parse_metadata(&root, &mut schematic).unwrap();
+        parse_metadata(&NbtMap::from_quartz_nbt(&root), &mut schematic).unwrap();
 
         assert_eq!(schematic.metadata.name, Some("Test Schematic".to_string()));
         assert_eq!(schematic.metadata.author, Some("Test Author".to_string()));
@@ -438,7 +419,7 @@ mod tests {
         println!("{:?}", root);
 
         let mut schematic = UniversalSchematic::new("Test Schematic".to_string());
-        parse_regions(&root, &mut schematic).unwrap();
+        parse_regions(&NbtMap::from_quartz_nbt(&root), &mut schematic).unwrap();
 
         assert_eq!(schematic.default_region_name, "TestRegion");
 
@@ -448,6 +429,53 @@ mod tests {
         assert_eq!(parsed_region.palette.len(), 2);
         assert_eq!(parsed_region.count_blocks(), 2); // 2 stone blocks
     }
+    #[test]
+    fn test_negative_size_regions_keep_layout() {
+        fn int_triple(x: i32, y: i32, z: i32) -> NbtTag {
+            let mut compound = NbtCompound::new();
+            compound.insert("x", NbtTag::Int(x));
+            compound.insert("y", NbtTag::Int(y));
+            compound.insert("z", NbtTag::Int(z));
+            NbtTag::Compound(compound)
+        }
+        fn region_nbt(position: (i32, i32, i32), size: (i32, i32, i32), blocks: &[&str], states: i64) -> NbtTag {
+            let mut region = NbtCompound::new();
+            region.insert("Position", int_triple(position.0, position.1, position.2));
+            region.insert("Size", int_triple(size.0, size.1, size.2));
+            let palette = blocks.iter().map(|name| BlockState::new(name.to_string()).to_nbt()).collect::<Vec<NbtTag>>();
+            region.insert("BlockStatePalette", NbtTag::List(NbtList::from(palette)));
+            region.insert("BlockStates", NbtTag::LongArray(vec![states]));
+            NbtTag::Compound(region)
+        }
+
+        // "Zeta" spans x 4..=5 and z 3..=5 with its Position on the far corner, and comes before "Alpha"
+        let mut regions = NbtCompound::new();
+        regions.insert("Zeta", region_nbt((5, 0, 5), (-2, 1, -3), &["minecraft:air", "minecraft:stone", "minecraft:dirt"], 1 | (2 << 10)));
+        regions.insert("Alpha", region_nbt((0, 0, 0), (1, 1, 1), &["minecraft:air", "minecraft:gold_block"], 1));
+        let mut root = NbtCompound::new();
+        root.insert("Regions", NbtTag::Compound(regions));
+
+        let mut schematic = UniversalSchematic::new("Layout".to_string());
+        parse_regions(&NbtMap::from_quartz_nbt(&root), &mut schematic).unwrap();
+        assert_eq!(schematic.get_region_names(), vec!["Zeta".to_string(), "Alpha".to_string()]);
+
+        let zeta = schematic.get_region("Zeta").unwrap();
+        assert_eq!((zeta.position, zeta.size), ((4, 0, 3), (2, 1, 3)));
+        assert_eq!(zeta.get_block(4, 0, 3).unwrap().name, "minecraft:stone");
+        assert_eq!(zeta.get_block(5, 0, 5).unwrap().name, "minecraft:dirt");
+
+        let regions = create_regions(&schematic, &ExportOptions::default()).to_quartz_nbt();
+        let names: Vec<&String> = regions.inner().keys().collect();
+        assert_eq!(names, vec!["Zeta", "Alpha"]);
+        let zeta = regions.get::<_, &NbtCompound>("Zeta").unwrap();
+        assert_eq!(zeta.get::<_, &NbtTag>("Position").unwrap(), &int_triple(5, 0, 5));
+        assert_eq!(zeta.get::<_, &NbtTag>("Size").unwrap(), &int_triple(-2, 1, -3));
+
+        let roundtrip = from_litematic(&to_litematic(&schematic).unwrap()).unwrap();
+        assert_eq!(roundtrip.get_region_names(), schematic.get_region_names());
+        assert_eq!(roundtrip.get_block(5, 0, 5).unwrap().name, "minecraft:dirt");
+    }
+
     #[test]
     fn test_simple_litematic() {
         let mut schematic = UniversalSchematic::new("Simple Cube".to_string());
@@ -529,4 +557,16 @@ mod tests {
         }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_empty_list_type_round_trip() {
+        let mut schematic = UniversalSchematic::new("Test Schematic".to_string());
+        schematic.set_block_str(0, 0, 0, "minecraft:chest");
+        let block_entity = BlockEntity::new("minecraft:chest".to_string(), (0, 0, 0))
+            .with_nbt_data("Items".to_string(), NbtValue::EmptyList(10));
+        schematic.set_block_entity(crate::BlockPosition { x: 0, y: 0, z: 0 }, block_entity);
+
+        let loaded = from_litematic(&to_litematic(&schematic).unwrap()).unwrap();
+        let block_entity = loaded.get_block_entity(crate::BlockPosition { x: 0, y: 0, z: 0 }).unwrap();
+        assert_eq!(block_entity.nbt.get("Items"), Some(&NbtValue::EmptyList(10)));
+    }
+}
@@ -0,0 +1,641 @@
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::sync::OnceLock;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use quartz_nbt::io::{read_nbt, Flavor};
+use crate::{BlockState, NucleationError, UniversalSchematic};
+use crate::block_entity::BlockEntity;
+use crate::entity::Entity;
+use crate::formats::legacy_blocks::LEGACY_BLOCKS;
+use crate::formats::schematic::parse_block_state;
+use crate::region::Region;
+use crate::utils::{NbtMap, NbtValue};
+
+const COLORS: [&str; 16] = [
+    "white", "orange", "magenta", "light_blue", "yellow", "lime", "pink", "gray",
+    "light_gray", "cyan", "purple", "blue", "brown", "green", "red", "black",
+];
+
+const DOOR_IDS: [u16; 7] = [64, 71, 193, 194, 195, 196, 197];
+const DOUBLE_PLANT_ID: u16 = 175;
+
+type LegacyCandidates = HashMap<String, Vec<(BlockState, u16, u8)>>;
+
+pub fn is_mcedit(data: &[u8]) -> bool {
+    let reader = BufReader::with_capacity(1 << 20, data);
+    let mut gz = GzDecoder::new(reader);
+    let (root, _) = match read_nbt(&mut gz, Flavor::Uncompressed) {
+        Ok(result) => result,
+        Err(_) => return false,
+    };
+
+    is_mcedit_root(&root)
+}
+
+// MCEdit / WorldEdit legacy schematics store numeric ids in `Blocks` and data values in `Data`
+pub(crate) fn is_mcedit_root(root: &NbtCompound) -> bool {
+    root.get::<_, &[i8]>("Blocks").is_ok() &&
+        root.get::<_, &[i8]>("Data").is_ok() &&
+        root.get::<_, i16>("Width").is_ok() &&
+        root.get::<_, i16>("Height").is_ok() &&
+        root.get::<_, i16>("Length").is_ok()
+}
+
+pub fn from_mcedit(data: &[u8]) -> Result<UniversalSchematic, NucleationError> {
+    let reader = BufReader::with_capacity(1 << 20, data);
+    let mut gz = GzDecoder::new(reader);
+    let (root, _) = read_nbt(&mut gz, Flavor::Uncompressed)?;
+    from_mcedit_root(&root)
+}
+
+pub(crate) fn from_mcedit_root(root: &NbtCompound) -> Result<UniversalSchematic, NucleationError> {
+    let width = root.get::<_, i16>("Width")? as i32;
+    let height = root.get::<_, i16>("Height")? as i32;
+    let length = root.get::<_, i16>("Length")? as i32;
+
+    let block_ids = read_block_ids(root, (width * height * length) as usize)?;
+    let block_data = root.get::<_, &[i8]>("Data")?;
+    if block_data.len() != block_ids.len() {
+        return Err(format!(
+            "Block data length mismatch: expected {}, got {}",
+            block_ids.len(),
+            block_data.len()
+        ).into());
+    }
+    let block_data: Vec<u8> = block_data.iter().map(|&d| d as u8 & 0x0F).collect();
+
+    let mut schematic = UniversalSchematic::new("Unnamed".to_string());
+    let mut region = Region::new(schematic.default_region_name.clone(), (0, 0, 0), (width, height, length));
+
+    // Legacy schematics reuse a handful of id:data pairs over and over
+    let mut palette_cache: HashMap<(u16, u8), usize> = HashMap::new();
+    let layer = (width * length) as usize;
+    for index in 0..block_ids.len() {
+        let (id, data) = (block_ids[index], block_data[index]);
+
+        // Doors and double plants only store half of their state in each block
+        if DOOR_IDS.contains(&id) || id == DOUBLE_PLANT_ID {
+            let below = index.checked_sub(layer).map(|i| (block_ids[i], block_data[i]));
+            let above = block_ids.get(index + layer).map(|&above_id| (above_id, block_data[index + layer]));
+            let state = two_block_state(id, data, below, above);
+            let palette_index = region.get_or_insert_in_palette(state);
+            region.blocks.set(index, palette_index);
+            continue;
+        }
+
+        let palette_index = match palette_cache.get(&(id, data)) {
+            Some(&palette_index) => palette_index,
+            None => {
+                let palette_index = region.get_or_insert_in_palette(legacy_to_block_state(id, data));
+                palette_cache.insert((id, data), palette_index);
+                palette_index
+            }
+        };
+        region.blocks.set(index, palette_index);
+    }
+
+    if let Ok(tile_entities) = root.get::<_, &NbtList>("TileEntities") {
+        for tag in tile_entities.iter() {
+            if let NbtTag::Compound(compound) = tag {
+                if let Some(block_entity) = parse_tile_entity(compound) {
+                    apply_tile_entity_state(&mut region, &block_entity);
+                    region.add_block_entity(block_entity);
+                }
+            }
+        }
+    }
+
+    if let Ok(entities) = root.get::<_, &NbtList>("Entities") {
+        for tag in entities.iter() {
+            if let NbtTag::Compound(compound) = tag {
+                region.add_entity(parse_entity(compound)?);
+            }
+        }
+    }
+
+    schematic.add_region(region);
+    Ok(schematic)
+}
+
+pub fn to_mcedit(schematic: &UniversalSchematic) -> Result<Vec<u8>, NucleationError> {
+    let mut root = NbtCompound::new();
+
+    let merged_region = schematic.get_merged_region();
+    let bounding_box = merged_region.get_bounding_box();
+    let origin = bounding_box.min;
+    let (width, height, length) = bounding_box.get_dimensions();
+
+    if width > i16::MAX as i32 || height > i16::MAX as i32 || length > i16::MAX as i32 {
+        return Err("Schematic is too large for the legacy format".into());
+    }
+
+    root.insert("Width", NbtTag::Short(width as i16));
+    root.insert("Height", NbtTag::Short(height as i16));
+    root.insert("Length", NbtTag::Short(length as i16));
+    root.insert("Materials", NbtTag::String("Alpha".to_string()));
+
+    let palette_mapping: Vec<(u16, u8)> = merged_region.palette.iter()
+        .map(block_state_to_legacy)
+        .collect();
+
+    let mut blocks = Vec::with_capacity(merged_region.blocks.len());
+    let mut data = Vec::with_capacity(merged_region.blocks.len());
+    let mut add_blocks = vec![0u8; merged_region.blocks.len().div_ceil(2)];
+    for (index, block_index) in merged_region.blocks.iter().enumerate() {
+        let (id, meta) = palette_mapping.get(block_index).copied().unwrap_or((0, 0));
+        blocks.push(id as u8 as i8);
+        data.push(meta as i8);
+        let upper = ((id >> 8) & 0x0F) as u8;
+        add_blocks[index >> 1] |= if index & 1 == 0 { upper } else { upper << 4 };
+    }
+    root.insert("Blocks", NbtTag::ByteArray(blocks));
+    root.insert("Data", NbtTag::ByteArray(data));
+    if add_blocks.iter().any(|&add| add != 0) {
+        root.insert("AddBlocks", NbtTag::ByteArray(add_blocks.into_iter().map(|add| add as i8).collect()));
+    }
+
+    let mut tile_entities = NbtList::new();
+    for block_entity in merged_region.block_entities.values() {
+        let mut compound = NbtCompound::new();
+        for (key, value) in &block_entity.nbt {
+            if key == "Id" || key == "Pos" {
+                continue;
+            }
+            compound.insert(key, value.to_quartz_nbt());
+        }
+        compound.insert("id", NbtTag::String(block_entity.id.clone()));
+        compound.insert("x", NbtTag::Int(block_entity.position.0 - origin.0));
+        compound.insert("y", NbtTag::Int(block_entity.position.1 - origin.1));
+        compound.insert("z", NbtTag::Int(block_entity.position.2 - origin.2));
+        tile_entities.push(NbtTag::Compound(compound));
+    }
+    root.insert("TileEntities", NbtTag::List(tile_entities));
+
+    let mut entities = NbtList::new();
+    for entity in &merged_region.entities {
+        let mut compound = entity.nbt.to_quartz_nbt();
+        compound.insert("id", NbtTag::String(entity.id.clone()));
+        compound.insert("Pos", NbtTag::List(NbtList::from(vec![
+            NbtTag::Double(entity.position.0 - origin.0 as f64),
+            NbtTag::Double(entity.position.1 - origin.1 as f64),
+            NbtTag::Double(entity.position.2 - origin.2 as f64),
+        ])));
+        entities.push(NbtTag::Compound(compound));
+    }
+    root.insert("Entities", NbtTag::List(entities));
+
+    root.insert("WEOriginX", NbtTag::Int(origin.0));
+    root.insert("WEOriginY", NbtTag::Int(origin.1));
+    root.insert("WEOriginZ", NbtTag::Int(origin.2));
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    quartz_nbt::io::write_nbt(&mut encoder, Some("Schematic"), &root, Flavor::Uncompressed)?;
+    Ok(encoder.finish()?)
+}
+
+fn legacy_block_table() -> &'static HashMap<(u16, u8), BlockState> {
+    static TABLE: OnceLock<HashMap<(u16, u8), BlockState>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        LEGACY_BLOCKS.iter()
+            .map(|&(id, data, block)| ((id, data), parse_block_state(block)))
+            .collect()
+    })
+}
+
+fn reverse_block_table() -> &'static LegacyCandidates {
+    static TABLE: OnceLock<LegacyCandidates> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = LegacyCandidates::new();
+        for &(id, data, block) in LEGACY_BLOCKS {
+            let block_state = parse_block_state(block);
+            table.entry(block_state.name.clone())
+                .or_default()
+                .push((block_state, id, data));
+        }
+        table
+    })
+}
+
+/// Converts a pre-1.13 numeric block id and data value to a flattened block state.
+/// Unknown data values fall back to the block's default, unknown ids become air.
+pub fn legacy_to_block_state(id: u16, data: u8) -> BlockState {
+    let table = legacy_block_table();
+    table.get(&(id, data))
+        .or_else(|| table.get(&(id, 0)))
+        .cloned()
+        .unwrap_or_else(|| BlockState::new("minecraft:air".to_string()))
+}
+
+/// Converts a flattened block state to the closest pre-1.13 numeric block id and data value.
+/// Blocks that did not exist before the flattening become air.
+pub fn block_state_to_legacy(block_state: &BlockState) -> (u16, u8) {
+    let candidates = match reverse_block_table().get(&block_state.name) {
+        Some(candidates) => candidates,
+        None => return (0, 0),
+    };
+
+    // Upper halves of doors and double plants carry their own data layout
+    let id = candidates[0].1;
+    if block_state.get_property("half").map(String::as_str) == Some("upper") {
+        if id == DOUBLE_PLANT_ID {
+            return (id, 0x8);
+        }
+        if DOOR_IDS.contains(&id) {
+            let hinge = (block_state.get_property("hinge").map(String::as_str) == Some("right")) as u8;
+            let powered = (block_state.get_property("powered").map(String::as_str) == Some("true")) as u8;
+            return (id, 0x8 | hinge | (powered << 1));
+        }
+    }
+
+    // Pick the entry sharing the most property values with the requested state
+    let mut best = (candidates[0].1, candidates[0].2);
+    let mut best_score = -1;
+    for (candidate, id, data) in candidates {
+        if candidate == block_state {
+            return (*id, *data);
+        }
+        let score = candidate.properties.iter()
+            .filter(|(key, value)| block_state.properties.get(*key) == Some(*value))
+            .count() as i32;
+        if score > best_score {
+            best_score = score;
+            best = (*id, *data);
+        }
+    }
+    best
+}
+
+fn read_block_ids(root: &NbtCompound, volume: usize) -> Result<Vec<u16>, NucleationError> {
+    let blocks = root.get::<_, &[i8]>("Blocks")?;
+    if blocks.len() != volume {
+        return Err(format!("Block data length mismatch: expected {}, got {}", volume, blocks.len()).into());
+    }
+
+    let mut ids: Vec<u16> = blocks.iter().map(|&b| b as u8 as u16).collect();
+
+    // Ids above 255 keep their upper four bits in AddBlocks, two blocks per byte
+    if let Ok(add_blocks) = root.get::<_, &[i8]>("AddBlocks") {
+        for (index, id) in ids.iter_mut().enumerate() {
+            let add = match add_blocks.get(index >> 1) {
+                Some(&add) => add as u8 as u16,
+                None => break,
+            };
+            let upper = if index & 1 == 0 { (add & 0x0F) << 8 } else { (add & 0xF0) << 4 };
+            *id |= upper;
+        }
+    }
+
+    Ok(ids)
+}
+
+fn two_block_state(id: u16, data: u8, below: Option<(u16, u8)>, above: Option<(u16, u8)>) -> BlockState {
+    let is_upper = data & 0x8 != 0;
+
+    if id == DOUBLE_PLANT_ID {
+        // The upper half does not know which plant it belongs to
+        return match below {
+            Some((below_id, below_data)) if is_upper && below_id == id => {
+                let mut state = legacy_to_block_state(id, below_data & 0x7);
+                state.set_property("half".to_string(), "upper".to_string());
+                state
+            }
+            _ => legacy_to_block_state(id, data),
+        };
+    }
+
+    // Doors keep facing and open in the lower half, hinge and powered in the upper half
+    let (lower_data, upper_data) = if is_upper {
+        match below {
+            Some((below_id, below_data)) if below_id == id => (below_data, data),
+            _ => return legacy_to_block_state(id, data),
+        }
+    } else {
+        match above {
+            Some((above_id, above_data)) if above_id == id => (data, above_data),
+            _ => return legacy_to_block_state(id, data),
+        }
+    };
+
+    let mut state = legacy_to_block_state(id, lower_data & 0x7);
+    state.set_property("half".to_string(), if is_upper { "upper" } else { "lower" }.to_string());
+    state.set_property("hinge".to_string(), if upper_data & 0x1 != 0 { "right" } else { "left" }.to_string());
+    state.set_property("powered".to_string(), (upper_data & 0x2 != 0).to_string());
+    state
+}
+
+pub(crate) fn modern_block_entity_id(id: &str) -> Option<String> {
+    let modern = match id {
+        "Chest" => "chest",
+        "Furnace" => "furnace",
+        "Sign" => "sign",
+        "MobSpawner" => "mob_spawner",
+        "Trap" => "dispenser",
+        "Dropper" => "dropper",
+        "Hopper" => "hopper",
+        "RecordPlayer" => "jukebox",
+        "Airportal" => "end_portal",
+        "Control" => "command_block",
+        "Beacon" => "beacon",
+        "Skull" => "skull",
+        "DLDetector" => "daylight_detector",
+        "Comparator" => "comparator",
+        "Banner" => "banner",
+        "Cauldron" => "brewing_stand",
+        "EnchantTable" => "enchanting_table",
+        "EndGateway" => "end_gateway",
+        "Piston" => "piston",
+        "Structure" => "structure_block",
+        "EnderChest" => "ender_chest",
+        // Note blocks and flower pots are plain block states since the flattening
+        "Music" | "FlowerPot" | "minecraft:noteblock" | "minecraft:flower_pot" => return None,
+        other if other.contains(':') => return Some(other.to_string()),
+        other => return Some(format!("minecraft:{}", other.to_lowercase())),
+    };
+    Some(format!("minecraft:{}", modern))
+}
+
+fn parse_tile_entity(compound: &NbtCompound) -> Option<BlockEntity> {
+    let id = modern_block_entity_id(compound.get::<_, &str>("id").ok()?)?;
+    let position = (
+        compound.get::<_, i32>("x").ok()?,
+        compound.get::<_, i32>("y").ok()?,
+        compound.get::<_, i32>("z").ok()?,
+    );
+
+    let mut block_entity = BlockEntity::new(id, position);
+    let mut nbt = NbtMap::from_quartz_nbt(compound);
+    for key in ["id", "x", "y", "z"] {
+        nbt.remove(key);
+    }
+    block_entity.nbt = nbt;
+    Some(block_entity)
+}
+
+// Skull types, bed colors and banner colors moved from the tile entity into the block state
+fn apply_tile_entity_state(region: &mut Region, block_entity: &BlockEntity) {
+    let (x, y, z) = block_entity.position;
+    let mut state = match region.get_block(x, y, z) {
+        Some(state) => state.clone(),
+        None => return,
+    };
+
+    let tag_as_i32 = |key: &str| -> Option<i32> {
+        match block_entity.nbt.get(key)? {
+            crate::utils::NbtValue::Byte(b) => Some(*b as i32),
+            crate::utils::NbtValue::Short(s) => Some(*s as i32),
+            crate::utils::NbtValue::Int(i) => Some(*i),
+            _ => None,
+        }
+    };
+
+    match block_entity.id.as_str() {
+        "minecraft:skull" => {
+            let skull = match tag_as_i32("SkullType").unwrap_or(0) {
+                1 => "wither_skeleton_skull",
+                2 => "zombie_head",
+                3 => "player_head",
+                4 => "creeper_head",
+                5 => "dragon_head",
+                _ => "skeleton_skull",
+            };
+            if state.name == "minecraft:skeleton_wall_skull" {
+                state.name = format!("minecraft:{}", skull.replace("_skull", "_wall_skull").replace("_head", "_wall_head"));
+            } else {
+                state.name = format!("minecraft:{}", skull);
+                let rotation = tag_as_i32("Rot").unwrap_or(0) & 0xF;
+                state.set_property("rotation".to_string(), rotation.to_string());
+            }
+        }
+        "minecraft:bed" => {
+            let color = COLORS[(tag_as_i32("color").unwrap_or(14) & 0xF) as usize];
+            state.name = format!("minecraft:{}_bed", color);
+        }
+        "minecraft:banner" => {
+            // Banner base colors use the inverted dye damage values
+            let color = COLORS[(15 - (tag_as_i32("Base").unwrap_or(15) & 0xF)) as usize];
+            state.name = state.name.replacen("white", color, 1);
+        }
+        _ => return,
+    }
+
+    region.set_block(x, y, z, state);
+}
+
+pub(crate) fn modern_entity_id(id: &str) -> String {
+    if id.contains(':') {
+        return id.to_string();
+    }
+
+    let renamed = match id {
+        "PigZombie" => "zombified_piglin",
+        "MinecartRideable" => "minecart",
+        "MinecartChest" => "chest_minecart",
+        "MinecartFurnace" => "furnace_minecart",
+        "MinecartTNT" => "tnt_minecart",
+        "MinecartHopper" => "hopper_minecart",
+        "MinecartSpawner" => "spawner_minecart",
+        "MinecartCommandBlock" => "command_block_minecart",
+        "EntityHorse" => "horse",
+        "VillagerGolem" => "iron_golem",
+        "LavaSlime" => "magma_cube",
+        "Ozelot" => "ocelot",
+        "SnowMan" => "snow_golem",
+        "MushroomCow" => "mooshroom",
+        "PrimedTnt" => "tnt",
+        "FallingSand" => "falling_block",
+        "XPOrb" => "experience_orb",
+        "ThrownPotion" => "potion",
+        "ThrownEnderpearl" => "ender_pearl",
+        "ThrownExpBottle" => "experience_bottle",
+        "EnderCrystal" => "end_crystal",
+        "WitherBoss" => "wither",
+        "EnderDragon" => "ender_dragon",
+        "Item" => "item",
+        other => {
+            // CamelCase to snake_case, e.g. ItemFrame -> item_frame
+            let mut snake = String::with_capacity(other.len() + 4);
+            for (i, c) in other.chars().enumerate() {
+                if c.is_ascii_uppercase() {
+                    if i > 0 {
+                        snake.push('_');
+                    }
+                    snake.push(c.to_ascii_lowercase());
+                } else {
+                    snake.push(c);
+                }
+            }
+            return format!("minecraft:{}", snake);
+        }
+    };
+    format!("minecraft:{}", renamed)
+}
+
+fn parse_entity(compound: &NbtCompound) -> Result<Entity, NucleationError> {
+    let id = modern_entity_id(compound.get::<_, &str>("id")?);
+    let position = compound.get::<_, &NbtList>("Pos")?;
+    if position.len() != 3 {
+        return Err("Invalid entity position".into());
+    }
+    let position = (position.get::<f64>(0)?, position.get::<f64>(1)?, position.get::<f64>(2)?);
+
+    let mut entity = Entity::new(id, position);
+    for (key, value) in compound.inner() {
+        if key == "id" || key == "Pos" {
+            continue;
+        }
+        entity.nbt.insert(key.clone(), NbtValue::from_quartz_nbt(value));
+    }
+    Ok(entity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::NbtValue;
+
+    fn create_legacy_schematic() -> Vec<u8> {
+        // 2x3x1 (width x height x length), index = (y * length + z) * width + x
+        let mut root = NbtCompound::new();
+        root.insert("Width", NbtTag::Short(2));
+        root.insert("Height", NbtTag::Short(3));
+        root.insert("Length", NbtTag::Short(1));
+        root.insert("Materials", NbtTag::String("Alpha".to_string()));
+        // y=0: stone, oak log along x; y=1: door lower (facing south, open), chest; y=2: door upper (right hinge), air
+        root.insert("Blocks", NbtTag::ByteArray(vec![1, 17, 64, 54, 64, 0]));
+        root.insert("Data", NbtTag::ByteArray(vec![0, 4, 5, 3, 9, 0]));
+
+        let mut chest = NbtCompound::new();
+        chest.insert("id", NbtTag::String("Chest".to_string()));
+        chest.insert("x", NbtTag::Int(1));
+        chest.insert("y", NbtTag::Int(1));
+        chest.insert("z", NbtTag::Int(0));
+        chest.insert("CustomName", NbtTag::String("Loot".to_string()));
+        root.insert("TileEntities", NbtTag::List(NbtList::from(vec![NbtTag::Compound(chest)])));
+
+        let mut pig = NbtCompound::new();
+        pig.insert("id", NbtTag::String("Pig".to_string()));
+        pig.insert("Pos", NbtTag::List(NbtList::from(vec![NbtTag::Double(0.5), NbtTag::Double(1.0), NbtTag::Double(0.5)])));
+        pig.insert("Saddle", NbtTag::Byte(1));
+        root.insert("Entities", NbtTag::List(NbtList::from(vec![NbtTag::Compound(pig)])));
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        quartz_nbt::io::write_nbt(&mut encoder, Some("Schematic"), &root, Flavor::Uncompressed).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_legacy_lookup() {
+        assert_eq!(legacy_to_block_state(1, 3).name, "minecraft:diorite");
+        assert_eq!(legacy_to_block_state(35, 14).name, "minecraft:red_wool");
+
+        let stairs = legacy_to_block_state(53, 6);
+        assert_eq!(stairs.name, "minecraft:oak_stairs");
+        assert_eq!(stairs.get_property("facing"), Some(&"south".to_string()));
+        assert_eq!(stairs.get_property("half"), Some(&"top".to_string()));
+
+        // Unknown data values fall back to the default state, unknown ids to air
+        assert_eq!(legacy_to_block_state(4, 7).name, "minecraft:cobblestone");
+        assert_eq!(legacy_to_block_state(254, 0).name, "minecraft:air");
+
+        assert_eq!(block_state_to_legacy(&stairs), (53, 6));
+        assert_eq!(block_state_to_legacy(&BlockState::new("minecraft:red_wool".to_string())), (35, 14));
+        assert_eq!(block_state_to_legacy(&BlockState::new("minecraft:cherry_log".to_string())), (0, 0));
+    }
+
+    #[test]
+    fn test_from_mcedit() {
+        let data = create_legacy_schematic();
+        assert!(is_mcedit(&data));
+        assert!(crate::schematic::is_schematic(&data));
+
+        let schematic = from_mcedit(&data).unwrap();
+        assert_eq!(schematic.get_dimensions(), (2, 3, 1));
+        assert_eq!(schematic.get_block(0, 0, 0).unwrap().name, "minecraft:stone");
+
+        let log = schematic.get_block(1, 0, 0).unwrap();
+        assert_eq!(log.name, "minecraft:oak_log");
+        assert_eq!(log.get_property("axis"), Some(&"x".to_string()));
+
+        let lower = schematic.get_block(0, 1, 0).unwrap();
+        assert_eq!(lower.name, "minecraft:oak_door");
+        assert_eq!(lower.get_property("half"), Some(&"lower".to_string()));
+        assert_eq!(lower.get_property("facing"), Some(&"south".to_string()));
+        assert_eq!(lower.get_property("open"), Some(&"true".to_string()));
+        assert_eq!(lower.get_property("hinge"), Some(&"right".to_string()));
+
+        let upper = schematic.get_block(0, 2, 0).unwrap();
+        assert_eq!(upper.get_property("half"), Some(&"upper".to_string()));
+        assert_eq!(upper.get_property("facing"), Some(&"south".to_string()));
+        assert_eq!(upper.get_property("hinge"), Some(&"right".to_string()));
+
+        let chest = schematic.get_block(1, 1, 0).unwrap();
+        assert_eq!(chest.name, "minecraft:chest");
+        assert_eq!(chest.get_property("facing"), Some(&"south".to_string()));
+
+        let block_entities = schematic.get_block_entities_as_list();
+        assert_eq!(block_entities.len(), 1);
+        assert_eq!(block_entities[0].id, "minecraft:chest");
+        assert_eq!(block_entities[0].position, (1, 1, 0));
+        assert_eq!(block_entities[0].nbt.get("CustomName"), Some(&NbtValue::String("Loot".to_string())));
+
+        let entities = schematic.get_entities_as_list();
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].id, "minecraft:pig");
+        assert_eq!(entities[0].position, (0.5, 1.0, 0.5));
+
+        // The Sponge entry point accepts legacy files too
+        let via_schematic = crate::schematic::from_schematic(&data).unwrap();
+        assert_eq!(via_schematic.get_block(1, 0, 0), schematic.get_block(1, 0, 0));
+    }
+
+    #[test]
+    fn test_add_blocks() {
+        let mut root = NbtCompound::new();
+        root.insert("Width", NbtTag::Short(2));
+        root.insert("Height", NbtTag::Short(1));
+        root.insert("Length", NbtTag::Short(2));
+        root.insert("Materials", NbtTag::String("Alpha".to_string()));
+        root.insert("Blocks", NbtTag::ByteArray(vec![1, 1, 1, 1]));
+        root.insert("Data", NbtTag::ByteArray(vec![0, 0, 0, 0]));
+        // Block 1 gets the upper nibble of the first byte, blocks 2 and 3 the second byte
+        root.insert("AddBlocks", NbtTag::ByteArray(vec![0x10, 0x00]));
+
+        assert_eq!(read_block_ids(&root, 4).unwrap(), vec![1, 0x101, 1, 1]);
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        quartz_nbt::io::write_nbt(&mut encoder, Some("Schematic"), &root, Flavor::Uncompressed).unwrap();
+        let schematic = from_mcedit(&encoder.finish().unwrap()).unwrap();
+        assert_eq!(schematic.get_block(0, 0, 0).unwrap().name, "minecraft:stone");
+        // Id 257 is not stone even though its lower byte is
+        assert_eq!(schematic.get_block(1, 0, 0).unwrap().name, "minecraft:air");
+        assert_eq!(schematic.get_block(1, 0, 1).unwrap().name, "minecraft:stone");
+    }
+
+    #[test]
+    fn test_mcedit_roundtrip() {
+        let schematic = from_mcedit(&create_legacy_schematic()).unwrap();
+        let data = to_mcedit(&schematic).unwrap();
+        assert!(is_mcedit(&data));
+
+        let loaded = from_mcedit(&data).unwrap();
+        for x in 0..2 {
+            for y in 0..3 {
+                assert_eq!(schematic.get_block(x, y, 0), loaded.get_block(x, y, 0));
+            }
+        }
+        assert_eq!(loaded.get_block_entities_as_list().len(), 1);
+        assert_eq!(loaded.get_entities_as_list()[0].id, "minecraft:pig");
+    }
+
+    #[test]
+    fn test_modern_ids() {
+        assert_eq!(modern_entity_id("ItemFrame"), "minecraft:item_frame");
+        assert_eq!(modern_entity_id("PigZombie"), "minecraft:zombified_piglin");
+        assert_eq!(modern_entity_id("minecraft:cow"), "minecraft:cow");
+        assert_eq!(modern_block_entity_id("Trap"), Some("minecraft:dispenser".to_string()));
+        assert_eq!(modern_block_entity_id("Music"), None);
+    }
+}
@@ -0,0 +1,502 @@
+use std::collections::HashMap;
+use quartz_nbt::{NbtCompound, NbtTag};
+use crate::{BlockState, NucleationError, UniversalSchematic};
+use crate::block_entity::BlockEntity;
+use crate::entity::Entity;
+use crate::formats::le_nbt::{read_le_nbt, write_le_nbt};
+use crate::formats::structure::STRUCTURE_VOID;
+use crate::region::Region;
+use crate::utils::{NbtMap, NbtValue};
+
+pub use crate::formats::bedrock_blocks::{bedrock_to_java, java_to_bedrock, BEDROCK_BLOCK_VERSION};
+
+// Java block entity ids and their Bedrock counterparts, as (java, bedrock)
+const BLOCK_ENTITY_IDS: &[(&str, &str)] = &[
+    ("chest", "Chest"),
+    ("trapped_chest", "Chest"),
+    ("ender_chest", "EnderChest"),
+    ("furnace", "Furnace"),
+    ("blast_furnace", "BlastFurnace"),
+    ("smoker", "Smoker"),
+    ("sign", "Sign"),
+    ("hanging_sign", "HangingSign"),
+    ("mob_spawner", "MobSpawner"),
+    ("dispenser", "Dispenser"),
+    ("dropper", "Dropper"),
+    ("hopper", "Hopper"),
+    ("jukebox", "Jukebox"),
+    ("beacon", "Beacon"),
+    ("skull", "Skull"),
+    ("daylight_detector", "DaylightDetector"),
+    ("comparator", "Comparator"),
+    ("banner", "Banner"),
+    ("brewing_stand", "BrewingStand"),
+    ("enchanting_table", "EnchantTable"),
+    ("end_gateway", "EndGateway"),
+    ("end_portal", "EndPortal"),
+    ("piston", "PistonArm"),
+    ("structure_block", "StructureBlock"),
+    ("shulker_box", "ShulkerBox"),
+    ("bed", "Bed"),
+    ("barrel", "Barrel"),
+    ("lectern", "Lectern"),
+    ("bell", "Bell"),
+    ("campfire", "Campfire"),
+    ("beehive", "Beehive"),
+    ("conduit", "Conduit"),
+    ("command_block", "CommandBlock"),
+    ("jigsaw", "JigsawBlock"),
+    ("chiseled_bookshelf", "ChiseledBookshelf"),
+    ("decorated_pot", "DecoratedPot"),
+];
+
+pub fn is_mcstructure(data: &[u8]) -> bool {
+    let root = match read_le_nbt(data) {
+        Ok((root, _)) => root,
+        Err(_) => return false,
+    };
+
+    root.get_as::<i32>("format_version").is_ok() &&
+        root.get_as::<&[NbtValue]>("size").is_ok() &&
+        root.get_as::<&NbtMap>("structure").is_ok()
+}
+
+pub fn from_mcstructure(data: &[u8]) -> Result<UniversalSchematic, NucleationError> {
+    let (root, _) = read_le_nbt(data)?;
+
+    let (width, height, length) = parse_int_triple(root.get_as::<&[NbtValue]>("size")?)?;
+    if width < 0 || height < 0 || length < 0 {
+        return Err(format!("Invalid structure size ({}, {}, {})", width, height, length).into());
+    }
+    let world_origin = match root.get_as::<&[NbtValue]>("structure_world_origin") {
+        Ok(list) => parse_int_triple(list)?,
+        Err(_) => (0, 0, 0),
+    };
+    let volume = width as usize * height as usize * length as usize;
+
+    let structure = root.get_as::<&NbtMap>("structure")?;
+    let layers = structure.get_as::<&[NbtValue]>("block_indices")?;
+    let primary = parse_layer(layers, 0, volume)?.ok_or("Missing primary block layer")?;
+    let waterlog = parse_layer(layers, 1, volume)?;
+
+    let palette = structure.get_as::<&NbtMap>("palette")?
+        .get_as::<&NbtMap>("default")?;
+    let block_palette = palette.get_as::<&[NbtValue]>("block_palette")?
+        .iter()
+        .map(|tag| match tag {
+            NbtValue::Compound(compound) => parse_palette_entry(compound),
+            _ => Err("Invalid palette entry".into()),
+        })
+        .collect::<Result<Vec<BlockState>, NucleationError>>()?;
+
+    let mut schematic = UniversalSchematic::new("Unnamed".to_string());
+    let mut region = Region::new(schematic.default_region_name.clone(), (0, 0, 0), (width, height, length));
+
+    // Palette indices are resolved lazily, waterlogged variants only exist once they are used
+    let mut plain_mapping: Vec<Option<usize>> = vec![None; block_palette.len()];
+    let mut waterlogged_mapping: Vec<Option<usize>> = vec![None; block_palette.len()];
+    let mut void_index = None;
+
+    // Bedrock stores blocks in x, y, z order with z changing fastest
+    let mut index = 0;
+    for x in 0..width {
+        for y in 0..height {
+            for z in 0..length {
+                let state = primary[index];
+                let is_waterlogged = waterlog.as_ref()
+                    .and_then(|layer| block_palette.get(layer[index] as usize))
+                    .map(|block| block.name == "minecraft:water")
+                    .unwrap_or(false);
+                index += 1;
+
+                let palette_index = if state < 0 {
+                    *void_index.get_or_insert_with(|| region.get_or_insert_in_palette(BlockState::new(STRUCTURE_VOID.to_string())))
+                } else {
+                    let state = state as usize;
+                    let block = block_palette.get(state)
+                        .ok_or_else(|| format!("Invalid palette index {} at ({}, {}, {})", state, x, y, z))?;
+                    let waterloggable = is_waterlogged && block.name != "minecraft:water" && block.name != "minecraft:air";
+                    let mapping = if waterloggable { &mut waterlogged_mapping } else { &mut plain_mapping };
+                    match mapping[state] {
+                        Some(palette_index) => palette_index,
+                        None => {
+                            let mut block = block.clone();
+                            if waterloggable {
+                                block.set_property("waterlogged".to_string(), "true".to_string());
+                            }
+                            let palette_index = region.get_or_insert_in_palette(block);
+                            mapping[state] = Some(palette_index);
+                            palette_index
+                        }
+                    }
+                };
+
+                let block_index = region.coords_to_index(x, y, z);
+                region.blocks.set(block_index, palette_index);
+            }
+        }
+    }
+
+    if let Ok(position_data) = palette.get_as::<&NbtMap>("block_position_data") {
+        for (key, tag) in position_data {
+            let index: usize = match key.parse() {
+                Ok(index) if index < volume => index,
+                _ => continue,
+            };
+            let position = (
+                (index / (height as usize * length as usize)) as i32,
+                ((index / length as usize) % height as usize) as i32,
+                (index % length as usize) as i32,
+            );
+            if let Some(nbt) = match tag {
+                NbtValue::Compound(compound) => compound.get_as::<&NbtMap>("block_entity_data").ok(),
+                _ => None,
+            } {
+                region.add_block_entity(parse_block_entity(nbt, position));
+            }
+        }
+    }
+
+    if let Ok(entities) = structure.get_as::<&[NbtValue]>("entities") {
+        for tag in entities {
+            if let NbtValue::Compound(compound) = tag {
+                region.add_entity(parse_entity(compound, world_origin)?);
+            }
+        }
+    }
+
+    schematic.add_region(region);
+    Ok(schematic)
+}
+
+pub fn to_mcstructure(schematic: &UniversalSchematic) -> Result<Vec<u8>, NucleationError> {
+    let merged_region = schematic.get_merged_region();
+    let bounding_box = merged_region.get_bounding_box();
+    let origin = bounding_box.min;
+    let (width, height, length) = bounding_box.get_dimensions();
+    let volume = width as usize * height as usize * length as usize;
+
+    let mut block_palette = Vec::new();
+    let mut seen: HashMap<String, i32> = HashMap::new();
+    let mut add_to_palette = |name: String, states: NbtCompound| -> i32 {
+        let mut entry = NbtCompound::new();
+        entry.insert("name", NbtTag::String(name));
+        entry.insert("states", NbtTag::Compound(states));
+        entry.insert("version", NbtTag::Int(BEDROCK_BLOCK_VERSION));
+        *seen.entry(entry.to_snbt()).or_insert_with(|| {
+            block_palette.push(NbtValue::Compound(NbtMap::from_quartz_nbt(&entry)));
+            block_palette.len() as i32 - 1
+        })
+    };
+
+    // Map each palette entry to its Bedrock index, structure void becomes -1
+    let water_index = {
+        let mut states = NbtCompound::new();
+        states.insert("liquid_depth", NbtTag::Int(0));
+        add_to_palette("minecraft:water".to_string(), states)
+    };
+    let mut palette_mapping: Vec<(i32, i32)> = Vec::with_capacity(merged_region.palette.len());
+    for block_state in &merged_region.palette {
+        if block_state.name == STRUCTURE_VOID {
+            palette_mapping.push((-1, -1));
+            continue;
+        }
+        let (name, states) = java_to_bedrock(block_state);
+        let waterlog = if block_state.get_property("waterlogged").map(|w| w == "true").unwrap_or(false) {
+            water_index
+        } else {
+            -1
+        };
+        palette_mapping.push((add_to_palette(name, states), waterlog));
+    }
+
+    let mut primary = vec![-1; volume];
+    let mut waterlog = vec![-1; volume];
+    let to_index = |(x, y, z): (i32, i32, i32)| -> usize {
+        (((x - origin.0) * height + (y - origin.1)) * length + (z - origin.2)) as usize
+    };
+    for (index, block_index) in merged_region.blocks.iter().enumerate() {
+        let (state, water) = palette_mapping.get(block_index).copied().unwrap_or((-1, -1));
+        let bedrock_index = to_index(merged_region.index_to_coords(index));
+        primary[bedrock_index] = state;
+        waterlog[bedrock_index] = water;
+    }
+
+    let mut block_position_data = NbtMap::new();
+    for block_entity in merged_region.block_entities.values() {
+        let mut data = NbtMap::new();
+        data.insert("block_entity_data", NbtValue::Compound(block_entity_to_bedrock_nbt(block_entity)));
+        block_position_data.insert(to_index(block_entity.position).to_string(), NbtValue::Compound(data));
+    }
+
+    let mut default_palette = NbtMap::new();
+    default_palette.insert("block_palette", NbtValue::List(block_palette));
+    default_palette.insert("block_position_data", NbtValue::Compound(block_position_data));
+    let mut palette = NbtMap::new();
+    palette.insert("default", NbtValue::Compound(default_palette));
+
+    let mut entities = Vec::new();
+    for entity in &merged_region.entities {
+        entities.push(NbtValue::Compound(entity_to_bedrock_nbt(entity)));
+    }
+
+    let layer_to_nbt = |layer: Vec<i32>| NbtValue::List(layer.into_iter().map(NbtValue::Int).collect());
+    let mut structure = NbtMap::new();
+    structure.insert("block_indices", NbtValue::List(vec![
+        layer_to_nbt(primary),
+        layer_to_nbt(waterlog),
+    ]));
+    structure.insert("entities", NbtValue::List(entities));
+    structure.insert("palette", NbtValue::Compound(palette));
+
+    let mut root = NbtMap::new();
+    root.insert("format_version", NbtValue::Int(1));
+    root.insert("size", int_triple_to_nbt((width, height, length)));
+    root.insert("structure", NbtValue::Compound(structure));
+    root.insert("structure_world_origin", int_triple_to_nbt(origin));
+
+    Ok(write_le_nbt(&root, ""))
+}
+
+fn parse_int_triple(list: &[NbtValue]) -> Result<(i32, i32, i32), NucleationError> {
+    match list {
+        [NbtValue::Int(x), NbtValue::Int(y), NbtValue::Int(z)] => Ok((*x, *y, *z)),
+        [_, _, _] => Err("Expected integer coordinates".into()),
+        _ => Err(format!("Expected 3 coordinates, got {}", list.len()).into()),
+    }
+}
+
+fn int_triple_to_nbt(value: (i32, i32, i32)) -> NbtValue {
+    NbtValue::List(vec![
+        NbtValue::Int(value.0),
+        NbtValue::Int(value.1),
+        NbtValue::Int(value.2),
+    ])
+}
+
+fn parse_layer(layers: &[NbtValue], index: usize, volume: usize) -> Result<Option<Vec<i32>>, NucleationError> {
+    let layer = match layers.get(index) {
+        Some(NbtValue::List(layer)) => layer.as_slice(),
+        Some(NbtValue::EmptyList(_)) => &[],
+        Some(_) => return Err("Invalid block layer".into()),
+        None => return Ok(None),
+    };
+    // Unused layers may be left empty
+    if layer.is_empty() && index > 0 {
+        return Ok(None);
+    }
+    if layer.len() != volume {
+        return Err(format!("Block layer has {} entries, expected {}", layer.len(), volume).into());
+    }
+
+    layer.iter()
+        .map(|tag| match tag {
+            NbtValue::Int(value) => Ok(*value),
+            _ => Err("Invalid block index".into()),
+        })
+        .collect::<Result<Vec<i32>, NucleationError>>()
+        .map(Some)
+}
+
+fn parse_palette_entry(compound: &NbtMap) -> Result<BlockState, NucleationError> {
+    let name = compound.get_as::<&str>("name")?;
+    let states = compound.get_as::<&NbtMap>("states").map(NbtMap::to_quartz_nbt).unwrap_or_default();
+    Ok(bedrock_to_java(name, &states))
+}
+
+fn java_block_entity_id(id: &str) -> String {
+    match BLOCK_ENTITY_IDS.iter().find(|(_, bedrock)| *bedrock == id) {
+        Some((java, _)) => format!("minecraft:{}", java),
+        None if id.contains(':') => id.to_string(),
+        None => format!("minecraft:{}", id.to_lowercase()),
+    }
+}
+
+fn bedrock_block_entity_id(id: &str) -> String {
+    let java = id.strip_prefix("minecraft:").unwrap_or(id);
+    match BLOCK_ENTITY_IDS.iter().find(|(j, _)| *j == java) {
+        Some((_, bedrock)) => bedrock.to_string(),
+        // snake_case to CamelCase, e.g. sculk_sensor -> SculkSensor
+        None => java.split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            })
+            .collect(),
+    }
+}
+
+// Bedrock item stacks use Name instead of id and carry a damage value
+fn convert_items(compound: &mut NbtMap, to_bedrock: bool) {
+    let items = match compound.get_mut("Items") {
+        Some(NbtValue::List(items)) => items,
+        _ => return,
+    };
+    for item in items.iter_mut() {
+        if let NbtValue::Compound(item) = item {
+            let (from, to) = if to_bedrock { ("id", "Name") } else { ("Name", "id") };
+            if let Ok(name) = item.get_as::<&str>(from).map(str::to_string) {
+                item.remove(from);
+                item.insert(to, NbtValue::String(name));
+            }
+            if to_bedrock {
+                if !item.contains_key("Damage") {
+                    item.insert("Damage", NbtValue::Short(0));
+                }
+            } else {
+                item.remove("WasPickedUp");
+            }
+        }
+    }
+}
+
+fn parse_block_entity(nbt: &NbtMap, position: (i32, i32, i32)) -> BlockEntity {
+    let id = java_block_entity_id(nbt.get_as::<&str>("id").unwrap_or("unknown"));
+    let mut nbt_map = nbt.clone();
+    convert_items(&mut nbt_map, false);
+
+    for key in ["id", "x", "y", "z", "isMovable"] {
+        nbt_map.remove(key);
+    }
+    BlockEntity { nbt: nbt_map, id, position }
+}
+
+fn block_entity_to_bedrock_nbt(block_entity: &BlockEntity) -> NbtMap {
+    let mut nbt = NbtMap::new();
+    for (key, value) in &block_entity.nbt {
+        if key == "Id" || key == "Pos" {
+            continue;
+        }
+        nbt.insert(key.clone(), value.clone());
+    }
+    convert_items(&mut nbt, true);
+
+    let (x, y, z) = block_entity.position;
+    nbt.insert("id", NbtValue::String(bedrock_block_entity_id(&block_entity.id)));
+    nbt.insert("x", NbtValue::Int(x));
+    nbt.insert("y", NbtValue::Int(y));
+    nbt.insert("z", NbtValue::Int(z));
+    nbt.insert("isMovable", NbtValue::Byte(1));
+    nbt
+}
+
+fn parse_entity(compound: &NbtMap, world_origin: (i32, i32, i32)) -> Result<Entity, NucleationError> {
+    let id = compound.get_as::<&str>("identifier")
+        .or_else(|_| compound.get_as::<&str>("id"))?;
+    let position = compound.get_as::<&[NbtValue]>("Pos")?;
+    if position.len() != 3 {
+        return Err("Invalid entity position".into());
+    }
+    let coordinate = |index: usize| -> Result<f64, NucleationError> {
+        match position.get(index) {
+            Some(NbtValue::Float(value)) => Ok(*value as f64),
+            Some(NbtValue::Double(value)) => Ok(*value),
+            _ => Err("Invalid entity position".into()),
+        }
+    };
+    // Bedrock stores entity positions in world coordinates
+    let position = (
+        coordinate(0)? - world_origin.0 as f64,
+        coordinate(1)? - world_origin.1 as f64,
+        coordinate(2)? - world_origin.2 as f64,
+    );
+
+    let mut entity = Entity::new(id.to_string(), position);
+    for (key, value) in compound {
+        if key == "identifier" || key == "id" || key == "Pos" {
+            continue;
+        }
+        entity.nbt.insert(key.clone(), value.clone());
+    }
+    Ok(entity)
+}
+
+fn entity_to_bedrock_nbt(entity: &Entity) -> NbtMap {
+    let mut nbt = entity.nbt.clone();
+    nbt.insert("identifier", NbtValue::String(entity.id.clone()));
+    nbt.insert("Pos", NbtValue::List(vec![
+        NbtValue::Float(entity.position.0 as f32),
+        NbtValue::Float(entity.position.1 as f32),
+        NbtValue::Float(entity.position.2 as f32),
+    ]));
+    nbt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::ItemStack;
+
+    #[test]
+    fn test_mcstructure_roundtrip() {
+        let mut schematic = UniversalSchematic::new("Bedrock".to_string());
+        let mut region = Region::new("Main".to_string(), (0, 0, 0), (2, 2, 3));
+        region.set_block(0, 0, 0, BlockState::new("minecraft:stone".to_string()));
+        region.set_block(1, 0, 2, BlockState::new("minecraft:oak_stairs".to_string())
+            .with_property("facing".to_string(), "east".to_string())
+            .with_property("half".to_string(), "bottom".to_string())
+            .with_property("shape".to_string(), "straight".to_string())
+            .with_property("waterlogged".to_string(), "true".to_string()));
+        region.set_block(0, 1, 1, BlockState::new("minecraft:chest".to_string())
+            .with_property("facing".to_string(), "north".to_string())
+            .with_property("type".to_string(), "single".to_string())
+            .with_property("waterlogged".to_string(), "false".to_string()));
+        region.set_block(1, 1, 1, BlockState::new(STRUCTURE_VOID.to_string()));
+        region.add_block_entity(BlockEntity::create_chest((0, 1, 1), vec![ItemStack::new("minecraft:diamond", 3).with_slot(0)]));
+        region.add_entity(Entity::new("minecraft:pig".to_string(), (0.5, 1.0, 0.5)));
+        schematic.add_region(region);
+
+        let data = to_mcstructure(&schematic).unwrap();
+        assert!(is_mcstructure(&data));
+        assert!(!crate::structure::is_structure(&data));
+
+        let loaded = from_mcstructure(&data).unwrap();
+        assert_eq!(loaded.get_dimensions(), (2, 2, 3));
+        for (x, y, z) in [(0, 0, 0), (1, 0, 2), (0, 1, 1), (1, 1, 1), (0, 0, 1)] {
+            assert_eq!(loaded.get_block(x, y, z), schematic.get_block(x, y, z), "block at ({}, {}, {})", x, y, z);
+        }
+
+        let chest = loaded.get_block_entity(crate::block_position::BlockPosition { x: 0, y: 1, z: 1 }).unwrap();
+        assert_eq!(chest.id, "minecraft:chest");
+        let items = match chest.nbt.get("Items") {
+            Some(crate::utils::NbtValue::List(items)) => items,
+            other => panic!("Unexpected items {:?}", other),
+        };
+        match &items[0] {
+            crate::utils::NbtValue::Compound(item) => {
+                assert_eq!(item.get("id"), Some(&crate::utils::NbtValue::String("minecraft:diamond".to_string())));
+            }
+            other => panic!("Unexpected item {:?}", other),
+        }
+
+        let entities = &loaded.get_region("Main").unwrap().entities;
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].id, "minecraft:pig");
+        assert_eq!(entities[0].position, (0.5, 1.0, 0.5));
+    }
+
+    #[test]
+    fn test_layer_order() {
+        let mut schematic = UniversalSchematic::new("Bedrock".to_string());
+        let mut region = Region::new("Main".to_string(), (0, 0, 0), (2, 2, 2));
+        region.set_block(0, 0, 1, BlockState::new("minecraft:stone".to_string()));
+        schematic.add_region(region);
+
+        let data = to_mcstructure(&schematic).unwrap();
+        let (root, _) = read_le_nbt(&data).unwrap();
+        let structure = root.get_as::<&NbtMap>("structure").unwrap();
+        let layers = structure.get_as::<&[NbtValue]>("block_indices").unwrap();
+        let primary = parse_layer(layers, 0, 8).unwrap().unwrap();
+        let palette = structure.get_as::<&NbtMap>("palette").unwrap()
+            .get_as::<&NbtMap>("default").unwrap()
+            .get_as::<&[NbtValue]>("block_palette").unwrap();
+
+        // z is the fastest changing axis
+        let stone = primary[1];
+        let entry = palette[stone as usize].as_compound().unwrap();
+        assert_eq!(entry.get_as::<&str>("name").unwrap(), "minecraft:stone");
+    }
+}
@@ -1,2 +1,115 @@
 pub mod litematic;
-pub mod schematic;
\ No newline at end of file
+pub mod schematic;
+pub mod structure;
+pub mod mcedit;
+pub mod mcstructure;
+pub mod anvil;
+pub mod axiom;
+pub mod vox;
+mod legacy_blocks;
+mod bedrock_blocks;
+mod le_nbt;
+
+use crate::block_entity::BlockEntity;
+use crate::entity::Entity;
+use crate::region::Region;
+use crate::utils::NbtMap;
+use crate::{NucleationError, UniversalSchematic};
+use std::borrow::Cow;
+use std::io::{Read, Write};
+
+/// Options shared by the exporters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Sort palettes by block string and entities by position, so the same schematic
+    /// always exports to the same bytes
+    pub deterministic: bool,
+    /// Milliseconds since the epoch written as the creation time, in place of the schematic's
+    /// own. Without it the schematic's creation time is kept, and missing timestamps default
+    /// to the current time, or to 0 when deterministic
+    pub timestamp: Option<u64>,
+    /// Crop every region to its non-air blocks before writing, see [`Region::trim`]
+    pub trim: bool,
+}
+
+impl ExportOptions {
+    pub fn deterministic() -> Self {
+        ExportOptions { deterministic: true, ..ExportOptions::default() }
+    }
+
+    pub fn with_trim(mut self) -> Self {
+        self.trim = true;
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub(crate) fn timestamp_millis(&self) -> u64 {
+        match self.timestamp {
+            Some(timestamp) => timestamp,
+            None if self.deterministic => 0,
+            None => current_time_millis(),
+        }
+    }
+
+    /// The schematic to write, a trimmed copy when trimming.
+    pub(crate) fn prepare<'a>(&self, schematic: &'a UniversalSchematic) -> Cow<'a, UniversalSchematic> {
+        if !self.trim {
+            return Cow::Borrowed(schematic);
+        }
+        let mut schematic = schematic.clone();
+        schematic.trim();
+        Cow::Owned(schematic)
+    }
+
+    /// Block entities of a region in position order; the region stores them in a `HashMap`.
+    pub(crate) fn block_entities<'a>(&self, region: &'a Region) -> Vec<&'a BlockEntity> {
+        let mut block_entities: Vec<_> = region.block_entities.values().collect();
+        block_entities.sort_by_key(|block_entity| block_entity.position);
+        block_entities
+    }
+
+    /// Entities of a region, in position order when deterministic and as stored otherwise.
+    pub(crate) fn entities<'a>(&self, region: &'a Region) -> Vec<&'a Entity> {
+        let mut entities: Vec<_> = region.entities.iter().collect();
+        if self.deterministic {
+            entities.sort_by(|a, b| {
+                a.position.0.total_cmp(&b.position.0)
+                    .then(a.position.1.total_cmp(&b.position.1))
+                    .then(a.position.2.total_cmp(&b.position.2))
+                    .then_with(|| a.id.cmp(&b.id))
+            });
+        }
+        entities
+    }
+}
+
+// Gzipped NBT goes through `NbtMap` rather than quartz_nbt so empty lists in block entity and
+// entity data keep their element type
+pub(crate) fn read_gzip_nbt(data: &[u8]) -> Result<NbtMap, NucleationError> {
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(data).read_to_end(&mut decompressed)?;
+    Ok(NbtMap::read_nbt(&decompressed)?.1)
+}
+
+pub(crate) fn write_gzip_nbt(root: &NbtMap, name: &str) -> Result<Vec<u8>, NucleationError> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&root.write_nbt(name))?;
+    Ok(encoder.finish()?)
+}
+
+fn current_time_millis() -> u64 {
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    let now = js_sys::Date::now() as u64;
+
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+
+    now
+}
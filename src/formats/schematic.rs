@@ -1,14 +1,12 @@
 use std::fmt;
-use std::io::{BufReader, Cursor, Read};
-
-use flate2::Compression;
-use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
-use quartz_nbt::{NbtCompound, NbtList, NbtTag};
-use quartz_nbt::io::{read_nbt, Flavor};
-use crate::{BlockState, UniversalSchematic};
+use std::io::{Cursor, Read};
+
+use crate::{BlockState, NucleationError, UniversalSchematic};
 use crate::block_entity::BlockEntity;
 use crate::entity::Entity;
+use crate::formats::{mcedit, read_gzip_nbt, write_gzip_nbt, ExportOptions};
+use crate::metadata::Metadata;
+use crate::utils::{NbtMap, NbtValue};
 use crate::region::Region;
 
 #[cfg(feature = "wasm")]
@@ -20,6 +18,7 @@ use web_sys::console;
 // enum for versions of schematics
 #[derive(Debug, Clone, Copy)]
 pub enum SchematicVersion {
+    V1,
     V2,
     V3,
 }
@@ -28,6 +27,7 @@ pub enum SchematicVersion {
 impl SchematicVersion {
     pub fn as_str(&self) -> &str {
         match self {
+            SchematicVersion::V1 => "v1",
             SchematicVersion::V2 => "v2",
             SchematicVersion::V3 => "v3",
         }
@@ -35,6 +35,7 @@ impl SchematicVersion {
 
     pub fn from_str(version: &str) -> Option<SchematicVersion> {
         match version {
+            "v1" => Some(SchematicVersion::V1),
             "v2" => Some(SchematicVersion::V2),
             "v3" => Some(SchematicVersion::V3),
             _ => None,
@@ -45,8 +46,16 @@ impl SchematicVersion {
         SchematicVersion::V3
     }
 
+    pub fn as_i32(&self) -> i32 {
+        match self {
+            SchematicVersion::V1 => 1,
+            SchematicVersion::V2 => 2,
+            SchematicVersion::V3 => 3,
+        }
+    }
+
     pub fn get_all() -> Vec<SchematicVersion> {
-        vec![SchematicVersion::V2, SchematicVersion::V3]
+        vec![SchematicVersion::V1, SchematicVersion::V2, SchematicVersion::V3]
     }
 
 
@@ -62,10 +71,8 @@ impl fmt::Display for SchematicVersion {
 
 pub fn is_schematic(data: &[u8]) -> bool {
     // Decompress the data
-    let reader = BufReader::with_capacity(1 << 20, data); // 1 MiB buf
-    let mut gz = GzDecoder::new(reader);
-    let (root, _) = match read_nbt(&mut gz, Flavor::Uncompressed) {
-        Ok(result) => result,
+    let root = match read_gzip_nbt(data) {
+        Ok(root) => root,
         Err(_) => {
             #[cfg(feature = "wasm")]
             let _: Result<(), JsValue> = Err(JsValue::from_str("Failed to read NBT data"));
@@ -75,78 +82,91 @@ pub fn is_schematic(data: &[u8]) -> bool {
 
 
     //things should be under Schematic tag if not treat root as the schematic
-    let root = root.get::<_, &NbtCompound>("Schematic").unwrap_or(&root);
+    let root = root.get_as::<&NbtMap>("Schematic").unwrap_or(&root);
+
+    // Pre-1.13 MCEdit schematics use numeric block ids
+    if mcedit::is_mcedit_root(&root.to_quartz_nbt()) {
+        return true;
+    }
 
     // get tge version of the schematic
-    let version = root.get::<_, i32>("Version");
+    let version = root.get_as::<i32>("Version");
     #[cfg(feature = "wasm")]
     console::log_1(&format!("Schematic Version: {:?}", version).into());
-    if version.is_err() {
-        return root.get::<_, &NbtCompound>("Blocks").is_ok();
-    }
 
+    let has_dimensions = root.get_as::<i16>("Width").is_ok() &&
+        root.get_as::<i16>("Height").is_ok() &&
+        root.get_as::<i16>("Length").is_ok();
 
-    // Check if it's a v3 schematic (which has a Blocks compound)
-    if version.unwrap() == 3 {
-        #[cfg(feature = "wasm")]
-        console::log_1(&format!("Detected v3 schematic").into());
-        return root.get::<_, &NbtCompound>("Blocks").is_ok();
+    match version {
+        // v3 keeps its palette and block data in a Blocks compound
+        Ok(3) => root.get_as::<&NbtMap>("Blocks").is_ok(),
+        // v1 and v2 keep them at the root
+        Ok(1) | Ok(2) => has_dimensions && root.get_as::<&[i8]>("BlockData").is_ok(),
+        Ok(_) => false,
+        // Some writers omit the Version tag entirely
+        Err(_) => root.get_as::<&NbtMap>("Blocks").is_ok() ||
+            (has_dimensions && root.get_as::<&NbtMap>("Palette").is_ok() && root.get_as::<&[i8]>("BlockData").is_ok()),
     }
-
-    // Otherwise check for v2 format
-    root.get::<_, i32>("DataVersion").is_ok() &&
-    root.get::<_, i16>("Width").is_ok() &&
-    root.get::<_, i16>("Height").is_ok() &&
-    root.get::<_, i16>("Length").is_ok() &&
-    root.get::<_, &Vec<i8>>("BlockData").is_ok()
 }
 
 
 // Default function uses v3 format
-pub fn to_schematic(schematic: &UniversalSchematic) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+pub fn to_schematic(schematic: &UniversalSchematic) -> Result<Vec<u8>, NucleationError> {
     to_schematic_version(schematic, SchematicVersion::get_default())
 }
 
-pub fn to_schematic_version(schematic: &UniversalSchematic, version: SchematicVersion) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+pub fn to_schematic_version(schematic: &UniversalSchematic, version: SchematicVersion) -> Result<Vec<u8>, NucleationError> {
+    to_schematic_with_options(schematic, version, &ExportOptions::default())
+}
+
+pub fn to_schematic_with_options(schematic: &UniversalSchematic, version: SchematicVersion, options: &ExportOptions) -> Result<Vec<u8>, NucleationError> {
+    let schematic = &*options.prepare(schematic);
     match version {
-        SchematicVersion::V2 => to_schematic_v2(schematic),
-        SchematicVersion::V3 => to_schematic_v3(schematic),
+        SchematicVersion::V1 | SchematicVersion::V2 => to_schematic_flat(schematic, version, options),
+        SchematicVersion::V3 => write_schematic_v3(schematic, options),
     }
 }
 
 // Version 3 format (recommended)
-pub fn to_schematic_v3(schematic: &UniversalSchematic) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut schematic_data = NbtCompound::new();
+pub fn to_schematic_v3(schematic: &UniversalSchematic) -> Result<Vec<u8>, NucleationError> {
+    write_schematic_v3(schematic, &ExportOptions::default())
+}
+
+fn write_schematic_v3(schematic: &UniversalSchematic, options: &ExportOptions) -> Result<Vec<u8>, NucleationError> {
+    let mut schematic_data = NbtMap::new();
 
     // Version 3 format
-    schematic_data.insert("Version", NbtTag::Int(3));
-    schematic_data.insert("DataVersion", NbtTag::Int(schematic.metadata.mc_version.unwrap_or(1343)));
+    schematic_data.insert("Version", NbtValue::Int(3));
+    schematic_data.insert("DataVersion", NbtValue::Int(schematic.metadata.mc_version.unwrap_or(1343)));
 
     let bounding_box = schematic.get_bounding_box();
     let (width, height, length) = bounding_box.get_dimensions();
 
-    schematic_data.insert("Width", NbtTag::Short((width as i16).abs()));
-    schematic_data.insert("Height", NbtTag::Short((height as i16).abs()));
-    schematic_data.insert("Length", NbtTag::Short((length as i16).abs()));
+    schematic_data.insert("Width", NbtValue::Short((width as i16).abs()));
+    schematic_data.insert("Height", NbtValue::Short((height as i16).abs()));
+    schematic_data.insert("Length", NbtValue::Short((length as i16).abs()));
 
-    let offset = vec![0, 0, 0];
-    schematic_data.insert("Offset", NbtTag::IntArray(offset));
+    schematic_data.insert("Offset", offset_to_nbt(schematic));
 
-    let merged_region = schematic.get_merged_region();
+    let mut merged_region = schematic.get_merged_region();
+    if options.deterministic {
+        merged_region.compact_palette();
+    }
 
     // Create the Blocks container (required in v3)
-    let mut blocks_container = NbtCompound::new();
+    let mut blocks_container = NbtMap::new();
 
     // Create clean palette and mapping
     let (palette_nbt, palette_mapping) = convert_palette_with_mapping(&merged_region.palette);
 
     // Store palette size before moving palette_nbt
     let palette_size = palette_nbt.len();
-    blocks_container.insert("Palette", palette_nbt);
+    blocks_container.insert("Palette", NbtValue::Compound(palette_nbt));
 
     // Remap block data using the new palette mapping
     let remapped_blocks: Vec<u32> = merged_region.blocks.iter()
-        .map(|&original_id| {
+        .map(|original_id| {
             if original_id < palette_mapping.len() {
                 palette_mapping[original_id] as u32
             } else {
@@ -167,7 +187,7 @@ pub fn to_schematic_v3(schematic: &UniversalSchematic) -> Result<Vec<u8>, Box<dy
         console::log_1(&format!("Block data size: {}", merged_region.blocks.len()).into());
 
         // Log first few mappings for debugging
-        for (i, &original_id) in merged_region.blocks.iter().take(10).enumerate() {
+        for (i, original_id) in merged_region.blocks.iter().take(10).enumerate() {
             let mapped_id = if original_id < palette_mapping.len() {
                 palette_mapping[original_id]
             } else {
@@ -183,116 +203,144 @@ pub fn to_schematic_v3(schematic: &UniversalSchematic) -> Result<Vec<u8>, Box<dy
         .collect();
 
     // Add block data to Blocks container (renamed from "BlockData" to "Data" in v3)
-    blocks_container.insert("Data", NbtTag::ByteArray(block_data.iter().map(|&x| x as i8).collect()));
-
-    // Add block entities to Blocks container
-    let mut block_entities = NbtList::new();
+    blocks_container.insert("Data", NbtValue::ByteArray(block_data.iter().map(|&x| x as i8).collect()));
 
-    // Entities remain at root level in v3 - with validation
-    let mut entities = NbtList::new();
+    // Block entities live in the Blocks container in v3, with their data in a Data sub-compound
+    let origin = bounding_box.min;
+    let mut block_entities = Vec::new();
+    let mut entities = Vec::new();
     for region in schematic.get_all_regions().values() {
-        block_entities.extend(convert_block_entities(region).iter().cloned());
-
-        let region_entities = convert_entities(region);
-
-        // Only add valid entities
-        for entity in region_entities.iter() {
-            if let NbtTag::Compound(compound) = entity {
-                // Validate that entity has required fields
-                if compound.contains_key("Id") && compound.contains_key("Pos") {
-                    entities.push(entity.clone());
-                } else {
-                    #[cfg(feature = "wasm")]
-                    console::log_1(&"Warning: Skipping invalid entity missing Id or Pos".into());
-                }
-            }
-        }
+        block_entities.extend(convert_block_entities(region, origin, SchematicVersion::V3, options));
+        entities.extend(convert_entities(region, origin, SchematicVersion::V3, options));
     }
-    blocks_container.insert("BlockEntities", NbtTag::List(block_entities));
+    blocks_container.insert("BlockEntities", NbtValue::List(block_entities));
 
     // Add the Blocks container to schematic data
-    schematic_data.insert("Blocks", NbtTag::Compound(blocks_container));
+    schematic_data.insert("Blocks", NbtValue::Compound(blocks_container));
 
-    schematic_data.insert("Entities", NbtTag::List(entities));
+    // Entities remain at root level in v3
+    schematic_data.insert("Entities", NbtValue::List(entities));
 
     // Add metadata
-    schematic_data.insert("Metadata", schematic.metadata.to_nbt());
+    schematic_data.insert("Metadata", metadata_to_nbt(schematic, options));
 
     // Create the proper root structure with "Schematic" tag
-    let mut root = NbtCompound::new();
-    root.insert("Schematic", NbtTag::Compound(schematic_data));
+    let mut root = NbtMap::new();
+    root.insert("Schematic", NbtValue::Compound(schematic_data));
 
     // Write NBT with proper compression
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-    quartz_nbt::io::write_nbt(&mut encoder, None, &root, quartz_nbt::io::Flavor::Uncompressed)?;
-    Ok(encoder.finish()?)
+    write_gzip_nbt(&root, "")
 }
 
 // Version 2 format (legacy compatibility)
-pub fn to_schematic_v2(schematic: &UniversalSchematic) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut schematic_data = NbtCompound::new();
+pub fn to_schematic_v2(schematic: &UniversalSchematic) -> Result<Vec<u8>, NucleationError> {
+    to_schematic_flat(schematic, SchematicVersion::V2, &ExportOptions::default())
+}
+
+// Version 1 format (pre-DataVersion, block entities only)
+pub fn to_schematic_v1(schematic: &UniversalSchematic) -> Result<Vec<u8>, NucleationError> {
+    to_schematic_flat(schematic, SchematicVersion::V1, &ExportOptions::default())
+}
 
-    schematic_data.insert("Version", NbtTag::Int(2)); // Schematic format version 2
-    schematic_data.insert("DataVersion", NbtTag::Int(schematic.metadata.mc_version.unwrap_or(1343)));
+// v1 and v2 share the same layout with palette and block data at the root
+fn to_schematic_flat(schematic: &UniversalSchematic, version: SchematicVersion, options: &ExportOptions) -> Result<Vec<u8>, NucleationError> {
+    let mut schematic_data = NbtMap::new();
+
+    schematic_data.insert("Version", NbtValue::Int(version.as_i32()));
+    if !matches!(version, SchematicVersion::V1) {
+        schematic_data.insert("DataVersion", NbtValue::Int(schematic.metadata.mc_version.unwrap_or(1343)));
+    }
 
     let bounding_box = schematic.get_bounding_box();
     let (width, height, length) = bounding_box.get_dimensions();
 
-    schematic_data.insert("Width", NbtTag::Short((width as i16).abs()));
-    schematic_data.insert("Height", NbtTag::Short((height as i16).abs()));
-    schematic_data.insert("Length", NbtTag::Short((length as i16).abs()));
+    schematic_data.insert("Width", NbtValue::Short((width as i16).abs()));
+    schematic_data.insert("Height", NbtValue::Short((height as i16).abs()));
+    schematic_data.insert("Length", NbtValue::Short((length as i16).abs()));
 
-    schematic_data.insert("Size", NbtTag::IntArray(vec![width as i32, height as i32, length as i32]));
+    schematic_data.insert("Offset", offset_to_nbt(schematic));
 
-    let offset = vec![0, 0, 0];
-    schematic_data.insert("Offset", NbtTag::IntArray(offset));
-
-    let merged_region = schematic.get_merged_region();
+    let mut merged_region = schematic.get_merged_region();
+    if options.deterministic {
+        merged_region.compact_palette();
+    }
 
-    schematic_data.insert("Palette", convert_palette_v2(&merged_region.palette).0);
-    schematic_data.insert("PaletteMax", convert_palette_v2(&merged_region.palette).1 + 1);
+    let (palette_nbt, palette_mapping) = convert_palette_with_mapping(&merged_region.palette);
+    schematic_data.insert("PaletteMax", NbtValue::Int(palette_nbt.len() as i32));
+    schematic_data.insert("Palette", NbtValue::Compound(palette_nbt));
 
     let block_data: Vec<u8> = merged_region.blocks.iter()
-        .flat_map(|&block_id| encode_varint(block_id as u32))
+        .flat_map(|block_id| encode_varint(palette_mapping.get(block_id).copied().unwrap_or(0) as u32))
         .collect();
 
-    schematic_data.insert("BlockData", NbtTag::ByteArray(block_data.iter().map(|&x| x as i8).collect()));
+    schematic_data.insert("BlockData", NbtValue::ByteArray(block_data.iter().map(|&x| x as i8).collect()));
 
-    let mut block_entities = NbtList::new();
-    let mut entities = NbtList::new();
+    let origin = bounding_box.min;
+    let mut block_entities = Vec::new();
+    let mut entities = Vec::new();
     for region in schematic.get_all_regions().values() {
-        block_entities.extend(convert_block_entities(region).iter().cloned());
-        entities.extend(convert_entities(region).iter().cloned());
+        block_entities.extend(convert_block_entities(region, origin, version, options));
+        entities.extend(convert_entities(region, origin, version, options));
     }
-    schematic_data.insert("BlockEntities", NbtTag::List(block_entities));
 
-    schematic_data.insert("Entities", NbtTag::List(entities));
+    if matches!(version, SchematicVersion::V1) {
+        // v1 calls them tile entities and has no entity support
+        schematic_data.insert("TileEntities", NbtValue::List(block_entities));
+    } else {
+        schematic_data.insert("BlockEntities", NbtValue::List(block_entities));
+        schematic_data.insert("Entities", NbtValue::List(entities));
+    }
 
-    schematic_data.insert("Metadata", schematic.metadata.to_nbt());
+    schematic_data.insert("Metadata", metadata_to_nbt(schematic, options));
 
     // Create the proper root structure with "Schematic" tag
-    let mut root = NbtCompound::new();
-    root.insert("Schematic", NbtTag::Compound(schematic_data));
+    let mut root = NbtMap::new();
+    root.insert("Schematic", NbtValue::Compound(schematic_data));
+
+    write_gzip_nbt(&root, "")
+}
+
+fn offset_to_nbt(schematic: &UniversalSchematic) -> NbtValue {
+    let (x, y, z) = anchor_offset(schematic)
+        .or(schematic.metadata.offset)
+        .unwrap_or((0, 0, 0));
+    NbtValue::IntArray(vec![x, y, z])
+}
+
+// Minimum corner relative to the anchor, how far from the paste position the blocks start
+fn anchor_offset(schematic: &UniversalSchematic) -> Option<(i32, i32, i32)> {
+    let (x, y, z) = schematic.anchor?;
+    let min = schematic.get_bounding_box().min;
+    Some((min.0 - x, min.1 - y, min.2 - z))
+}
 
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-    quartz_nbt::io::write_nbt(&mut encoder, None, &root, quartz_nbt::io::Flavor::Uncompressed)?;
-    Ok(encoder.finish()?)
+fn metadata_to_nbt(schematic: &UniversalSchematic, options: &ExportOptions) -> NbtValue {
+    let mut metadata = schematic.metadata.clone();
+    metadata.we_offset = anchor_offset(schematic).or(metadata.we_offset);
+    metadata.created = options.timestamp.or(metadata.created);
+    let created = metadata.created;
+    let mut metadata = NbtValue::from_quartz_nbt(&metadata.to_nbt());
+    // Sponge stores the creation time as Date
+    if let (NbtValue::Compound(compound), Some(created)) = (&mut metadata, created) {
+        compound.insert("Date", NbtValue::Long(created as i64));
+    }
+    metadata
 }
 
 // Palette conversion for v3 (creates clean sequential indices)
-fn convert_palette(palette: &Vec<BlockState>) -> (NbtCompound, i32) {
+fn convert_palette(palette: &Vec<BlockState>) -> (NbtMap, i32) {
     let (nbt_palette, _) = convert_palette_with_mapping(palette);
     let max_id = nbt_palette.len() as i32 - 1;
     (nbt_palette, max_id)
 }
 
 // Helper function that returns both palette and mapping for index conversion
-fn convert_palette_with_mapping(palette: &Vec<BlockState>) -> (NbtCompound, Vec<i32>) {
-    let mut nbt_palette = NbtCompound::new();
+fn convert_palette_with_mapping(palette: &Vec<BlockState>) -> (NbtMap, Vec<i32>) {
+    let mut nbt_palette = NbtMap::new();
     let mut mapping = vec![0i32; palette.len()]; // Default all to air (index 0)
 
     // Always start with air at index 0
-    nbt_palette.insert("minecraft:air", NbtTag::Int(0));
+    nbt_palette.insert("minecraft:air", NbtValue::Int(0));
     let mut next_id = 1;
 
     for (original_id, block_state) in palette.iter().enumerate() {
@@ -308,21 +356,13 @@ fn convert_palette_with_mapping(palette: &Vec<BlockState>) -> (NbtCompound, Vec<
             continue;
         }
 
-        let key = if block_state.properties.is_empty() {
-            block_state.name.clone()
-        } else {
-            format!("{}[{}]", block_state.name,
-                    block_state.properties.iter()
-                        .map(|(k, v)| format!("{}={}", k, v))
-                        .collect::<Vec<_>>()
-                        .join(","))
-        };
+        let key = block_state.to_string();
 
         // Check if this block state already exists in the palette
         let mut found_id = None;
-        for (existing_key, tag) in nbt_palette.inner() {
+        for (existing_key, tag) in nbt_palette.iter() {
             if existing_key == &key {
-                if let NbtTag::Int(id) = tag {
+                if let NbtValue::Int(id) = tag {
                     found_id = Some(*id);
                     break;
                 }
@@ -332,7 +372,7 @@ fn convert_palette_with_mapping(palette: &Vec<BlockState>) -> (NbtCompound, Vec<
         let assigned_id = if let Some(id) = found_id {
             id
         } else {
-            nbt_palette.insert(&key, NbtTag::Int(next_id));
+            nbt_palette.insert(key, NbtValue::Int(next_id));
             let id = next_id;
             next_id += 1;
             id
@@ -344,84 +384,78 @@ fn convert_palette_with_mapping(palette: &Vec<BlockState>) -> (NbtCompound, Vec<
     (nbt_palette, mapping)
 }
 
-// Palette conversion for v2 (legacy behavior with air at index 0)
-fn convert_palette_v2(palette: &Vec<BlockState>) -> (NbtCompound, i32) {
-    let mut nbt_palette = NbtCompound::new();
-    let mut max_id = 0;
-
-    // Always ensure air is at index 0
-    nbt_palette.insert("minecraft:air", NbtTag::Int(0));
-
-    let mut next_id = 1; // Start at 1 since air is at 0
-
-    for block_state in palette.iter() {
-        if block_state.name == "minecraft:air" {
-            continue; // Skip air blocks as we already added it at index 0
-        }
-
-        let key = if block_state.properties.is_empty() {
-            block_state.name.clone()
-        } else {
-            format!("{}[{}]", block_state.name,
-                    block_state.properties.iter()
-                        .map(|(k, v)| format!("{}={}", k, v))
-                        .collect::<Vec<_>>()
-                        .join(","))
-        };
+pub fn from_schematic(data: &[u8]) -> Result<UniversalSchematic, NucleationError> {
+    let root = read_gzip_nbt(data)?;
 
-        nbt_palette.insert(&key, NbtTag::Int(next_id));
-        max_id = max_id.max(next_id);
-        next_id += 1;
+    let schem = root.get_as::<&NbtMap>("Schematic").unwrap_or(&root);
+    let mcedit_root = schem.to_quartz_nbt();
+    if mcedit::is_mcedit_root(&mcedit_root) {
+        return mcedit::from_mcedit_root(&mcedit_root);
     }
 
-    (nbt_palette, max_id as i32)
-}
-pub fn from_schematic(data: &[u8]) -> Result<UniversalSchematic, Box<dyn std::error::Error>> {
-    let reader   = BufReader::with_capacity(1 << 20, data);   // 1 MiB buf
-    let mut gz   = GzDecoder::new(reader);
-    let (root, _) = read_nbt(&mut gz, Flavor::Uncompressed)?;
-
-    let schem = root.get::<_, &NbtCompound>("Schematic").unwrap_or(&root);
-    let schem_version = schem.get::<_, i32>("Version")?;
-
-    let name = if let Some(metadata) = schem.get::<_, &NbtCompound>("Metadata").ok() {
-        metadata.get::<_, &str>("Name").ok().map(|s| s.to_string())
-    } else {
-        None
-    }.unwrap_or_else(|| "Unnamed".to_string());
+    // Infer the version from the layout when the Version tag is missing
+    let schem_version = match schem.get_as::<i32>("Version") {
+        Ok(version) => version,
+        Err(_) if schem.get_as::<&NbtMap>("Blocks").is_ok() => 3,
+        Err(_) if schem.get_as::<i32>("DataVersion").is_ok() => 2,
+        Err(_) => 1,
+    };
+    if !(1..=3).contains(&schem_version) {
+        return Err(NucleationError::UnsupportedVersion { format: "Sponge schematic", version: schem_version });
+    }
 
-    let mc_version = schem.get::<_, i32>("DataVersion").ok();
+    let mut metadata = match schem.get_as::<&NbtMap>("Metadata") {
+        Ok(metadata_nbt) => {
+            let mut metadata = Metadata::from_nbt(&metadata_nbt.to_quartz_nbt())?;
+            if metadata.created.is_none() {
+                metadata.created = metadata_nbt.get_as::<i64>("Date").ok().map(|date| date as u64);
+            }
+            metadata
+        }
+        Err(_) => Metadata::default(),
+    };
+    metadata.name = metadata.name.or_else(|| Some("Unnamed".to_string()));
+    metadata.mc_version = schem.get_as::<i32>("DataVersion").ok();
+    metadata.offset = match schem.get_as::<&[i32]>("Offset") {
+        Ok(&[x, y, z]) => Some((x, y, z)),
+        _ => None,
+    };
 
-    let mut schematic = UniversalSchematic::new(name);
-    schematic.metadata.mc_version = mc_version;
+    let mut schematic = UniversalSchematic::new(String::new());
+    // Blocks start at (0, 0, 0), so the WorldEdit origin lies at minus the offset
+    schematic.anchor = metadata.we_offset.map(|(x, y, z)| (-x, -y, -z));
+    schematic.metadata = metadata;
 
-    let width = schem.get::<_, i16>("Width")? as u32;
-    let height = schem.get::<_, i16>("Height")? as u32;
-    let length = schem.get::<_, i16>("Length")? as u32;
+    let width = schem.get_as::<i16>("Width")? as u16 as u32;
+    let height = schem.get_as::<i16>("Height")? as u16 as u32;
+    let length = schem.get_as::<i16>("Length")? as u16 as u32;
 
     let block_container=
-    if schem_version == 2 {
+    if schem_version < 3 {
         schem
     } else {
-        schem.get::<_, &NbtCompound>("Blocks")?
+        schem.get_as::<&NbtMap>("Blocks")?
     };
 
-    let block_palette = parse_block_palette(&block_container)?;
+    let block_palette = parse_block_palette(block_container)?;
 
-    let block_data = parse_block_data(&block_container, width, height, length)?;
+    let block_data = parse_block_data(block_container, width, height, length)?;
 
 
     let mut region = Region::new("Main".to_string(), (0, 0, 0), (width as i32, height as i32, length as i32));
     region.palette = block_palette;
 
     region.blocks = block_data.iter().map(|&x| x as usize).collect();
+    region.check_palette_indices()?;
 
-    let block_entities = parse_block_entities(&block_container)?;
+    // v1 names them TileEntities, v2 and v3 BlockEntities
+    let block_entities_key = if schem_version == 1 { "TileEntities" } else { "BlockEntities" };
+    let block_entities = parse_block_entities(block_container, block_entities_key)?;
     for block_entity in block_entities {
         region.add_block_entity(block_entity);
     }
 
-    let entities = parse_entities(&schem)?;
+    let entities = parse_entities(schem)?;
     for entity in entities {
         region.add_entity(entity);
     }
@@ -432,34 +466,75 @@ pub fn from_schematic(data: &[u8]) -> Result<UniversalSchematic, Box<dyn std::er
 
 
 
-fn convert_block_entities(region: &Region) -> NbtList {
-    let mut block_entities = NbtList::new();
+// Positions are written relative to the minimum corner of the schematic.
+// v1 and v2 store block entity data inline, v3 nests it in a Data compound.
+fn convert_block_entities(region: &Region, origin: (i32, i32, i32), version: SchematicVersion, options: &ExportOptions) -> Vec<NbtValue> {
+    let mut block_entities = Vec::new();
 
-    for (_, block_entity) in &region.block_entities {
-        block_entities.push(block_entity.to_nbt());
+    for block_entity in options.block_entities(region) {
+        let (x, y, z) = block_entity.position;
+        let mut data = NbtMap::new();
+        for (key, value) in &block_entity.nbt {
+            if key == "Id" || key == "Pos" {
+                continue;
+            }
+            data.insert(key.clone(), value.clone());
+        }
+
+        let mut compound = match version {
+            SchematicVersion::V3 => {
+                let mut compound = NbtMap::new();
+                compound.insert("Data", NbtValue::Compound(data));
+                compound
+            }
+            SchematicVersion::V2 => data,
+            SchematicVersion::V1 => {
+                data.insert("ContentVersion", NbtValue::Int(1));
+                data
+            }
+        };
+        compound.insert("Pos", NbtValue::IntArray(vec![x - origin.0, y - origin.1, z - origin.2]));
+        compound.insert("Id", NbtValue::String(block_entity.id.clone()));
+        block_entities.push(NbtValue::Compound(compound));
     }
 
     block_entities
 }
 
-fn convert_entities(region: &Region) -> NbtList {
-    let mut entities = NbtList::new();
+fn convert_entities(region: &Region, origin: (i32, i32, i32), version: SchematicVersion, options: &ExportOptions) -> Vec<NbtValue> {
+    let mut entities = Vec::new();
+
+    for entity in options.entities(region) {
+        let data = entity.nbt.clone();
 
-    for entity in &region.entities {
-        entities.push(entity.to_nbt());
+        let mut compound = match version {
+            SchematicVersion::V3 => {
+                let mut compound = NbtMap::new();
+                compound.insert("Data", NbtValue::Compound(data));
+                compound
+            }
+            _ => data,
+        };
+        compound.insert("Pos", NbtValue::List(vec![
+            NbtValue::Double(entity.position.0 - origin.0 as f64),
+            NbtValue::Double(entity.position.1 - origin.1 as f64),
+            NbtValue::Double(entity.position.2 - origin.2 as f64),
+        ]));
+        compound.insert("Id", NbtValue::String(entity.id.clone()));
+        entities.push(NbtValue::Compound(compound));
     }
 
     entities
 }
 
-fn parse_block_palette(region_tag: &NbtCompound) -> Result<Vec<BlockState>, Box<dyn std::error::Error>> {
-    let palette_compound = region_tag.get::<_, &NbtCompound>("Palette")?;
-    let palette_max = region_tag.get::<_, i32>("PaletteMax") // V2
+fn parse_block_palette(region_tag: &NbtMap) -> Result<Vec<BlockState>, NucleationError> {
+    let palette_compound = region_tag.get_as::<&NbtMap>("Palette")?;
+    let palette_max = region_tag.get_as::<i32>("PaletteMax") // V2
         .unwrap_or(palette_compound.len() as i32) as usize; // V3
     let mut palette = vec![BlockState::new("minecraft:air".to_string()); palette_max + 1];
 
-    for (block_state_str, value) in palette_compound.inner() {
-        if let NbtTag::Int(id) = value {
+    for (block_state_str, value) in palette_compound.iter() {
+        if let NbtValue::Int(id) = value {
             let block_state = parse_block_state(block_state_str);
             palette[*id as usize] = block_state;
         }
@@ -468,7 +543,7 @@ fn parse_block_palette(region_tag: &NbtCompound) -> Result<Vec<BlockState>, Box<
     Ok(palette)
 }
 
-fn parse_block_state(input: &str) -> BlockState {
+pub(crate) fn parse_block_state(input: &str) -> BlockState {
     if let Some((name, properties_str)) = input.split_once('[') {
         let name = name.to_string();
         let properties = properties_str
@@ -505,7 +580,7 @@ pub fn encode_varint(value: u32) -> Vec<u8> {
     bytes
 }
 
-fn decode_varint<R: Read>(reader: &mut R) -> Result<u32, Box<dyn std::error::Error>> {
+fn decode_varint<R: Read>(reader: &mut R) -> Result<u32, NucleationError> {
     let mut result = 0u32;
     let mut shift = 0;
     loop {
@@ -523,15 +598,15 @@ fn decode_varint<R: Read>(reader: &mut R) -> Result<u32, Box<dyn std::error::Err
 }
 
 fn parse_block_data(
-    region_tag: &NbtCompound,
+    region_tag: &NbtMap,
     width: u32,
     height: u32,
     length: u32,
-) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+) -> Result<Vec<u32>, NucleationError> {
     // V2 = BlockData, V3 = Data
     let block_data_i8 = region_tag
-        .get::<_, &Vec<i8>>("BlockData")
-        .or(region_tag.get::<_, &Vec<i8>>("Data"))?;
+        .get_as::<&[i8]>("BlockData")
+        .or(region_tag.get_as::<&[i8]>("Data"))?;
 
     let mut block_data_u8: &[u8] = unsafe {
         std::slice::from_raw_parts(block_data_i8.as_ptr() as *const u8,
@@ -575,13 +650,37 @@ fn parse_block_data(
 
 
 
-fn parse_block_entities(region_tag: &NbtCompound) -> Result<Vec<BlockEntity>, Box<dyn std::error::Error>> {
-    let block_entities_list = region_tag.get::<_, &NbtList>("BlockEntities")?;
+fn parse_block_entities(region_tag: &NbtMap, key: &str) -> Result<Vec<BlockEntity>, NucleationError> {
+    if !region_tag.contains_key(key) {
+        return Ok(Vec::new());
+    }
+    let block_entities_list = region_tag.get_as::<&[NbtValue]>(key)?;
     let mut block_entities = Vec::new();
 
-    for tag in block_entities_list.iter() {
-        if let NbtTag::Compound(compound) = tag {
-            let block_entity = BlockEntity::from_nbt(compound);
+    for tag in block_entities_list {
+        if let NbtValue::Compound(compound) = tag {
+            let id = compound.get_as::<&str>("Id")
+                .or_else(|_| compound.get_as::<&str>("id"))?
+                .to_string();
+            let position = match compound.get_as::<&[i32]>("Pos")? {
+                &[x, y, z] => (x, y, z),
+                _ => return Err("Invalid block entity position".into()),
+            };
+
+            // v3 nests the data, older files keep it next to Pos and Id
+            let nbt = match compound.get_as::<&NbtMap>("Data") {
+                Ok(data) => data.clone(),
+                Err(_) => {
+                    let mut nbt = compound.clone();
+                    for key in ["Id", "id", "Pos", "ContentVersion"] {
+                        nbt.remove(key);
+                    }
+                    nbt
+                }
+            };
+
+            let mut block_entity = BlockEntity::new(id, position);
+            block_entity.nbt = nbt;
             block_entities.push(block_entity);
         }
     }
@@ -589,16 +688,39 @@ fn parse_block_entities(region_tag: &NbtCompound) -> Result<Vec<BlockEntity>, Bo
     Ok(block_entities)
 }
 
-fn parse_entities(region_tag: &NbtCompound) -> Result<Vec<Entity>, Box<dyn std::error::Error>> {
+fn parse_entities(region_tag: &NbtMap) -> Result<Vec<Entity>, NucleationError> {
     if !region_tag.contains_key("Entities") {
         return Ok(Vec::new());
     }
-    let entities_list = region_tag.get::<_, &NbtList>("Entities")?;
+    let entities_list = region_tag.get_as::<&[NbtValue]>("Entities")?;
     let mut entities = Vec::new();
 
-    for tag in entities_list.iter() {
-        if let NbtTag::Compound(compound) = tag {
-            entities.push(Entity::from_nbt(compound)?);
+    for tag in entities_list {
+        if let NbtValue::Compound(compound) = tag {
+            let id = compound.get_as::<&str>("Id")
+                .or_else(|_| compound.get_as::<&str>("id"))?;
+            let position = match compound.get_as::<&[NbtValue]>("Pos")? {
+                [NbtValue::Double(x), NbtValue::Double(y), NbtValue::Double(z)] => (*x, *y, *z),
+                _ => return Err("Invalid entity position".into()),
+            };
+
+            let mut entity = Entity::new(id.to_string(), position);
+            let data = compound.get_as::<&NbtMap>("Data").unwrap_or(compound);
+            for (key, value) in data {
+                match key.as_str() {
+                    "Id" | "id" | "Pos" => continue,
+                    // Older versions of this library nested entity data under NBT
+                    "NBT" => if let NbtValue::Compound(nested) = value {
+                        for (key, value) in nested {
+                            entity.nbt.insert(key.clone(), value.clone());
+                        }
+                    },
+                    _ => {
+                        entity.nbt.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            entities.push(entity);
         }
     }
 
@@ -618,6 +740,55 @@ mod tests {
     use crate::litematic::{from_litematic, to_litematic};
 
     use super::*;
+    use crate::block_position::BlockPosition;
+    use crate::utils::NbtValue;
+    use flate2::read::GzDecoder;
+    use quartz_nbt::{NbtCompound, NbtList};
+    use quartz_nbt::io::{read_nbt, Flavor};
+
+    #[test]
+    fn test_deterministic_export() {
+        // Same content built in opposite orders, so palettes and entity lists differ in order.
+        // The corners are set first so both regions grow to the same bounds.
+        let build = |reverse: bool| {
+            let mut schematic = UniversalSchematic::new("Deterministic".to_string());
+            let corner = BlockState::new(if reverse { "minecraft:dirt" } else { "minecraft:glass" }.to_string());
+            schematic.set_block(0, 0, 0, corner.clone());
+            schematic.set_block(7, 0, 0, corner);
+            let mut positions: Vec<i32> = (0..8).collect();
+            if reverse {
+                positions.reverse();
+            }
+            for &x in &positions {
+                let block = if x % 2 == 0 { "minecraft:stone" } else { "minecraft:chest[facing=north]" };
+                schematic.set_block_from_string(x, 0, 0, block).unwrap();
+                if x % 2 == 1 {
+                    schematic.set_block_entity(BlockPosition { x, y: 0, z: 0 }, BlockEntity::new("minecraft:chest".to_string(), (x, 0, 0)));
+                }
+                schematic.add_entity(Entity::new("minecraft:pig".to_string(), (x as f64 + 0.5, 1.0, 0.5)));
+            }
+            schematic
+        };
+        let (a, b) = (build(false), build(true));
+        let options = ExportOptions::deterministic();
+
+        let schem_a = to_schematic_with_options(&a, SchematicVersion::V3, &options).unwrap();
+        assert_eq!(schem_a, to_schematic_with_options(&b, SchematicVersion::V3, &options).unwrap());
+        assert_eq!(schem_a, to_schematic_with_options(&a, SchematicVersion::V3, &options).unwrap());
+        assert_ne!(to_schematic(&a).unwrap(), to_schematic(&b).unwrap());
+
+        let options = options.with_timestamp(1_700_000_000_000);
+        let litematic_a = crate::litematic::to_litematic_with_options(&a, &options).unwrap();
+        assert_eq!(litematic_a, crate::litematic::to_litematic_with_options(&b, &options).unwrap());
+        assert_eq!(from_litematic(&litematic_a).unwrap().metadata.created, Some(1_700_000_000_000));
+
+        let mut dated = a.clone();
+        dated.metadata.created = Some(5);
+        let schem = to_schematic_with_options(&dated, SchematicVersion::V3, &options).unwrap();
+        assert_eq!(from_schematic(&schem).unwrap().metadata.created, Some(1_700_000_000_000));
+        let litematic = crate::litematic::to_litematic_with_options(&dated, &options).unwrap();
+        assert_eq!(from_litematic(&litematic).unwrap().metadata.created, Some(1_700_000_000_000));
+    }
 
     #[test]
     fn test_schematic_file_generation() {
@@ -692,13 +863,13 @@ mod tests {
 
     #[test]
     fn test_parse_block_data() {
-        let mut nbt = NbtCompound::new();
+        let mut nbt = NbtMap::new();
         let block_data = vec![0, 1, 2, 1, 0, 2, 1, 0]; // 8 blocks
         let encoded_block_data: Vec<u8> = block_data.iter()
             .flat_map(|&v| encode_varint(v))
             .collect();
 
-        nbt.insert("BlockData", NbtTag::ByteArray(encoded_block_data.iter().map(|&x| x as i8).collect()));
+        nbt.insert("BlockData", NbtValue::ByteArray(encoded_block_data.iter().map(|&x| x as i8).collect()));
 
         let parsed_data = parse_block_data(&nbt, 2, 2, 2).expect("Failed to parse block data");
         assert_eq!(parsed_data, vec![0, 1, 2, 1, 0, 2, 1, 0]);
@@ -719,31 +890,10 @@ mod tests {
 
         // V3 now ensures air is always at index 0 for WorldEdit compatibility
         assert_eq!(max_id, 3); // air=0, stone=1, dirt=2, wool=3
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:air").unwrap(), 0);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:stone").unwrap(), 1);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:dirt").unwrap(), 2);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:wool[color=red]").unwrap(), 3);
-    }
-
-    #[test]
-    fn test_convert_palette_v2() {
-        let palette = vec![
-            BlockState::new("minecraft:stone".to_string()),
-            BlockState::new("minecraft:dirt".to_string()),
-            BlockState {
-                name: "minecraft:wool".to_string(),
-                properties: [("color".to_string(), "red".to_string())].into_iter().collect(),
-            },
-        ];
-
-        let (nbt_palette, max_id) = convert_palette_v2(&palette);
-
-        // V2 behavior: Air is always at index 0, other blocks follow
-        assert_eq!(max_id, 3); // Air=0, stone=1, dirt=2, wool=3
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:air").unwrap(), 0);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:stone").unwrap(), 1);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:dirt").unwrap(), 2);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:wool[color=red]").unwrap(), 3);
+        assert_eq!(nbt_palette.get_as::<i32>("minecraft:air").unwrap(), 0);
+        assert_eq!(nbt_palette.get_as::<i32>("minecraft:stone").unwrap(), 1);
+        assert_eq!(nbt_palette.get_as::<i32>("minecraft:dirt").unwrap(), 2);
+        assert_eq!(nbt_palette.get_as::<i32>("minecraft:wool[color=red]").unwrap(), 3);
     }
 
     #[test]
@@ -758,9 +908,9 @@ mod tests {
 
         // V3 with air explicitly in palette - air should still be at index 0
         assert_eq!(max_id, 2);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:air").unwrap(), 0);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:stone").unwrap(), 1);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:dirt").unwrap(), 2);
+        assert_eq!(nbt_palette.get_as::<i32>("minecraft:air").unwrap(), 0);
+        assert_eq!(nbt_palette.get_as::<i32>("minecraft:stone").unwrap(), 1);
+        assert_eq!(nbt_palette.get_as::<i32>("minecraft:dirt").unwrap(), 2);
     }
 
     #[test]
@@ -774,9 +924,9 @@ mod tests {
         let (nbt_palette, mapping) = convert_palette_with_mapping(&palette);
 
         // Check palette structure
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:air").unwrap(), 0);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:stone").unwrap(), 1);
-        assert_eq!(nbt_palette.get::<_, i32>("minecraft:dirt").unwrap(), 2);
+        assert_eq!(nbt_palette.get_as::<i32>("minecraft:air").unwrap(), 0);
+        assert_eq!(nbt_palette.get_as::<i32>("minecraft:stone").unwrap(), 1);
+        assert_eq!(nbt_palette.get_as::<i32>("minecraft:dirt").unwrap(), 2);
 
         // Check mapping array
         assert_eq!(mapping[0], 1); // stone -> 1
@@ -824,4 +974,123 @@ mod tests {
         let mut schematic_output_file = File::create(output_schematic_name).expect("Failed to create schematic file");
         schematic_output_file.write_all(&schematic_output_data).expect("Failed to write schematic file");
     }
-}
\ No newline at end of file
+
+    fn chest_schematic() -> UniversalSchematic {
+        let mut schematic = UniversalSchematic::new("Chest".to_string());
+        let mut region = Region::new("Main".to_string(), (0, 0, 0), (2, 1, 1));
+        region.set_block(1, 0, 0, BlockState::new("minecraft:chest".to_string()));
+        region.add_block_entity(
+            BlockEntity::new("minecraft:chest".to_string(), (1, 0, 0))
+                .with_nbt_data("CustomName".to_string(), NbtValue::String("Loot".to_string()))
+        );
+        schematic.add_region(region);
+        schematic.metadata.offset = Some((4, -2, 7));
+        schematic
+    }
+
+    #[test]
+    fn test_trim_on_export() {
+        let mut schematic = UniversalSchematic::new("Trim".to_string());
+        schematic.set_block_str(0, 0, 0, "minecraft:stone");
+        schematic.set_block_str(100, 2, 3, "minecraft:stone");
+        let untrimmed = from_schematic(&to_schematic(&schematic).unwrap()).unwrap();
+        assert!(untrimmed.get_dimensions().0 > 101);
+
+        let options = ExportOptions::default().with_trim();
+        let data = to_schematic_with_options(&schematic, SchematicVersion::V3, &options).unwrap();
+        assert_eq!(from_schematic(&data).unwrap().get_dimensions(), (101, 3, 4));
+
+        // An empty region at the origin does not keep the bounds from shrinking
+        let mut schematic = UniversalSchematic::new("Trim".to_string());
+        let mut far = Region::new("Far".to_string(), (1000, 5, 1000), (1, 1, 1));
+        far.set_block(1000, 5, 1000, BlockState::new("minecraft:stone".to_string()));
+        schematic.add_region(far);
+        assert_eq!(schematic.get_bounding_box().min, (0, 0, 0));
+        let data = to_schematic_with_options(&schematic, SchematicVersion::V3, &options).unwrap();
+        assert_eq!(from_schematic(&data).unwrap().get_dimensions(), (1, 1, 1));
+
+        schematic.trim();
+        assert_eq!(schematic.get_bounding_box(), crate::bounding_box::BoundingBox::new((1000, 5, 1000), (1000, 5, 1000)));
+    }
+
+    #[test]
+    fn test_anchor_written_as_offset() {
+        let mut schematic = UniversalSchematic::new("Anchored".to_string());
+        schematic.set_block_str(10, 64, 5, "minecraft:stone");
+        schematic.set_block_str(12, 64, 5, "minecraft:stone");
+        schematic.normalize();
+        schematic.set_anchor(1, 0, -3);
+
+        let data = to_schematic(&schematic).unwrap();
+        let (root, _) = quartz_nbt::io::read_nbt(&mut flate2::read::GzDecoder::new(data.as_slice()), quartz_nbt::io::Flavor::Uncompressed).unwrap();
+        let schem = root.get::<_, &NbtCompound>("Schematic").unwrap();
+        assert_eq!(schem.get::<_, &[i32]>("Offset").unwrap(), &[-1, 0, 3]);
+        assert_eq!(schem.get::<_, &NbtCompound>("Metadata").unwrap().get::<_, i32>("WEOffsetZ").unwrap(), 3);
+
+        let loaded = from_schematic(&data).unwrap();
+        assert_eq!(loaded.get_anchor(), Some((1, 0, -3)));
+    }
+
+    #[test]
+    fn test_v1_roundtrip() {
+        let schematic = chest_schematic();
+        let data = to_schematic_version(&schematic, SchematicVersion::V1).expect("Failed to write v1 schematic");
+        assert!(is_schematic(&data));
+
+        let loaded = from_schematic(&data).expect("Failed to read v1 schematic");
+        assert_eq!(loaded.get_block(1, 0, 0).map(|b| b.get_name().as_str()), Some("minecraft:chest"));
+        assert_eq!(loaded.metadata.offset, Some((4, -2, 7)));
+        let block_entity = loaded.get_block_entity(BlockPosition { x: 1, y: 0, z: 0 }).expect("Missing block entity");
+        assert_eq!(block_entity.nbt.get("CustomName"), Some(&NbtValue::String("Loot".to_string())));
+        assert!(block_entity.nbt.get("ContentVersion").is_none());
+    }
+
+    #[test]
+    fn test_v2_palette_starts_with_air() {
+        let mut schematic = UniversalSchematic::new("Palette".to_string());
+        schematic.set_block_str(0, 0, 0, "minecraft:stone");
+        schematic.set_block_str(1, 0, 0, "minecraft:dirt");
+        let data = to_schematic_version(&schematic, SchematicVersion::V2).unwrap();
+
+        let (root, _) = read_nbt(&mut GzDecoder::new(data.as_slice()), Flavor::Uncompressed).unwrap();
+        let schem = root.get::<_, &NbtCompound>("Schematic").unwrap();
+        let palette = schem.get::<_, &NbtCompound>("Palette").unwrap();
+        assert_eq!(palette.get::<_, i32>("minecraft:air").unwrap(), 0);
+        assert_eq!(schem.get::<_, i32>("PaletteMax").unwrap(), palette.len() as i32);
+    }
+
+    #[test]
+    fn test_v3_block_entity_layout() {
+        let schematic = chest_schematic();
+        let data = to_schematic_version(&schematic, SchematicVersion::V3).expect("Failed to write v3 schematic");
+
+        let (root, _) = read_nbt(&mut GzDecoder::new(data.as_slice()), Flavor::Uncompressed).unwrap();
+        let schem = root.get::<_, &NbtCompound>("Schematic").unwrap();
+        let blocks = schem.get::<_, &NbtCompound>("Blocks").unwrap();
+        let block_entities = blocks.get::<_, &NbtList>("BlockEntities").unwrap();
+        let block_entity = block_entities.get::<&NbtCompound>(0).unwrap();
+        assert_eq!(block_entity.get::<_, &str>("Id").unwrap(), "minecraft:chest");
+        assert_eq!(block_entity.get::<_, &[i32]>("Pos").unwrap(), &[1, 0, 0]);
+        let data_compound = block_entity.get::<_, &NbtCompound>("Data").unwrap();
+        assert_eq!(data_compound.get::<_, &str>("CustomName").unwrap(), "Loot");
+
+        let loaded = from_schematic(&data).expect("Failed to read v3 schematic");
+        let block_entity = loaded.get_block_entity(BlockPosition { x: 1, y: 0, z: 0 }).expect("Missing block entity");
+        assert_eq!(block_entity.nbt.get("CustomName"), Some(&NbtValue::String("Loot".to_string())));
+    }
+
+    #[test]
+    fn test_empty_list_type_round_trip() {
+        let mut schematic = chest_schematic();
+        let mut block_entity = schematic.get_block_entity(BlockPosition { x: 1, y: 0, z: 0 }).unwrap().clone();
+        block_entity.nbt.insert("Items", NbtValue::EmptyList(10));
+        schematic.set_block_entity(BlockPosition { x: 1, y: 0, z: 0 }, block_entity);
+
+        for version in SchematicVersion::get_all() {
+            let data = to_schematic_version(&schematic, version).unwrap();
+            let loaded = from_schematic(&data).unwrap();
+            let block_entity = loaded.get_block_entity(BlockPosition { x: 1, y: 0, z: 0 }).unwrap();
+            assert_eq!(block_entity.nbt.get("Items"), Some(&NbtValue::EmptyList(10)), "{}", version);
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::io::Read;
+use flate2::read::GzDecoder;
+use crate::{BlockState, NucleationError, UniversalSchematic};
+use crate::block_entity::BlockEntity;
+use crate::entity::Entity;
+use crate::formats::write_gzip_nbt;
+use crate::region::Region;
+use crate::utils::{FromNbtValue, NbtMap, NbtValue};
+
+// Blocks that are not listed in a structure file are left untouched when it is placed.
+// We represent them explicitly so that a read/write roundtrip keeps them out of the file.
+pub const STRUCTURE_VOID: &str = "minecraft:structure_void";
+
+pub fn is_structure(data: &[u8]) -> bool {
+    let root = match read_root(data) {
+        Ok(root) => root,
+        Err(_) => return false,
+    };
+
+    // Check for required fields as per the vanilla structure format
+    root.get_as::<&[NbtValue]>("size").is_ok() &&
+        root.get_as::<&[NbtValue]>("blocks").is_ok() &&
+        (root.get_as::<&[NbtValue]>("palette").is_ok() || root.get_as::<&[NbtValue]>("palettes").is_ok())
+}
+
+// Returns the number of palette variants (e.g. shipwrecks store one palette per wood type)
+pub fn get_palette_count(data: &[u8]) -> Result<usize, NucleationError> {
+    let root = read_root(data)?;
+    Ok(parse_palettes(&root)?.len())
+}
+
+// Loads the structure using its first palette
+pub fn from_structure(data: &[u8]) -> Result<UniversalSchematic, NucleationError> {
+    from_structure_with_palette(data, 0)
+}
+
+pub fn from_structure_with_palette(data: &[u8], palette_index: usize) -> Result<UniversalSchematic, NucleationError> {
+    let root = read_root(data)?;
+    let palettes = parse_palettes(&root)?;
+    let palette = palettes.get(palette_index).ok_or_else(|| {
+        format!("Palette index {} out of range (structure has {} palettes)", palette_index, palettes.len())
+    })?;
+
+    let mut schematic = UniversalSchematic::new("Unnamed".to_string());
+    schematic.metadata.mc_version = root.get_as::<i32>("DataVersion").ok();
+
+    let size = parse_int_triple(root.get_as::<&[NbtValue]>("size")?)?;
+    let mut region = Region::new(schematic.default_region_name.clone(), (0, 0, 0), size);
+
+    // Everything that is not listed in `blocks` is structure void
+    let void_index = region.get_or_insert_in_palette(BlockState::new(STRUCTURE_VOID.to_string()));
+    region.blocks.fill(void_index);
+
+    let palette_mapping: Vec<usize> = palette.iter()
+        .map(|block_state| region.get_or_insert_in_palette(block_state.clone()))
+        .collect();
+
+    let blocks = root.get_as::<&[NbtValue]>("blocks")?;
+    for tag in blocks {
+        let block = match tag {
+            NbtValue::Compound(compound) => compound,
+            _ => continue,
+        };
+
+        let (x, y, z) = parse_int_triple(block.get_as::<&[NbtValue]>("pos")?)?;
+        if !region.is_in_region(x, y, z) {
+            return Err(format!("Block position ({}, {}, {}) is outside of the structure size", x, y, z).into());
+        }
+
+        let state = block.get_as::<i32>("state")? as usize;
+        let palette_index = *palette_mapping.get(state)
+            .ok_or(NucleationError::InvalidPaletteIndex { index: state, palette_len: palette_mapping.len() })?;
+        let index = region.coords_to_index(x, y, z);
+        region.blocks.set(index, palette_index);
+
+        if let Ok(nbt) = block.get_as::<&NbtMap>("nbt") {
+            region.add_block_entity(parse_block_entity(nbt, (x, y, z)));
+        }
+    }
+
+    if let Ok(entities) = root.get_as::<&[NbtValue]>("entities") {
+        for tag in entities {
+            if let NbtValue::Compound(compound) = tag {
+                region.add_entity(parse_entity(compound)?);
+            }
+        }
+    }
+
+    schematic.add_region(region);
+    Ok(schematic)
+}
+
+pub fn to_structure(schematic: &UniversalSchematic) -> Result<Vec<u8>, NucleationError> {
+    let mut root = NbtMap::new();
+
+    root.insert("DataVersion", NbtValue::Int(schematic.metadata.mc_version.unwrap_or(3700)));
+
+    let merged_region = schematic.get_merged_region();
+    let bounding_box = merged_region.get_bounding_box();
+    let origin = bounding_box.min;
+    let (width, height, length) = bounding_box.get_dimensions();
+    root.insert("size", NbtValue::List(vec![
+        NbtValue::Int(width),
+        NbtValue::Int(height),
+        NbtValue::Int(length),
+    ]));
+
+    // Build the structure palette, leaving out structure void
+    let mut palette = Vec::new();
+    let mut palette_mapping: Vec<Option<i32>> = vec![None; merged_region.palette.len()];
+    let mut seen: HashMap<&BlockState, i32> = HashMap::new();
+    for (original_id, block_state) in merged_region.palette.iter().enumerate() {
+        if block_state.name == STRUCTURE_VOID {
+            continue;
+        }
+        let id = *seen.entry(block_state).or_insert_with(|| {
+            palette.push(NbtValue::from_quartz_nbt(&block_state.to_nbt()));
+            palette.len() as i32 - 1
+        });
+        palette_mapping[original_id] = Some(id);
+    }
+    root.insert("palette", list_of(palette, 10));
+
+    let mut blocks = Vec::new();
+    for (index, block_index) in merged_region.blocks.iter().enumerate() {
+        let state = match palette_mapping.get(block_index).copied().flatten() {
+            Some(state) => state,
+            None => continue,
+        };
+        let (x, y, z) = merged_region.index_to_coords(index);
+
+        let mut block = NbtMap::new();
+        block.insert("pos", int_triple_to_nbt((x - origin.0, y - origin.1, z - origin.2)));
+        block.insert("state", NbtValue::Int(state));
+        if let Some(block_entity) = merged_region.block_entities.get(&(x, y, z)) {
+            block.insert("nbt", NbtValue::Compound(block_entity_to_structure_nbt(block_entity)));
+        }
+        blocks.push(NbtValue::Compound(block));
+    }
+    root.insert("blocks", list_of(blocks, 10));
+
+    let mut entities = Vec::new();
+    for entity in &merged_region.entities {
+        entities.push(NbtValue::Compound(entity_to_structure_nbt(entity, origin)));
+    }
+    root.insert("entities", list_of(entities, 10));
+
+    write_gzip_nbt(&root, "")
+}
+
+// Empty lists keep the element type vanilla expects
+fn list_of(values: Vec<NbtValue>, element_id: u8) -> NbtValue {
+    if values.is_empty() {
+        NbtValue::EmptyList(element_id)
+    } else {
+        NbtValue::List(values)
+    }
+}
+
+fn read_root(data: &[u8]) -> Result<NbtMap, NucleationError> {
+    // Structure files are gzipped, but accept uncompressed NBT as well
+    let decompressed = if data.starts_with(&[0x1f, 0x8b]) {
+        let mut decoder = GzDecoder::new(data);
+        let mut decompressed = Vec::new();
+        decoder.read_to_end(&mut decompressed)?;
+        decompressed
+    } else {
+        data.to_vec()
+    };
+
+    let (_, root) = NbtMap::read_nbt(&decompressed)?;
+    Ok(root)
+}
+
+fn parse_palettes(root: &NbtMap) -> Result<Vec<Vec<BlockState>>, NucleationError> {
+    if let Ok(palette) = root.get_as::<&[NbtValue]>("palette") {
+        return Ok(vec![parse_palette(palette)?]);
+    }
+
+    let palettes = root.get_as::<&[NbtValue]>("palettes")?;
+    let mut result = Vec::with_capacity(palettes.len());
+    for tag in palettes {
+        if let Some(palette) = <&[NbtValue]>::from_nbt_value(tag) {
+            result.push(parse_palette(palette)?);
+        }
+    }
+
+    if result.is_empty() {
+        return Err("Structure has no palettes".into());
+    }
+    Ok(result)
+}
+
+fn parse_palette(palette: &[NbtValue]) -> Result<Vec<BlockState>, NucleationError> {
+    let mut result = Vec::with_capacity(palette.len());
+    for tag in palette {
+        match tag {
+            NbtValue::Compound(compound) => result.push(BlockState::from_nbt(&compound.to_quartz_nbt())?),
+            _ => return Err("Invalid palette entry".into()),
+        }
+    }
+    Ok(result)
+}
+
+fn parse_int_triple(list: &[NbtValue]) -> Result<(i32, i32, i32), NucleationError> {
+    match list {
+        [NbtValue::Int(x), NbtValue::Int(y), NbtValue::Int(z)] => Ok((*x, *y, *z)),
+        [_, _, _] => Err("Expected integer coordinates".into()),
+        _ => Err(format!("Expected 3 coordinates, got {}", list.len()).into()),
+    }
+}
+
+fn parse_double_triple(list: &[NbtValue]) -> Result<(f64, f64, f64), NucleationError> {
+    match list {
+        [NbtValue::Double(x), NbtValue::Double(y), NbtValue::Double(z)] => Ok((*x, *y, *z)),
+        [_, _, _] => Err("Expected double coordinates".into()),
+        _ => Err(format!("Expected 3 coordinates, got {}", list.len()).into()),
+    }
+}
+
+fn int_triple_to_nbt(value: (i32, i32, i32)) -> NbtValue {
+    NbtValue::List(vec![
+        NbtValue::Int(value.0),
+        NbtValue::Int(value.1),
+        NbtValue::Int(value.2),
+    ])
+}
+
+fn parse_block_entity(nbt: &NbtMap, position: (i32, i32, i32)) -> BlockEntity {
+    let mut nbt_map = nbt.clone();
+    let id = nbt.get_as::<&str>("id").unwrap_or("unknown").to_string();
+    nbt_map.remove("id");
+    BlockEntity { nbt: nbt_map, id, position }
+}
+
+fn block_entity_to_structure_nbt(block_entity: &BlockEntity) -> NbtMap {
+    let mut nbt = NbtMap::new();
+    for (key, value) in &block_entity.nbt {
+        // Our own bookkeeping keys; structures store the position on the block instead
+        if key == "Id" || key == "Pos" {
+            continue;
+        }
+        nbt.insert(key.clone(), value.clone());
+    }
+    nbt.insert("id", NbtValue::String(block_entity.id.clone()));
+    nbt
+}
+
+fn parse_entity(compound: &NbtMap) -> Result<Entity, NucleationError> {
+    let position = parse_double_triple(compound.get_as::<&[NbtValue]>("pos")?)?;
+    let nbt = compound.get_as::<&NbtMap>("nbt")?;
+
+    let id = nbt.get_as::<&str>("id")?.to_string();
+    let mut entity = Entity::new(id, position);
+    for (key, value) in nbt {
+        if key == "id" || key == "Pos" {
+            continue;
+        }
+        entity.nbt.insert(key.clone(), value.clone());
+    }
+    Ok(entity)
+}
+
+fn entity_to_structure_nbt(entity: &Entity, origin: (i32, i32, i32)) -> NbtMap {
+    let position = (
+        entity.position.0 - origin.0 as f64,
+        entity.position.1 - origin.1 as f64,
+        entity.position.2 - origin.2 as f64,
+    );
+
+    let mut nbt = entity.nbt.clone();
+    nbt.insert("id", NbtValue::String(entity.id.clone()));
+    nbt.insert("Pos", NbtValue::List(vec![
+        NbtValue::Double(position.0),
+        NbtValue::Double(position.1),
+        NbtValue::Double(position.2),
+    ]));
+
+    let mut compound = NbtMap::new();
+    compound.insert("pos", NbtValue::List(vec![
+        NbtValue::Double(position.0),
+        NbtValue::Double(position.1),
+        NbtValue::Double(position.2),
+    ]));
+    compound.insert("blockPos", int_triple_to_nbt((
+        position.0.floor() as i32,
+        position.1.floor() as i32,
+        position.2.floor() as i32,
+    )));
+    compound.insert("nbt", NbtValue::Compound(nbt));
+    compound
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_structure_nbt(palettes: Vec<Vec<BlockState>>) -> Vec<u8> {
+        let mut root = NbtMap::new();
+        root.insert("DataVersion", NbtValue::Int(3465));
+        root.insert("size", int_triple_to_nbt((2, 2, 2)));
+
+        let to_list = |palette: &Vec<BlockState>| NbtValue::List(palette.iter().map(|b| NbtValue::from_quartz_nbt(&b.to_nbt())).collect());
+        if palettes.len() == 1 {
+            root.insert("palette", to_list(&palettes[0]));
+        } else {
+            root.insert("palettes", NbtValue::List(palettes.iter().map(to_list).collect()));
+        }
+
+        let mut blocks = Vec::new();
+        for (pos, state) in [((0, 0, 0), 0), ((1, 0, 0), 1), ((0, 1, 0), 1)] {
+            let mut block = NbtMap::new();
+            block.insert("pos", int_triple_to_nbt(pos));
+            block.insert("state", NbtValue::Int(state));
+            blocks.push(NbtValue::Compound(block));
+        }
+        root.insert("blocks", NbtValue::List(blocks));
+        root.insert("entities", NbtValue::EmptyList(10));
+
+        write_gzip_nbt(&root, "").unwrap()
+    }
+
+    #[test]
+    fn test_from_structure() {
+        let data = create_structure_nbt(vec![vec![
+            BlockState::new("minecraft:air".to_string()),
+            BlockState::new("minecraft:stone".to_string()),
+        ]]);
+
+        assert!(is_structure(&data));
+        let schematic = from_structure(&data).unwrap();
+
+        assert_eq!(schematic.metadata.mc_version, Some(3465));
+        assert_eq!(schematic.get_dimensions(), (2, 2, 2));
+        assert_eq!(schematic.get_block(0, 0, 0).unwrap().name, "minecraft:air");
+        assert_eq!(schematic.get_block(1, 0, 0).unwrap().name, "minecraft:stone");
+        assert_eq!(schematic.get_block(0, 1, 0).unwrap().name, "minecraft:stone");
+        // Blocks that were not listed are structure void
+        assert_eq!(schematic.get_block(1, 1, 1).unwrap().name, STRUCTURE_VOID);
+    }
+
+    #[test]
+    fn test_multiple_palettes() {
+        let data = create_structure_nbt(vec![
+            vec![
+                BlockState::new("minecraft:air".to_string()),
+                BlockState::new("minecraft:oak_planks".to_string()),
+            ],
+            vec![
+                BlockState::new("minecraft:air".to_string()),
+                BlockState::new("minecraft:spruce_planks".to_string()),
+            ],
+        ]);
+
+        assert!(is_structure(&data));
+        assert_eq!(get_palette_count(&data).unwrap(), 2);
+
+        let first = from_structure(&data).unwrap();
+        assert_eq!(first.get_block(1, 0, 0).unwrap().name, "minecraft:oak_planks");
+
+        let second = from_structure_with_palette(&data, 1).unwrap();
+        assert_eq!(second.get_block(1, 0, 0).unwrap().name, "minecraft:spruce_planks");
+
+        assert!(from_structure_with_palette(&data, 2).is_err());
+    }
+
+    #[test]
+    fn test_structure_roundtrip() {
+        let mut schematic = UniversalSchematic::new("Structure".to_string());
+        let mut region = Region::new("Main".to_string(), (0, 0, 0), (2, 2, 1));
+        region.set_block(0, 0, 0, BlockState::new("minecraft:stone".to_string()));
+        region.set_block(1, 0, 0, BlockState::new("minecraft:chest".to_string()).with_property("facing".to_string(), "north".to_string()));
+        region.set_block(1, 1, 0, BlockState::new(STRUCTURE_VOID.to_string()));
+        region.add_block_entity(BlockEntity::new("minecraft:chest".to_string(), (1, 0, 0))
+            .with_nbt_data("CustomName".to_string(), NbtValue::String("Loot".to_string())));
+        region.add_entity(Entity::new("minecraft:armor_stand".to_string(), (0.5, 1.0, 0.5)));
+        schematic.add_region(region);
+
+        let data = to_structure(&schematic).unwrap();
+        assert!(is_structure(&data));
+        assert!(!crate::litematic::is_litematic(&data));
+        assert!(!crate::schematic::is_schematic(&data));
+
+        let loaded = from_structure(&data).unwrap();
+        assert_eq!(loaded.get_dimensions(), (2, 2, 1));
+        assert_eq!(loaded.get_block(0, 0, 0).unwrap().name, "minecraft:stone");
+        let chest_state = loaded.get_block(1, 0, 0).unwrap();
+        assert_eq!(chest_state.name, "minecraft:chest");
+        assert_eq!(chest_state.get_property("facing"), Some(&"north".to_string()));
+        assert_eq!(loaded.get_block(0, 1, 0).unwrap().name, "minecraft:air");
+        assert_eq!(loaded.get_block(1, 1, 0).unwrap().name, STRUCTURE_VOID);
+
+        let block_entities = loaded.get_block_entities_as_list();
+        assert_eq!(block_entities.len(), 1);
+        assert_eq!(block_entities[0].id, "minecraft:chest");
+        assert_eq!(block_entities[0].position, (1, 0, 0));
+        assert_eq!(block_entities[0].nbt.get("CustomName"), Some(&NbtValue::String("Loot".to_string())));
+
+        let entities = loaded.get_entities_as_list();
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].id, "minecraft:armor_stand");
+        assert_eq!(entities[0].position, (0.5, 1.0, 0.5));
+    }
+}
@@ -0,0 +1,761 @@
+// MagicaVoxel .vox files. Every region becomes a model placed by the scene graph, blocks are stored as palette
+// colors and mapped back to blocks with a configurable block to color table.
+// MagicaVoxel is z-up, a voxel (x, y, z) corresponds to the block (x, z, -1 - y) so that builds are not mirrored.
+use std::collections::HashMap;
+use crate::{BlockState, NucleationError, UniversalSchematic};
+use crate::formats::structure::STRUCTURE_VOID;
+use crate::region::Region;
+
+const VOX_VERSION: i32 = 150;
+// MagicaVoxel refuses models with a side longer than this
+const MAX_MODEL_SIZE: i32 = 256;
+const MAX_GRAPH_DEPTH: usize = 64;
+const EMPTY_BLOCKS: &[&str] = &["minecraft:air", "minecraft:cave_air", "minecraft:void_air", STRUCTURE_VOID];
+
+// Rough average texture colors, used when no other table is given
+const DEFAULT_BLOCK_COLORS: &[(&str, [u8; 3])] = &[
+    ("minecraft:white_wool", [0xe9, 0xec, 0xec]),
+    ("minecraft:orange_wool", [0xf0, 0x76, 0x13]),
+    ("minecraft:magenta_wool", [0xbd, 0x44, 0xb3]),
+    ("minecraft:light_blue_wool", [0x3a, 0xaf, 0xd9]),
+    ("minecraft:yellow_wool", [0xf8, 0xc6, 0x27]),
+    ("minecraft:lime_wool", [0x70, 0xb9, 0x19]),
+    ("minecraft:pink_wool", [0xed, 0x8d, 0xac]),
+    ("minecraft:gray_wool", [0x3e, 0x44, 0x47]),
+    ("minecraft:light_gray_wool", [0x8e, 0x8e, 0x86]),
+    ("minecraft:cyan_wool", [0x15, 0x89, 0x91]),
+    ("minecraft:purple_wool", [0x79, 0x2a, 0xac]),
+    ("minecraft:blue_wool", [0x35, 0x39, 0x9d]),
+    ("minecraft:brown_wool", [0x72, 0x47, 0x28]),
+    ("minecraft:green_wool", [0x54, 0x6d, 0x1b]),
+    ("minecraft:red_wool", [0xa1, 0x27, 0x22]),
+    ("minecraft:black_wool", [0x14, 0x15, 0x19]),
+    ("minecraft:white_concrete", [0xcf, 0xd5, 0xd6]),
+    ("minecraft:orange_concrete", [0xe0, 0x61, 0x01]),
+    ("minecraft:magenta_concrete", [0xa9, 0x30, 0x9f]),
+    ("minecraft:light_blue_concrete", [0x23, 0x89, 0xc7]),
+    ("minecraft:yellow_concrete", [0xf1, 0xaf, 0x15]),
+    ("minecraft:lime_concrete", [0x5e, 0xa9, 0x18]),
+    ("minecraft:pink_concrete", [0xd5, 0x65, 0x8f]),
+    ("minecraft:gray_concrete", [0x37, 0x3a, 0x3e]),
+    ("minecraft:light_gray_concrete", [0x7d, 0x7d, 0x73]),
+    ("minecraft:cyan_concrete", [0x15, 0x77, 0x88]),
+    ("minecraft:purple_concrete", [0x64, 0x20, 0x9c]),
+    ("minecraft:blue_concrete", [0x2c, 0x2e, 0x8f]),
+    ("minecraft:brown_concrete", [0x60, 0x3c, 0x20]),
+    ("minecraft:green_concrete", [0x49, 0x5b, 0x24]),
+    ("minecraft:red_concrete", [0x8e, 0x21, 0x21]),
+    ("minecraft:black_concrete", [0x08, 0x0a, 0x0f]),
+    ("minecraft:terracotta", [0x98, 0x5e, 0x43]),
+    ("minecraft:white_terracotta", [0xd1, 0xb2, 0xa1]),
+    ("minecraft:orange_terracotta", [0xa1, 0x53, 0x25]),
+    ("minecraft:magenta_terracotta", [0x95, 0x58, 0x6c]),
+    ("minecraft:light_blue_terracotta", [0x71, 0x6d, 0x8a]),
+    ("minecraft:yellow_terracotta", [0xba, 0x85, 0x23]),
+    ("minecraft:lime_terracotta", [0x67, 0x75, 0x35]),
+    ("minecraft:pink_terracotta", [0xa1, 0x4e, 0x4e]),
+    ("minecraft:gray_terracotta", [0x39, 0x2a, 0x23]),
+    ("minecraft:light_gray_terracotta", [0x87, 0x6b, 0x62]),
+    ("minecraft:cyan_terracotta", [0x56, 0x5b, 0x5b]),
+    ("minecraft:purple_terracotta", [0x76, 0x46, 0x56]),
+    ("minecraft:blue_terracotta", [0x4a, 0x3b, 0x5b]),
+    ("minecraft:brown_terracotta", [0x4d, 0x33, 0x23]),
+    ("minecraft:green_terracotta", [0x4c, 0x53, 0x2a]),
+    ("minecraft:red_terracotta", [0x8f, 0x3d, 0x2e]),
+    ("minecraft:black_terracotta", [0x25, 0x17, 0x10]),
+    ("minecraft:stone", [0x7d, 0x7d, 0x7d]),
+    ("minecraft:dirt", [0x86, 0x60, 0x43]),
+    ("minecraft:grass_block", [0x5d, 0x9a, 0x3a]),
+    ("minecraft:sand", [0xdb, 0xcf, 0xa3]),
+    ("minecraft:oak_planks", [0xa2, 0x83, 0x4f]),
+    ("minecraft:spruce_planks", [0x73, 0x55, 0x31]),
+    ("minecraft:birch_planks", [0xc0, 0xaf, 0x79]),
+    ("minecraft:dark_oak_planks", [0x42, 0x2b, 0x14]),
+    ("minecraft:bricks", [0x96, 0x61, 0x53]),
+    ("minecraft:snow_block", [0xf9, 0xfe, 0xfe]),
+    ("minecraft:quartz_block", [0xec, 0xe6, 0xdf]),
+    ("minecraft:iron_block", [0xdc, 0xdc, 0xdc]),
+    ("minecraft:gold_block", [0xf6, 0xd0, 0x3e]),
+    ("minecraft:diamond_block", [0x62, 0xed, 0xe4]),
+    ("minecraft:emerald_block", [0x2a, 0xcb, 0x58]),
+    ("minecraft:lapis_block", [0x1f, 0x43, 0x8c]),
+    ("minecraft:redstone_block", [0xaf, 0x18, 0x05]),
+    ("minecraft:coal_block", [0x10, 0x10, 0x10]),
+    ("minecraft:netherrack", [0x6f, 0x35, 0x35]),
+    ("minecraft:obsidian", [0x0f, 0x0b, 0x19]),
+    ("minecraft:prismarine", [0x63, 0xa1, 0x94]),
+    ("minecraft:sea_lantern", [0xac, 0xc8, 0xbe]),
+    ("minecraft:ice", [0x91, 0xb7, 0xfd]),
+];
+
+/// Table mapping blocks to the colors used for them in .vox files.
+/// Exports look blocks up by exact state first and then by name, imports pick the block with the closest color.
+#[derive(Clone, Debug)]
+pub struct VoxPalette {
+    entries: Vec<(BlockState, [u8; 3])>,
+}
+
+impl Default for VoxPalette {
+    fn default() -> Self {
+        VoxPalette {
+            entries: DEFAULT_BLOCK_COLORS.iter()
+                .map(|(name, color)| (BlockState::new(name.to_string()), *color))
+                .collect(),
+        }
+    }
+}
+
+impl VoxPalette {
+    /// An empty table, every block gets a color derived from its name on export
+    pub fn new() -> Self {
+        VoxPalette { entries: Vec::new() }
+    }
+
+    pub fn with_block(mut self, block: BlockState, color: [u8; 3]) -> Self {
+        self.insert(block, color);
+        self
+    }
+
+    /// Sets the color of a block, replacing an existing entry for the same state
+    pub fn insert(&mut self, block: BlockState, color: [u8; 3]) {
+        match self.entries.iter_mut().find(|(existing, _)| *existing == block) {
+            Some(entry) => entry.1 = color,
+            None => self.entries.push((block, color)),
+        }
+    }
+
+    pub fn color_of(&self, block: &BlockState) -> Option<[u8; 3]> {
+        self.entries.iter()
+            .find(|(entry, _)| entry == block)
+            .or_else(|| self.entries.iter().find(|(entry, _)| entry.name == block.name))
+            .map(|(_, color)| *color)
+    }
+
+    pub fn closest_block(&self, color: [u8; 3]) -> Option<&BlockState> {
+        self.entries.iter()
+            .min_by_key(|(_, entry)| color_distance(*entry, color))
+            .map(|(block, _)| block)
+    }
+
+    fn color_or_fallback(&self, block: &BlockState) -> [u8; 3] {
+        self.color_of(block).unwrap_or_else(|| {
+            // FNV-1a of the name, so unknown blocks get stable and distinct colors
+            let hash = block.name.bytes().fold(0x811c_9dc5u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193));
+            [(hash >> 16) as u8, (hash >> 8) as u8, hash as u8]
+        })
+    }
+}
+
+fn color_distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    // Weighted towards green like the eye, see "redmean" color distance approximations
+    let dr = a[0] as i32 - b[0] as i32;
+    let dg = a[1] as i32 - b[1] as i32;
+    let db = a[2] as i32 - b[2] as i32;
+    (2 * dr * dr + 4 * dg * dg + 3 * db * db) as u32
+}
+
+pub fn is_vox(data: &[u8]) -> bool {
+    data.len() >= 8 && &data[..4] == b"VOX "
+}
+
+pub fn from_vox(data: &[u8]) -> Result<UniversalSchematic, NucleationError> {
+    from_vox_with_palette(data, &VoxPalette::default())
+}
+
+/// Imports a .vox file, every voxel becomes the block of `palette` closest to its color
+pub fn from_vox_with_palette(data: &[u8], palette: &VoxPalette) -> Result<UniversalSchematic, NucleationError> {
+    if !is_vox(data) {
+        return Err(NucleationError::UnsupportedFormat("not a MagicaVoxel file".to_string()));
+    }
+    if palette.entries.is_empty() {
+        return Err("Block color table is empty".into());
+    }
+
+    let mut reader = VoxReader { data, position: 8 };
+    let (id, content, children) = reader.read_chunk()?;
+    if id != *b"MAIN" || !content.is_empty() {
+        return Err("Missing MAIN chunk".into());
+    }
+
+    let mut models: Vec<Model> = Vec::new();
+    let mut nodes: HashMap<i32, Node> = HashMap::new();
+    let mut colors = default_colors();
+    let mut pending_size = None;
+    let mut reader = VoxReader { data: children, position: 0 };
+    while !reader.is_empty() {
+        let (id, content, _) = reader.read_chunk()?;
+        let mut content = VoxReader { data: content, position: 0 };
+        match &id {
+            b"SIZE" => pending_size = Some((content.read_i32()?, content.read_i32()?, content.read_i32()?)),
+            b"XYZI" => {
+                let size = pending_size.take().ok_or("XYZI chunk without SIZE")?;
+                let count = content.read_len(4)?;
+                let mut voxels = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let voxel = content.take(4)?;
+                    voxels.push(([voxel[0] as i32, voxel[1] as i32, voxel[2] as i32], voxel[3]));
+                }
+                models.push(Model { size: [size.0, size.1, size.2], voxels });
+            }
+            b"RGBA" => {
+                // Entry i holds the color of index i + 1
+                for index in 0..255 {
+                    let rgba = content.take(4)?;
+                    colors[index + 1] = [rgba[0], rgba[1], rgba[2]];
+                }
+            }
+            b"nTRN" => {
+                let id = content.read_i32()?;
+                let attributes = content.read_dict()?;
+                let child = content.read_i32()?;
+                let _reserved = content.read_i32()?;
+                let _layer = content.read_i32()?;
+                let frames = content.read_i32()?;
+                let frame = if frames > 0 { content.read_dict()? } else { HashMap::new() };
+                let transform = Transform::from_frame(&frame)?;
+                nodes.insert(id, Node::Transform { name: attributes.get("_name").cloned(), child, transform });
+            }
+            b"nGRP" => {
+                let id = content.read_i32()?;
+                content.read_dict()?;
+                let count = content.read_len(4)?;
+                let children = (0..count).map(|_| content.read_i32()).collect::<Result<Vec<_>, _>>()?;
+                nodes.insert(id, Node::Group(children));
+            }
+            b"nSHP" => {
+                let id = content.read_i32()?;
+                content.read_dict()?;
+                let count = content.read_len(4)?;
+                let mut shapes = Vec::with_capacity(count);
+                for _ in 0..count {
+                    shapes.push(content.read_i32()?);
+                    content.read_dict()?;
+                }
+                nodes.insert(id, Node::Shape(shapes));
+            }
+            _ => {}
+        }
+    }
+
+    // Files without a scene graph place every model with its minimum corner at the origin
+    let mut instances = Vec::new();
+    if nodes.is_empty() {
+        for (index, model) in models.iter().enumerate() {
+            let translation = [model.size[0] / 2, model.size[1] / 2, model.size[2] / 2];
+            instances.push((None, index as i32, Transform { rotation: IDENTITY, translation }));
+        }
+    } else {
+        collect_instances(&nodes, 0, Transform { rotation: IDENTITY, translation: [0; 3] }, None, 0, &mut instances)?;
+    }
+
+    let mut schematic = UniversalSchematic::new("Unnamed".to_string());
+    let mut block_cache: HashMap<u8, BlockState> = HashMap::new();
+    for (index, (name, model_id, transform)) in instances.into_iter().enumerate() {
+        let model = models.get(model_id as usize)
+            .ok_or_else(|| format!("Shape references missing model {}", model_id))?;
+
+        let blocks: Vec<((i32, i32, i32), u8)> = model.voxels.iter()
+            .map(|&(voxel, color)| (transform.apply(voxel, model.size), color))
+            .map(|([x, y, z], color)| ((x, z, -1 - y), color))
+            .collect();
+        if blocks.is_empty() {
+            continue;
+        }
+        let mut min = blocks[0].0;
+        let mut max = blocks[0].0;
+        for &((x, y, z), _) in &blocks {
+            min = (min.0.min(x), min.1.min(y), min.2.min(z));
+            max = (max.0.max(x), max.1.max(y), max.2.max(z));
+        }
+
+        let name = if index == 0 {
+            if let Some(name) = name {
+                schematic.default_region_name = name;
+            }
+            schematic.default_region_name.clone()
+        } else {
+            let base = name.unwrap_or_else(|| format!("Model {}", index));
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while schematic.get_region(&name).is_some() {
+                name = format!("{} ({})", base, suffix);
+                suffix += 1;
+            }
+            name
+        };
+
+        let size = (max.0 - min.0 + 1, max.1 - min.1 + 1, max.2 - min.2 + 1);
+        let mut region = Region::new(name, min, size);
+        for ((x, y, z), color) in blocks {
+            let block = block_cache.entry(color).or_insert_with(|| {
+                palette.closest_block(colors[color as usize]).cloned().unwrap_or_else(|| BlockState::new("minecraft:stone".to_string()))
+            });
+            let block_index = region.get_or_insert_in_palette(block.clone());
+            let index = region.coords_to_index(x, y, z);
+            region.blocks.set(index, block_index);
+        }
+        schematic.add_region(region);
+    }
+
+    Ok(schematic)
+}
+
+pub fn to_vox(schematic: &UniversalSchematic) -> Result<Vec<u8>, NucleationError> {
+    to_vox_with_palette(schematic, &VoxPalette::default())
+}
+
+/// Exports every region as a model, regions larger than 256 blocks along an axis are split into several models
+pub fn to_vox_with_palette(schematic: &UniversalSchematic, palette: &VoxPalette) -> Result<Vec<u8>, NucleationError> {
+    let mut colors: Vec<[u8; 3]> = Vec::new();
+    let mut color_indices: HashMap<[u8; 3], u8> = HashMap::new();
+
+    let mut region_names = schematic.get_region_names();
+    if region_names.len() > 1 {
+        region_names[1..].sort();
+    }
+
+    let mut models: Vec<PlacedModel> = Vec::new();
+    for region_name in region_names {
+        let region = match schematic.get_region(&region_name) {
+            Some(region) => region,
+            None => continue,
+        };
+        let bounding_box = region.get_bounding_box();
+        let (min, max) = (bounding_box.min, bounding_box.max);
+
+        // Voxel axes, x stays x, y runs along -z and z is up
+        let vox_min = [min.0, -1 - max.2, min.1];
+        let vox_size = [max.0 - min.0 + 1, max.2 - min.2 + 1, max.1 - min.1 + 1];
+        let tiles = [0, 1, 2].map(|axis| (vox_size[axis] + MAX_MODEL_SIZE - 1) / MAX_MODEL_SIZE);
+        let mut tile_voxels: HashMap<[i32; 3], Vec<([i32; 3], u8)>> = HashMap::new();
+
+        for (index, palette_index) in region.blocks.iter().enumerate() {
+            let block = match region.palette.get(palette_index) {
+                Some(block) if !EMPTY_BLOCKS.contains(&block.name.as_str()) => block,
+                _ => continue,
+            };
+            let (x, y, z) = region.index_to_coords(index);
+            let local = [x - vox_min[0], -1 - z - vox_min[1], y - vox_min[2]];
+
+            let color = palette.color_or_fallback(block);
+            let color_index = match color_indices.get(&color) {
+                Some(&color_index) => color_index,
+                None if colors.len() < 255 => {
+                    colors.push(color);
+                    color_indices.insert(color, colors.len() as u8);
+                    colors.len() as u8
+                }
+                // Out of palette slots, fall back to the closest color we already have
+                None => {
+                    let closest = (0..colors.len()).min_by_key(|&i| color_distance(colors[i], color)).unwrap_or(0);
+                    color_indices.insert(color, closest as u8 + 1);
+                    closest as u8 + 1
+                }
+            };
+
+            let tile = local.map(|value| value / MAX_MODEL_SIZE);
+            let voxel = local.map(|value| value % MAX_MODEL_SIZE);
+            tile_voxels.entry(tile).or_default().push((voxel, color_index));
+        }
+
+        let mut tile_keys: Vec<[i32; 3]> = tile_voxels.keys().copied().collect();
+        tile_keys.sort();
+        for tile in tile_keys {
+            let voxels = tile_voxels.remove(&tile).unwrap_or_default();
+            let tile_min = [0, 1, 2].map(|axis| vox_min[axis] + tile[axis] * MAX_MODEL_SIZE);
+            let tile_size = [0, 1, 2].map(|axis| (vox_size[axis] - tile[axis] * MAX_MODEL_SIZE).min(MAX_MODEL_SIZE));
+            let name = if tiles == [1, 1, 1] {
+                region_name.clone()
+            } else {
+                format!("{} [{}, {}, {}]", region_name, tile[0], tile[1], tile[2])
+            };
+            models.push(PlacedModel { name, min: tile_min, model: Model { size: tile_size, voxels } });
+        }
+    }
+    if models.is_empty() {
+        let model = Model { size: [1; 3], voxels: Vec::new() };
+        models.push(PlacedModel { name: schematic.default_region_name.clone(), min: [0; 3], model });
+    }
+
+    let mut children = Vec::new();
+    for PlacedModel { model: Model { size, voxels }, .. } in &models {
+        let mut content = Vec::new();
+        for value in size {
+            content.extend_from_slice(&value.to_le_bytes());
+        }
+        children.extend(chunk(b"SIZE", &content));
+
+        let mut content = (voxels.len() as u32).to_le_bytes().to_vec();
+        for (voxel, color) in voxels {
+            content.extend(voxel.map(|value| value as u8));
+            content.push(*color);
+        }
+        children.extend(chunk(b"XYZI", &content));
+    }
+
+    // Scene graph: root transform, a group, then a transform and shape per model
+    let mut content = transform_node(0, &[], 1, -1, &[]);
+    children.extend(chunk(b"nTRN", &content));
+    content = Vec::new();
+    content.extend_from_slice(&1i32.to_le_bytes());
+    write_dict(&mut content, &[]);
+    content.extend_from_slice(&(models.len() as i32).to_le_bytes());
+    for index in 0..models.len() as i32 {
+        content.extend_from_slice(&(2 + 2 * index).to_le_bytes());
+    }
+    children.extend(chunk(b"nGRP", &content));
+    for (index, PlacedModel { name, min, model }) in models.iter().enumerate() {
+        let (node, size) = (2 + 2 * index as i32, model.size);
+        let translation = format!("{} {} {}", min[0] + size[0] / 2, min[1] + size[1] / 2, min[2] + size[2] / 2);
+        children.extend(chunk(b"nTRN", &transform_node(node, &[("_name", name)], node + 1, 0, &[("_t", &translation)])));
+
+        let mut content = (node + 1).to_le_bytes().to_vec();
+        write_dict(&mut content, &[]);
+        content.extend_from_slice(&1i32.to_le_bytes());
+        content.extend_from_slice(&(index as i32).to_le_bytes());
+        write_dict(&mut content, &[]);
+        children.extend(chunk(b"nSHP", &content));
+    }
+
+    let mut content = Vec::with_capacity(1024);
+    for index in 0..256 {
+        let color = colors.get(index).copied().unwrap_or([0, 0, 0]);
+        content.extend_from_slice(&color);
+        content.push(if index < colors.len() { 255 } else { 0 });
+    }
+    children.extend(chunk(b"RGBA", &content));
+
+    let mut out = b"VOX ".to_vec();
+    out.extend_from_slice(&VOX_VERSION.to_le_bytes());
+    out.extend_from_slice(b"MAIN");
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    out.extend(children);
+    Ok(out)
+}
+
+struct Model {
+    size: [i32; 3],
+    voxels: Vec<([i32; 3], u8)>,
+}
+
+// A model and the voxel position of its minimum corner
+struct PlacedModel {
+    name: String,
+    min: [i32; 3],
+    model: Model,
+}
+
+enum Node {
+    Transform { name: Option<String>, child: i32, transform: Transform },
+    Group(Vec<i32>),
+    Shape(Vec<i32>),
+}
+
+const IDENTITY: [[i32; 3]; 3] = [[1, 0, 0], [0, 1, 0], [0, 0, 1]];
+
+#[derive(Clone, Copy)]
+struct Transform {
+    rotation: [[i32; 3]; 3],
+    translation: [i32; 3],
+}
+
+impl Transform {
+    fn from_frame(frame: &HashMap<String, String>) -> Result<Self, NucleationError> {
+        let mut translation = [0; 3];
+        if let Some(value) = frame.get("_t") {
+            let values: Vec<i32> = value.split_whitespace().map(|v| v.parse()).collect::<Result<_, _>>()
+                .map_err(|_| format!("Invalid translation '{}'", value))?;
+            if values.len() != 3 {
+                return Err(format!("Invalid translation '{}'", value).into());
+            }
+            translation = [values[0], values[1], values[2]];
+        }
+
+        let mut rotation = IDENTITY;
+        if let Some(value) = frame.get("_r") {
+            // Packed rotation: column of the non-zero entry in rows 0 and 1, then the sign of each row
+            let packed: u8 = value.parse().map_err(|_| format!("Invalid rotation '{}'", value))?;
+            let first = (packed & 3) as usize;
+            let second = ((packed >> 2) & 3) as usize;
+            if first > 2 || second > 2 || first == second {
+                return Err(format!("Invalid rotation {}", packed).into());
+            }
+            let columns = [first, second, 3 - first - second];
+            rotation = [[0; 3]; 3];
+            for (row, &column) in columns.iter().enumerate() {
+                rotation[row][column] = if packed & (0x10 << row) != 0 { -1 } else { 1 };
+            }
+        }
+
+        Ok(Transform { rotation, translation })
+    }
+
+    fn rotate(&self, vector: [i32; 3]) -> [i32; 3] {
+        [0, 1, 2].map(|row| (0..3).map(|column| self.rotation[row][column] * vector[column]).sum())
+    }
+
+    // The child transform applied first, then this one
+    fn then(&self, child: &Transform) -> Transform {
+        let mut rotation = [[0; 3]; 3];
+        for (row, values) in rotation.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.rotation[row][k] * child.rotation[k][column]).sum();
+            }
+        }
+        let rotated = self.rotate(child.translation);
+        Transform { rotation, translation: [0, 1, 2].map(|axis| rotated[axis] + self.translation[axis]) }
+    }
+
+    // Models rotate around their center, computed in half voxels to stay exact for even sizes
+    fn apply(&self, voxel: [i32; 3], size: [i32; 3]) -> [i32; 3] {
+        let centered = [0, 1, 2].map(|axis| 2 * voxel[axis] + 1 - size[axis]);
+        let rotated = self.rotate(centered);
+        [0, 1, 2].map(|axis| (rotated[axis] + 2 * self.translation[axis]).div_euclid(2))
+    }
+}
+
+fn collect_instances(
+    nodes: &HashMap<i32, Node>,
+    id: i32,
+    transform: Transform,
+    name: Option<String>,
+    depth: usize,
+    instances: &mut Vec<(Option<String>, i32, Transform)>,
+) -> Result<(), NucleationError> {
+    if depth > MAX_GRAPH_DEPTH {
+        return Err("Scene graph is nested too deeply".into());
+    }
+    match nodes.get(&id).ok_or_else(|| format!("Missing scene graph node {}", id))? {
+        Node::Transform { name: own_name, child, transform: own } => {
+            let name = own_name.clone().or(name);
+            collect_instances(nodes, *child, transform.then(own), name, depth + 1, instances)?;
+        }
+        Node::Group(children) => {
+            for &child in children {
+                collect_instances(nodes, child, transform, name.clone(), depth + 1, instances)?;
+            }
+        }
+        Node::Shape(models) => {
+            for &model in models {
+                instances.push((name.clone(), model, transform));
+            }
+        }
+    }
+    Ok(())
+}
+
+// MagicaVoxel's built-in palette, used when a file has no RGBA chunk: a 6x6x6 color cube without black,
+// followed by ramps of red, green, blue and gray
+fn default_colors() -> [[u8; 3]; 256] {
+    let mut colors = [[0u8; 3]; 256];
+    let levels = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    let ramp = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+    let mut index = 1;
+    for r in levels {
+        for g in levels {
+            for b in levels {
+                if index < 216 {
+                    colors[index] = [r, g, b];
+                    index += 1;
+                }
+            }
+        }
+    }
+    for channel in 0..4 {
+        for value in ramp {
+            colors[index] = match channel {
+                0 => [value, 0, 0],
+                1 => [0, value, 0],
+                2 => [0, 0, value],
+                _ => [value, value, value],
+            };
+            index += 1;
+        }
+    }
+    colors
+}
+
+fn chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+    let mut out = id.to_vec();
+    out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(content);
+    out
+}
+
+fn write_dict(out: &mut Vec<u8>, entries: &[(&str, &str)]) {
+    out.extend_from_slice(&(entries.len() as i32).to_le_bytes());
+    for (key, value) in entries {
+        for string in [key, value] {
+            out.extend_from_slice(&(string.len() as i32).to_le_bytes());
+            out.extend_from_slice(string.as_bytes());
+        }
+    }
+}
+
+fn transform_node(id: i32, attributes: &[(&str, &str)], child: i32, layer: i32, frame: &[(&str, &str)]) -> Vec<u8> {
+    let mut content = id.to_le_bytes().to_vec();
+    write_dict(&mut content, attributes);
+    content.extend_from_slice(&child.to_le_bytes());
+    content.extend_from_slice(&(-1i32).to_le_bytes());
+    content.extend_from_slice(&layer.to_le_bytes());
+    content.extend_from_slice(&1i32.to_le_bytes());
+    write_dict(&mut content, frame);
+    content
+}
+
+// Chunk id, content and children
+type Chunk<'a> = ([u8; 4], &'a [u8], &'a [u8]);
+
+struct VoxReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> VoxReader<'a> {
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], NucleationError> {
+        if len > self.data.len() - self.position {
+            return Err("Unexpected end of vox data".into());
+        }
+        let bytes = &self.data[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, NucleationError> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_len(&mut self, element_size: usize) -> Result<usize, NucleationError> {
+        let len = self.read_i32()?;
+        if len < 0 || (len as usize).saturating_mul(element_size) > self.data.len() - self.position {
+            return Err(format!("Invalid vox length {}", len).into());
+        }
+        Ok(len as usize)
+    }
+
+    fn read_string(&mut self) -> Result<String, NucleationError> {
+        let len = self.read_len(1)?;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn read_dict(&mut self) -> Result<HashMap<String, String>, NucleationError> {
+        let count = self.read_len(8)?;
+        let mut dict = HashMap::with_capacity(count);
+        for _ in 0..count {
+            let key = self.read_string()?;
+            dict.insert(key, self.read_string()?);
+        }
+        Ok(dict)
+    }
+
+    fn read_chunk(&mut self) -> Result<Chunk<'a>, NucleationError> {
+        let id = self.take(4)?;
+        let content_len = self.read_len(1)?;
+        let children_len = self.read_len(1)?;
+        let content = self.take(content_len)?;
+        let children = self.take(children_len)?;
+        Ok(([id[0], id[1], id[2], id[3]], content, children))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vox_roundtrip() {
+        let mut schematic = UniversalSchematic::new("Vox".to_string());
+        schematic.set_block(0, 0, 0, BlockState::new("minecraft:red_wool".to_string()));
+        schematic.set_block(3, 2, 1, BlockState::new("minecraft:blue_concrete".to_string()));
+        schematic.set_block(1, 0, 4, BlockState::new("minecraft:gold_block".to_string()));
+
+        let mut tower = Region::new("Tower".to_string(), (10, 0, -5), (1, 3, 1));
+        for y in 0..3 {
+            tower.set_block(10, y, -5, BlockState::new("minecraft:stone".to_string()));
+        }
+        schematic.add_region(tower);
+
+        let data = to_vox(&schematic).unwrap();
+        assert!(is_vox(&data));
+        let loaded = from_vox(&data).unwrap();
+
+        assert_eq!(loaded.get_region_names().len(), 2);
+        assert_eq!(loaded.get_block(0, 0, 0).unwrap().name, "minecraft:red_wool");
+        assert_eq!(loaded.get_block(3, 2, 1).unwrap().name, "minecraft:blue_concrete");
+        assert_eq!(loaded.get_block(1, 0, 4).unwrap().name, "minecraft:gold_block");
+        assert_eq!(loaded.get_block(2, 1, 2).unwrap().name, "minecraft:air");
+
+        let tower = loaded.get_region("Tower").unwrap();
+        let bounding_box = tower.get_bounding_box();
+        assert_eq!((bounding_box.min, bounding_box.max), ((10, 0, -5), (10, 2, -5)));
+        assert_eq!(tower.get_block(10, 1, -5).unwrap().name, "minecraft:stone");
+    }
+
+    #[test]
+    fn test_vox_custom_palette_and_rotation() {
+        // A 2x1x1 model rotated a quarter turn around z by its transform node
+        let mut children = Vec::new();
+        let mut content = Vec::new();
+        for value in [2i32, 1, 1] {
+            content.extend_from_slice(&value.to_le_bytes());
+        }
+        children.extend(chunk(b"SIZE", &content));
+        let mut content = 2u32.to_le_bytes().to_vec();
+        content.extend_from_slice(&[0, 0, 0, 1, 1, 0, 0, 2]);
+        children.extend(chunk(b"XYZI", &content));
+        children.extend(chunk(b"nTRN", &transform_node(0, &[], 1, -1, &[])));
+        // Rows (0, -1, 0), (1, 0, 0), (0, 0, 1)
+        children.extend(chunk(b"nTRN", &transform_node(1, &[("_name", "Rotated")], 2, 0, &[("_r", "17"), ("_t", "0 0 0")])));
+        let mut content = 2i32.to_le_bytes().to_vec();
+        write_dict(&mut content, &[]);
+        content.extend_from_slice(&1i32.to_le_bytes());
+        content.extend_from_slice(&0i32.to_le_bytes());
+        write_dict(&mut content, &[]);
+        children.extend(chunk(b"nSHP", &content));
+
+        let mut content = Vec::new();
+        content.extend_from_slice(&[250, 10, 10, 255, 10, 10, 240, 255]);
+        content.resize(1024, 0);
+        children.extend(chunk(b"RGBA", &content));
+
+        let mut data = b"VOX ".to_vec();
+        data.extend_from_slice(&150i32.to_le_bytes());
+        data.extend(chunk(b"MAIN", &[]));
+        let children_len = (children.len() as u32).to_le_bytes();
+        let len = data.len();
+        data[len - 4..].copy_from_slice(&children_len);
+        data.extend(children);
+
+        let palette = VoxPalette::new()
+            .with_block(BlockState::new("minecraft:redstone_block".to_string()), [255, 0, 0])
+            .with_block(BlockState::new("minecraft:lapis_block".to_string()), [0, 0, 255]);
+        let schematic = from_vox_with_palette(&data, &palette).unwrap();
+        assert_eq!(schematic.default_region_name, "Rotated");
+
+        // Voxel x runs along voxel y after the rotation, which is block -z
+        let region = schematic.get_region("Rotated").unwrap();
+        let bounding_box = region.get_bounding_box();
+        assert_eq!((bounding_box.min, bounding_box.max), ((0, 0, -1), (0, 0, 0)));
+        assert_eq!(schematic.get_block(0, 0, 0).unwrap().name, "minecraft:redstone_block");
+        assert_eq!(schematic.get_block(0, 0, -1).unwrap().name, "minecraft:lapis_block");
+
+        assert!(from_vox_with_palette(&data, &VoxPalette::new()).is_err());
+        assert!(from_vox(&data[..data.len() - 100]).is_err());
+    }
+
+    #[test]
+    fn test_default_colors() {
+        let colors = default_colors();
+        assert_eq!(colors[1], [0xff, 0xff, 0xff]);
+        assert_eq!(colors[215], [0x00, 0x00, 0x33]);
+        assert_eq!(colors[216], [0xee, 0x00, 0x00]);
+        assert_eq!(colors[255], [0x11, 0x11, 0x11]);
+    }
+}
@@ -3,6 +3,7 @@
 // Core modules
 mod universal_schematic;
 mod region;
+mod block_storage;
 mod block_state;
 mod entity;
 pub mod block_entity;
@@ -14,6 +15,12 @@ mod block_position;
 pub mod utils;
 mod item;
 mod chunk;
+mod transform;
+mod csg;
+mod diff;
+mod pattern;
+pub mod shapes;
+pub mod error;
 
 // Feature-specific modules
 #[cfg(feature = "wasm")]
@@ -27,9 +34,17 @@ mod php;
 
 // Public re-exports
 pub use universal_schematic::UniversalSchematic;
+pub use error::NucleationError;
 pub use block_state::BlockState;
 pub use region::Region;
-pub use formats::{litematic, schematic};
+pub use block_storage::{BlockStorage, SectionedStorage};
+pub use formats::{litematic, schematic, structure, mcedit, mcstructure, anvil, axiom, vox, ExportOptions};
+pub use bounding_box::BoundingBox;
+pub use block_position::BlockPosition;
+pub use transform::Axis;
+pub use csg::{BooleanOperation, ConflictPolicy};
+pub use diff::{diff, diff_with, BlockChange, BlockEntityChange, SchematicDiff};
+pub use pattern::{Mask, Pattern};
 pub use print_utils::{format_schematic, format_json_schematic};
 
 // Re-export WASM types when building with WASM feature
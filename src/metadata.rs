@@ -1,5 +1,7 @@
 use quartz_nbt::{NbtCompound, NbtTag};
 use serde::{Deserialize, Serialize};
+use crate::utils::NbtMap;
+use crate::NucleationError;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Metadata {
@@ -11,6 +13,22 @@ pub struct Metadata {
     pub lm_version: Option<i32>,
     pub mc_version: Option<i32>,
     pub we_version: Option<i32>,
+    /// Sponge `Offset`: the minimum corner relative to the paste origin. Exporters write the
+    /// corner's offset from the anchor instead when the schematic has one
+    #[serde(default)]
+    pub offset: Option<(i32, i32, i32)>,
+    /// WorldEdit origin relative to the minimum corner (`WEOffsetX/Y/Z`)
+    #[serde(default)]
+    pub we_offset: Option<(i32, i32, i32)>,
+    /// Free-form tags, e.g. from an Axiom blueprint header
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// PNG preview image
+    #[serde(default)]
+    pub thumbnail: Option<Vec<u8>>,
+    /// Header fields of the source format that have no field of their own, written back on export
+    #[serde(default)]
+    pub extra: NbtMap,
 }
 impl Default for Metadata {
     fn default() -> Self {
@@ -23,6 +41,11 @@ impl Default for Metadata {
             lm_version: None,
             mc_version: None,
             we_version: None,
+            offset: None,
+            we_offset: None,
+            tags: Vec::new(),
+            thumbnail: None,
+            extra: NbtMap::new(),
         }
     }
 }
@@ -47,6 +70,11 @@ impl Metadata {
             lm_version,
             mc_version,
             we_version,
+            offset: None,
+            we_offset: None,
+            tags: Vec::new(),
+            thumbnail: None,
+            extra: NbtMap::new(),
         }
     }
 
@@ -77,11 +105,16 @@ impl Metadata {
         if let Some(we_version) = self.we_version {
             compound.insert("we_version", NbtTag::Int(we_version));
         }
+        if let Some((x, y, z)) = self.we_offset {
+            compound.insert("WEOffsetX", NbtTag::Int(x));
+            compound.insert("WEOffsetY", NbtTag::Int(y));
+            compound.insert("WEOffsetZ", NbtTag::Int(z));
+        }
 
         NbtTag::Compound(compound)
     }
 
-    pub fn from_nbt(nbt: &NbtCompound) -> Result<Self, String> {
+    pub fn from_nbt(nbt: &NbtCompound) -> Result<Self, NucleationError> {
         let name = nbt.get::<_, &str>("Name").map_err(|_| "").ok().map(|s| s.to_string());
         let author = nbt.get::<_, &str>("Author").map_err(|_| "").ok().map(|s| s.to_string());
         let description = nbt.get::<_, &str>("Description").map_err(|_| "").ok().map(|s| s.to_string());
@@ -91,6 +124,13 @@ impl Metadata {
         let mc_version = nbt.get::<_, i32>("mc_version").map_err(|_| 0).ok();
         let we_version = nbt.get::<_, i32>("we_version").map_err(|_| 0).ok();
 
-        Ok(Metadata::new(name, author, description, created, modified, lm_version, mc_version, we_version))
+        let we_offset = match (nbt.get::<_, i32>("WEOffsetX"), nbt.get::<_, i32>("WEOffsetY"), nbt.get::<_, i32>("WEOffsetZ")) {
+            (Ok(x), Ok(y), Ok(z)) => Some((x, y, z)),
+            _ => None,
+        };
+
+        let mut metadata = Metadata::new(name, author, description, created, modified, lm_version, mc_version, we_version);
+        metadata.we_offset = we_offset;
+        Ok(metadata)
     }
 }
@@ -0,0 +1,505 @@
+// src/pattern.rs
+//! Patterns and masks in the WorldEdit syntax:
+//!
+//! * patterns: `stone`, `oak_stairs[facing=north]`, `50%stone,30%andesite,20%cobblestone`
+//!   (entries without a weight weigh 1)
+//! * masks: `stone,dirt` (any of), `#minecraft:logs` (block tag), `!mask` (not),
+//!   `>mask` (above a match), `<mask` (below a match), `~mask` (next to a match), and several
+//!   masks separated by spaces, which must all match: `!air >grass_block`
+use crate::utils::block_string_syntax;
+use crate::{BlockState, NucleationError, UniversalSchematic};
+
+/// What the edit operations place: a single block, or a weighted random choice between blocks.
+/// The choice at a position only depends on the seed and the position, so the same pattern
+/// paints the same blocks whatever the order or the region they are written in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Block(BlockState),
+    Random { blocks: Vec<(f64, BlockState)>, seed: u64 },
+}
+
+impl Pattern {
+    pub fn block(block: BlockState) -> Self {
+        Pattern::Block(block)
+    }
+
+    /// Parses a pattern, see the module documentation for the syntax. Block names without a
+    /// namespace are put in `minecraft:`.
+    pub fn parse(pattern: &str) -> Result<Self, NucleationError> {
+        let mut blocks = Vec::new();
+        for (start, entry) in split_top_level(pattern, |c| c == ',') {
+            let (weight, block_start) = match entry.split_once('%') {
+                Some((weight, _)) if !weight.contains('[') => {
+                    let weight = weight.trim().parse::<f64>()
+                        .map_err(|_| block_string_syntax(start, format!("Invalid weight '{}'", weight)))?;
+                    (weight, start + entry.find('%').unwrap() + 1)
+                }
+                _ => (1.0, start),
+            };
+            blocks.push((weight, parse_block(&pattern[block_start..start + entry.len()], block_start)?));
+        }
+        if blocks.len() == 1 {
+            return Ok(Pattern::Block(blocks.remove(0).1));
+        }
+        Pattern::random(blocks)
+    }
+
+    /// Weighted choice between `blocks`. Weights are relative and need not add up to 100.
+    pub fn random(blocks: Vec<(f64, BlockState)>) -> Result<Self, NucleationError> {
+        if blocks.is_empty() {
+            return Err("A random pattern needs at least one block".into());
+        }
+        if let Some((weight, block)) = blocks.iter().find(|(weight, _)| !(*weight > 0.0 && weight.is_finite())) {
+            return Err(format!("Invalid weight {} for {}", weight, block).into());
+        }
+        Ok(Pattern::Random { blocks, seed: 0 })
+    }
+
+    /// Sets the seed of a random pattern, other patterns are returned unchanged.
+    pub fn with_seed(mut self, new_seed: u64) -> Self {
+        if let Pattern::Random { seed, .. } = &mut self {
+            *seed = new_seed;
+        }
+        self
+    }
+
+    /// Resolves the blocks of the pattern to indices with `index_of`, usually a region's
+    /// `get_or_insert_in_palette`, so painting only deals with palette indices.
+    pub(crate) fn sampler(&self, mut index_of: impl FnMut(&BlockState) -> usize) -> Sampler {
+        match self {
+            Pattern::Block(block) => Sampler { indices: vec![index_of(block)], cumulative: Vec::new(), seed: 0 },
+            Pattern::Random { blocks, seed } => {
+                let total: f64 = blocks.iter().map(|(weight, _)| weight).sum();
+                let mut sum = 0.0;
+                let cumulative = blocks.iter()
+                    .map(|(weight, _)| {
+                        sum += weight;
+                        sum / total
+                    })
+                    .collect();
+                let indices = blocks.iter().map(|(_, block)| index_of(block)).collect();
+                Sampler { indices, cumulative, seed: *seed }
+            }
+        }
+    }
+}
+
+impl From<BlockState> for Pattern {
+    fn from(block: BlockState) -> Self {
+        Pattern::Block(block)
+    }
+}
+
+/// A pattern resolved to palette indices.
+pub(crate) struct Sampler {
+    indices: Vec<usize>,
+    cumulative: Vec<f64>,
+    seed: u64,
+}
+
+impl Sampler {
+    pub(crate) fn sample(&self, (x, y, z): (i32, i32, i32)) -> usize {
+        if self.indices.len() == 1 {
+            return self.indices[0];
+        }
+        let mut hash = self.seed;
+        for coordinate in [x, y, z] {
+            hash = mix(hash ^ coordinate as u32 as u64);
+        }
+        let roll = (hash >> 11) as f64 / (1u64 << 53) as f64;
+        let choice = self.cumulative.partition_point(|&threshold| threshold <= roll);
+        self.indices[choice.min(self.indices.len() - 1)]
+    }
+}
+
+// splitmix64 finalizer
+fn mix(mut hash: u64) -> u64 {
+    hash = hash.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// Which existing blocks an edit operation may change.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mask {
+    /// Every block, air included.
+    Any,
+    /// Blocks with one of these names and at least the properties given, so `oak_stairs`
+    /// matches every stair orientation. Names without a namespace are in `minecraft:`.
+    Blocks(Vec<BlockState>),
+    /// Blocks in a block tag such as `minecraft:logs`, see [`Mask::is_known_tag`].
+    Tag(String),
+    Not(Box<Mask>),
+    /// Matches when any of the masks does.
+    Either(Vec<Mask>),
+    /// Matches when all of the masks do.
+    All(Vec<Mask>),
+    /// Matches when the block at the given offset matches, `>stone` is `Offset(stone, (0, -1, 0))`.
+    Offset(Box<Mask>, (i32, i32, i32)),
+    /// Matches when one of the six neighbouring blocks matches.
+    Adjacent(Box<Mask>),
+}
+
+const NEIGHBOURS: [(i32, i32, i32); 6] = [(-1, 0, 0), (1, 0, 0), (0, -1, 0), (0, 1, 0), (0, 0, -1), (0, 0, 1)];
+
+// Tag name, then the name suffixes, whole names and exceptions of its blocks, all without the
+// `minecraft:` namespace. Tags where a suffix would catch other blocks (`pumpkin_stem`,
+// `potted_oak_sapling`, ...) list their members as in the vanilla tag files
+type BlockTag = (&'static str, &'static [&'static str], &'static [&'static str], &'static [&'static str]);
+
+const BLOCK_TAGS: &[BlockTag] = &[
+    ("logs", &[], &[
+        "oak_log", "oak_wood", "stripped_oak_log", "stripped_oak_wood", "spruce_log",
+        "spruce_wood", "stripped_spruce_log", "stripped_spruce_wood", "birch_log", "birch_wood",
+        "stripped_birch_log", "stripped_birch_wood", "jungle_log", "jungle_wood",
+        "stripped_jungle_log", "stripped_jungle_wood", "acacia_log", "acacia_wood",
+        "stripped_acacia_log", "stripped_acacia_wood", "dark_oak_log", "dark_oak_wood",
+        "stripped_dark_oak_log", "stripped_dark_oak_wood", "pale_oak_log", "pale_oak_wood",
+        "stripped_pale_oak_log", "stripped_pale_oak_wood", "mangrove_log", "mangrove_wood",
+        "stripped_mangrove_log", "stripped_mangrove_wood", "cherry_log", "cherry_wood",
+        "stripped_cherry_log", "stripped_cherry_wood", "crimson_stem", "crimson_hyphae",
+        "stripped_crimson_stem", "stripped_crimson_hyphae", "warped_stem", "warped_hyphae",
+        "stripped_warped_stem", "stripped_warped_hyphae",
+    ], &[]),
+    ("planks", &["_planks"], &[], &[]),
+    ("leaves", &["_leaves"], &[], &[]),
+    ("saplings", &[], &[
+        "oak_sapling", "spruce_sapling", "birch_sapling", "jungle_sapling", "acacia_sapling",
+        "dark_oak_sapling", "pale_oak_sapling", "cherry_sapling", "azalea", "flowering_azalea",
+        "mangrove_propagule",
+    ], &[]),
+    ("wool", &["_wool"], &[], &[]),
+    ("wool_carpets", &["_carpet"], &[], &["moss_carpet", "pale_moss_carpet"]),
+    ("stairs", &["_stairs"], &[], &[]),
+    ("slabs", &["_slab"], &[], &[]),
+    ("walls", &["_wall"], &[], &[]),
+    ("fences", &["_fence"], &[], &[]),
+    ("fence_gates", &["_fence_gate"], &[], &[]),
+    ("doors", &["_door"], &[], &[]),
+    ("trapdoors", &["_trapdoor"], &[], &[]),
+    ("buttons", &["_button"], &[], &[]),
+    ("pressure_plates", &["_pressure_plate"], &[], &[]),
+    ("signs", &[], &[
+        "oak_sign", "oak_wall_sign", "spruce_sign", "spruce_wall_sign", "birch_sign",
+        "birch_wall_sign", "jungle_sign", "jungle_wall_sign", "acacia_sign", "acacia_wall_sign",
+        "dark_oak_sign", "dark_oak_wall_sign", "pale_oak_sign", "pale_oak_wall_sign",
+        "mangrove_sign", "mangrove_wall_sign", "cherry_sign", "cherry_wall_sign", "bamboo_sign",
+        "bamboo_wall_sign", "crimson_sign", "crimson_wall_sign", "warped_sign", "warped_wall_sign",
+    ], &[]),
+    ("beds", &["_bed"], &[], &[]),
+    ("banners", &["_banner"], &[], &[]),
+    ("candles", &["_candle"], &["candle"], &[]),
+    ("shulker_boxes", &["_shulker_box"], &["shulker_box"], &[]),
+    ("terracotta", &[], &[
+        "terracotta", "white_terracotta", "orange_terracotta", "magenta_terracotta",
+        "light_blue_terracotta", "yellow_terracotta", "lime_terracotta", "pink_terracotta",
+        "gray_terracotta", "light_gray_terracotta", "cyan_terracotta", "purple_terracotta",
+        "blue_terracotta", "brown_terracotta", "green_terracotta", "red_terracotta",
+        "black_terracotta",
+    ], &[]),
+    ("rails", &[], &["rail", "powered_rail", "detector_rail", "activator_rail"], &[]),
+    ("sand", &[], &["sand", "red_sand", "suspicious_sand"], &[]),
+    ("ice", &[], &["ice", "packed_ice", "blue_ice", "frosted_ice"], &[]),
+    ("anvil", &[], &["anvil", "chipped_anvil", "damaged_anvil"], &[]),
+    ("base_stone_overworld", &[], &["stone", "granite", "diorite", "andesite", "tuff", "deepslate"], &[]),
+    ("dirt", &[], &[
+        "dirt", "coarse_dirt", "rooted_dirt", "grass_block", "podzol", "mycelium", "moss_block",
+        "pale_moss_block", "mud", "muddy_mangrove_roots",
+    ], &[]),
+];
+
+impl Mask {
+    pub fn blocks(blocks: Vec<BlockState>) -> Self {
+        Mask::Blocks(blocks)
+    }
+
+    /// Parses a mask, see the module documentation for the syntax.
+    pub fn parse(mask: &str) -> Result<Self, NucleationError> {
+        let mut masks = split_top_level(mask, char::is_whitespace)
+            .into_iter()
+            .filter(|(_, part)| !part.is_empty())
+            .map(|(start, part)| parse_mask_component(part, start))
+            .collect::<Result<Vec<_>, _>>()?;
+        match masks.len() {
+            0 => Err(block_string_syntax(0, "Empty mask")),
+            1 => Ok(masks.remove(0)),
+            _ => Ok(Mask::All(masks)),
+        }
+    }
+
+    /// Whether `tag` (with or without the `minecraft:` namespace) is a block tag masks know.
+    pub fn is_known_tag(tag: &str) -> bool {
+        let tag = tag.strip_prefix("minecraft:").unwrap_or(tag);
+        BLOCK_TAGS.iter().any(|(name, ..)| *name == tag)
+    }
+
+    pub fn negate(self) -> Self {
+        match self {
+            Mask::Not(mask) => *mask,
+            mask => Mask::Not(Box::new(mask)),
+        }
+    }
+
+    /// Whether the mask looks at other blocks than the one tested (`>`, `<`, `~`).
+    pub fn is_positional(&self) -> bool {
+        match self {
+            Mask::Offset(..) | Mask::Adjacent(_) => true,
+            Mask::Not(mask) => mask.is_positional(),
+            Mask::Either(masks) | Mask::All(masks) => masks.iter().any(Mask::is_positional),
+            Mask::Any | Mask::Blocks(_) | Mask::Tag(_) => false,
+        }
+    }
+
+    /// Tests the block at a position of `schematic`. Positions outside its regions are air.
+    pub fn test(&self, schematic: &UniversalSchematic, x: i32, y: i32, z: i32) -> bool {
+        let air = BlockState::new("minecraft:air".to_string());
+        self.test_at(schematic, (x, y, z), &air)
+    }
+
+    /// [`Mask::test`] with the air block for positions outside the regions passed in.
+    pub(crate) fn test_at(&self, schematic: &UniversalSchematic, (x, y, z): (i32, i32, i32), air: &BlockState) -> bool {
+        match self {
+            Mask::Offset(mask, (dx, dy, dz)) => mask.test_at(schematic, (x + dx, y + dy, z + dz), air),
+            Mask::Adjacent(mask) => NEIGHBOURS.iter()
+                .any(|(dx, dy, dz)| mask.test_at(schematic, (x + dx, y + dy, z + dz), air)),
+            Mask::Not(mask) => !mask.test_at(schematic, (x, y, z), air),
+            Mask::Either(masks) => masks.iter().any(|mask| mask.test_at(schematic, (x, y, z), air)),
+            Mask::All(masks) => masks.iter().all(|mask| mask.test_at(schematic, (x, y, z), air)),
+            Mask::Any | Mask::Blocks(_) | Mask::Tag(_) => {
+                self.matches_block(schematic.get_block(x, y, z).unwrap_or(air))
+            }
+        }
+    }
+
+    /// Tests a block on its own. Positional masks need the blocks around it, see
+    /// [`Mask::test`], and never match here.
+    pub fn matches(&self, block: &BlockState) -> bool {
+        !self.is_positional() && self.matches_block(block)
+    }
+
+    fn matches_block(&self, block: &BlockState) -> bool {
+        match self {
+            Mask::Any => true,
+            Mask::Blocks(blocks) => blocks.iter().any(|filter| {
+                same_name(&filter.name, &block.name)
+                    && filter.properties.iter().all(|(key, value)| block.properties.get(key) == Some(value))
+            }),
+            Mask::Tag(tag) => has_tag(tag, &block.name),
+            Mask::Not(mask) => !mask.matches_block(block),
+            Mask::Either(masks) => masks.iter().any(|mask| mask.matches_block(block)),
+            Mask::All(masks) => masks.iter().all(|mask| mask.matches_block(block)),
+            Mask::Offset(..) | Mask::Adjacent(_) => false,
+        }
+    }
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    let strip = |name: &str| name.strip_prefix("minecraft:").unwrap_or(name).to_string();
+    a == b || strip(a) == strip(b)
+}
+
+fn has_tag(tag: &str, name: &str) -> bool {
+    let tag = tag.strip_prefix("minecraft:").unwrap_or(tag);
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    BLOCK_TAGS.iter()
+        .find(|(tag_name, ..)| *tag_name == tag)
+        .is_some_and(|(_, suffixes, names, except)| {
+            !except.contains(&name)
+                && (names.contains(&name) || suffixes.iter().any(|suffix| name.ends_with(suffix)))
+        })
+}
+
+fn parse_mask_component(text: &str, start: usize) -> Result<Mask, NucleationError> {
+    let rest = || parse_mask_component(&text[1..], start + 1).map(Box::new);
+    match text.chars().next() {
+        Some('!') => Ok(Mask::Not(rest()?)),
+        Some('>') => Ok(Mask::Offset(rest()?, (0, -1, 0))),
+        Some('<') => Ok(Mask::Offset(rest()?, (0, 1, 0))),
+        Some('~') => Ok(Mask::Adjacent(rest()?)),
+        _ => {
+            let mut blocks = Vec::new();
+            let mut masks = Vec::new();
+            for (entry_start, entry) in split_top_level(text, |c| c == ',') {
+                let entry_start = start + entry_start;
+                match entry.strip_prefix('#') {
+                    Some(tag) if Mask::is_known_tag(tag) => {
+                        let tag = if tag.contains(':') { tag.to_string() } else { format!("minecraft:{}", tag) };
+                        masks.push(Mask::Tag(tag));
+                    }
+                    Some(tag) => return Err(block_string_syntax(entry_start, format!("Unknown block tag '{}'", tag))),
+                    None if entry == "*" => masks.push(Mask::Any),
+                    None => blocks.push(parse_block(entry, entry_start)?),
+                }
+            }
+            if !blocks.is_empty() {
+                masks.insert(0, Mask::Blocks(blocks));
+            }
+            Ok(if masks.len() == 1 { masks.remove(0) } else { Mask::Either(masks) })
+        }
+    }
+}
+
+// A block string of a pattern or mask starting at byte `start` of the whole expression
+fn parse_block(text: &str, start: usize) -> Result<BlockState, NucleationError> {
+    let text = text.trim();
+    let (mut block, nbt) = UniversalSchematic::parse_block_string(text).map_err(|error| match error {
+        NucleationError::BlockStringSyntax { pos, message } => block_string_syntax(start + pos, message),
+        error => error,
+    })?;
+    if block.name.is_empty() {
+        return Err(block_string_syntax(start, "Missing block name"));
+    }
+    if nbt.is_some() {
+        return Err(block_string_syntax(start + text.find('{').unwrap_or(0), "Patterns and masks can't hold NBT"));
+    }
+    if !block.name.contains(':') {
+        block.name = format!("minecraft:{}", block.name);
+    }
+    Ok(block)
+}
+
+// Splits at the separators outside of `[...]` and `{...}`, with the byte offset of each part
+fn split_top_level(text: &str, is_separator: impl Fn(char) -> bool) -> Vec<(usize, &str)> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut part_start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth -= 1,
+            c if depth == 0 && is_separator(c) => {
+                parts.push((part_start, &text[part_start..index]));
+                part_start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push((part_start, &text[part_start..]));
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_pattern_is_seeded() {
+        let stone = BlockState::new("minecraft:stone".to_string());
+        let dirt = BlockState::new("minecraft:dirt".to_string());
+        let pattern = Pattern::random(vec![(3.0, stone.clone()), (1.0, dirt.clone())]).unwrap();
+
+        let sampler = pattern.sampler(|block| if *block == stone { 1 } else { 2 });
+        let samples: Vec<usize> = (0..1000).map(|x| sampler.sample((x, 0, 0))).collect();
+        let stones = samples.iter().filter(|&&index| index == 1).count();
+        assert!((650..850).contains(&stones), "{} stones", stones);
+        assert_eq!(samples, (0..1000).map(|x| sampler.sample((x, 0, 0))).collect::<Vec<_>>());
+
+        let reseeded = pattern.with_seed(7).sampler(|block| if *block == stone { 1 } else { 2 });
+        assert!((0..1000).any(|x| reseeded.sample((x, 0, 0)) != samples[x as usize]));
+
+        assert!(Pattern::random(Vec::new()).is_err());
+        assert!(Pattern::random(vec![(0.0, dirt)]).is_err());
+    }
+
+    #[test]
+    fn test_mask_matches() {
+        let stairs = BlockState::new("minecraft:oak_stairs".to_string())
+            .with_property("facing".to_string(), "north".to_string())
+            .with_property("half".to_string(), "bottom".to_string());
+
+        assert!(Mask::Any.matches(&stairs));
+        assert!(Mask::blocks(vec![BlockState::new("oak_stairs".to_string())]).matches(&stairs));
+        let facing_north = BlockState::new("oak_stairs".to_string())
+            .with_property("facing".to_string(), "north".to_string());
+        assert!(Mask::blocks(vec![facing_north.clone()]).matches(&stairs));
+        let facing_south = facing_north.with_property("facing".to_string(), "south".to_string());
+        assert!(!Mask::blocks(vec![facing_south.clone()]).matches(&stairs));
+        assert!(Mask::blocks(vec![facing_south]).negate().matches(&stairs));
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        let stone = BlockState::new("minecraft:stone".to_string());
+        assert_eq!(Pattern::parse("stone").unwrap(), Pattern::Block(stone.clone()));
+
+        let stairs = Pattern::parse("oak_stairs[facing=north,half=top]").unwrap();
+        let Pattern::Block(stairs) = stairs else { panic!("expected a single block") };
+        assert_eq!(stairs.name, "minecraft:oak_stairs");
+        assert_eq!(stairs.get_property("half").map(String::as_str), Some("top"));
+
+        let Pattern::Random { blocks, .. } = Pattern::parse("50%stone, 30%andesite,20%cobblestone").unwrap() else {
+            panic!("expected a random pattern")
+        };
+        let weights: Vec<f64> = blocks.iter().map(|(weight, _)| *weight).collect();
+        assert_eq!(weights, vec![50.0, 30.0, 20.0]);
+        assert_eq!(blocks[2].1.name, "minecraft:cobblestone");
+
+        let Pattern::Random { blocks, .. } = Pattern::parse("stone,dirt").unwrap() else {
+            panic!("expected a random pattern")
+        };
+        assert_eq!(blocks.iter().map(|(weight, _)| *weight).sum::<f64>(), 2.0);
+
+        match Pattern::parse("stone,x%dirt") {
+            Err(NucleationError::BlockStringSyntax { pos, .. }) => assert_eq!(pos, 6),
+            other => panic!("unexpected {:?}", other),
+        }
+        match Pattern::parse("stone,oak_stairs[facing]") {
+            Err(NucleationError::BlockStringSyntax { pos, .. }) => assert!(pos > 6),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(Pattern::parse("stone,,dirt").is_err());
+        assert!(Pattern::parse("chest{Items:[]}").is_err());
+    }
+
+    #[test]
+    fn test_parse_mask() {
+        let log = BlockState::new("minecraft:stripped_oak_log".to_string());
+        let air = BlockState::new("minecraft:air".to_string());
+
+        assert!(Mask::parse("#minecraft:logs").unwrap().matches(&log));
+        assert!(Mask::parse("#logs").unwrap().matches(&log));
+        assert!(!Mask::parse("#planks").unwrap().matches(&log));
+        assert!(Mask::parse("#not_a_tag").is_err());
+
+        assert!(Mask::parse("!air").unwrap().matches(&log));
+        assert!(!Mask::parse("!air").unwrap().matches(&air));
+        assert!(Mask::parse("stone,#logs").unwrap().matches(&log));
+        assert!(!Mask::parse("!stone,#logs").unwrap().matches(&log));
+        assert!(!Mask::parse("!air air").unwrap().matches(&log));
+        assert!(Mask::parse("*").unwrap().matches(&air));
+
+        assert_eq!(
+            Mask::parse(">grass_block").unwrap(),
+            Mask::Offset(Box::new(Mask::Blocks(vec![BlockState::new("minecraft:grass_block".to_string())])), (0, -1, 0))
+        );
+        let mask = Mask::parse("!air ~water").unwrap();
+        assert!(mask.is_positional());
+        assert!(matches!(mask, Mask::All(ref masks) if masks.len() == 2));
+        assert!(!mask.matches(&log));
+        assert!(!Mask::parse("!~water").unwrap().matches(&log));
+        assert!(Mask::parse("  ").is_err());
+    }
+
+    fn tagged(tag: &str, name: &str) -> bool {
+        Mask::parse(tag).unwrap().matches(&BlockState::new(format!("minecraft:{}", name)))
+    }
+
+    #[test]
+    fn test_block_tags() {
+        let cases: &[(&str, &[&str], &[&str])] = &[
+            ("#logs", &["crimson_stem", "stripped_cherry_wood"], &["pumpkin_stem", "melon_stem", "attached_melon_stem", "mushroom_stem"]),
+            ("#terracotta", &["terracotta", "light_blue_terracotta"], &["light_blue_glazed_terracotta"]),
+            ("#saplings", &["birch_sapling", "mangrove_propagule"], &["potted_birch_sapling", "potted_azalea_bush"]),
+            ("#signs", &["bamboo_sign", "warped_wall_sign"], &["oak_hanging_sign", "oak_wall_hanging_sign"]),
+        ];
+        for (tag, members, non_members) in cases {
+            for name in *members {
+                assert!(tagged(tag, name), "{} should match {}", tag, name);
+            }
+            for name in *non_members {
+                assert!(!tagged(tag, name), "{} should not match {}", tag, name);
+            }
+        }
+    }
+}
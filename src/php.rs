@@ -9,10 +9,16 @@ use std::collections::HashMap;
 use crate::{
     UniversalSchematic,
     BlockState,
-    formats::{litematic, schematic},
+    NucleationError,
+    formats::{litematic, schematic, structure, mcstructure, axiom, vox},
     print_utils::{format_schematic, format_json_schematic},
 };
 
+// Exceptions carry `NucleationError::code` so PHP callers can tell failures apart.
+fn php_error(context: &str, error: NucleationError) -> PhpException {
+    PhpException::new(format!("{}: {}", context, error), error.code(), ext_php_rs::zend::ce::exception())
+}
+
 /// Simple test function to verify the extension works
 #[php_function]
 pub fn nucleation_hello() -> String {
@@ -39,6 +45,14 @@ pub fn nucleation_detect_format(data: String) -> String {
         "litematic".to_string()
     } else if schematic::is_schematic(bytes) {
         "schematic".to_string()
+    } else if structure::is_structure(bytes) {
+        "structure".to_string()
+    } else if mcstructure::is_mcstructure(bytes) {
+        "mcstructure".to_string()
+    } else if axiom::is_axiom(bytes) {
+        "axiom".to_string()
+    } else if vox::is_vox(bytes) {
+        "vox".to_string()
     } else {
         "unknown".to_string()
     }
@@ -52,25 +66,53 @@ pub fn nucleation_convert_format(input_data: String, output_format: String) -> P
     // Load the schematic based on input format
     let schematic = if litematic::is_litematic(bytes) {
         litematic::from_litematic(bytes)
-            .map_err(|e| PhpException::default(format!("Failed to load litematic: {}", e)))?
+            .map_err(|e| php_error("Failed to load litematic", e))?
     } else if schematic::is_schematic(bytes) {
         schematic::from_schematic(bytes)
-            .map_err(|e| PhpException::default(format!("Failed to load schematic: {}", e)))?
+            .map_err(|e| php_error("Failed to load schematic", e))?
+    } else if structure::is_structure(bytes) {
+        structure::from_structure(bytes)
+            .map_err(|e| php_error("Failed to load structure", e))?
+    } else if mcstructure::is_mcstructure(bytes) {
+        mcstructure::from_mcstructure(bytes)
+            .map_err(|e| php_error("Failed to load mcstructure", e))?
+    } else if axiom::is_axiom(bytes) {
+        axiom::from_axiom(bytes)
+            .map_err(|e| php_error("Failed to load Axiom blueprint", e))?
+    } else if vox::is_vox(bytes) {
+        vox::from_vox(bytes)
+            .map_err(|e| php_error("Failed to load vox", e))?
     } else {
-        return Err(PhpException::default("Unknown input format".to_string()));
+        return Err(php_error("Failed to load", NucleationError::UnsupportedFormat("unknown input format".to_string())));
     };
 
     // Convert to target format
     let output_bytes = match output_format.to_lowercase().as_str() {
         "litematic" => {
             litematic::to_litematic(&schematic)
-                .map_err(|e| PhpException::default(format!("Failed to convert to litematic: {}", e)))?
+                .map_err(|e| php_error("Failed to convert to litematic", e))?
         }
         "schematic" => {
             schematic::to_schematic(&schematic)
-                .map_err(|e| PhpException::default(format!("Failed to convert to schematic: {}", e)))?
+                .map_err(|e| php_error("Failed to convert to schematic", e))?
+        }
+        "structure" => {
+            structure::to_structure(&schematic)
+                .map_err(|e| php_error("Failed to convert to structure", e))?
+        }
+        "mcstructure" => {
+            mcstructure::to_mcstructure(&schematic)
+                .map_err(|e| php_error("Failed to convert to mcstructure", e))?
+        }
+        "axiom" => {
+            axiom::to_axiom(&schematic)
+                .map_err(|e| php_error("Failed to convert to Axiom blueprint", e))?
+        }
+        "vox" => {
+            vox::to_vox(&schematic)
+                .map_err(|e| php_error("Failed to convert to vox", e))?
         }
-        _ => return Err(PhpException::default("Unsupported output format".to_string()))
+        _ => return Err(php_error("Failed to convert", NucleationError::UnsupportedFormat(output_format)))
     };
 
     // Convert bytes to string (this is not ideal but works for now)
@@ -104,7 +146,7 @@ impl NucleationSchematic {
                     self.inner = schematic;
                     Ok(true)
                 }
-                Err(e) => Err(PhpException::default(format!("Failed to load litematic: {}", e)))
+                Err(e) => Err(php_error("Failed to load litematic", e))
             }
         } else if schematic::is_schematic(bytes) {
             match schematic::from_schematic(bytes) {
@@ -112,10 +154,42 @@ impl NucleationSchematic {
                     self.inner = schematic;
                     Ok(true)
                 }
-                Err(e) => Err(PhpException::default(format!("Failed to load schematic: {}", e)))
+                Err(e) => Err(php_error("Failed to load schematic", e))
+            }
+        } else if structure::is_structure(bytes) {
+            match structure::from_structure(bytes) {
+                Ok(schematic) => {
+                    self.inner = schematic;
+                    Ok(true)
+                }
+                Err(e) => Err(php_error("Failed to load structure", e))
+            }
+        } else if mcstructure::is_mcstructure(bytes) {
+            match mcstructure::from_mcstructure(bytes) {
+                Ok(schematic) => {
+                    self.inner = schematic;
+                    Ok(true)
+                }
+                Err(e) => Err(php_error("Failed to load mcstructure", e))
+            }
+        } else if axiom::is_axiom(bytes) {
+            match axiom::from_axiom(bytes) {
+                Ok(schematic) => {
+                    self.inner = schematic;
+                    Ok(true)
+                }
+                Err(e) => Err(php_error("Failed to load Axiom blueprint", e))
+            }
+        } else if vox::is_vox(bytes) {
+            match vox::from_vox(bytes) {
+                Ok(schematic) => {
+                    self.inner = schematic;
+                    Ok(true)
+                }
+                Err(e) => Err(php_error("Failed to load vox", e))
             }
         } else {
-            Err(PhpException::default("Unknown or unsupported format".to_string()))
+            Err(php_error("Failed to load", NucleationError::UnsupportedFormat("unknown input format".to_string())))
         }
     }
 
@@ -128,7 +202,7 @@ impl NucleationSchematic {
                 self.inner = schematic;
                 Ok(true)
             }
-            Err(e) => Err(PhpException::default(format!("Failed to load litematic: {}", e)))
+            Err(e) => Err(php_error("Failed to load litematic", e))
         }
     }
 
@@ -141,7 +215,59 @@ impl NucleationSchematic {
                 self.inner = schematic;
                 Ok(true)
             }
-            Err(e) => Err(PhpException::default(format!("Failed to load schematic: {}", e)))
+            Err(e) => Err(php_error("Failed to load schematic", e))
+        }
+    }
+
+    /// Load from vanilla structure data
+    #[php_method]
+    pub fn from_structure(&mut self, data: String) -> PhpResult<bool> {
+        let bytes = data.as_bytes();
+        match structure::from_structure(bytes) {
+            Ok(schematic) => {
+                self.inner = schematic;
+                Ok(true)
+            }
+            Err(e) => Err(php_error("Failed to load structure", e))
+        }
+    }
+
+    /// Load from Bedrock mcstructure data
+    #[php_method]
+    pub fn from_mcstructure(&mut self, data: String) -> PhpResult<bool> {
+        let bytes = data.as_bytes();
+        match mcstructure::from_mcstructure(bytes) {
+            Ok(schematic) => {
+                self.inner = schematic;
+                Ok(true)
+            }
+            Err(e) => Err(php_error("Failed to load mcstructure", e))
+        }
+    }
+
+    /// Load from Axiom blueprint data
+    #[php_method]
+    pub fn from_axiom(&mut self, data: String) -> PhpResult<bool> {
+        let bytes = data.as_bytes();
+        match axiom::from_axiom(bytes) {
+            Ok(schematic) => {
+                self.inner = schematic;
+                Ok(true)
+            }
+            Err(e) => Err(php_error("Failed to load Axiom blueprint", e))
+        }
+    }
+
+    /// Load from MagicaVoxel data
+    #[php_method]
+    pub fn from_vox(&mut self, data: String) -> PhpResult<bool> {
+        let bytes = data.as_bytes();
+        match vox::from_vox(bytes) {
+            Ok(schematic) => {
+                self.inner = schematic;
+                Ok(true)
+            }
+            Err(e) => Err(php_error("Failed to load vox", e))
         }
     }
 
@@ -150,7 +276,7 @@ impl NucleationSchematic {
     pub fn to_litematic(&self) -> PhpResult<String> {
         match litematic::to_litematic(&self.inner) {
             Ok(data) => Ok(String::from_utf8_lossy(&data).to_string()),
-            Err(e) => Err(PhpException::default(format!("Failed to export to litematic: {}", e)))
+            Err(e) => Err(php_error("Failed to export to litematic", e))
         }
     }
 
@@ -159,7 +285,43 @@ impl NucleationSchematic {
     pub fn to_schematic(&self) -> PhpResult<String> {
         match schematic::to_schematic(&self.inner) {
             Ok(data) => Ok(String::from_utf8_lossy(&data).to_string()),
-            Err(e) => Err(PhpException::default(format!("Failed to export to schematic: {}", e)))
+            Err(e) => Err(php_error("Failed to export to schematic", e))
+        }
+    }
+
+    /// Export to vanilla structure format
+    #[php_method]
+    pub fn to_structure(&self) -> PhpResult<String> {
+        match structure::to_structure(&self.inner) {
+            Ok(data) => Ok(String::from_utf8_lossy(&data).to_string()),
+            Err(e) => Err(php_error("Failed to export to structure", e))
+        }
+    }
+
+    /// Export to Bedrock mcstructure format
+    #[php_method]
+    pub fn to_mcstructure(&self) -> PhpResult<String> {
+        match mcstructure::to_mcstructure(&self.inner) {
+            Ok(data) => Ok(String::from_utf8_lossy(&data).to_string()),
+            Err(e) => Err(php_error("Failed to export to mcstructure", e))
+        }
+    }
+
+    /// Export to Axiom blueprint format
+    #[php_method]
+    pub fn to_axiom(&self) -> PhpResult<String> {
+        match axiom::to_axiom(&self.inner) {
+            Ok(data) => Ok(String::from_utf8_lossy(&data).to_string()),
+            Err(e) => Err(php_error("Failed to export to Axiom blueprint", e))
+        }
+    }
+
+    /// Export to MagicaVoxel format
+    #[php_method]
+    pub fn to_vox(&self) -> PhpResult<String> {
+        match vox::to_vox(&self.inner) {
+            Ok(data) => Ok(String::from_utf8_lossy(&data).to_string()),
+            Err(e) => Err(php_error("Failed to export to vox", e))
         }
     }
 
@@ -175,7 +337,7 @@ impl NucleationSchematic {
     #[php_method]
     pub fn set_block_from_string(&mut self, x: i32, y: i32, z: i32, block_string: String) -> PhpResult<()> {
         self.inner.set_block_from_string(x, y, z, &block_string)
-            .map_err(|e| PhpException::default(format!("Failed to set block from string: {}", e)))?;
+            .map_err(|e| php_error("Failed to set block from string", e))?;
         Ok(())
     }
 
@@ -389,7 +551,7 @@ impl NucleationSchematic {
             &bounds,
             (target_x, target_y, target_z),
             &excluded
-        ).map_err(|e| PhpException::default(format!("Failed to copy region: {}", e)))?;
+        ).map_err(|e| php_error("Failed to copy region", e))?;
 
         Ok(())
     }
@@ -406,16 +568,28 @@ pub fn nucleation_create_schematic(name: String) -> NucleationSchematic {
 #[php_function]
 pub fn nucleation_load_from_file(file_path: String) -> PhpResult<NucleationSchematic> {
     let data = std::fs::read(&file_path)
-        .map_err(|e| PhpException::default(format!("Failed to read file: {}", e)))?;
+        .map_err(|e| php_error("Failed to read file", e.into()))?;
 
     let inner = if litematic::is_litematic(&data) {
         litematic::from_litematic(&data)
-            .map_err(|e| PhpException::default(format!("Failed to load litematic: {}", e)))?
+            .map_err(|e| php_error("Failed to load litematic", e))?
     } else if schematic::is_schematic(&data) {
         schematic::from_schematic(&data)
-            .map_err(|e| PhpException::default(format!("Failed to load schematic: {}", e)))?
+            .map_err(|e| php_error("Failed to load schematic", e))?
+    } else if structure::is_structure(&data) {
+        structure::from_structure(&data)
+            .map_err(|e| php_error("Failed to load structure", e))?
+    } else if mcstructure::is_mcstructure(&data) {
+        mcstructure::from_mcstructure(&data)
+            .map_err(|e| php_error("Failed to load mcstructure", e))?
+    } else if axiom::is_axiom(&data) {
+        axiom::from_axiom(&data)
+            .map_err(|e| php_error("Failed to load Axiom blueprint", e))?
+    } else if vox::is_vox(&data) {
+        vox::from_vox(&data)
+            .map_err(|e| php_error("Failed to load vox", e))?
     } else {
-        return Err(PhpException::default("Unknown or unsupported format".to_string()));
+        return Err(php_error("Failed to load", NucleationError::UnsupportedFormat("unknown input format".to_string())));
     };
 
     Ok(NucleationSchematic { inner })
@@ -427,17 +601,33 @@ pub fn nucleation_save_to_file(schematic: &NucleationSchematic, file_path: Strin
     let data = match format.to_lowercase().as_str() {
         "litematic" => {
             litematic::to_litematic(&schematic.inner)
-                .map_err(|e| PhpException::default(format!("Failed to export to litematic: {}", e)))?
+                .map_err(|e| php_error("Failed to export to litematic", e))?
         }
         "schematic" => {
             schematic::to_schematic(&schematic.inner)
-                .map_err(|e| PhpException::default(format!("Failed to export to schematic: {}", e)))?
+                .map_err(|e| php_error("Failed to export to schematic", e))?
+        }
+        "structure" => {
+            structure::to_structure(&schematic.inner)
+                .map_err(|e| php_error("Failed to export to structure", e))?
+        }
+        "mcstructure" => {
+            mcstructure::to_mcstructure(&schematic.inner)
+                .map_err(|e| php_error("Failed to export to mcstructure", e))?
+        }
+        "axiom" => {
+            axiom::to_axiom(&schematic.inner)
+                .map_err(|e| php_error("Failed to export to Axiom blueprint", e))?
+        }
+        "vox" => {
+            vox::to_vox(&schematic.inner)
+                .map_err(|e| php_error("Failed to export to vox", e))?
         }
-        _ => return Err(PhpException::default("Unsupported format".to_string()))
+        _ => return Err(php_error("Failed to save", NucleationError::UnsupportedFormat(format)))
     };
 
     std::fs::write(&file_path, data)
-        .map_err(|e| PhpException::default(format!("Failed to write file: {}", e)))?;
+        .map_err(|e| php_error("Failed to write file", e.into()))?;
 
     Ok(true)
 }
@@ -1,16 +1,24 @@
 // src/python.rs
 #![cfg(feature = "python")]
 use pyo3::prelude::*;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
 use pyo3::types::{PyDict, PyList, PyBytes};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 
 use crate::{
     UniversalSchematic,
     BlockState,
+    Axis,
+    ConflictPolicy,
+    Mask,
+    Pattern,
+    SchematicDiff,
+    diff_with,
     utils::{NbtValue, NbtMap},
-    formats::{litematic, schematic},
+    formats::{litematic, schematic, structure, mcedit, mcstructure, axiom, vox},
     print_utils::{format_schematic, format_json_schematic},
     bounding_box::BoundingBox,
     block_position::BlockPosition,
@@ -21,6 +29,37 @@ use crate::{
 use quartz_nbt::NbtTag;
 use bytemuck;
 
+create_exception!(nucleation, NucleationError, PyException, "Base class of all errors raised by nucleation.");
+create_exception!(nucleation, IoError, NucleationError);
+create_exception!(nucleation, NbtError, NucleationError);
+create_exception!(nucleation, UnsupportedFormatError, NucleationError);
+create_exception!(nucleation, UnsupportedVersionError, NucleationError);
+create_exception!(nucleation, MissingTagError, NucleationError);
+create_exception!(nucleation, InvalidPaletteIndexError, NucleationError);
+create_exception!(nucleation, BlockStringSyntaxError, NucleationError);
+create_exception!(nucleation, RegionNotFoundError, NucleationError);
+create_exception!(nucleation, JsonError, NucleationError);
+create_exception!(nucleation, SnbtError, NucleationError);
+create_exception!(nucleation, InvalidDataError, NucleationError);
+
+fn to_py_err(error: crate::NucleationError) -> PyErr {
+    use crate::NucleationError as E;
+    let message = error.to_string();
+    match error {
+        E::Io(_) => IoError::new_err(message),
+        E::Nbt(_) => NbtError::new_err(message),
+        E::UnsupportedFormat(_) => UnsupportedFormatError::new_err(message),
+        E::UnsupportedVersion { .. } => UnsupportedVersionError::new_err(message),
+        E::MissingTag { .. } => MissingTagError::new_err(message),
+        E::InvalidPaletteIndex { .. } => InvalidPaletteIndexError::new_err(message),
+        E::BlockStringSyntax { .. } => BlockStringSyntaxError::new_err(message),
+        E::RegionNotFound(_) => RegionNotFoundError::new_err(message),
+        E::Json(_) => JsonError::new_err(message),
+        E::Snbt { .. } => SnbtError::new_err(message),
+        E::InvalidData(_) => InvalidDataError::new_err(message),
+    }
+}
+
 #[pyclass(name = "BlockState")]
 #[derive(Clone)]
 pub struct PyBlockState {
@@ -47,7 +86,7 @@ impl PyBlockState {
     }
 
     #[getter]
-    pub fn properties(&self) -> HashMap<String, String> {
+    pub fn properties(&self) -> BTreeMap<String, String> {
         self.inner.properties.clone()
     }
 
@@ -83,37 +122,103 @@ impl PySchematic {
     pub fn from_data(&mut self, data: &[u8]) -> PyResult<()> {
         if litematic::is_litematic(data) {
             self.inner = litematic::from_litematic(data)
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+                .map_err(to_py_err)?;
         } else if schematic::is_schematic(data) {
             self.inner = schematic::from_schematic(data)
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+                .map_err(to_py_err)?;
+        } else if structure::is_structure(data) {
+            self.inner = structure::from_structure(data)
+                .map_err(to_py_err)?;
+        } else if mcstructure::is_mcstructure(data) {
+            self.inner = mcstructure::from_mcstructure(data)
+                .map_err(to_py_err)?;
+        } else if axiom::is_axiom(data) {
+            self.inner = axiom::from_axiom(data)
+                .map_err(to_py_err)?;
+        } else if vox::is_vox(data) {
+            self.inner = vox::from_vox(data)
+                .map_err(to_py_err)?;
         } else {
-            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>("Unknown or unsupported schematic format"));
+            return Err(UnsupportedFormatError::new_err("Unknown or unsupported schematic format"));
         }
         Ok(())
     }
 
     pub fn from_litematic(&mut self, data: &[u8]) -> PyResult<()> {
         self.inner = litematic::from_litematic(data)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+            .map_err(to_py_err)?;
         Ok(())
     }
 
     pub fn to_litematic(&self, py: Python<'_>) -> PyResult<PyObject> {
         let bytes = litematic::to_litematic(&self.inner)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string()))?;
+            .map_err(to_py_err)?;
         Ok(PyBytes::new(py, &bytes).into())
     }
 
     pub fn from_schematic(&mut self, data: &[u8]) -> PyResult<()> {
         self.inner = schematic::from_schematic(data)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+            .map_err(to_py_err)?;
         Ok(())
     }
 
     pub fn to_schematic(&self, py: Python<'_>) -> PyResult<PyObject> {
         let bytes = schematic::to_schematic(&self.inner)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string()))?;
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &bytes).into())
+    }
+
+    pub fn from_structure(&mut self, data: &[u8]) -> PyResult<()> {
+        self.inner = structure::from_structure(data)
+            .map_err(to_py_err)?;
+        Ok(())
+    }
+
+    pub fn to_structure(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let bytes = structure::to_structure(&self.inner)
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &bytes).into())
+    }
+
+    pub fn from_mcstructure(&mut self, data: &[u8]) -> PyResult<()> {
+        self.inner = mcstructure::from_mcstructure(data)
+            .map_err(to_py_err)?;
+        Ok(())
+    }
+
+    pub fn to_mcstructure(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let bytes = mcstructure::to_mcstructure(&self.inner)
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &bytes).into())
+    }
+
+    pub fn from_axiom(&mut self, data: &[u8]) -> PyResult<()> {
+        self.inner = axiom::from_axiom(data)
+            .map_err(to_py_err)?;
+        Ok(())
+    }
+
+    pub fn to_axiom(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let bytes = axiom::to_axiom(&self.inner)
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &bytes).into())
+    }
+
+    pub fn from_vox(&mut self, data: &[u8]) -> PyResult<()> {
+        self.inner = vox::from_vox(data)
+            .map_err(to_py_err)?;
+        Ok(())
+    }
+
+    pub fn to_vox(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let bytes = vox::to_vox(&self.inner)
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &bytes).into())
+    }
+
+    pub fn to_mcedit(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let bytes = mcedit::to_mcedit(&self.inner)
+            .map_err(to_py_err)?;
         Ok(PyBytes::new(py, &bytes).into())
     }
 
@@ -137,7 +242,7 @@ impl PySchematic {
 
     pub fn set_block_from_string(&mut self, x: i32, y: i32, z: i32, block_string: &str) -> PyResult<()> {
         self.inner.set_block_from_string(x, y, z, block_string)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e))?;
+            .map_err(to_py_err)?;
         Ok(())
     }
 
@@ -149,7 +254,7 @@ impl PySchematic {
     ) {
         let block_state = BlockState {
             name: block_name.to_string(),
-            properties,
+            properties: properties.into_iter().collect(),
         };
         self.inner.set_block(x, y, z, block_state);
     }
@@ -167,10 +272,194 @@ impl PySchematic {
             .iter()
             .map(|s| UniversalSchematic::parse_block_string(s).map(|(bs, _)| bs))
             .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e))?;
+            .map_err(to_py_err)?;
 
         self.inner.copy_region(&from_schematic.inner, &bounds, (target_x, target_y, target_z), &excluded)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))
+            .map_err(to_py_err)
+    }
+
+    pub fn rotate_x(&mut self, degrees: i32) -> PyResult<()> {
+        self.inner.rotate_x(degrees).map_err(to_py_err)
+    }
+
+    pub fn rotate_y(&mut self, degrees: i32) -> PyResult<()> {
+        self.inner.rotate_y(degrees).map_err(to_py_err)
+    }
+
+    pub fn rotate_z(&mut self, degrees: i32) -> PyResult<()> {
+        self.inner.rotate_z(degrees).map_err(to_py_err)
+    }
+
+    pub fn mirror_x(&mut self) {
+        self.inner.mirror(Axis::X);
+    }
+
+    pub fn mirror_y(&mut self) {
+        self.inner.mirror(Axis::Y);
+    }
+
+    pub fn mirror_z(&mut self) {
+        self.inner.mirror(Axis::Z);
+    }
+
+    pub fn translate(&mut self, dx: i32, dy: i32, dz: i32) {
+        self.inner.translate(dx, dy, dz);
+    }
+
+    pub fn normalize(&mut self) -> (i32, i32, i32) {
+        self.inner.normalize()
+    }
+
+    #[pyo3(signature = (air_blocks=None))]
+    pub fn trim(&mut self, air_blocks: Option<Vec<String>>) {
+        let air_blocks = air_blocks.unwrap_or_default();
+        self.inner.trim_with(&air_blocks.iter().map(String::as_str).collect::<Vec<_>>());
+    }
+
+    pub fn set_anchor(&mut self, x: i32, y: i32, z: i32) {
+        self.inner.set_anchor(x, y, z);
+    }
+
+    pub fn get_anchor(&self) -> Option<(i32, i32, i32)> {
+        self.inner.get_anchor()
+    }
+
+    #[pyo3(signature = (min_x, min_y, min_z, max_x, max_y, max_z, pattern, seed=None))]
+    pub fn fill(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        pattern: &str,
+        seed: Option<u64>,
+    ) -> PyResult<usize> {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        let pattern = Pattern::parse(pattern).map_err(to_py_err)?.with_seed(seed.unwrap_or_default());
+        Ok(self.inner.fill(&bounds, &pattern))
+    }
+
+    #[pyo3(signature = (min_x, min_y, min_z, max_x, max_y, max_z, mask, pattern, seed=None))]
+    pub fn replace(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        mask: &str,
+        pattern: &str,
+        seed: Option<u64>,
+    ) -> PyResult<usize> {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        let mask = Mask::parse(mask).map_err(to_py_err)?;
+        let pattern = Pattern::parse(pattern).map_err(to_py_err)?.with_seed(seed.unwrap_or_default());
+        Ok(self.inner.replace(&bounds, &mask, &pattern))
+    }
+
+    #[pyo3(signature = (min_x, min_y, min_z, max_x, max_y, max_z, pattern, seed=None))]
+    pub fn walls(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        pattern: &str,
+        seed: Option<u64>,
+    ) -> PyResult<usize> {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        let pattern = Pattern::parse(pattern).map_err(to_py_err)?.with_seed(seed.unwrap_or_default());
+        Ok(self.inner.walls(&bounds, &pattern))
+    }
+
+    #[pyo3(signature = (min_x, min_y, min_z, max_x, max_y, max_z, pattern, seed=None))]
+    pub fn outline(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        pattern: &str,
+        seed: Option<u64>,
+    ) -> PyResult<usize> {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        let pattern = Pattern::parse(pattern).map_err(to_py_err)?.with_seed(seed.unwrap_or_default());
+        Ok(self.inner.outline(&bounds, &pattern))
+    }
+
+    #[pyo3(signature = (min_x, min_y, min_z, max_x, max_y, max_z, thickness=1))]
+    pub fn hollow(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        thickness: u32,
+    ) -> usize {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        self.inner.hollow(&bounds, thickness)
+    }
+
+    #[pyo3(signature = (min_x, min_y, min_z, max_x, max_y, max_z, pattern, seed=None))]
+    pub fn overlay(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        pattern: &str,
+        seed: Option<u64>,
+    ) -> PyResult<usize> {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        let pattern = Pattern::parse(pattern).map_err(to_py_err)?.with_seed(seed.unwrap_or_default());
+        Ok(self.inner.overlay(&bounds, &pattern))
+    }
+
+    pub fn find_blocks(&self, mask: &str) -> PyResult<Vec<(i32, i32, i32)>> {
+        let mask = Mask::parse(mask).map_err(to_py_err)?;
+        Ok(self.inner.find_blocks(&mask).into_iter().map(|p| (p.x, p.y, p.z)).collect())
+    }
+
+    pub fn count_matching(&self, mask: &str) -> PyResult<usize> {
+        let mask = Mask::parse(mask).map_err(to_py_err)?;
+        Ok(self.inner.count_matching(&mask))
+    }
+
+    #[pyo3(signature = (other, dx=0, dy=0, dz=0, policy="replace"))]
+    pub fn union(&mut self, other: &PySchematic, dx: i32, dy: i32, dz: i32, policy: &str) -> PyResult<()> {
+        let policy = ConflictPolicy::parse(policy).map_err(to_py_err)?;
+        self.inner.union(&other.inner, (dx, dy, dz), policy).map_err(to_py_err)
+    }
+
+    #[pyo3(signature = (other, dx=0, dy=0, dz=0, policy="replace"))]
+    pub fn intersection(&mut self, other: &PySchematic, dx: i32, dy: i32, dz: i32, policy: &str) -> PyResult<()> {
+        let policy = ConflictPolicy::parse(policy).map_err(to_py_err)?;
+        self.inner.intersection(&other.inner, (dx, dy, dz), policy).map_err(to_py_err)
+    }
+
+    #[pyo3(signature = (other, dx=0, dy=0, dz=0))]
+    pub fn difference(&mut self, other: &PySchematic, dx: i32, dy: i32, dz: i32) {
+        self.inner.difference(&other.inner, (dx, dy, dz));
+    }
+
+    #[pyo3(signature = (other, dx=0, dy=0, dz=0))]
+    pub fn xor(&mut self, other: &PySchematic, dx: i32, dy: i32, dz: i32) {
+        self.inner.xor(&other.inner, (dx, dy, dz));
+    }
+
+    /// JSON patch from this schematic to `other`, blocks only differing in `ignored_properties` are left out.
+    #[pyo3(signature = (other, ignored_properties=None))]
+    pub fn diff(&self, other: &PySchematic, ignored_properties: Option<Vec<String>>) -> PyResult<String> {
+        let ignored_properties = ignored_properties.unwrap_or_default();
+        let ignored_properties: Vec<&str> = ignored_properties.iter().map(String::as_str).collect();
+        diff_with(&self.inner, &other.inner, &ignored_properties).to_json().map_err(to_py_err)
+    }
+
+    #[pyo3(signature = (other, ignored_properties=None))]
+    pub fn diff_bytes(&self, py: Python<'_>, other: &PySchematic, ignored_properties: Option<Vec<String>>) -> PyResult<PyObject> {
+        let ignored_properties = ignored_properties.unwrap_or_default();
+        let ignored_properties: Vec<&str> = ignored_properties.iter().map(String::as_str).collect();
+        let bytes = diff_with(&self.inner, &other.inner, &ignored_properties).to_bytes().map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &bytes).into())
+    }
+
+    pub fn apply_patch(&mut self, patch: &str) -> PyResult<()> {
+        SchematicDiff::from_json(patch)
+            .and_then(|patch| patch.apply(&mut self.inner))
+            .map_err(to_py_err)
+    }
+
+    pub fn apply_patch_bytes(&mut self, data: &[u8]) -> PyResult<()> {
+        SchematicDiff::from_bytes(data)
+            .and_then(|patch| patch.apply(&mut self.inner))
+            .map_err(to_py_err)
     }
 
     pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<PyBlockState> {
@@ -348,6 +637,7 @@ fn nbt_value_to_python(py: Python<'_>, value: &NbtValue) -> PyResult<PyObject> {
             let pylist = PyList::new(py, la.clone())?;
             Ok(pylist.into())
         }
+        NbtValue::EmptyList(_) => Ok(PyList::empty(py).into()),
     }
 }
 
@@ -386,16 +676,29 @@ fn save_schematic(schematic: &PySchematic, path: &str, format: &str) -> PyResult
         let py_bytes = match format {
             "litematic" => schematic.to_litematic(py)?,
             "schematic" => schematic.to_schematic(py)?,
+            "structure" => schematic.to_structure(py)?,
+            "mcedit" => schematic.to_mcedit(py)?,
+            "mcstructure" => schematic.to_mcstructure(py)?,
+            "axiom" => schematic.to_axiom(py)?,
+            "vox" => schematic.to_vox(py)?,
             "auto" => {
                 if path.ends_with(".litematic") {
                     schematic.to_litematic(py)?
+                } else if path.ends_with(".nbt") {
+                    schematic.to_structure(py)?
+                } else if path.ends_with(".mcstructure") {
+                    schematic.to_mcstructure(py)?
+                } else if path.ends_with(".bp") {
+                    schematic.to_axiom(py)?
+                } else if path.ends_with(".vox") {
+                    schematic.to_vox(py)?
                 } else {
                     schematic.to_schematic(py)?
                 }
             }
             other => {
                 return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
-                    "Unknown format '{}', choose 'litematic', 'schematic', or 'auto'",
+                    "Unknown format '{}', choose 'litematic', 'schematic', 'structure', 'mcedit', 'mcstructure', 'axiom', 'vox', or 'auto'",
                     other
                 )))
             }
@@ -416,6 +719,18 @@ fn save_schematic(schematic: &PySchematic, path: &str, format: &str) -> PyResult
 fn nucleation(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PySchematic>()?;
     m.add_class::<PyBlockState>()?;
+    m.add("NucleationError", m.py().get_type::<NucleationError>())?;
+    m.add("IoError", m.py().get_type::<IoError>())?;
+    m.add("NbtError", m.py().get_type::<NbtError>())?;
+    m.add("UnsupportedFormatError", m.py().get_type::<UnsupportedFormatError>())?;
+    m.add("UnsupportedVersionError", m.py().get_type::<UnsupportedVersionError>())?;
+    m.add("MissingTagError", m.py().get_type::<MissingTagError>())?;
+    m.add("InvalidPaletteIndexError", m.py().get_type::<InvalidPaletteIndexError>())?;
+    m.add("BlockStringSyntaxError", m.py().get_type::<BlockStringSyntaxError>())?;
+    m.add("RegionNotFoundError", m.py().get_type::<RegionNotFoundError>())?;
+    m.add("SnbtError", m.py().get_type::<SnbtError>())?;
+    m.add("JsonError", m.py().get_type::<JsonError>())?;
+    m.add("InvalidDataError", m.py().get_type::<InvalidDataError>())?;
     m.add_function(wrap_pyfunction!(debug_schematic, m)?)?;
     m.add_function(wrap_pyfunction!(debug_json_schematic, m)?)?;
     m.add_function(wrap_pyfunction!(load_schematic, m)?)?;
@@ -1,22 +1,30 @@
 use std::collections::HashMap;
 use quartz_nbt::{NbtCompound, NbtList, NbtTag};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use crate::{ BlockState};
+use crate::{BlockState, NucleationError};
 use crate::block_entity::BlockEntity;
+use crate::block_storage::BlockStorage;
 use crate::block_position::BlockPosition;
+use crate::csg::{BooleanOperation, ConflictPolicy, Side};
 use crate::bounding_box::BoundingBox;
 use crate::entity::Entity;
+use crate::pattern::{Mask, Pattern};
+use crate::transform::{Axis, Transform};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Region {
     pub name: String,
     pub position: (i32, i32, i32),
     pub size: (i32, i32, i32),
-    pub blocks: Vec<usize>,
+    pub blocks: BlockStorage,
     pub(crate) palette: Vec<BlockState>,
     pub entities: Vec<Entity>,
     #[serde(serialize_with = "serialize_block_entities", deserialize_with = "deserialize_block_entities")]
     pub block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    /// Axes along which the region was created with a negative size. Litematica anchors such regions
+    /// at their far corner, so this is kept to write them back the way they were read.
+    #[serde(default)]
+    pub negative_size: (bool, bool, bool),
     #[serde(skip, default = "HashMap::new")]
     palette_index: HashMap<BlockState, usize>,
 
@@ -68,22 +76,57 @@ impl Region {
             name,
             position: position_and_size.0,
             size: position_and_size.1,
-            blocks: vec![0; volume],
+            blocks: BlockStorage::new(volume),
             palette,
             palette_index,
             entities: Vec::new(),
             block_entities: HashMap::new(),
+            negative_size: (size.0 < 0, size.1 < 0, size.2 < 0),
             bbox: bounding_box,
         };
         region.rebuild_bbox();
         region
     }
 
+    /// Region backed by [`BlockStorage::Sectioned`]: only 16³ sections that contain blocks are
+    /// stored, and the region grows to exactly the blocks set instead of in padded steps.
+    pub fn new_sparse(name: String, position: (i32, i32, i32), size: (i32, i32, i32)) -> Self {
+        let mut region = Region::new(name, position, (1, 1, 1));
+        let bounding_box = BoundingBox::from_position_and_size(position, size);
+        region.position = bounding_box.min;
+        region.size = bounding_box.get_dimensions();
+        region.negative_size = (size.0 < 0, size.1 < 0, size.2 < 0);
+        region.blocks = BlockStorage::sectioned(region.position, region.size);
+        region.rebuild_bbox();
+        region
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        self.blocks.is_sectioned()
+    }
+
+    /// Empty region using the same storage kind as this one.
+    pub fn new_like(&self, name: String, position: (i32, i32, i32), size: (i32, i32, i32)) -> Self {
+        if self.is_sparse() {
+            Region::new_sparse(name, position, size)
+        } else {
+            Region::new(name, position, size)
+        }
+    }
+
     #[inline(always)]
     pub fn rebuild_bbox(&mut self) {
         self.bbox = BoundingBox::from_position_and_size(self.position, self.size);
     }
 
+    /// Fails on the first block whose index does not point into the palette.
+    pub(crate) fn check_palette_indices(&self) -> Result<(), NucleationError> {
+        match self.blocks.iter().find(|&index| index >= self.palette.len()) {
+            Some(index) => Err(NucleationError::InvalidPaletteIndex { index, palette_len: self.palette.len() }),
+            None => Ok(()),
+        }
+    }
+
     pub fn get_or_insert_in_palette(&mut self, block: BlockState) -> usize {
         match self.palette_index.get(&block) {
             Some(&index) => index,
@@ -97,24 +140,103 @@ impl Region {
     }
 
     pub fn is_empty(&self) -> bool {
-        // Check if all blocks in the region are air (typically index 0 in palette)
-        self.blocks.iter().all(|&block_index| {
-            self.palette[block_index as usize].name == "minecraft:air"
-        })
+        self.non_air_blocks().next().is_none()
     }
 
     /// Alternative implementation checking for non-air blocks
     pub fn has_non_air_blocks(&self) -> bool {
-        self.blocks.iter().any(|&block_index| {
-            self.palette[block_index as usize].name != "minecraft:air"
-        })
+        !self.is_empty()
     }
 
     /// Count non-air blocks (if this method doesn't exist already)
     pub fn count_non_air_blocks(&self) -> usize {
-        self.blocks.iter()
-            .filter(|&&block_index| self.palette[block_index as usize].name != "minecraft:air")
-            .count()
+        self.non_air_blocks().count()
+    }
+
+    /// `(index, palette index)` of every block that is not air. When air is palette entry 0 this
+    /// skips the empty sections of sparse regions.
+    pub(crate) fn non_air_blocks(&self) -> Box<dyn Iterator<Item = (usize, usize)> + '_> {
+        let is_air = |block_index: usize| self.palette[block_index].name == "minecraft:air";
+        if self.palette.first().is_some_and(|block| block.name == "minecraft:air") {
+            Box::new(self.blocks.iter_non_zero().filter(move |&(_, block_index)| !is_air(block_index)))
+        } else {
+            Box::new(self.blocks.iter().enumerate().filter(move |&(_, block_index)| !is_air(block_index)))
+        }
+    }
+
+    /// Smallest box holding every block that is neither air nor one of `air_blocks`, `None`
+    /// when there is no such block.
+    pub fn tight_bounding_box(&self, air_blocks: &[&str]) -> Option<BoundingBox> {
+        let mut bounds: Option<BoundingBox> = None;
+        for (index, block_index) in self.non_air_blocks() {
+            if air_blocks.contains(&self.palette[block_index].name.as_str()) {
+                continue;
+            }
+            let position = self.index_to_coords(index);
+            let point = BoundingBox::new(position, position);
+            bounds = Some(match bounds {
+                Some(bounds) => bounds.union(&point),
+                None => point,
+            });
+        }
+        bounds
+    }
+
+    /// Shrinks the region to its non-air blocks, dropping the margins `expand_to_fit` leaves.
+    /// A region without blocks shrinks to its minimum corner.
+    pub fn trim(&mut self) {
+        self.trim_with(&[]);
+    }
+
+    /// Like [`Region::trim`], also treating the given blocks as air, e.g. `minecraft:cave_air`
+    /// or `minecraft:structure_void`. These are dropped when they lie outside the new bounds.
+    pub fn trim_with(&mut self, air_blocks: &[&str]) {
+        let bounds = self.tight_bounding_box(air_blocks)
+            .unwrap_or_else(|| BoundingBox::new(self.bbox.min, self.bbox.min));
+        self.crop(&bounds);
+    }
+
+    // Reallocates to the part of the region inside `bounds`. Blocks outside are dropped, so
+    // `bounds` may also lie elsewhere for a region without blocks to keep
+    pub(crate) fn crop(&mut self, bounds: &BoundingBox) {
+        if *bounds == self.bbox {
+            return;
+        }
+        let mut blocks = match self.blocks {
+            BlockStorage::Sectioned(_) => BlockStorage::sectioned(bounds.min, bounds.get_dimensions()),
+            _ => BlockStorage::new(bounds.volume() as usize),
+        };
+        for (index, block_index) in self.blocks.iter_non_zero() {
+            let (x, y, z) = self.index_to_coords(index);
+            if bounds.contains((x, y, z)) {
+                blocks.set(bounds.coords_to_index(x, y, z), block_index);
+            }
+        }
+        self.blocks = blocks;
+        self.position = bounds.min;
+        self.size = bounds.get_dimensions();
+        self.rebuild_bbox();
+        self.block_entities.retain(|&position, _| bounds.contains(position));
+    }
+
+    /// Drops palette entries no block uses and sorts the rest by block string, keeping the
+    /// first entry (air) in place, so regions with equal blocks get equal palettes.
+    pub fn compact_palette(&mut self) {
+        let mut used = vec![false; self.palette.len()];
+        for index in &self.blocks {
+            used[index] = true;
+        }
+        let mut order: Vec<usize> = (1..self.palette.len()).filter(|&index| used[index]).collect();
+        order.sort_by_cached_key(|&index| self.palette[index].to_string());
+        order.insert(0, 0);
+
+        let mut remap = vec![0; self.palette.len()];
+        for (new_index, &old_index) in order.iter().enumerate() {
+            remap[old_index] = new_index;
+        }
+        self.palette = order.iter().map(|&index| self.palette[index].clone()).collect();
+        self.blocks.map_in_place(|block| remap[block]);
+        self.rebuild_palette_index();
     }
 
     // Add this after from_nbt deserialization
@@ -136,7 +258,7 @@ impl Region {
 
         let index = self.coords_to_index(x, y, z);
         let palette_index = self.get_or_insert_in_palette(block);
-        self.blocks[index] = palette_index;
+        self.blocks.set(index, palette_index);
         true
     }
 
@@ -172,13 +294,25 @@ impl Region {
         self.bbox.get_dimensions()
     }
 
+    /// Position and size in the orientation the region was created with, negative sizes
+    /// are anchored at the maximum corner (see `negative_size`)
+    pub fn get_signed_position_and_size(&self) -> ((i32, i32, i32), (i32, i32, i32)) {
+        let (min, max) = (self.bbox.min, self.bbox.max);
+        let (width, height, length) = self.bbox.get_dimensions();
+        let axis = |negative: bool, min: i32, max: i32, size: i32| if negative { (max, -size) } else { (min, size) };
+        let x = axis(self.negative_size.0, min.0, max.0, width);
+        let y = axis(self.negative_size.1, min.1, max.1, height);
+        let z = axis(self.negative_size.2, min.2, max.2, length);
+        ((x.0, y.0, z.0), (x.1, y.1, z.1))
+    }
+
     pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<&BlockState> {
         if !self.is_in_region(x, y, z) {
             return None;
         }
 
         let index = self.coords_to_index(x, y, z);
-        let block_index = self.blocks[index];
+        let block_index = self.blocks.get(index);
         let palette_index = self.palette.get(block_index);
         palette_index
     }
@@ -189,7 +323,7 @@ impl Region {
         }
 
         let index = self.coords_to_index(x, y, z);
-        let block_index = self.blocks[index];
+        let block_index = self.blocks.get(index);
         Some(block_index)
     }
 
@@ -208,7 +342,10 @@ impl Region {
         let current_size = current_bounding_box.get_dimensions();
 
         // Choose expansion strategy based on current size
-        let expansion_size = if current_volume < 1000 {
+        let expansion_size = if self.is_sparse() {
+            // Sparse regions: growing moves no data, so no padding
+            (0, 0, 0)
+        } else if current_volume < 1000 {
             // Small regions: fixed large expansion
             (64, 64, 64)
         } else if current_volume < 100_000 {
@@ -267,14 +404,22 @@ impl Region {
             return;
         }
 
+        if let BlockStorage::Sectioned(storage) = &mut self.blocks {
+            storage.reshape(new_position, new_size);
+            self.position = new_position;
+            self.size = new_size;
+            self.rebuild_bbox();
+            return;
+        }
+
         let air_id = self.palette.iter().position(|b| b.name == "minecraft:air").unwrap();
-        let mut new_blocks = vec![air_id; new_bounding_box.volume() as usize];
+        let mut new_blocks = BlockStorage::filled(new_bounding_box.volume() as usize, air_id);
 
         // Copy existing blocks efficiently
-        for index in 0..self.blocks.len() {
+        for (index, block) in self.blocks.iter().enumerate() {
             let (x, y, z) = self.index_to_coords(index);
             let new_index = new_bounding_box.coords_to_index(x, y, z);
-            new_blocks[new_index] = self.blocks[index];
+            new_blocks.set(new_index, block);
         }
 
         self.position = new_position;
@@ -282,12 +427,53 @@ impl Region {
         self.blocks = new_blocks;
         self.rebuild_bbox();
     }
+
+    /// Grows the region to exactly hold `bounds`, without the margin `expand_to_fit` adds.
+    pub(crate) fn expand_to_contain(&mut self, bounds: &BoundingBox) {
+        self.expand_to_bounding_box(self.bbox.union(bounds));
+    }
+
+    /// Sets the blocks of `bounds` that lie in the region, are picked by `shape` and accepted by
+    /// `mask` to `pattern`, working on palette indices. Block entities of replaced blocks are
+    /// dropped. Returns how many blocks were set.
+    pub(crate) fn paint(
+        &mut self,
+        bounds: &BoundingBox,
+        shape: impl Fn((i32, i32, i32)) -> bool,
+        mask: &Mask,
+        pattern: &Pattern,
+    ) -> usize {
+        let Some(bounds) = bounds.intersection(&self.bbox) else {
+            return 0;
+        };
+        let sampler = pattern.sampler(|block| self.get_or_insert_in_palette(block.clone()));
+        let accepted: Vec<bool> = self.palette.iter().map(|block| mask.matches(block)).collect();
+
+        let mut count = 0;
+        for y in bounds.min.1..=bounds.max.1 {
+            for z in bounds.min.2..=bounds.max.2 {
+                for x in bounds.min.0..=bounds.max.0 {
+                    let index = self.bbox.coords_to_index(x, y, z);
+                    if !shape((x, y, z)) || !accepted[self.blocks.get(index)] {
+                        continue;
+                    }
+                    self.blocks.set(index, sampler.sample((x, y, z)));
+                    self.block_entities.remove(&(x, y, z));
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
     fn calculate_bits_per_block(&self) -> usize {
         let palette_size = self.palette.len();
         let bits_per_block = std::cmp::max((palette_size as f64).log2().ceil() as usize, 2);
         bits_per_block
     }
 
+    /// Overlays `other` on this region, its non-air blocks win. Unlike a [`Region::combine`]
+    /// union, all entities and block entities of both sides are kept.
     pub fn merge(&mut self, other: &Region) {
         let bounding_box = self.get_bounding_box().union(&other.get_bounding_box());
         let other_bounding_box = other.get_bounding_box();
@@ -296,46 +482,42 @@ impl Region {
         let new_size = combined_bounding_box.get_dimensions();
         let new_position = combined_bounding_box.min;
 
-        let mut new_blocks = vec![0; (combined_bounding_box.volume()) as usize];
+        let mut new_blocks = if self.is_sparse() {
+            BlockStorage::sectioned(new_position, new_size)
+        } else {
+            BlockStorage::new(combined_bounding_box.volume() as usize)
+        };
         let mut new_palette = self.palette.clone();
         let mut reverse_new_palette: HashMap<BlockState, usize> = HashMap::new();
         for (index, block) in self.palette.iter().enumerate() {
             reverse_new_palette.insert(block.clone(), index);
         }
 
-        // Process blocks from current region
-        for index in 0..self.blocks.len() {
+        // Process blocks from current region, entries of 0 keep the default
+        for (index, block_index) in self.blocks.iter_non_zero() {
             let (x, y, z) = self.index_to_coords(index);
             let new_index = combined_bounding_box.coords_to_index(x, y, z);
-            let block_index = self.blocks[index];
             let block = &self.palette[block_index];
             if let Some(palette_index) = reverse_new_palette.get(block) {
-                new_blocks[new_index] = *palette_index;
+                new_blocks.set(new_index, *palette_index);
             } else {
-                new_blocks[new_index] = new_palette.len();
+                new_blocks.set(new_index, new_palette.len());
                 new_palette.push(block.clone());
                 reverse_new_palette.insert(block.clone(), new_palette.len() - 1);
             }
         }
 
         // Process blocks from other region
-        for index in 0..other.blocks.len() {
+        for (index, block_palette_index) in other.non_air_blocks() {
             let (x, y, z) = other.index_to_coords(index);
             let new_index = combined_bounding_box.coords_to_index(x, y, z);
-            let block_palette_index = other.blocks[index];
             let block = &other.palette[block_palette_index];
             if let Some(palette_index) = reverse_new_palette.get(block) {
-                if block.name == "minecraft:air" {
-                    continue;
-                }
-                new_blocks[new_index] = *palette_index;
+                new_blocks.set(new_index, *palette_index);
             } else {
                 new_palette.push(block.clone());
                 reverse_new_palette.insert(block.clone(), new_palette.len() - 1);
-                if block.name == "minecraft:air" {
-                    continue;
-                }
-                new_blocks[new_index] = new_palette.len() - 1;
+                new_blocks.set(new_index, new_palette.len() - 1);
             }
         }
 
@@ -364,6 +546,230 @@ impl Region {
         self.block_entities.extend(other.block_entities.iter().map(|(&pos, be)| (pos, be.clone())));
     }
 
+    /// Combines this region with `other` moved by `offset`. Where both have a block `policy` picks
+    /// the one that stays, block entities go along with their blocks. Entities are kept where
+    /// their side could keep blocks: a union keeps all of them, an intersection those of the
+    /// winning side inside the other's bounds, a difference or xor those not standing in a block
+    /// of the other side.
+    /// Unions and xors grow the region to hold both sides, the other operations keep its bounds.
+    /// Nothing changes when `policy` fails.
+    pub fn combine(
+        &mut self,
+        other: &Region,
+        offset: (i32, i32, i32),
+        operation: BooleanOperation,
+        policy: ConflictPolicy,
+    ) -> Result<(), NucleationError> {
+        let move_other = Transform::translation(offset);
+        let other_bounds = move_other.bounding_box(&other.bbox);
+        let bounds = match operation {
+            BooleanOperation::Union | BooleanOperation::Xor => self.bbox.union(&other_bounds),
+            BooleanOperation::Intersection | BooleanOperation::Difference => self.bbox.clone(),
+        };
+        let solid = |block: &&BlockState| block.name != "minecraft:air";
+        let mine_at = |(x, y, z): (i32, i32, i32)| self.get_block(x, y, z).filter(solid);
+        let their_index_at = |(x, y, z): (i32, i32, i32)| {
+            let position = (x - offset.0, y - offset.1, z - offset.2);
+            if !other.bbox.contains(position) {
+                return None;
+            }
+            let index = other.blocks.get(other.bbox.coords_to_index(position.0, position.1, position.2));
+            Some(index).filter(|&index| solid(&&other.palette[index]))
+        };
+        let theirs_at = |position| their_index_at(position).map(|index| &other.palette[index]);
+
+        let mut palette = self.palette.clone();
+        let mut palette_index = self.palette_index.clone();
+        let air = BlockState::new("minecraft:air".to_string());
+        let air_index = *palette_index.entry(air.clone()).or_insert_with(|| {
+            palette.push(air);
+            palette.len() - 1
+        });
+        let mut blocks = if self.is_sparse() && air_index == 0 {
+            BlockStorage::sectioned(bounds.min, bounds.get_dimensions())
+        } else {
+            BlockStorage::filled(bounds.volume() as usize, air_index)
+        };
+        let mut block_entities = HashMap::new();
+
+        // Palette indices of the other side's blocks here, filled in as they are first used
+        let mut their_indices = vec![None; other.palette.len()];
+        let mut set_theirs = |position: (i32, i32, i32),
+                              block_index: usize,
+                              blocks: &mut BlockStorage,
+                              block_entities: &mut HashMap<(i32, i32, i32), BlockEntity>| {
+            let index = *their_indices[block_index].get_or_insert_with(|| {
+                let block = &other.palette[block_index];
+                *palette_index.entry(block.clone()).or_insert_with(|| {
+                    palette.push(block.clone());
+                    palette.len() - 1
+                })
+            });
+            blocks.set(bounds.coords_to_index(position.0, position.1, position.2), index);
+            let source = (position.0 - offset.0, position.1 - offset.1, position.2 - offset.2);
+            if let Some(block_entity) = other.block_entities.get(&source) {
+                let mut block_entity = block_entity.clone();
+                move_other.block_entity(&mut block_entity);
+                block_entities.insert(position, block_entity);
+            }
+        };
+
+        for (index, block_index) in self.non_air_blocks() {
+            let position = self.bbox.index_to_coords(index);
+            let theirs = their_index_at(position);
+            let their_block = theirs.map(|index| &other.palette[index]);
+            match operation.pick(policy, position, Some(&self.palette[block_index]), their_block)? {
+                Some(Side::Mine) => {
+                    blocks.set(bounds.coords_to_index(position.0, position.1, position.2), block_index);
+                    if let Some(block_entity) = self.block_entities.get(&position) {
+                        block_entities.insert(position, block_entity.clone());
+                    }
+                }
+                Some(Side::Theirs) => {
+                    set_theirs(position, theirs.expect("picked blocks exist"), &mut blocks, &mut block_entities)
+                }
+                None => {}
+            }
+        }
+        for (index, block_index) in other.non_air_blocks() {
+            let position = move_other.apply(other.bbox.index_to_coords(index));
+            if mine_at(position).is_some() {
+                continue;
+            }
+            if operation.pick(policy, position, None, Some(&other.palette[block_index]))?.is_some() {
+                set_theirs(position, block_index, &mut blocks, &mut block_entities);
+            }
+        }
+
+        let block_of = |entity: &Entity| {
+            let (x, y, z) = entity.position;
+            (x.floor() as i32, y.floor() as i32, z.floor() as i32)
+        };
+        let keep_mine = |entity: &&Entity| match operation {
+            BooleanOperation::Union => true,
+            BooleanOperation::Intersection => {
+                policy != ConflictPolicy::Replace && other_bounds.contains(block_of(entity))
+            }
+            BooleanOperation::Difference | BooleanOperation::Xor => theirs_at(block_of(entity)).is_none(),
+        };
+        let keep_theirs = |entity: &Entity| match operation {
+            BooleanOperation::Union => true,
+            BooleanOperation::Intersection => {
+                policy == ConflictPolicy::Replace && self.bbox.contains(block_of(entity))
+            }
+            BooleanOperation::Difference => false,
+            BooleanOperation::Xor => mine_at(block_of(entity)).is_none(),
+        };
+        let mut entities: Vec<Entity> = self.entities.iter().filter(keep_mine).cloned().collect();
+        for entity in &other.entities {
+            let mut entity = entity.clone();
+            move_other.entity(&mut entity);
+            if keep_theirs(&entity) {
+                entities.push(entity);
+            }
+        }
+
+        if bounds != self.bbox {
+            self.position = bounds.min;
+            self.size = bounds.get_dimensions();
+        }
+        self.blocks = blocks;
+        self.palette = palette;
+        self.palette_index = palette_index;
+        self.block_entities = block_entities;
+        self.entities = entities;
+        self.rebuild_bbox();
+        Ok(())
+    }
+
+    /// Rotates the region around the center of block (0, 0, 0) by a multiple of 90 degrees,
+    /// clockwise when looking from the positive end of `axis`. Block states, block entities and
+    /// entities are turned along.
+    pub fn rotate(&mut self, axis: Axis, degrees: i32) -> Result<(), NucleationError> {
+        self.transform(&Transform::rotation(axis, degrees)?);
+        Ok(())
+    }
+
+    pub fn rotate_x(&mut self, degrees: i32) -> Result<(), NucleationError> {
+        self.rotate(Axis::X, degrees)
+    }
+
+    pub fn rotate_y(&mut self, degrees: i32) -> Result<(), NucleationError> {
+        self.rotate(Axis::Y, degrees)
+    }
+
+    pub fn rotate_z(&mut self, degrees: i32) -> Result<(), NucleationError> {
+        self.rotate(Axis::Z, degrees)
+    }
+
+    /// Flips the region along `axis` around the center of block 0, so x becomes -x for
+    /// `Axis::X`. Block states, block entities and entities are mirrored along.
+    pub fn mirror(&mut self, axis: Axis) {
+        self.transform(&Transform::mirror(axis));
+    }
+
+    /// Moves the region with its block entities and entities. Only the position changes, the
+    /// block data is not copied.
+    pub fn translate(&mut self, dx: i32, dy: i32, dz: i32) {
+        self.transform(&Transform::translation((dx, dy, dz)));
+    }
+
+    pub(crate) fn transform(&mut self, transform: &Transform) {
+        if transform.is_identity() {
+            return;
+        }
+        if transform.is_translation() {
+            self.position = transform.apply(self.position);
+            if let BlockStorage::Sectioned(storage) = &mut self.blocks {
+                storage.translate(transform.offset());
+            }
+            self.rebuild_bbox();
+            self.transform_entities(transform);
+            return;
+        }
+        let bounding_box = transform.bounding_box(&self.get_bounding_box());
+        let mut region = self.new_like(self.name.clone(), bounding_box.min, bounding_box.get_dimensions());
+
+        // The first entry lands on index 0 again, so unset entries keep their meaning
+        region.palette.clear();
+        region.palette_index.clear();
+        let remap: Vec<usize> = self.palette.iter()
+            .map(|block| region.get_or_insert_in_palette(transform.block_state(block)))
+            .collect();
+        for (index, block_index) in self.blocks.iter_non_zero() {
+            let (x, y, z) = transform.apply(self.index_to_coords(index));
+            region.blocks.set(bounding_box.coords_to_index(x, y, z), remap[block_index]);
+        }
+
+        let negative_size = [self.negative_size.0, self.negative_size.1, self.negative_size.2];
+        let mut rotated_negative_size = [false; 3];
+        for (axis, &negative) in negative_size.iter().enumerate() {
+            rotated_negative_size[transform.axis(axis)] = negative;
+        }
+
+        self.position = region.position;
+        self.size = region.size;
+        self.blocks = region.blocks;
+        self.palette = region.palette;
+        self.palette_index = region.palette_index;
+        self.negative_size = rotated_negative_size.into();
+        self.rebuild_bbox();
+        self.transform_entities(transform);
+    }
+
+    fn transform_entities(&mut self, transform: &Transform) {
+        self.block_entities = std::mem::take(&mut self.block_entities)
+            .into_values()
+            .map(|mut block_entity| {
+                transform.block_entity(&mut block_entity);
+                (block_entity.position, block_entity)
+            })
+            .collect();
+        for entity in &mut self.entities {
+            transform.entity(entity);
+        }
+    }
+
     pub fn add_entity(&mut self, entity: Entity) {
         self.entities.push(entity);
     }
@@ -391,7 +797,7 @@ impl Region {
         tag.insert("Size", NbtTag::IntArray(vec![self.size.0, self.size.1, self.size.2]));
 
         let mut blocks_tag = NbtCompound::new();
-        for (index, &block_index) in self.blocks.iter().enumerate() {
+        for (index, block_index) in self.blocks.iter().enumerate() {
             let (x, y, z) = self.index_to_coords(index);
             blocks_tag.insert(&format!("{},{},{}", x, y, z), NbtTag::Int(block_index as i32));
         }
@@ -412,23 +818,20 @@ impl Region {
         NbtTag::Compound(tag)
     }
 
-    pub fn from_nbt(nbt: &NbtCompound) -> Result<Self, String> {
-        let name = nbt.get::<_, &str>("Name")
-            .map_err(|e| format!("Failed to get Region Name: {}", e))?
-            .to_string();
+    pub fn from_nbt(nbt: &NbtCompound) -> Result<Self, NucleationError> {
+        let name = nbt.get::<_, &str>("Name")?.to_string();
 
         let position = match nbt.get::<_, &NbtTag>("Position") {
             Ok(NbtTag::IntArray(arr)) if arr.len() == 3 => (arr[0], arr[1], arr[2]),
-            _ => return Err("Invalid Position tag".to_string()),
+            _ => return Err("Invalid Position tag".into()),
         };
 
         let size = match nbt.get::<_, &NbtTag>("Size") {
             Ok(NbtTag::IntArray(arr)) if arr.len() == 3 => (arr[0], arr[1], arr[2]),
-            _ => return Err("Invalid Size tag".to_string()),
+            _ => return Err("Invalid Size tag".into()),
         };
 
-        let palette_tag = nbt.get::<_, &NbtList>("Palette")
-            .map_err(|e| format!("Failed to get Palette: {}", e))?;
+        let palette_tag = nbt.get::<_, &NbtList>("Palette")?;
         let palette: Vec<BlockState> = palette_tag.iter()
             .filter_map(|tag| {
                 if let NbtTag::Compound(compound) = tag {
@@ -439,23 +842,24 @@ impl Region {
             })
             .collect();
 
-        let blocks_tag = nbt.get::<_, &NbtCompound>("Blocks")
-            .map_err(|e| format!("Failed to get Blocks: {}", e))?;
-        let mut blocks = vec![0; (size.0 * size.1 * size.2) as usize];
+        let blocks_tag = nbt.get::<_, &NbtCompound>("Blocks")?;
+        let mut blocks = BlockStorage::new((size.0 * size.1 * size.2) as usize);
         for (key, value) in blocks_tag.inner() {
             if let NbtTag::Int(index) = value {
                 let coords: Vec<i32> = key.split(',')
                     .map(|s| s.parse::<i32>().unwrap())
                     .collect();
                 if coords.len() == 3 {
+                    if *index as usize >= palette.len() {
+                        return Err(NucleationError::InvalidPaletteIndex { index: *index as usize, palette_len: palette.len() });
+                    }
                     let block_index = (coords[1] * size.0 * size.2 + coords[2] * size.0 + coords[0]) as usize;
-                    blocks[block_index] = *index as usize;
+                    blocks.set(block_index, *index as usize);
                 }
             }
         }
 
-        let entities_tag = nbt.get::<_, &NbtList>("Entities")
-            .map_err(|e| format!("Failed to get Entities: {}", e))?;
+        let entities_tag = nbt.get::<_, &NbtList>("Entities")?;
         let entities = entities_tag.iter()
             .filter_map(|tag| {
                 if let NbtTag::Compound(compound) = tag {
@@ -466,8 +870,7 @@ impl Region {
             })
             .collect();
 
-        let block_entities_tag = nbt.get::<_, &NbtCompound>("BlockEntities")
-            .map_err(|e| format!("Failed to get BlockEntities: {}", e))?;
+        let block_entities_tag = nbt.get::<_, &NbtCompound>("BlockEntities")?;
         let mut block_entities = HashMap::new();
         for (key, value) in block_entities_tag.inner() {
             if let NbtTag::Compound(be_compound) = value {
@@ -490,6 +893,7 @@ impl Region {
             palette,
             entities,
             block_entities,
+            negative_size: (false, false, false),
             palette_index: HashMap::new(),
             bbox: BoundingBox::from_position_and_size(position, size),
         };
@@ -526,12 +930,12 @@ impl Region {
         region_nbt
     }
 
-    pub fn unpack_block_states(&self, packed_states: &[i64]) -> Vec<usize> {
+    pub fn unpack_block_states(&self, packed_states: &[i64]) -> BlockStorage {
         let bits_per_block = self.calculate_bits_per_block();
         let mask = (1 << bits_per_block) - 1;
         let volume = self.volume();
 
-        let mut blocks = Vec::with_capacity(volume);
+        let mut blocks = BlockStorage::new(volume);
 
         for index in 0..volume {
             let bit_index = index * bits_per_block;
@@ -548,40 +952,14 @@ impl Region {
                 low_bits | (high_bits << (64 - start_offset))
             };
 
-            blocks.push(value as usize);
+            blocks.set(index, value as usize);
         }
 
         blocks
     }
 
     pub(crate) fn create_packed_block_states(&self) -> Vec<i64> {
-        let bits_per_block = self.calculate_bits_per_block();
-        let size = self.blocks.len();
-        let expected_len = (size * bits_per_block + 63) / 64; // Equivalent to ceil(size * bits_per_block / 64)
-
-        let mut packed_states = vec![0i64; expected_len];
-        let mask = (1i64 << bits_per_block) - 1;
-
-        for (index, &block_state) in self.blocks.iter().enumerate() {
-            let bit_index = index * bits_per_block;
-            let start_long_index = bit_index / 64;
-            let end_long_index = (bit_index + bits_per_block - 1) / 64;
-            let start_offset = bit_index % 64;
-
-            let value = (block_state as i64) & mask;
-
-            if start_long_index == end_long_index {
-                packed_states[start_long_index] |= value << start_offset;
-            } else {
-                packed_states[start_long_index] |= value << start_offset;
-                packed_states[end_long_index] |= value >> (64 - start_offset);
-            }
-        }
-
-        // Handle negative numbers
-        packed_states.iter_mut().for_each(|x| *x = *x as u64 as i64);
-
-        packed_states
+        pack_block_indices(self.blocks.iter(), self.calculate_bits_per_block(), true)
     }
 
     pub fn get_palette(&self) -> Vec<BlockState> {
@@ -599,14 +977,14 @@ impl Region {
     pub fn count_block_types(&self) -> HashMap<BlockState, usize> {
         let mut block_counts = HashMap::new();
         for block_index in &self.blocks {
-            let block_state = &self.palette[*block_index];
+            let block_state = &self.palette[block_index];
             *block_counts.entry(block_state.clone()).or_insert(0) += 1;
         }
         block_counts
     }
 
     pub fn count_blocks(&self) -> usize {
-        self.blocks.iter().filter(|&&block_index| block_index != 0).count()
+        self.blocks.iter_non_zero().count()
     }
 
     pub fn get_palette_index(&self, block: &BlockState) -> Option<usize> {
@@ -614,6 +992,46 @@ impl Region {
     }
 }
 
+// Packs palette indices into longs. Litematica lets values span two longs,
+// chunk sections since 1.16 pad each long instead.
+pub(crate) fn pack_block_indices(indices: impl ExactSizeIterator<Item = usize>, bits_per_block: usize, spanning: bool) -> Vec<i64> {
+    let values_per_long = 64 / bits_per_block;
+    let expected_len = if spanning {
+        (indices.len() * bits_per_block).div_ceil(64)
+    } else {
+        indices.len().div_ceil(values_per_long)
+    };
+
+    let mut packed_states = vec![0i64; expected_len];
+    let mask = (1i64 << bits_per_block) - 1;
+
+    for (index, block_state) in indices.enumerate() {
+        let value = (block_state as i64) & mask;
+
+        if !spanning {
+            packed_states[index / values_per_long] |= value << ((index % values_per_long) * bits_per_block);
+            continue;
+        }
+
+        let bit_index = index * bits_per_block;
+        let start_long_index = bit_index / 64;
+        let end_long_index = (bit_index + bits_per_block - 1) / 64;
+        let start_offset = bit_index % 64;
+
+        if start_long_index == end_long_index {
+            packed_states[start_long_index] |= value << start_offset;
+        } else {
+            packed_states[start_long_index] |= value << start_offset;
+            packed_states[end_long_index] |= value >> (64 - start_offset);
+        }
+    }
+
+    // Handle negative numbers
+    packed_states.iter_mut().for_each(|x| *x = *x as u64 as i64);
+
+    packed_states
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -631,10 +1049,11 @@ mod tests {
             name: "Test".to_string(),
             position: (0, 0, 0),
             size: (16, 1, 1),
-            blocks: blocks.clone(),
+            blocks: blocks.clone().into(),
             palette,
             entities: Vec::new(),
             block_entities: HashMap::new(),
+            negative_size: (false, false, false),
             palette_index: HashMap::new(),
             bbox: BoundingBox::from_position_and_size((0, 0, 0), (16, 1, 1)),
         };
@@ -643,7 +1062,7 @@ mod tests {
         assert_eq!(packed_states, vec![-3013672028691362751, 33756]);
 
         let unpacked_blocks = region.unpack_block_states(&packed_states);
-        assert_eq!(unpacked_blocks, blocks);
+        assert_eq!(unpacked_blocks.to_vec(), blocks);
     }
 
 
@@ -1106,6 +1525,20 @@ mod tests {
         assert_eq!(region1.get_block(-2, -2, -2), Some(&stone));
     }
 
+    #[test]
+    fn test_merge_keeps_block_entities_on_air() {
+        let mut region1 = Region::new("Test1".to_string(), (0, 0, 0), (2, 2, 2));
+        let mut region2 = Region::new("Test2".to_string(), (2, 0, 0), (2, 2, 2));
+        region1.add_block_entity(BlockEntity::new("minecraft:chest".to_string(), (1, 1, 1)));
+        region2.add_block_entity(BlockEntity::new("minecraft:barrel".to_string(), (3, 0, 0)));
+
+        region1.merge(&region2);
+
+        assert_eq!(region1.block_entities.len(), 2);
+        assert!(region1.get_block_entity(BlockPosition { x: 1, y: 1, z: 1 }).is_some());
+        assert!(region1.get_block_entity(BlockPosition { x: 3, y: 0, z: 0 }).is_some());
+    }
+
     #[test]
     fn test_expand_to_fit_preserve_blocks() {
         let mut region = Region::new("Test".to_string(), (1, 0, 1), (-2, 2, -2));
@@ -1128,4 +1561,40 @@ mod tests {
 
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_sparse_region_serde_round_trip() {
+        let mut region = Region::new_sparse("Sparse".to_string(), (-1024, -64, -1024), (1 << 11, 384, 1 << 11));
+        region.set_block(100, 70, -300, BlockState::new("minecraft:stone".to_string()));
+        region.set_block(-1000, -64, 1000, BlockState::new("minecraft:glass".to_string()));
+
+        let json = serde_json::to_string(&region).unwrap();
+        let mut read: Region = serde_json::from_str(&json).unwrap();
+        read.rebuild_bbox();
+        assert!(read.is_sparse());
+        assert_eq!(read.get_bounding_box(), region.get_bounding_box());
+        assert_eq!(read.get_block(100, 70, -300).unwrap().name, "minecraft:stone");
+        assert_eq!(read.get_block(-1000, -64, 1000).unwrap().name, "minecraft:glass");
+        assert_eq!(read.count_non_air_blocks(), 2);
+    }
+
+    #[test]
+    fn test_trim() {
+        let mut region = Region::new("Test".to_string(), (0, 0, 0), (1, 1, 1));
+        let stone = BlockState::new("minecraft:stone".to_string());
+        region.set_block(3, 1, 2, stone.clone());
+        region.set_block(5, 4, 2, stone.clone());
+        region.set_block(9, 0, 0, BlockState::new("minecraft:structure_void".to_string()));
+        region.add_block_entity(BlockEntity::new("minecraft:chest".to_string(), (5, 4, 2)));
+        assert!(region.get_dimensions().0 > 64);
+
+        region.trim();
+        assert_eq!(region.get_bounding_box(), BoundingBox::new((3, 0, 0), (9, 4, 2)));
+
+        region.trim_with(&["minecraft:structure_void"]);
+        assert_eq!(region.get_bounding_box(), BoundingBox::new((3, 1, 2), (5, 4, 2)));
+        assert_eq!(region.get_block(3, 1, 2), Some(&stone));
+        assert_eq!(region.get_block(5, 4, 2), Some(&stone));
+        assert_eq!(region.count_blocks(), 2);
+        assert!(region.get_block_entity(BlockPosition { x: 5, y: 4, z: 2 }).is_some());
+    }
+}
@@ -0,0 +1,398 @@
+// src/shapes.rs
+//! Shapes rasterised the way WorldEdit's `//sphere`, `//cyl`, `//cone`, `//pyramid`, `//line`
+//! and `//curve` do, so generated builds look like the ones made in game.
+use std::collections::HashSet;
+use crate::block_position::BlockPosition;
+use crate::bounding_box::BoundingBox;
+use crate::pattern::{Mask, Pattern};
+use crate::UniversalSchematic;
+
+// How many points `Curve` samples per block of curve length, as `//curve` does
+const CURVE_QUALITY: f64 = 10.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    /// Radii are along x, y and z; the shape is `//sphere` when they are equal.
+    Ellipsoid { center: BlockPosition, radius: (f64, f64, f64) },
+    /// Upright cylinder with radii along x and z, going down from `base` when `height` is negative.
+    /// Hollow cylinders have no caps, like `//hcyl`.
+    Cylinder { base: BlockPosition, radius: (f64, f64), height: i32 },
+    /// Upright cone standing on `base`, radii along x and z.
+    Cone { base: BlockPosition, radius: (f64, f64), height: i32 },
+    /// Square pyramid standing on `base`, `size` blocks high and `2 * size - 1` wide.
+    Pyramid { base: BlockPosition, size: i32 },
+    /// Ring around the y axis: `major_radius` from the center to the middle of the tube.
+    Torus { center: BlockPosition, major_radius: f64, minor_radius: f64 },
+    /// Straight line, swept with a ball of radius `thickness` (0 for single blocks).
+    Line { from: BlockPosition, to: BlockPosition, thickness: f64 },
+    /// Kochanek–Bartels spline through every one of `points`, with tension, bias and
+    /// continuity 0 as `//curve` uses it, swept like a line.
+    Curve { points: Vec<BlockPosition>, thickness: f64 },
+}
+
+impl Shape {
+    pub fn sphere(center: BlockPosition, radius: f64) -> Self {
+        Shape::Ellipsoid { center, radius: (radius, radius, radius) }
+    }
+
+    pub fn cylinder(base: BlockPosition, radius: f64, height: i32) -> Self {
+        Shape::Cylinder { base, radius: (radius, radius), height }
+    }
+
+    pub fn cone(base: BlockPosition, radius: f64, height: i32) -> Self {
+        Shape::Cone { base, radius: (radius, radius), height }
+    }
+
+    /// Positions of the blocks of the shape, or of its one block thick shell when not `filled`.
+    pub fn positions(&self, filled: bool) -> HashSet<(i32, i32, i32)> {
+        match self {
+            Shape::Ellipsoid { center, radius } => ellipsoid(center, *radius, filled),
+            Shape::Cylinder { base, radius, height } => cylinder(base, *radius, *height, filled),
+            Shape::Cone { base, radius, height } => cone(base, *radius, *height, filled),
+            Shape::Pyramid { base, size } => pyramid(base, *size, filled),
+            Shape::Torus { center, major_radius, minor_radius } => {
+                let inside = torus(center, *major_radius, *minor_radius);
+                if filled { inside } else { hollowed(&inside) }
+            }
+            Shape::Line { from, to, thickness } => {
+                let line = balloon(&line(from, to), *thickness);
+                if filled { line } else { hollowed(&line) }
+            }
+            Shape::Curve { points, thickness } => {
+                let curve = balloon(&spline(points), *thickness);
+                if filled { curve } else { hollowed(&curve) }
+            }
+        }
+    }
+
+    /// Sets the blocks of the shape in `schematic` to `pattern`. Returns how many blocks were set.
+    pub fn draw(&self, schematic: &mut UniversalSchematic, pattern: &Pattern, filled: bool) -> usize {
+        let positions = self.positions(filled);
+        let Some(bounds) = positions.iter()
+            .map(|&position| BoundingBox::new(position, position))
+            .reduce(|bounds, point| bounds.union(&point))
+        else {
+            return 0;
+        };
+        schematic.paint(&bounds, |position| positions.contains(&position), &Mask::Any, pattern, true)
+    }
+}
+
+// WorldEdit's makeSphere: each octant is walked outwards until it leaves the ellipsoid, and a
+// block is on the shell when one of its outer neighbours is outside
+fn ellipsoid(center: &BlockPosition, radius: (f64, f64, f64), filled: bool) -> HashSet<(i32, i32, i32)> {
+    let (radius_x, radius_y, radius_z) = (radius.0 + 0.5, radius.1 + 0.5, radius.2 + 0.5);
+    let length_sq = |x: f64, y: f64, z: f64| x * x + y * y + z * z;
+    let mut positions = HashSet::new();
+
+    'x: for x in 0..=radius_x.ceil() as i32 {
+        let (xn, next_xn) = (x as f64 / radius_x, (x + 1) as f64 / radius_x);
+        'y: for y in 0..=radius_y.ceil() as i32 {
+            let (yn, next_yn) = (y as f64 / radius_y, (y + 1) as f64 / radius_y);
+            for z in 0..=radius_z.ceil() as i32 {
+                let (zn, next_zn) = (z as f64 / radius_z, (z + 1) as f64 / radius_z);
+                if length_sq(xn, yn, zn) > 1.0 {
+                    match (y, z) {
+                        (0, 0) => break 'x,
+                        (_, 0) => break 'y,
+                        _ => break,
+                    }
+                }
+                if !filled
+                    && length_sq(next_xn, yn, zn) <= 1.0
+                    && length_sq(xn, next_yn, zn) <= 1.0
+                    && length_sq(xn, yn, next_zn) <= 1.0
+                {
+                    continue;
+                }
+                for (sx, sy, sz) in OCTANTS {
+                    positions.insert((center.x + sx * x, center.y + sy * y, center.z + sz * z));
+                }
+            }
+        }
+    }
+    positions
+}
+
+const OCTANTS: [(i32, i32, i32); 8] = [
+    (1, 1, 1), (-1, 1, 1), (1, -1, 1), (1, 1, -1), (-1, -1, 1), (1, -1, -1), (-1, 1, -1), (-1, -1, -1),
+];
+
+const QUADRANTS: [(i32, i32); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
+
+// WorldEdit's makeCylinder
+fn cylinder(base: &BlockPosition, radius: (f64, f64), height: i32, filled: bool) -> HashSet<(i32, i32, i32)> {
+    let (radius_x, radius_z) = (radius.0 + 0.5, radius.1 + 0.5);
+    let bottom = if height < 0 { base.y + height } else { base.y };
+    let length_sq = |x: f64, z: f64| x * x + z * z;
+    let mut positions = HashSet::new();
+
+    'x: for x in 0..=radius_x.ceil() as i32 {
+        let (xn, next_xn) = (x as f64 / radius_x, (x + 1) as f64 / radius_x);
+        for z in 0..=radius_z.ceil() as i32 {
+            let (zn, next_zn) = (z as f64 / radius_z, (z + 1) as f64 / radius_z);
+            if length_sq(xn, zn) > 1.0 {
+                if z == 0 {
+                    break 'x;
+                }
+                break;
+            }
+            if !filled && length_sq(next_xn, zn) <= 1.0 && length_sq(xn, next_zn) <= 1.0 {
+                continue;
+            }
+            for y in bottom..bottom + height.abs() {
+                for (sx, sz) in QUADRANTS {
+                    positions.insert((base.x + sx * x, y, base.z + sz * z));
+                }
+            }
+        }
+    }
+    positions
+}
+
+// WorldEdit's makeCone, with a one block thick shell
+fn cone(base: &BlockPosition, radius: (f64, f64), height: i32, filled: bool) -> HashSet<(i32, i32, i32)> {
+    let thickness = 1.0;
+    let (rx2, ry2, rz2) = (radius.0 * radius.0, (height * height) as f64, radius.1 * radius.1);
+    let mut positions = HashSet::new();
+
+    for y in 0..height {
+        let y_term = ((y - height) as f64).powi(2) / ry2;
+        'x: for x in 0..=radius.0.ceil() as i32 {
+            let x_term = (x * x) as f64 / rx2;
+            for z in 0..=radius.1.ceil() as i32 {
+                let z_term = (z * z) as f64 / rz2;
+                let distance = x_term + z_term - y_term;
+                if distance > 1.0 {
+                    if z == 0 {
+                        break 'x;
+                    }
+                    break;
+                }
+                if !filled {
+                    let x_next = (x as f64 + thickness).powi(2) / rx2 + z_term - y_term;
+                    let y_next = x_term + z_term - (y as f64 + thickness - height as f64).powi(2) / ry2;
+                    let z_next = x_term + (z as f64 + thickness).powi(2) / rz2 - y_term;
+                    if x_next <= 0.0 && z_next <= 0.0 && y_next <= 0.0 && y as f64 + thickness != height as f64 {
+                        continue;
+                    }
+                }
+                if distance <= 0.0 {
+                    for (sx, sz) in QUADRANTS {
+                        positions.insert((base.x + sx * x, base.y + y, base.z + sz * z));
+                    }
+                }
+            }
+        }
+    }
+    positions
+}
+
+// WorldEdit's makePyramid: layer `y` is a square of half width `size - y - 1`
+fn pyramid(base: &BlockPosition, size: i32, filled: bool) -> HashSet<(i32, i32, i32)> {
+    let mut positions = HashSet::new();
+    for y in 0..size {
+        let half = size - y - 1;
+        for x in 0..=half {
+            for z in 0..=half {
+                if filled || x == half || z == half {
+                    for (sx, sz) in QUADRANTS {
+                        positions.insert((base.x + sx * x, base.y + y, base.z + sz * z));
+                    }
+                }
+            }
+        }
+    }
+    positions
+}
+
+// Blocks whose center lies in the tube, with the same half block allowance as spheres
+fn torus(center: &BlockPosition, major_radius: f64, minor_radius: f64) -> HashSet<(i32, i32, i32)> {
+    let tube = minor_radius + 0.5;
+    let reach = (major_radius + tube).ceil() as i32;
+    let height = tube.ceil() as i32;
+    let mut positions = HashSet::new();
+    for x in -reach..=reach {
+        for z in -reach..=reach {
+            let ring = ((x * x + z * z) as f64).sqrt() - major_radius;
+            for y in -height..=height {
+                if ring * ring + (y * y) as f64 <= tube * tube {
+                    positions.insert((center.x + x, center.y + y, center.z + z));
+                }
+            }
+        }
+    }
+    positions
+}
+
+// WorldEdit's drawLine: one block per step along the axis that changes the most
+fn line(from: &BlockPosition, to: &BlockPosition) -> HashSet<(i32, i32, i32)> {
+    let start = [from.x, from.y, from.z];
+    let delta = [to.x - from.x, to.y - from.y, to.z - from.z];
+    let steps = delta.iter().map(|d| d.abs()).max().unwrap();
+    if steps == 0 {
+        return HashSet::from([(from.x, from.y, from.z)]);
+    }
+    (0..=steps)
+        .map(|step| {
+            // Java's Math.round, halves go up
+            let at = |axis: usize| start[axis] + (step as f64 * delta[axis] as f64 / steps as f64 + 0.5).floor() as i32;
+            (at(0), at(1), at(2))
+        })
+        .collect()
+}
+
+// WorldEdit's drawSpline over its KochanekBartelsInterpolation. With tension, bias and
+// continuity 0 every segment is a Catmull-Rom cubic, the end points repeated past the ends
+fn spline(points: &[BlockPosition]) -> HashSet<(i32, i32, i32)> {
+    let points: Vec<[f64; 3]> = points.iter().map(|p| [p.x as f64, p.y as f64, p.z as f64]).collect();
+    if points.len() < 2 {
+        return points.iter().map(|&[x, y, z]| (x as i32, y as i32, z as i32)).collect();
+    }
+    let node = |index: isize| points[index.clamp(0, points.len() as isize - 1) as usize];
+    let segments = points.len() - 1;
+    let point_at = |t: f64| {
+        let position = t * segments as f64;
+        let index = (position.floor() as usize).min(segments - 1);
+        let u = position - index as f64;
+        let i = index as isize;
+        let (p0, p1, p2, p3) = (node(i - 1), node(i), node(i + 1), node(i + 2));
+        let mut point = [0.0; 3];
+        for axis in 0..3 {
+            let a = -0.5 * p0[axis] + 1.5 * p1[axis] - 1.5 * p2[axis] + 0.5 * p3[axis];
+            let b = p0[axis] - 2.5 * p1[axis] + 2.0 * p2[axis] - 0.5 * p3[axis];
+            let c = -0.5 * p0[axis] + 0.5 * p2[axis];
+            point[axis] = ((a * u + b) * u + c) * u + p1[axis];
+        }
+        point
+    };
+
+    let steps = 100 * segments;
+    let length: f64 = (0..steps)
+        .map(|i| {
+            let (a, b) = (point_at(i as f64 / steps as f64), point_at((i + 1) as f64 / steps as f64));
+            ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+        })
+        .sum();
+    let samples = (length * CURVE_QUALITY).ceil().max(1.0) as usize;
+    (0..=samples)
+        .map(|i| {
+            let [x, y, z] = point_at(i as f64 / samples as f64);
+            (x.floor() as i32, y.floor() as i32, z.floor() as i32)
+        })
+        .collect()
+}
+
+// WorldEdit's getBallooned: every block within `radius` of one of the positions
+fn balloon(positions: &HashSet<(i32, i32, i32)>, radius: f64) -> HashSet<(i32, i32, i32)> {
+    let reach = radius.ceil() as i32;
+    let mut ballooned = HashSet::new();
+    for &(x, y, z) in positions {
+        for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                for dz in -reach..=reach {
+                    if ((dx * dx + dy * dy + dz * dz) as f64).sqrt() <= radius {
+                        ballooned.insert((x + dx, y + dy, z + dz));
+                    }
+                }
+            }
+        }
+    }
+    ballooned
+}
+
+// WorldEdit's getHollowed: the positions missing one of their six neighbours
+fn hollowed(positions: &HashSet<(i32, i32, i32)>) -> HashSet<(i32, i32, i32)> {
+    positions.iter()
+        .copied()
+        .filter(|&(x, y, z)| {
+            [(x + 1, y, z), (x - 1, y, z), (x, y + 1, z), (x, y - 1, z), (x, y, z + 1), (x, y, z - 1)]
+                .iter()
+                .any(|neighbour| !positions.contains(neighbour))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: i32, y: i32, z: i32) -> BlockPosition {
+        BlockPosition { x, y, z }
+    }
+
+    #[test]
+    fn test_sphere_matches_worldedit() {
+        // `//sphere stone 1` places a 3x3x3 cube without its corners, radius 2 a rounded 5x5x5
+        assert_eq!(Shape::sphere(at(0, 0, 0), 1.0).positions(true).len(), 19);
+        let sphere = Shape::sphere(at(0, 0, 0), 2.0).positions(true);
+        assert_eq!(sphere.len(), 81);
+        assert!(sphere.contains(&(2, 0, 0)) && !sphere.contains(&(2, 2, 0)));
+
+        let hollow = Shape::sphere(at(0, 0, 0), 5.0).positions(false);
+        assert!(!hollow.contains(&(0, 0, 0)) && hollow.contains(&(5, 0, 0)));
+        assert!(hollow.is_subset(&Shape::sphere(at(0, 0, 0), 5.0).positions(true)));
+    }
+
+    #[test]
+    fn test_cylinder_cone_and_pyramid() {
+        // A radius 1 disc is a plus sign with corners: 3x3
+        let cylinder = Shape::cylinder(at(0, 10, 0), 1.0, 3).positions(true);
+        assert_eq!(cylinder.len(), 27);
+        assert!(cylinder.contains(&(0, 12, 0)) && !cylinder.contains(&(0, 13, 0)));
+        let down = Shape::cylinder(at(0, 10, 0), 1.0, -3).positions(true);
+        assert!(down.contains(&(0, 7, 0)) && !down.contains(&(0, 10, 0)));
+        let tube = Shape::cylinder(at(0, 0, 0), 4.0, 2).positions(false);
+        assert!(!tube.contains(&(0, 0, 0)) && tube.contains(&(4, 1, 0)));
+
+        let cone = Shape::cone(at(0, 0, 0), 3.0, 4).positions(true);
+        assert!(cone.contains(&(3, 0, 0)) && cone.contains(&(0, 3, 0)) && !cone.contains(&(0, 4, 0)));
+        assert!(!cone.contains(&(1, 3, 0)));
+
+        let pyramid = Shape::Pyramid { base: at(0, 0, 0), size: 3 }.positions(true);
+        assert_eq!(pyramid.len(), 25 + 9 + 1);
+        let hollow = Shape::Pyramid { base: at(0, 0, 0), size: 3 }.positions(false);
+        assert_eq!(hollow.len(), 16 + 8 + 1);
+    }
+
+    #[test]
+    fn test_torus() {
+        let torus = Shape::Torus { center: at(0, 0, 0), major_radius: 4.0, minor_radius: 1.0 }.positions(true);
+        assert!(torus.contains(&(4, 0, 0)) && torus.contains(&(0, 1, -4)));
+        assert!(!torus.contains(&(0, 0, 0)) && !torus.contains(&(4, 2, 0)));
+        let shell = Shape::Torus { center: at(0, 0, 0), major_radius: 6.0, minor_radius: 2.0 }.positions(false);
+        assert!(!shell.contains(&(6, 0, 0)) && shell.contains(&(8, 0, 0)));
+    }
+
+    #[test]
+    fn test_line_and_curve() {
+        let line = Shape::Line { from: at(0, 0, 0), to: at(4, 2, 0), thickness: 0.0 }.positions(true);
+        let expected: HashSet<_> = [(0, 0, 0), (1, 1, 0), (2, 1, 0), (3, 2, 0), (4, 2, 0)].into_iter().collect();
+        assert_eq!(line, expected);
+
+        let thick = Shape::Line { from: at(0, 0, 0), to: at(10, 0, 0), thickness: 1.0 }.positions(true);
+        assert_eq!(thick.len(), 11 * 5 + 2);
+        let hollow = Shape::Line { from: at(0, 0, 0), to: at(10, 0, 0), thickness: 2.0 }.positions(false);
+        assert!(!hollow.contains(&(5, 0, 0)) && hollow.contains(&(5, 2, 0)));
+
+        let points = vec![at(0, 0, 0), at(5, 10, 0), at(10, 0, 0)];
+        let curve = Shape::Curve { points: points.clone(), thickness: 0.0 }.positions(true);
+        for point in &points {
+            assert!(curve.contains(&(point.x, point.y, point.z)), "misses {:?}", point);
+        }
+        assert_eq!(curve.iter().map(|&(_, y, _)| y).max(), Some(10));
+        assert!(!curve.contains(&(5, 0, 0)));
+        // Two points make a straight line
+        let straight = Shape::Curve { points: vec![at(0, 0, 0), at(6, 0, 0)], thickness: 0.0 }.positions(true);
+        assert_eq!(straight, (0..=6).map(|x| (x, 0, 0)).collect());
+    }
+
+    #[test]
+    fn test_draw_shape() {
+        let mut schematic = UniversalSchematic::new("Shapes".to_string());
+        let stone = Pattern::parse("stone").unwrap();
+        assert_eq!(Shape::sphere(at(10, 10, 10), 2.0).draw(&mut schematic, &stone, true), 81);
+        assert_eq!(schematic.get_bounding_box(), BoundingBox::new((8, 8, 8), (12, 12, 12)));
+        assert_eq!(schematic.count_matching(&Mask::parse("stone").unwrap()), 81);
+    }
+}
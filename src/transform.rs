@@ -0,0 +1,562 @@
+use std::collections::BTreeMap;
+use crate::block_entity::BlockEntity;
+use crate::bounding_box::BoundingBox;
+use crate::entity::Entity;
+use crate::utils::NbtValue;
+use crate::{BlockState, NucleationError};
+
+/// Axis to rotate around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Down,
+    Up,
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Direction {
+    const ALL: [Direction; 6] = [
+        Direction::Down,
+        Direction::Up,
+        Direction::North,
+        Direction::South,
+        Direction::West,
+        Direction::East,
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Direction::ALL.into_iter().find(|direction| direction.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Direction::Down => "down",
+            Direction::Up => "up",
+            Direction::North => "north",
+            Direction::South => "south",
+            Direction::West => "west",
+            Direction::East => "east",
+        }
+    }
+
+    fn vector(self) -> (i32, i32, i32) {
+        match self {
+            Direction::Down => (0, -1, 0),
+            Direction::Up => (0, 1, 0),
+            Direction::North => (0, 0, -1),
+            Direction::South => (0, 0, 1),
+            Direction::West => (-1, 0, 0),
+            Direction::East => (1, 0, 0),
+        }
+    }
+
+    fn from_vector(vector: (i32, i32, i32)) -> Self {
+        Direction::ALL.into_iter()
+            .find(|direction| direction.vector() == vector)
+            .expect("Transforms map unit vectors to unit vectors")
+    }
+
+    fn is_vertical(self) -> bool {
+        matches!(self, Direction::Up | Direction::Down)
+    }
+
+    /// Index used by the `Facing` tag of item frames.
+    fn id(self) -> i8 {
+        Direction::ALL.iter().position(|&direction| direction == self).unwrap() as i8
+    }
+
+    /// Index used by the `facing` tag of paintings, horizontal directions only.
+    fn horizontal_id(self) -> Option<i8> {
+        match self {
+            Direction::South => Some(0),
+            Direction::West => Some(1),
+            Direction::North => Some(2),
+            Direction::East => Some(3),
+            _ => None,
+        }
+    }
+
+    fn from_horizontal_id(id: i8) -> Option<Self> {
+        [Direction::South, Direction::West, Direction::North, Direction::East]
+            .get(id as usize)
+            .copied()
+    }
+}
+
+// Blocks whose `facing` may point up or down in addition to the horizontal directions
+const VERTICAL_FACING_BLOCKS: &[&str] = &[
+    "piston", "sticky_piston", "piston_head", "moving_piston", "dispenser", "dropper",
+    "observer", "end_rod", "lightning_rod", "barrel", "command_block", "chain_command_block",
+    "repeating_command_block", "amethyst_cluster", "large_amethyst_bud", "medium_amethyst_bud",
+    "small_amethyst_bud", "hopper",
+];
+
+fn can_face_vertically(name: &str, direction: Direction) -> bool {
+    if name == "hopper" {
+        return direction == Direction::Down;
+    }
+    VERTICAL_FACING_BLOCKS.contains(&name) || name.ends_with("shulker_box")
+}
+
+/// A rotation by quarter turns or a mirror, stored as an integer matrix acting on block
+/// coordinates, followed by a translation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Transform {
+    matrix: [[i32; 3]; 3],
+    offset: (i32, i32, i32),
+}
+
+impl Transform {
+    const IDENTITY: Transform = Transform { matrix: [[1, 0, 0], [0, 1, 0], [0, 0, 1]], offset: (0, 0, 0) };
+
+    /// Clockwise rotation when looking from the positive end of `axis` toward the origin, so a
+    /// rotation around Y turns north to east like Minecraft's clockwise structure rotation.
+    pub(crate) fn rotation(axis: Axis, degrees: i32) -> Result<Self, NucleationError> {
+        if degrees % 90 != 0 {
+            return Err(format!("Rotation must be a multiple of 90 degrees, got {}", degrees).into());
+        }
+        let quarter_turn = Transform {
+            matrix: match axis {
+                Axis::X => [[1, 0, 0], [0, 0, 1], [0, -1, 0]],
+                Axis::Y => [[0, 0, -1], [0, 1, 0], [1, 0, 0]],
+                Axis::Z => [[0, 1, 0], [-1, 0, 0], [0, 0, 1]],
+            },
+            offset: (0, 0, 0),
+        };
+        Ok((0..(degrees / 90).rem_euclid(4)).fold(Transform::IDENTITY, |transform, _| transform.then(&quarter_turn)))
+    }
+
+    /// Flips coordinates along `axis` around the center of block 0.
+    pub(crate) fn mirror(axis: Axis) -> Self {
+        let mut transform = Transform::IDENTITY;
+        let index = match axis {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        };
+        transform.matrix[index][index] = -1;
+        transform
+    }
+
+    pub(crate) fn translation(offset: (i32, i32, i32)) -> Self {
+        Transform { offset, ..Transform::IDENTITY }
+    }
+
+    /// This transform followed by `next`.
+    pub(crate) fn then(&self, next: &Transform) -> Transform {
+        let mut matrix = [[0; 3]; 3];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| next.matrix[i][k] * self.matrix[k][j]).sum();
+            }
+        }
+        Transform { matrix, offset: next.apply(self.offset) }
+    }
+
+    pub(crate) fn is_identity(&self) -> bool {
+        *self == Transform::IDENTITY
+    }
+
+    /// Whether the transform only moves things, without turning them.
+    pub(crate) fn is_translation(&self) -> bool {
+        self.matrix == Transform::IDENTITY.matrix
+    }
+
+    pub(crate) fn offset(&self) -> (i32, i32, i32) {
+        self.offset
+    }
+
+    pub(crate) fn apply(&self, position: (i32, i32, i32)) -> (i32, i32, i32) {
+        let (x, y, z) = self.apply_matrix(position);
+        (x + self.offset.0, y + self.offset.1, z + self.offset.2)
+    }
+
+    fn apply_matrix(&self, (x, y, z): (i32, i32, i32)) -> (i32, i32, i32) {
+        let m = &self.matrix;
+        (
+            m[0][0] * x + m[0][1] * y + m[0][2] * z,
+            m[1][0] * x + m[1][1] * y + m[1][2] * z,
+            m[2][0] * x + m[2][1] * y + m[2][2] * z,
+        )
+    }
+
+    fn apply_vector(&self, (x, y, z): (f64, f64, f64)) -> (f64, f64, f64) {
+        let m = self.matrix.map(|row| row.map(f64::from));
+        (
+            m[0][0] * x + m[0][1] * y + m[0][2] * z,
+            m[1][0] * x + m[1][1] * y + m[1][2] * z,
+            m[2][0] * x + m[2][1] * y + m[2][2] * z,
+        )
+    }
+
+    // Block coordinates turn around the center of block (0, 0, 0), so exact positions do too.
+    // Each row sums to 1 or -1, which moves that center by a whole block or not at all.
+    fn apply_position(&self, position: (f64, f64, f64)) -> (f64, f64, f64) {
+        let (x, y, z) = self.apply_vector(position);
+        let shift = |row: usize, offset: i32| {
+            let sum: i32 = self.matrix[row].iter().sum();
+            f64::from(offset + (1 - sum) / 2)
+        };
+        (x + shift(0, self.offset.0), y + shift(1, self.offset.1), z + shift(2, self.offset.2))
+    }
+
+    fn direction(&self, direction: Direction) -> Direction {
+        Direction::from_vector(self.apply_matrix(direction.vector()))
+    }
+
+    /// Index of the axis that `axis` (0 = x, 1 = y, 2 = z) is moved onto.
+    pub(crate) fn axis(&self, axis: usize) -> usize {
+        (0..3).find(|&i| self.matrix[i][axis] != 0).unwrap()
+    }
+
+    /// Whether the horizontal plane is turned over, which swaps left and right.
+    fn mirrors_horizontally(&self) -> bool {
+        let south = self.direction(Direction::South).horizontal_id();
+        let west = self.direction(Direction::West).horizontal_id();
+        match (south, west) {
+            (Some(south), Some(west)) => (west - south).rem_euclid(4) != 1,
+            _ => false,
+        }
+    }
+
+    /// Where a horizontal angle in degrees (0 = south, 90 = west, like yaw and sign rotation)
+    /// ends up, when the transform keeps up pointing up.
+    fn horizontal_angle(&self, angle: f32) -> Option<f32> {
+        if self.direction(Direction::Up) != Direction::Up {
+            return None;
+        }
+        let south = self.direction(Direction::South).horizontal_id()? as f32 * 90.0;
+        Some(if self.mirrors_horizontally() { south - angle } else { south + angle })
+    }
+
+    pub(crate) fn bounding_box(&self, bounding_box: &BoundingBox) -> BoundingBox {
+        let a = self.apply(bounding_box.min);
+        let b = self.apply(bounding_box.max);
+        BoundingBox::new(
+            (a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)),
+            (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)),
+        )
+    }
+
+    /// The block with its orientation properties turned along. Blocks that cannot take the new
+    /// orientation, like stairs turned on their side, are returned unchanged.
+    pub(crate) fn block_state(&self, block: &BlockState) -> BlockState {
+        if self.is_translation() || block.properties.is_empty() {
+            return block.clone();
+        }
+        match self.block_properties(block) {
+            Some(properties) => BlockState { name: block.name.clone(), properties },
+            None => block.clone(),
+        }
+    }
+
+    fn block_properties(&self, block: &BlockState) -> Option<BTreeMap<String, String>> {
+        let name = block.name.strip_prefix("minecraft:").unwrap_or(&block.name);
+        let up = self.direction(Direction::Up);
+        // Walls use `up` for the post, not for a connection
+        let is_wall = name.ends_with("_wall");
+
+        let mut properties = BTreeMap::new();
+        for (key, value) in &block.properties {
+            let (key, value) = match (key.as_str(), value.as_str()) {
+                ("face", _) => continue,
+                ("facing", _) if block.properties.contains_key("face") => continue,
+                ("facing", facing) => {
+                    let facing = Direction::from_name(facing)?;
+                    let rotated = self.direction(facing);
+                    if rotated.is_vertical() && !can_face_vertically(name, rotated) {
+                        return None;
+                    }
+                    (key.clone(), rotated.name().to_string())
+                }
+                ("axis", axis) => {
+                    let direction = match axis {
+                        "x" => Direction::East,
+                        "y" => Direction::Up,
+                        "z" => Direction::South,
+                        _ => return None,
+                    };
+                    let axis = match self.direction(direction) {
+                        Direction::East | Direction::West => "x",
+                        Direction::Up | Direction::Down => "y",
+                        Direction::North | Direction::South => "z",
+                    };
+                    if axis == "y" && name == "nether_portal" {
+                        return None;
+                    }
+                    (key.clone(), axis.to_string())
+                }
+                ("rotation", rotation) => {
+                    let rotation: i32 = rotation.parse().ok()?;
+                    let angle = self.horizontal_angle(rotation as f32 * 22.5)?;
+                    (key.clone(), ((angle / 22.5).round() as i32).rem_euclid(16).to_string())
+                }
+                ("half" | "type", "top" | "bottom") | ("half", "upper" | "lower") | ("attachment", "floor" | "ceiling") => {
+                    let value = match (up, value.as_str()) {
+                        (Direction::Up, value) => value,
+                        (Direction::Down, "top") => "bottom",
+                        (Direction::Down, "bottom") => "top",
+                        (Direction::Down, "upper") => "lower",
+                        (Direction::Down, "lower") => "upper",
+                        (Direction::Down, "floor") => "ceiling",
+                        (Direction::Down, "ceiling") => "floor",
+                        _ => return None,
+                    };
+                    (key.clone(), value.to_string())
+                }
+                ("half", _) | ("attachment", _) => {
+                    if up != Direction::Up {
+                        return None;
+                    }
+                    (key.clone(), value.clone())
+                }
+                ("vertical_direction", direction) => {
+                    let rotated = self.direction(Direction::from_name(direction)?);
+                    if !rotated.is_vertical() {
+                        return None;
+                    }
+                    (key.clone(), rotated.name().to_string())
+                }
+                ("shape", shape) if shape == "straight" || shape.contains("left") || shape.contains("right") => {
+                    (key.clone(), self.handed(shape))
+                }
+                ("hinge" | "type", "left" | "right") => (key.clone(), self.handed(value)),
+                ("shape", shape) => (key.clone(), self.rail_shape(shape)?),
+                ("orientation", orientation) => {
+                    let (front, top) = orientation.split_once('_')?;
+                    let front = self.direction(Direction::from_name(front)?);
+                    let top = self.direction(Direction::from_name(top)?);
+                    if !front.is_vertical() && top != Direction::Up {
+                        return None;
+                    }
+                    (key.clone(), format!("{}_{}", front.name(), top.name()))
+                }
+                (side, _) if Direction::from_name(side).is_some() && !(is_wall && side == "up") => {
+                    let rotated = self.direction(Direction::from_name(side)?);
+                    if !block.properties.contains_key(rotated.name()) || (is_wall && rotated.is_vertical()) {
+                        return None;
+                    }
+                    (rotated.name().to_string(), value.clone())
+                }
+                _ => (key.clone(), value.clone()),
+            };
+            properties.insert(key, value);
+        }
+
+        // Buttons, levers and grindstones attach to the floor, a wall or the ceiling
+        if let (Some(face), Some(facing)) = (block.properties.get("face"), block.properties.get("facing")) {
+            let facing = Direction::from_name(facing)?;
+            let attached = match face.as_str() {
+                "floor" => Direction::Up,
+                "ceiling" => Direction::Down,
+                "wall" => facing,
+                _ => return None,
+            };
+            let rotated = self.direction(attached);
+            let facing = match (rotated.is_vertical(), attached.is_vertical()) {
+                (false, _) => rotated,
+                (true, true) => self.direction(facing),
+                (true, false) => up,
+            };
+            let face = match rotated {
+                Direction::Up => "floor",
+                Direction::Down => "ceiling",
+                _ => "wall",
+            };
+            properties.insert("face".to_string(), face.to_string());
+            properties.insert("facing".to_string(), facing.name().to_string());
+        }
+
+        Some(properties)
+    }
+
+    // Seen from above, so stairs turned upside down by a half turn around X swap sides too
+    fn handed(&self, value: &str) -> String {
+        match self.mirrors_horizontally() {
+            true if value.contains("left") => value.replace("left", "right"),
+            true => value.replace("right", "left"),
+            false => value.to_string(),
+        }
+    }
+
+    // Rails have to stay on the ground
+    fn rail_shape(&self, shape: &str) -> Option<String> {
+        if self.direction(Direction::Up) != Direction::Up {
+            return None;
+        }
+        if let Some(direction) = shape.strip_prefix("ascending_") {
+            return Some(format!("ascending_{}", self.direction(Direction::from_name(direction)?).name()));
+        }
+        let (a, b) = shape.split_once('_')?;
+        let (a, b) = (self.direction(Direction::from_name(a)?), self.direction(Direction::from_name(b)?));
+        // Shapes list north/south before east/west
+        let shape = match (a, b) {
+            (Direction::East | Direction::West, Direction::North | Direction::South) => format!("{}_{}", b.name(), a.name()),
+            (Direction::South, Direction::North) | (Direction::West, Direction::East) => format!("{}_{}", b.name(), a.name()),
+            _ => format!("{}_{}", a.name(), b.name()),
+        };
+        Some(shape)
+    }
+
+    pub(crate) fn block_entity(&self, block_entity: &mut BlockEntity) {
+        block_entity.position = self.apply(block_entity.position);
+        let (x, y, z) = block_entity.position;
+        if let Some(NbtValue::IntArray(position)) = block_entity.nbt.get_mut("Pos") {
+            if position.len() == 3 {
+                *position = vec![x, y, z];
+            }
+        }
+        for (key, value) in [("x", x), ("y", y), ("z", z)] {
+            if let Some(NbtValue::Int(coordinate)) = block_entity.nbt.get_mut(key) {
+                *coordinate = value;
+            }
+        }
+    }
+
+    pub(crate) fn entity(&self, entity: &mut Entity) {
+        entity.position = self.apply_position(entity.position);
+        let (x, y, z) = entity.position;
+        if let Some(NbtValue::List(position)) = entity.nbt.get_mut("Pos") {
+            if let [NbtValue::Double(px), NbtValue::Double(py), NbtValue::Double(pz)] = position.as_mut_slice() {
+                (*px, *py, *pz) = (x, y, z);
+            }
+        }
+        if let Some(NbtValue::List(rotation)) = entity.nbt.get_mut("Rotation") {
+            if let [NbtValue::Float(yaw), NbtValue::Float(pitch)] = rotation.as_mut_slice() {
+                (*yaw, *pitch) = self.yaw_and_pitch(*yaw, *pitch);
+            }
+        }
+
+        // Hanging entities store the block they hang on and the direction they face
+        if let (Some(NbtValue::Int(x)), Some(NbtValue::Int(y)), Some(NbtValue::Int(z))) =
+            (entity.nbt.get("TileX"), entity.nbt.get("TileY"), entity.nbt.get("TileZ"))
+        {
+            let (x, y, z) = self.apply((*x, *y, *z));
+            for (key, value) in [("TileX", x), ("TileY", y), ("TileZ", z)] {
+                entity.nbt.insert(key.to_string(), NbtValue::Int(value));
+            }
+        }
+        if let Some(NbtValue::Byte(facing)) = entity.nbt.get_mut("Facing") {
+            if let Some(&direction) = Direction::ALL.get(*facing as usize) {
+                *facing = self.direction(direction).id();
+            }
+        }
+        if let Some(NbtValue::Byte(facing)) = entity.nbt.get_mut("facing") {
+            if let Some(id) = Direction::from_horizontal_id(*facing).and_then(|direction| self.direction(direction).horizontal_id()) {
+                *facing = id;
+            }
+        }
+    }
+
+    fn yaw_and_pitch(&self, yaw: f32, pitch: f32) -> (f32, f32) {
+        let wrap = |angle: f32| (angle + 180.0).rem_euclid(360.0) - 180.0;
+        if let Some(yaw) = self.horizontal_angle(yaw) {
+            return (wrap(yaw), pitch);
+        }
+        let (yaw_radians, pitch_radians) = ((yaw as f64).to_radians(), (pitch as f64).to_radians());
+        let look = (
+            -yaw_radians.sin() * pitch_radians.cos(),
+            -pitch_radians.sin(),
+            yaw_radians.cos() * pitch_radians.cos(),
+        );
+        let (x, y, z) = self.apply_vector(look);
+        let pitch = (-y).clamp(-1.0, 1.0).asin().to_degrees();
+        // Looking straight up or down has no yaw, keep the old one
+        let yaw = if x.abs() < 1e-9 && z.abs() < 1e-9 { yaw as f64 } else { (-x).atan2(z).to_degrees() };
+        (wrap(yaw as f32), pitch as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotated(block: &str, axis: Axis, degrees: i32) -> String {
+        let transform = Transform::rotation(axis, degrees).unwrap();
+        let block = crate::utils::parse_block_state(block, block).unwrap();
+        transform.block_state(&block).to_string()
+    }
+
+    #[test]
+    fn test_rotation_positions() {
+        let y = Transform::rotation(Axis::Y, 90).unwrap();
+        assert_eq!(y.apply((0, 0, -1)), (1, 0, 0));
+        assert_eq!(Transform::rotation(Axis::Y, -90).unwrap(), Transform::rotation(Axis::Y, 270).unwrap());
+        assert!(Transform::rotation(Axis::Y, 360).unwrap().is_identity());
+        assert!(Transform::rotation(Axis::Y, 45).is_err());
+        assert_eq!(Transform::rotation(Axis::X, 90).unwrap().apply((0, 1, 0)), (0, 0, -1));
+        assert_eq!(Transform::rotation(Axis::Z, 90).unwrap().apply((0, 1, 0)), (1, 0, 0));
+    }
+
+    #[test]
+    fn test_rotate_block_properties() {
+        assert_eq!(rotated("minecraft:oak_stairs[facing=north,half=bottom,shape=inner_left]", Axis::Y, 90),
+                   "minecraft:oak_stairs[facing=east,half=bottom,shape=inner_left]");
+        assert_eq!(rotated("minecraft:oak_stairs[facing=east,half=bottom,shape=inner_right]", Axis::X, 180),
+                   "minecraft:oak_stairs[facing=east,half=top,shape=inner_left]");
+        // Stairs cannot face up, so they stay as they are
+        assert_eq!(rotated("minecraft:oak_stairs[facing=north,half=bottom,shape=straight]", Axis::X, 90),
+                   "minecraft:oak_stairs[facing=north,half=bottom,shape=straight]");
+        assert_eq!(rotated("minecraft:piston[extended=false,facing=north]", Axis::X, 90), "minecraft:piston[extended=false,facing=down]");
+        assert_eq!(rotated("minecraft:oak_log[axis=x]", Axis::Y, 90), "minecraft:oak_log[axis=z]");
+        assert_eq!(rotated("minecraft:oak_log[axis=y]", Axis::Z, 90), "minecraft:oak_log[axis=x]");
+        assert_eq!(rotated("minecraft:oak_sign[rotation=14]", Axis::Y, 90), "minecraft:oak_sign[rotation=2]");
+        assert_eq!(rotated("minecraft:oak_fence[east=false,north=true,south=false,west=true]", Axis::Y, 90),
+                   "minecraft:oak_fence[east=true,north=true,south=false,west=false]");
+        assert_eq!(rotated("minecraft:cobblestone_wall[east=none,north=tall,south=none,up=true,west=low]", Axis::Y, 180),
+                   "minecraft:cobblestone_wall[east=low,north=none,south=tall,up=true,west=none]");
+        assert_eq!(rotated("minecraft:rail[shape=north_east]", Axis::Y, 90), "minecraft:rail[shape=south_east]");
+        assert_eq!(rotated("minecraft:rail[shape=ascending_west]", Axis::Y, 270), "minecraft:rail[shape=ascending_south]");
+        assert_eq!(rotated("minecraft:red_mushroom_block[down=false,east=true,north=false,south=true,up=true,west=false]", Axis::X, 90),
+                   "minecraft:red_mushroom_block[down=false,east=true,north=true,south=false,up=true,west=false]");
+        assert_eq!(rotated("minecraft:stone_button[face=wall,facing=north,powered=false]", Axis::X, 90),
+                   "minecraft:stone_button[face=ceiling,facing=north,powered=false]");
+    }
+
+    fn mirrored(block: &str, axis: Axis) -> String {
+        let block = crate::utils::parse_block_state(block, block).unwrap();
+        Transform::mirror(axis).block_state(&block).to_string()
+    }
+
+    #[test]
+    fn test_mirror_block_properties() {
+        assert_eq!(mirrored("minecraft:oak_stairs[facing=east,half=bottom,shape=inner_left]", Axis::X),
+                   "minecraft:oak_stairs[facing=west,half=bottom,shape=inner_right]");
+        assert_eq!(mirrored("minecraft:oak_stairs[facing=east,half=bottom,shape=outer_left]", Axis::Y),
+                   "minecraft:oak_stairs[facing=east,half=top,shape=outer_left]");
+        assert_eq!(mirrored("minecraft:oak_door[facing=north,half=lower,hinge=left,open=false,powered=false]", Axis::Z),
+                   "minecraft:oak_door[facing=south,half=lower,hinge=right,open=false,powered=false]");
+        assert_eq!(mirrored("minecraft:oak_door[facing=north,half=lower,hinge=left,open=false,powered=false]", Axis::Y),
+                   "minecraft:oak_door[facing=north,half=upper,hinge=left,open=false,powered=false]");
+        assert_eq!(mirrored("minecraft:tall_grass[half=upper]", Axis::Y), "minecraft:tall_grass[half=lower]");
+        assert_eq!(mirrored("minecraft:stone_slab[type=bottom,waterlogged=false]", Axis::Y),
+                   "minecraft:stone_slab[type=top,waterlogged=false]");
+        assert_eq!(mirrored("minecraft:oak_sign[rotation=4,waterlogged=false]", Axis::X), "minecraft:oak_sign[rotation=12,waterlogged=false]");
+        assert_eq!(mirrored("minecraft:oak_sign[rotation=1,waterlogged=false]", Axis::Z), "minecraft:oak_sign[rotation=7,waterlogged=false]");
+        assert_eq!(mirrored("minecraft:chest[facing=north,type=left,waterlogged=false]", Axis::X),
+                   "minecraft:chest[facing=north,type=right,waterlogged=false]");
+        assert_eq!(mirrored("minecraft:rail[shape=north_east]", Axis::X), "minecraft:rail[shape=north_west]");
+    }
+
+    #[test]
+    fn test_rotate_entity() {
+        let mut entity = Entity::new("minecraft:armor_stand".to_string(), (1.5, 0.0, 0.5));
+        entity.nbt.insert("Rotation".to_string(), NbtValue::List(vec![NbtValue::Float(0.0), NbtValue::Float(10.0)]));
+        Transform::rotation(Axis::Y, 90).unwrap().entity(&mut entity);
+        assert_eq!(entity.position, (0.5, 0.0, 1.5));
+        assert_eq!(entity.nbt.get("Rotation"), Some(&NbtValue::List(vec![NbtValue::Float(90.0), NbtValue::Float(10.0)])));
+
+        Transform::mirror(Axis::X).entity(&mut entity);
+        assert_eq!(entity.position, (0.5, 0.0, 1.5));
+        assert_eq!(entity.nbt.get("Rotation"), Some(&NbtValue::List(vec![NbtValue::Float(-90.0), NbtValue::Float(10.0)])));
+    }
+}
@@ -2,23 +2,31 @@ use crate::block_entity::BlockEntity;
 use crate::block_position::BlockPosition;
 use crate::bounding_box::BoundingBox;
 use crate::chunk::Chunk;
+use crate::csg::{BooleanOperation, ConflictPolicy};
 use crate::entity::Entity;
 use crate::metadata::Metadata;
+use crate::pattern::{Mask, Pattern};
 use crate::region::Region;
+use crate::transform::{Axis, Transform};
 use crate::utils::NbtValue;
-use crate::utils::{parse_custom_name, parse_items_array, NbtMap};
-use crate::BlockState;
+use crate::utils::{block_string_syntax, compound_to_snbt, parse_block_nbt, parse_block_state, split_block_string, NbtMap};
+use crate::{BlockState, NucleationError};
 use quartz_nbt::{NbtCompound, NbtTag};
 use rand::SeedableRng;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct UniversalSchematic {
     pub metadata: Metadata,
     pub default_region: Region,
-    pub other_regions: HashMap<String, Region>,
+    pub other_regions: IndexMap<String, Region>,
     pub default_region_name: String,
+    /// Paste origin. Exports write the minimum corner relative to it as the Sponge `Offset`
+    /// and the WorldEdit `WEOffset`.
+    #[serde(default)]
+    pub anchor: Option<(i32, i32, i32)>,
     #[serde(skip, default = "HashMap::new")]
     block_state_cache: HashMap<String, BlockState>,
 }
@@ -56,14 +64,25 @@ impl UniversalSchematic {
                 ..Metadata::default()
             },
             default_region: Region::new(default_region_name.clone(), (0, 0, 0), (1, 1, 1)),
-            other_regions: HashMap::new(),
+            other_regions: IndexMap::new(),
             default_region_name,
+            anchor: None,
             block_state_cache: HashMap::new(),
         }
     }
 
-    pub fn get_all_regions(&self) -> HashMap<String, &Region> {
-        let mut all_regions = HashMap::new();
+    /// Schematic whose regions use sparse sectioned storage, for huge builds that are mostly
+    /// air. See [`Region::new_sparse`].
+    pub fn new_sparse(name: String) -> Self {
+        let mut schematic = UniversalSchematic::new(name);
+        schematic.default_region =
+            Region::new_sparse(schematic.default_region_name.clone(), (0, 0, 0), (1, 1, 1));
+        schematic
+    }
+
+    /// All regions keyed by name, the default region first and the others in insertion order
+    pub fn get_all_regions(&self) -> IndexMap<String, &Region> {
+        let mut all_regions = IndexMap::new();
         all_regions.insert(self.default_region_name.clone(), &self.default_region);
         all_regions.extend(
             self.other_regions
@@ -73,12 +92,106 @@ impl UniversalSchematic {
         all_regions
     }
 
+    /// Rotates every region around the center of block (0, 0, 0) by a multiple of 90 degrees,
+    /// clockwise when looking from the positive end of `axis`, so the regions keep their places
+    /// relative to each other. See [`Region::rotate`].
+    pub fn rotate(&mut self, axis: Axis, degrees: i32) -> Result<(), NucleationError> {
+        self.transform(&Transform::rotation(axis, degrees)?);
+        Ok(())
+    }
+
+    pub fn rotate_x(&mut self, degrees: i32) -> Result<(), NucleationError> {
+        self.rotate(Axis::X, degrees)
+    }
+
+    pub fn rotate_y(&mut self, degrees: i32) -> Result<(), NucleationError> {
+        self.rotate(Axis::Y, degrees)
+    }
+
+    pub fn rotate_z(&mut self, degrees: i32) -> Result<(), NucleationError> {
+        self.rotate(Axis::Z, degrees)
+    }
+
+    /// Flips every region along `axis` around the center of block 0, so x becomes -x for
+    /// `Axis::X`. See [`Region::mirror`].
+    pub fn mirror(&mut self, axis: Axis) {
+        self.transform(&Transform::mirror(axis));
+    }
+
+    /// Moves every region and the anchor. Only region positions change, no block data is copied.
+    pub fn translate(&mut self, dx: i32, dy: i32, dz: i32) {
+        self.transform(&Transform::translation((dx, dy, dz)));
+    }
+
+    /// Moves everything so the minimum corner of the bounding box is at (0, 0, 0) and returns
+    /// the translation that was applied.
+    pub fn normalize(&mut self) -> (i32, i32, i32) {
+        let min = self.get_bounding_box().min;
+        let offset = (-min.0, -min.1, -min.2);
+        self.translate(offset.0, offset.1, offset.2);
+        offset
+    }
+
+    /// Shrinks every region to its non-air blocks. See [`Region::trim`]. Regions without blocks
+    /// shrink to a single block inside the others, so they no longer add to the bounds.
+    pub fn trim(&mut self) {
+        self.trim_with(&[]);
+    }
+
+    /// Like [`UniversalSchematic::trim`], also treating the given blocks as air.
+    pub fn trim_with(&mut self, air_blocks: &[&str]) {
+        let regions: Vec<&mut Region> = std::iter::once(&mut self.default_region)
+            .chain(self.other_regions.values_mut())
+            .collect();
+        let tight: Vec<Option<BoundingBox>> = regions.iter()
+            .map(|region| region.tight_bounding_box(air_blocks))
+            .collect();
+        let corner = tight.iter().flatten()
+            .map(|bounds| bounds.min)
+            .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)));
+        for (region, bounds) in regions.into_iter().zip(tight) {
+            let bounds = bounds.unwrap_or_else(|| {
+                let corner = corner.unwrap_or(region.get_bounding_box().min);
+                BoundingBox::new(corner, corner)
+            });
+            region.crop(&bounds);
+        }
+    }
+
+    pub fn set_anchor(&mut self, x: i32, y: i32, z: i32) {
+        self.anchor = Some((x, y, z));
+    }
+
+    pub fn get_anchor(&self) -> Option<(i32, i32, i32)> {
+        self.anchor
+    }
+
+    /// Moves everything so the anchor, or the minimum corner without one, is at (0, 0, 0) and
+    /// returns the translation that was applied.
+    pub fn reorigin(&mut self) -> (i32, i32, i32) {
+        let origin = self.anchor.unwrap_or(self.get_bounding_box().min);
+        let offset = (-origin.0, -origin.1, -origin.2);
+        self.translate(offset.0, offset.1, offset.2);
+        offset
+    }
+
+    fn transform(&mut self, transform: &Transform) {
+        self.default_region.transform(transform);
+        for region in self.other_regions.values_mut() {
+            region.transform(transform);
+        }
+        self.anchor = self.anchor.map(|anchor| transform.apply(anchor));
+    }
+
     pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: BlockState) -> bool {
         // Check if the default region is empty and needs repositioning
         if self.default_region.is_empty() {
             // Reposition the default region to the first block's location
-            self.default_region =
-                Region::new(self.default_region_name.clone(), (x, y, z), (1, 1, 1));
+            self.default_region = self.default_region.new_like(
+                self.default_region_name.clone(),
+                (x, y, z),
+                (1, 1, 1),
+            );
         }
 
         self.default_region.set_block(x, y, z, block)
@@ -116,7 +229,9 @@ impl UniversalSchematic {
             let region = self
                 .other_regions
                 .entry(region_name.to_string())
-                .or_insert_with(|| Region::new(region_name.to_string(), (x, y, z), (1, 1, 1)));
+                .or_insert_with(|| {
+                    self.default_region.new_like(region_name.to_string(), (x, y, z), (1, 1, 1))
+                });
             region.set_block(x, y, z, block)
         }
     }
@@ -206,8 +321,7 @@ impl UniversalSchematic {
                 let block_state = BlockState::new(format!("minecraft:{}", name)).with_properties(
                     props
                         .iter()
-                        .map(|&(k, v)| (k.to_string(), v.to_string()))
-                        .collect(),
+                        .map(|&(k, v)| (k.to_string(), v.to_string())),
                 );
                 (c, block_state)
             })
@@ -290,7 +404,7 @@ impl UniversalSchematic {
                 .other_regions
                 .entry(region_name.to_string())
                 .or_insert_with(|| {
-                    Region::new(
+                    self.default_region.new_like(
                         region_name.to_string(),
                         (position.x, position.y, position.z),
                         (1, 1, 1),
@@ -306,14 +420,14 @@ impl UniversalSchematic {
         // Add blocks from default region
         let default_palette = self.default_region.get_palette();
         for block_index in &self.default_region.blocks {
-            blocks.push(default_palette[*block_index as usize].clone());
+            blocks.push(default_palette[block_index].clone());
         }
 
         // Add blocks from other regions
         for region in self.other_regions.values() {
             let region_palette = region.get_palette();
             for block_index in &region.blocks {
-                blocks.push(region_palette[*block_index as usize].clone());
+                blocks.push(region_palette[block_index].clone());
             }
         }
         blocks
@@ -354,17 +468,12 @@ impl UniversalSchematic {
         bounding_box.get_dimensions()
     }
 
-    pub fn get_json_string(&self) -> Result<String, String> {
+    pub fn get_json_string(&self) -> Result<String, NucleationError> {
         // Attempt to serialize the metadata
-        let metadata_json = serde_json::to_string(&self.metadata).map_err(|e| {
-            format!(
-                "Failed to serialize 'metadata' in UniversalSchematic: {}",
-                e
-            )
-        })?;
+        let metadata_json = serde_json::to_string(&self.metadata)?;
 
         // Create a temporary combined regions map for serialization
-        let mut combined_regions = HashMap::new();
+        let mut combined_regions = IndexMap::new();
         combined_regions.insert(
             self.default_region_name.clone(),
             self.default_region.clone(),
@@ -372,8 +481,7 @@ impl UniversalSchematic {
         combined_regions.extend(self.other_regions.clone());
 
         // Attempt to serialize the combined regions
-        let regions_json = serde_json::to_string(&combined_regions)
-            .map_err(|e| format!("Failed to serialize 'regions' in UniversalSchematic: {}", e))?;
+        let regions_json = serde_json::to_string(&combined_regions)?;
 
         // Combine everything into a single JSON object manually
         let combined_json = format!(
@@ -440,7 +548,7 @@ impl UniversalSchematic {
         if name == self.default_region_name {
             None // Cannot remove the default region
         } else {
-            self.other_regions.remove(name)
+            self.other_regions.shift_remove(name)
         }
     }
 
@@ -483,7 +591,7 @@ impl UniversalSchematic {
                 .other_regions
                 .entry(region_name.to_string())
                 .or_insert_with(|| {
-                    Region::new(region_name.to_string(), block_entity.position, (1, 1, 1))
+                    self.default_region.new_like(region_name.to_string(), block_entity.position, (1, 1, 1))
                 });
             region.add_block_entity(block_entity);
             true
@@ -527,7 +635,7 @@ impl UniversalSchematic {
                         entity.position.1.round() as i32,
                         entity.position.2.round() as i32,
                     );
-                    Region::new(region_name.to_string(), rounded_position, (1, 1, 1))
+                    self.default_region.new_like(region_name.to_string(), rounded_position, (1, 1, 1))
                 });
             region.add_entity(entity);
             true
@@ -570,27 +678,24 @@ impl UniversalSchematic {
             "DefaultRegion",
             NbtTag::String(self.default_region_name.clone()),
         );
+        if let Some((x, y, z)) = self.anchor {
+            root.insert("Anchor", NbtTag::IntArray(vec![x, y, z]));
+        }
 
         root
     }
 
-    pub fn from_nbt(nbt: NbtCompound) -> Result<Self, String> {
-        let metadata = Metadata::from_nbt(
-            nbt.get::<_, &NbtCompound>("Metadata")
-                .map_err(|e| format!("Failed to get Metadata: {}", e))?,
-        )?;
-
-        let regions_tag = nbt
-            .get::<_, &NbtCompound>("Regions")
-            .map_err(|e| format!("Failed to get Regions: {}", e))?;
-
-        let default_region_name = nbt
-            .get::<_, &str>("DefaultRegion")
-            .map_err(|e| format!("Failed to get DefaultRegion: {}", e))?
-            .to_string();
+    pub fn from_nbt(nbt: NbtCompound) -> Result<Self, NucleationError> {
+        let metadata = Metadata::from_nbt(nbt.get::<_, &NbtCompound>("Metadata")?)?;
+        let regions_tag = nbt.get::<_, &NbtCompound>("Regions")?;
+        let default_region_name = nbt.get::<_, &str>("DefaultRegion")?.to_string();
+        let anchor = match nbt.get::<_, &[i32]>("Anchor") {
+            Ok(&[x, y, z]) => Some((x, y, z)),
+            _ => None,
+        };
 
         let mut default_region = None;
-        let mut other_regions = HashMap::new();
+        let mut other_regions = IndexMap::new();
 
         for (region_name, region_tag) in regions_tag.inner() {
             if let NbtTag::Compound(region_compound) = region_tag {
@@ -603,13 +708,15 @@ impl UniversalSchematic {
             }
         }
 
-        let default_region = default_region.ok_or("Default region not found in NBT")?;
+        let default_region = default_region
+            .ok_or_else(|| NucleationError::RegionNotFound(default_region_name.clone()))?;
 
         Ok(UniversalSchematic {
             metadata,
             default_region,
             other_regions,
             default_region_name,
+            anchor,
             block_state_cache: HashMap::new(),
         })
     }
@@ -619,24 +726,24 @@ impl UniversalSchematic {
     }
 
     /// Swap the default region with another region by name
-    pub fn swap_default_region(&mut self, region_name: &str) -> Result<(), String> {
+    pub fn swap_default_region(&mut self, region_name: &str) -> Result<(), NucleationError> {
         if region_name == self.default_region_name {
             return Ok(()); // Already the default region
         }
 
-        if let Some(new_default) = self.other_regions.remove(region_name) {
+        if let Some((index, _, new_default)) = self.other_regions.shift_remove_full(region_name) {
             let old_default = std::mem::replace(&mut self.default_region, new_default);
             let old_default_name = self.default_region_name.clone();
 
             // Update the default region name
             self.default_region_name = region_name.to_string();
 
-            // Put the old default into other_regions
-            self.other_regions.insert(old_default_name, old_default);
+            // Put the old default into other_regions where the new default was
+            self.other_regions.shift_insert(index, old_default_name, old_default);
 
             Ok(())
         } else {
-            Err(format!("Region '{}' not found", region_name))
+            Err(NucleationError::RegionNotFound(region_name.to_string()))
         }
     }
 
@@ -658,11 +765,11 @@ impl UniversalSchematic {
         bounding_box
     }
 
-    pub fn to_schematic(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    pub fn to_schematic(&self) -> Result<Vec<u8>, NucleationError> {
         crate::formats::schematic::to_schematic(self)
     }
 
-    pub fn from_schematic(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn from_schematic(data: &[u8]) -> Result<Self, NucleationError> {
         crate::formats::schematic::from_schematic(data)
     }
 
@@ -685,13 +792,321 @@ impl UniversalSchematic {
         block_counts
     }
 
+    /// Sets every block of `bounds` to `pattern`. Returns how many blocks were set. Like the
+    /// other edit operations, the corners of `bounds` may be given in any order.
+    pub fn fill(&mut self, bounds: &BoundingBox, pattern: &Pattern) -> usize {
+        let bounds = BoundingBox::from_corners(bounds.min, bounds.max);
+        self.paint(&bounds, |_| true, &Mask::Any, pattern, true)
+    }
+
+    /// Sets the blocks of `bounds` that `mask` accepts to `pattern`.
+    pub fn replace(&mut self, bounds: &BoundingBox, mask: &Mask, pattern: &Pattern) -> usize {
+        let bounds = BoundingBox::from_corners(bounds.min, bounds.max);
+        self.paint(&bounds, |_| true, mask, pattern, true)
+    }
+
+    /// Sets the four vertical sides of `bounds` to `pattern`.
+    pub fn walls(&mut self, bounds: &BoundingBox, pattern: &Pattern) -> usize {
+        let bounds = BoundingBox::from_corners(bounds.min, bounds.max);
+        let (min, max) = (bounds.min, bounds.max);
+        let on_wall = |(x, _, z): (i32, i32, i32)| x == min.0 || x == max.0 || z == min.2 || z == max.2;
+        self.paint(&bounds, on_wall, &Mask::Any, pattern, true)
+    }
+
+    /// Sets all six faces of `bounds` to `pattern`.
+    pub fn outline(&mut self, bounds: &BoundingBox, pattern: &Pattern) -> usize {
+        let bounds = BoundingBox::from_corners(bounds.min, bounds.max);
+        let (min, max) = (bounds.min, bounds.max);
+        let on_face = |(x, y, z): (i32, i32, i32)| {
+            x == min.0 || x == max.0 || y == min.1 || y == max.1 || z == min.2 || z == max.2
+        };
+        self.paint(&bounds, on_face, &Mask::Any, pattern, true)
+    }
+
+    /// Hollows out the objects in `bounds` like WorldEdit's `//hollow`: blocks more than
+    /// `thickness` steps away from the air around them, or from the outside of `bounds`, are
+    /// set to air. Returns how many blocks were removed.
+    pub fn hollow(&mut self, bounds: &BoundingBox, thickness: u32) -> usize {
+        // Everything outside the regions is air connected to the outside, so only the regions
+        // and one layer of air around them need to be looked at
+        let regions = self.get_bounding_box();
+        let around = BoundingBox::new(
+            (regions.min.0 - 1, regions.min.1 - 1, regions.min.2 - 1),
+            (regions.max.0 + 1, regions.max.1 + 1, regions.max.2 + 1),
+        );
+        let Some(bounds) = BoundingBox::from_corners(bounds.min, bounds.max).intersection(&around) else {
+            return 0;
+        };
+        let bounds = &bounds;
+        let volume = bounds.volume() as usize;
+        let neighbours = |(x, y, z): (i32, i32, i32)| {
+            [(x - 1, y, z), (x + 1, y, z), (x, y - 1, z), (x, y + 1, z), (x, y, z - 1), (x, y, z + 1)]
+        };
+
+        // Air connected to the outside of the bounds
+        let mut queue: VecDeque<(i32, i32, i32)> = (0..volume)
+            .map(|index| bounds.index_to_coords(index))
+            .filter(|&position| neighbours(position).iter().any(|&neighbour| !bounds.contains(neighbour)))
+            .filter(|&(x, y, z)| self.is_air_at(x, y, z))
+            .collect();
+        let mut outside = vec![false; volume];
+        for &(x, y, z) in &queue {
+            outside[bounds.coords_to_index(x, y, z)] = true;
+        }
+        while let Some(position) = queue.pop_front() {
+            for (x, y, z) in neighbours(position) {
+                if bounds.contains((x, y, z)) {
+                    let index = bounds.coords_to_index(x, y, z);
+                    if !outside[index] && self.is_air_at(x, y, z) {
+                        outside[index] = true;
+                        queue.push_back((x, y, z));
+                    }
+                }
+            }
+        }
+
+        // Peel off `thickness` layers of shell, each touching the previous one or the outside
+        for _ in 0..thickness {
+            let layer: Vec<usize> = (0..volume)
+                .filter(|&index| !outside[index])
+                .filter(|&index| {
+                    neighbours(bounds.index_to_coords(index)).iter().any(|&(x, y, z)| {
+                        !bounds.contains((x, y, z)) || outside[bounds.coords_to_index(x, y, z)]
+                    })
+                })
+                .collect();
+            for index in layer {
+                outside[index] = true;
+            }
+        }
+
+        let air = BlockState::new("minecraft:air".to_string());
+        let interior = |(x, y, z): (i32, i32, i32)| !outside[bounds.coords_to_index(x, y, z)];
+        let solid = Mask::blocks(vec![air.clone()]).negate();
+        self.paint(bounds, interior, &solid, &Pattern::Block(air), false)
+    }
+
+    /// Places `pattern` on top of the highest block of every column of `bounds`, like
+    /// WorldEdit's `//overlay`. Columns without blocks are left alone.
+    pub fn overlay(&mut self, bounds: &BoundingBox, pattern: &Pattern) -> usize {
+        // Columns outside the regions have no blocks
+        let Some(bounds) = BoundingBox::from_corners(bounds.min, bounds.max).intersection(&self.get_bounding_box()) else {
+            return 0;
+        };
+        let mut tops = HashSet::new();
+        for x in bounds.min.0..=bounds.max.0 {
+            for z in bounds.min.2..=bounds.max.2 {
+                let top = (bounds.min.1..=bounds.max.1).rev().find(|&y| !self.is_air_at(x, y, z));
+                if let Some(y) = top {
+                    tops.insert((x, y + 1, z));
+                }
+            }
+        }
+        if tops.is_empty() {
+            return 0;
+        }
+        let above = BoundingBox::new(
+            (bounds.min.0, bounds.min.1 + 1, bounds.min.2),
+            (bounds.max.0, bounds.max.1 + 1, bounds.max.2),
+        );
+        self.paint(&above, |position| tops.contains(&position), &Mask::Any, pattern, true)
+    }
+
+    fn is_air_at(&self, x: i32, y: i32, z: i32) -> bool {
+        self.get_block(x, y, z).is_none_or(|block| block.name == "minecraft:air")
+    }
+
+    // Paints into every region overlapping `bounds`. With `grow` the default region is first
+    // grown to hold `bounds` if the mask picks blocks outside all regions, so those are painted
+    // too. Positional masks see the blocks as they were before painting
+    pub(crate) fn paint(
+        &mut self,
+        bounds: &BoundingBox,
+        shape: impl Fn((i32, i32, i32)) -> bool,
+        mask: &Mask,
+        pattern: &Pattern,
+        grow: bool,
+    ) -> usize {
+        let air = BlockState::new("minecraft:air".to_string());
+        if !mask.is_positional() {
+            let grow = grow && mask.matches(&air);
+            return self.paint_regions(bounds, &shape, mask, pattern, grow);
+        }
+
+        let selected: Vec<bool> = (0..bounds.volume() as usize)
+            .map(|index| bounds.index_to_coords(index))
+            .map(|position| shape(position) && mask.test_at(self, position, &air))
+            .collect();
+        let grow = grow && selected.iter().enumerate().any(|(index, &selected)| {
+            let (x, y, z) = bounds.index_to_coords(index);
+            selected && self.get_block(x, y, z).is_none()
+        });
+        let shape = |(x, y, z): (i32, i32, i32)| selected[bounds.coords_to_index(x, y, z)];
+        self.paint_regions(bounds, &shape, &Mask::Any, pattern, grow)
+    }
+
+    fn paint_regions(
+        &mut self,
+        bounds: &BoundingBox,
+        shape: &dyn Fn((i32, i32, i32)) -> bool,
+        mask: &Mask,
+        pattern: &Pattern,
+        grow: bool,
+    ) -> usize {
+        if grow {
+            if self.default_region.is_empty() {
+                // Start over at `bounds` rather than stretch the empty region to reach it
+                let (position, size) = bounds.to_position_and_size();
+                let mut region = self.default_region.new_like(self.default_region_name.clone(), position, size);
+                region.entities = std::mem::take(&mut self.default_region.entities);
+                region.block_entities = std::mem::take(&mut self.default_region.block_entities);
+                region.negative_size = self.default_region.negative_size;
+                self.default_region = region;
+            } else {
+                self.default_region.expand_to_contain(bounds);
+            }
+        }
+        let mut count = self.default_region.paint(bounds, shape, mask, pattern);
+        for region in self.other_regions.values_mut() {
+            count += region.paint(bounds, shape, mask, pattern);
+        }
+        count
+    }
+
+    /// Positions of the blocks `mask` accepts, air outside the regions excluded.
+    pub fn find_blocks(&self, mask: &Mask) -> Vec<BlockPosition> {
+        let air = BlockState::new("minecraft:air".to_string());
+        let mut found = Vec::new();
+        for region in std::iter::once(&self.default_region).chain(self.other_regions.values()) {
+            if mask.is_positional() || mask.matches(&air) {
+                let bounds = region.get_bounding_box();
+                for index in 0..bounds.volume() as usize {
+                    let (x, y, z) = bounds.index_to_coords(index);
+                    if mask.test_at(self, (x, y, z), &air) {
+                        found.push(BlockPosition { x, y, z });
+                    }
+                }
+            } else {
+                let accepted: Vec<bool> = region.palette.iter().map(|block| mask.matches(block)).collect();
+                for (index, block_index) in region.non_air_blocks() {
+                    if accepted[block_index] {
+                        let (x, y, z) = region.index_to_coords(index);
+                        found.push(BlockPosition { x, y, z });
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Number of blocks `mask` accepts, see [`UniversalSchematic::find_blocks`].
+    pub fn count_matching(&self, mask: &Mask) -> usize {
+        self.find_blocks(mask).len()
+    }
+
+    /// Combines this schematic with `other` moved by `offset`, see [`Region::combine`]. Each
+    /// region is combined on its own and keeps its name and bounds. Unions and xors add the
+    /// remaining blocks of each region of `other`, moved, to the region of the same name, or
+    /// as a new region; its default region goes to the default region. Nothing changes when
+    /// `policy` fails.
+    pub fn combine(
+        &mut self,
+        other: &UniversalSchematic,
+        offset: (i32, i32, i32),
+        operation: BooleanOperation,
+        policy: ConflictPolicy,
+    ) -> Result<(), NucleationError> {
+        let mine = self.get_merged_region();
+        let theirs = other.get_merged_region();
+        if policy == ConflictPolicy::Fail {
+            mine.clone().combine(&theirs, offset, BooleanOperation::Intersection, policy)?;
+        }
+        let mut regions: Vec<&mut Region> = std::iter::once(&mut self.default_region)
+            .chain(self.other_regions.values_mut())
+            .collect();
+
+        if matches!(operation, BooleanOperation::Intersection | BooleanOperation::Difference) {
+            for region in regions {
+                region.combine(&theirs, offset, operation, policy)?;
+            }
+            return Ok(());
+        }
+
+        // Where both sides have a block one of them is dropped, or both for xors, so the
+        // remaining blocks can be laid over each other. Unions keep every entity
+        let union = operation == BooleanOperation::Union;
+        let subtract = |region: &mut Region, from: &Region, offset: (i32, i32, i32)| {
+            let entities = union.then(|| region.entities.clone());
+            region.combine(from, offset, BooleanOperation::Difference, ConflictPolicy::Keep)
+                .expect("differences never conflict");
+            if let Some(entities) = entities {
+                region.entities = entities;
+            }
+        };
+        if !union || policy == ConflictPolicy::Replace {
+            for region in regions.iter_mut() {
+                subtract(region, &theirs, offset);
+            }
+        }
+        for region in std::iter::once(&other.default_region).chain(other.other_regions.values()) {
+            let mut region = region.clone();
+            region.translate(offset.0, offset.1, offset.2);
+            if !union || policy != ConflictPolicy::Replace {
+                subtract(&mut region, &mine, (0, 0, 0));
+            }
+            if region.name == other.default_region_name {
+                region.name = self.default_region_name.clone();
+            }
+            match self.get_region_mut(&region.name) {
+                Some(existing) => existing.merge(&region),
+                None if region.is_empty() && region.entities.is_empty() => {}
+                None => {
+                    self.other_regions.insert(region.name.clone(), region);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the blocks of `other` moved by `offset`, `policy` picks the block where both have one.
+    pub fn union(
+        &mut self,
+        other: &UniversalSchematic,
+        offset: (i32, i32, i32),
+        policy: ConflictPolicy,
+    ) -> Result<(), NucleationError> {
+        self.combine(other, offset, BooleanOperation::Union, policy)
+    }
+
+    /// Keeps only the blocks that `other` moved by `offset` also has, `policy` picks which of the two.
+    pub fn intersection(
+        &mut self,
+        other: &UniversalSchematic,
+        offset: (i32, i32, i32),
+        policy: ConflictPolicy,
+    ) -> Result<(), NucleationError> {
+        self.combine(other, offset, BooleanOperation::Intersection, policy)
+    }
+
+    /// Removes the blocks that `other` moved by `offset` has.
+    pub fn difference(&mut self, other: &UniversalSchematic, offset: (i32, i32, i32)) {
+        self.combine(other, offset, BooleanOperation::Difference, ConflictPolicy::Keep)
+            .expect("differences never conflict");
+    }
+
+    /// Keeps the blocks that only one of this schematic and `other` moved by `offset` has.
+    pub fn xor(&mut self, other: &UniversalSchematic, offset: (i32, i32, i32)) {
+        self.combine(other, offset, BooleanOperation::Xor, ConflictPolicy::Keep)
+            .expect("xors never conflict");
+    }
+
     pub fn copy_region(
         &mut self,
         from_schematic: &UniversalSchematic,
         bounds: &BoundingBox,
         target_position: (i32, i32, i32),
         excluded_blocks: &[BlockState],
-    ) -> Result<(), String> {
+    ) -> Result<(), NucleationError> {
         let offset = (
             target_position.0 - bounds.min.0,
             target_position.1 - bounds.min.1,
@@ -813,11 +1228,7 @@ impl UniversalSchematic {
         };
 
         // Process default region - skip air blocks for consistency with split_into_chunks_indices
-        for (index, &palette_index) in self.default_region.blocks.iter().enumerate() {
-            if palette_index == 0 {
-                continue; // Skip air blocks
-            }
-
+        for (index, _) in self.default_region.blocks.iter_non_zero() {
             let (x, y, z) = self.default_region.index_to_coords(index);
             let chunk_x = get_chunk_coord(x, chunk_width);
             let chunk_y = get_chunk_coord(y, chunk_height);
@@ -832,11 +1243,7 @@ impl UniversalSchematic {
 
         // Process other regions - skip air blocks for consistency with split_into_chunks_indices
         for region in self.other_regions.values() {
-            for (index, &palette_index) in region.blocks.iter().enumerate() {
-                if palette_index == 0 {
-                    continue; // Skip air blocks
-                }
-
+            for (index, _) in region.blocks.iter_non_zero() {
                 let (x, y, z) = region.index_to_coords(index);
                 let chunk_x = get_chunk_coord(x, chunk_width);
                 let chunk_y = get_chunk_coord(y, chunk_height);
@@ -868,7 +1275,7 @@ impl UniversalSchematic {
                 let (x, y, z) = self.default_region.index_to_coords(index);
                 Some((
                     BlockPosition { x, y, z },
-                    &self.default_region.palette[*block_index as usize],
+                    &self.default_region.palette[block_index],
                 ))
             },
         );
@@ -882,7 +1289,7 @@ impl UniversalSchematic {
                     let (x, y, z) = region.index_to_coords(index);
                     Some((
                         BlockPosition { x, y, z },
-                        &region.palette[*block_index as usize],
+                        &region.palette[block_index],
                     ))
                 })
         });
@@ -892,25 +1299,19 @@ impl UniversalSchematic {
 
     pub fn iter_blocks_indices(&self) -> impl Iterator<Item = (BlockPosition, usize)> + '_ {
         // Iterator for default region - returns palette indices directly
-        let default_iter = self.default_region.blocks.iter().enumerate().filter_map(
-            move |(index, &palette_index)| {
-                // Skip air blocks (usually index 0) to reduce data transfer
-                if palette_index == 0 {
-                    return None;
-                }
+        // Skip air blocks (usually index 0) to reduce data transfer
+        let default_iter = self.default_region.blocks.iter_non_zero().map(
+            move |(index, palette_index)| {
                 let (x, y, z) = self.default_region.index_to_coords(index);
-                Some((BlockPosition { x, y, z }, palette_index))
+                (BlockPosition { x, y, z }, palette_index)
             },
         );
 
         // Iterator for other regions
         let other_iter = self.other_regions.values().flat_map(|region| {
-            region.blocks.iter().enumerate().filter_map(move |(index, &palette_index)| {
-                if palette_index == 0 {
-                    return None;
-                }
+            region.blocks.iter_non_zero().map(move |(index, palette_index)| {
                 let (x, y, z) = region.index_to_coords(index);
-                Some((BlockPosition { x, y, z }, palette_index))
+                (BlockPosition { x, y, z }, palette_index)
             })
         });
 
@@ -1016,11 +1417,7 @@ impl UniversalSchematic {
         };
 
         // Process default region
-        for (index, &palette_index) in self.default_region.blocks.iter().enumerate() {
-            if palette_index == 0 {
-                continue; // Skip air blocks
-            }
-
+        for (index, palette_index) in self.default_region.blocks.iter_non_zero() {
             let (x, y, z) = self.default_region.index_to_coords(index);
             let chunk_x = get_chunk_coord(x, chunk_width);
             let chunk_y = get_chunk_coord(y, chunk_height);
@@ -1035,11 +1432,7 @@ impl UniversalSchematic {
 
         // Process other regions
         for region in self.other_regions.values() {
-            for (index, &palette_index) in region.blocks.iter().enumerate() {
-                if palette_index == 0 {
-                    continue; // Skip air blocks
-                }
-
+            for (index, palette_index) in region.blocks.iter_non_zero() {
                 let (x, y, z) = region.index_to_coords(index);
                 let chunk_x = get_chunk_coord(x, chunk_width);
                 let chunk_y = get_chunk_coord(y, chunk_height);
@@ -1091,11 +1484,7 @@ impl UniversalSchematic {
             offset_x, offset_y, offset_z,
             offset_x + width, offset_y + height, offset_z + length
         ) {
-            for (index, &palette_index) in self.default_region.blocks.iter().enumerate() {
-                if palette_index == 0 {
-                    continue; // Skip air
-                }
-
+            for (index, palette_index) in self.default_region.blocks.iter_non_zero() {
                 let (x, y, z) = self.default_region.index_to_coords(index);
                 if x >= offset_x && x < offset_x + width &&
                     y >= offset_y && y < offset_y + height &&
@@ -1111,11 +1500,7 @@ impl UniversalSchematic {
                 offset_x, offset_y, offset_z,
                 offset_x + width, offset_y + height, offset_z + length
             ) {
-                for (index, &palette_index) in region.blocks.iter().enumerate() {
-                    if palette_index == 0 {
-                        continue; // Skip air
-                    }
-
+                for (index, palette_index) in region.blocks.iter_non_zero() {
                     let (x, y, z) = region.index_to_coords(index);
                     if x >= offset_x && x < offset_x + width &&
                         y >= offset_y && y < offset_y + height &&
@@ -1254,7 +1639,7 @@ impl UniversalSchematic {
         y: i32,
         z: i32,
         block_string: &str,
-    ) -> Result<bool, String> {
+    ) -> Result<bool, NucleationError> {
         let (block_state, nbt_data) = Self::parse_block_string(block_string)?;
 
         // Set the basic block first
@@ -1277,6 +1662,16 @@ impl UniversalSchematic {
         Ok(true)
     }
 
+    /// Formats the block at a position as a block string with its block entity data as SNBT,
+    /// the inverse of `set_block_from_string` and usable in `/setblock`.
+    pub fn get_block_string(&self, x: i32, y: i32, z: i32) -> Option<String> {
+        let block = self.get_block(x, y, z)?;
+        match self.get_block_entity(BlockPosition { x, y, z }) {
+            Some(block_entity) => Some(format!("{}{}", block, compound_to_snbt(&block_entity.nbt))),
+            None => Some(block.to_string()),
+        }
+    }
+
     /// Parses a block string into its components (block state and optional NBT data)
     fn calculate_items_for_signal(signal_strength: u8) -> u32 {
         if signal_strength == 0 {
@@ -1322,72 +1717,32 @@ impl UniversalSchematic {
     /// Parse a block string into its components, handling special signal strength case
     pub fn parse_block_string(
         block_string: &str,
-    ) -> Result<(BlockState, Option<HashMap<String, NbtValue>>), String> {
-        let mut parts = block_string.splitn(2, '{');
-        let block_state_str = parts.next().unwrap().trim();
-        let nbt_str = parts.next().map(|s| s.trim_end_matches('}'));
-
-        // Parse block state
-        let block_state = if block_state_str.contains('[') {
-            let mut state_parts = block_state_str.splitn(2, '[');
-            let block_name = state_parts.next().unwrap();
-            let properties_str = state_parts
-                .next()
-                .ok_or("Missing properties closing bracket")?
-                .trim_end_matches(']');
-
-            let mut properties = HashMap::new();
-            for prop in properties_str.split(',') {
-                let mut kv = prop.split('=');
-                let key = kv.next().ok_or("Missing property key")?.trim();
-                let value = kv
-                    .next()
-                    .ok_or("Missing property value")?
-                    .trim()
-                    .trim_matches(|c| c == '\'' || c == '"');
-                properties.insert(key.to_string(), value.to_string());
-            }
-
-            BlockState::new(block_name.to_string()).with_properties(properties)
-        } else {
-            BlockState::new(block_state_str.to_string())
-        };
+    ) -> Result<(BlockState, Option<NbtMap>), NucleationError> {
+        let (block_state_str, nbt_str) = split_block_string(block_string);
+        let block_state = parse_block_state(block_string, block_state_str)?;
 
         // Parse NBT data if present
-        let nbt_data = if let Some(nbt_str) = nbt_str {
-            let mut nbt_map = HashMap::new();
-
-            // Check for signal strength specification
-            if block_state.get_name() == "minecraft:barrel" && nbt_str.contains("signal=") {
-                if let Some(signal_str) = nbt_str.split('=').nth(1) {
-                    let signal_strength: u8 = signal_str
-                        .trim()
-                        .parse()
-                        .map_err(|_| "Invalid signal strength value")?;
-
-                    if signal_strength > 15 {
-                        return Err("Signal strength must be between 0 and 15".to_string());
-                    }
+        let nbt_data = match nbt_str {
+            // Barrels take a `{signal=N}` shorthand that fills them for a comparator output
+            Some((nbt_start, nbt_str)) if block_state.get_name() == "minecraft:barrel" && nbt_str.contains("signal=") => {
+                let signal_pos = nbt_start + nbt_str.find('=').unwrap() + 1;
+                let signal_str = nbt_str.split('=').nth(1).unwrap().trim_end_matches('}');
+                let signal_strength: u8 = signal_str
+                    .trim()
+                    .parse()
+                    .map_err(|_| block_string_syntax(signal_pos, "Invalid signal strength value"))?;
 
-                    let items = Self::create_barrel_items_nbt(signal_strength);
-                    nbt_map.insert("Items".to_string(), NbtValue::List(items));
-                }
-            } else {
-                // Handle regular NBT parsing
-                if nbt_str.contains("Items:[") {
-                    let items = parse_items_array(nbt_str)?;
-                    nbt_map.insert("Items".to_string(), NbtValue::List(items));
+                if signal_strength > 15 {
+                    return Err(block_string_syntax(signal_pos, "Signal strength must be between 0 and 15"));
                 }
 
-                if nbt_str.contains("CustomName:") {
-                    let name = parse_custom_name(nbt_str)?;
-                    nbt_map.insert("CustomName".to_string(), NbtValue::String(name));
-                }
+                let mut nbt_map = NbtMap::new();
+                let items = Self::create_barrel_items_nbt(signal_strength);
+                nbt_map.insert("Items".to_string(), NbtValue::List(items));
+                Some(nbt_map)
             }
-
-            Some(nbt_map)
-        } else {
-            None
+            Some((nbt_start, nbt_str)) => Some(parse_block_nbt(nbt_start, nbt_str)?),
+            None => None,
         };
 
         Ok((block_state, nbt_data))
@@ -1511,6 +1866,300 @@ mod tests {
     use quartz_nbt::io::{read_nbt, write_nbt};
     use std::io::Cursor;
 
+    #[test]
+    fn test_rotate_schematic() {
+        let mut schematic = UniversalSchematic::new("Rotate".to_string());
+        schematic.set_block_str(0, 0, 0, "minecraft:stone");
+        schematic.set_block_from_string(3, 0, 0, "minecraft:oak_stairs[facing=north,half=bottom,shape=straight]").unwrap();
+        schematic.set_block_entity(BlockPosition { x: 3, y: 0, z: 0 }, BlockEntity::new("minecraft:chest".to_string(), (3, 0, 0)));
+        schematic.add_entity(Entity::new("minecraft:pig".to_string(), (3.5, 1.0, 0.5)));
+        let original = schematic.clone();
+
+        schematic.rotate_y(90).unwrap();
+        assert_eq!(schematic.get_block(0, 0, 3).unwrap().to_string(), "minecraft:oak_stairs[facing=east,half=bottom,shape=straight]");
+        assert_eq!(schematic.get_block(0, 0, 0).unwrap().name, "minecraft:stone");
+        assert!(schematic.get_block_entity(BlockPosition { x: 0, y: 0, z: 3 }).is_some());
+        assert_eq!(schematic.default_region.entities[0].position, (0.5, 1.0, 3.5));
+        assert!(schematic.rotate_y(45).is_err());
+
+        schematic.rotate_y(270).unwrap();
+        schematic.rotate_x(180).unwrap();
+        schematic.rotate_x(180).unwrap();
+        for (position, block) in original.iter_blocks() {
+            assert_eq!(schematic.get_block(position.x, position.y, position.z), Some(block));
+        }
+        assert_eq!(schematic.default_region.entities, original.default_region.entities);
+    }
+
+    #[test]
+    fn test_mirror_schematic() {
+        let mut schematic = UniversalSchematic::new("Mirror".to_string());
+        schematic.set_block_from_string(2, 0, 1, "minecraft:oak_stairs[facing=east,half=bottom,shape=inner_left]").unwrap();
+        schematic.set_block_entity(BlockPosition { x: 2, y: 0, z: 1 }, BlockEntity::new("minecraft:chest".to_string(), (2, 0, 1)));
+        schematic.add_entity(Entity::new("minecraft:pig".to_string(), (2.25, 0.0, 1.5)));
+
+        schematic.mirror(Axis::X);
+        assert_eq!(schematic.get_block(-2, 0, 1).unwrap().to_string(), "minecraft:oak_stairs[facing=west,half=bottom,shape=inner_right]");
+        assert!(schematic.get_block_entity(BlockPosition { x: -2, y: 0, z: 1 }).is_some());
+        assert_eq!(schematic.default_region.entities[0].position, (-1.25, 0.0, 1.5));
+
+        schematic.mirror(Axis::Y);
+        assert_eq!(schematic.get_block(-2, 0, 1).unwrap().to_string(), "minecraft:oak_stairs[facing=west,half=top,shape=inner_right]");
+    }
+
+    #[test]
+    fn test_edit_operations() {
+        let stone = Pattern::block(BlockState::new("minecraft:stone".to_string()));
+        let glass = Pattern::block(BlockState::new("minecraft:glass".to_string()));
+        let name = |schematic: &UniversalSchematic, x, y, z| {
+            schematic.get_block(x, y, z).map(|block| block.name.clone()).unwrap_or_default()
+        };
+
+        let mut schematic = UniversalSchematic::new("Edit".to_string());
+        let cube = BoundingBox::new((10, 0, 10), (14, 4, 14));
+        assert_eq!(schematic.fill(&cube, &stone), 125);
+        assert_eq!(schematic.get_bounding_box(), cube);
+
+        // The middle 3x3x3 is more than one block from the outside
+        assert_eq!(schematic.hollow(&cube, 1), 27);
+        assert_eq!(name(&schematic, 12, 2, 12), "minecraft:air");
+        assert_eq!(name(&schematic, 11, 1, 11), "minecraft:air");
+        assert_eq!(name(&schematic, 10, 2, 12), "minecraft:stone");
+        assert_eq!(schematic.default_region.count_non_air_blocks(), 98);
+
+        let stone_mask = Mask::blocks(vec![BlockState::new("stone".to_string())]);
+        assert_eq!(schematic.replace(&BoundingBox::new((10, 4, 10), (14, 4, 14)), &stone_mask, &glass), 25);
+        assert_eq!(name(&schematic, 12, 4, 12), "minecraft:glass");
+
+        assert_eq!(schematic.overlay(&cube, &stone), 25);
+        assert_eq!(name(&schematic, 12, 5, 12), "minecraft:stone");
+        assert_eq!(schematic.get_bounding_box().max.1, 5);
+
+        let mut frame = UniversalSchematic::new("Frame".to_string());
+        let room = BoundingBox::new((0, 0, 0), (4, 3, 5));
+        assert_eq!(frame.walls(&room, &glass), 2 * (5 + 4) * 4);
+        assert_eq!(name(&frame, 2, 1, 2), "minecraft:air");
+        assert_eq!(frame.outline(&room, &stone), 5 * 4 * 6 - 3 * 2 * 4);
+        assert_eq!(name(&frame, 0, 1, 2), "minecraft:stone");
+        assert_eq!(name(&frame, 2, 0, 2), "minecraft:stone");
+        assert_eq!(name(&frame, 2, 1, 2), "minecraft:air");
+    }
+
+    #[test]
+    fn test_edit_operations_with_swapped_corners() {
+        let stone = Pattern::block(BlockState::new("minecraft:stone".to_string()));
+        let mut schematic = UniversalSchematic::new("Edit".to_string());
+        let cube = BoundingBox::new((14, 4, 14), (10, 0, 10));
+        assert_eq!(schematic.fill(&cube, &stone), 125);
+        assert_eq!(schematic.get_bounding_box(), BoundingBox::new((10, 0, 10), (14, 4, 14)));
+        assert_eq!(schematic.hollow(&cube, 1), 27);
+
+        // Only the blocks near the regions are looked at, however large the box
+        let huge = BoundingBox::new((1 << 20, 1 << 20, 1 << 20), (-(1 << 20), -(1 << 20), -(1 << 20)));
+        assert_eq!(schematic.hollow(&huge, 1), 0);
+        assert_eq!(schematic.overlay(&huge, &stone), 25);
+        assert_eq!(schematic.default_region.count_non_air_blocks(), 98 + 25);
+
+        // An empty default region is moved to the filled blocks with its entities
+        let mut schematic = UniversalSchematic::new("Edit".to_string());
+        schematic.add_entity(Entity::new("minecraft:pig".to_string(), (0.5, 0.0, 0.5)));
+        schematic.default_region.negative_size = (true, false, false);
+        schematic.fill(&BoundingBox::new((100, 0, 0), (101, 0, 0)), &stone);
+        assert_eq!(schematic.default_region.position, (100, 0, 0));
+        assert_eq!(schematic.default_region.entities.len(), 1);
+        assert_eq!(schematic.default_region.negative_size, (true, false, false));
+    }
+
+    #[test]
+    fn test_edit_with_expressions() {
+        let mut schematic = UniversalSchematic::new("Expressions".to_string());
+        let ground = BoundingBox::new((0, 0, 0), (9, 0, 9));
+        schematic.fill(&ground, &Pattern::parse("grass_block").unwrap());
+        schematic.fill(&BoundingBox::new((0, 0, 0), (0, 0, 9)), &Pattern::parse("water").unwrap());
+
+        // Above the grass, outside of the region the fill made
+        let above = BoundingBox::new((0, 1, 0), (9, 1, 9));
+        let flowers = schematic.replace(&above, &Mask::parse(">grass_block").unwrap(), &Pattern::parse("poppy").unwrap());
+        assert_eq!(flowers, 90);
+        assert_eq!(schematic.get_block(5, 1, 5).unwrap().name, "minecraft:poppy");
+        assert_eq!(schematic.get_block(0, 1, 5).unwrap().name, "minecraft:air");
+
+        // Shore: grass next to water, masks see the blocks from before the replace
+        let sand = schematic.replace(&ground, &Mask::parse("grass_block ~water").unwrap(), &Pattern::parse("sand").unwrap());
+        assert_eq!(sand, 10);
+        assert_eq!(schematic.count_matching(&Mask::parse("sand").unwrap()), 10);
+        assert_eq!(schematic.find_blocks(&Mask::parse("water").unwrap()).len(), 10);
+        assert!(schematic.find_blocks(&Mask::parse("#sand").unwrap()).iter().all(|position| position.x == 1));
+
+        // Seeded random patterns paint the same blocks every time
+        let pattern = Pattern::parse("50%stone,50%andesite").unwrap().with_seed(42);
+        let mut a = UniversalSchematic::new("A".to_string());
+        let mut b = UniversalSchematic::new("B".to_string());
+        a.fill(&ground, &pattern);
+        b.fill(&ground, &pattern);
+        assert_eq!(a.find_blocks(&Mask::parse("stone").unwrap()), b.find_blocks(&Mask::parse("stone").unwrap()));
+        assert!(a.count_matching(&Mask::parse("stone").unwrap()) > 20);
+        assert!(a.count_matching(&Mask::parse("andesite").unwrap()) > 20);
+    }
+
+    #[test]
+    fn test_boolean_operations() {
+        let stone = Pattern::parse("stone").unwrap();
+        let glass = Pattern::parse("glass").unwrap();
+        let cube = |pattern: &Pattern| {
+            let mut schematic = UniversalSchematic::new("Cube".to_string());
+            schematic.fill(&BoundingBox::new((0, 0, 0), (3, 3, 3)), pattern);
+            schematic
+        };
+        let name = |schematic: &UniversalSchematic, x, y, z| {
+            schematic.get_block(x, y, z).map(|block| block.name.clone()).unwrap_or_default()
+        };
+        let solid = |schematic: &UniversalSchematic| schematic.count_matching(&Mask::parse("!air").unwrap());
+
+        let mut other = cube(&glass);
+        other.set_block_entity(BlockPosition { x: 3, y: 3, z: 3 }, BlockEntity::new("minecraft:chest".to_string(), (3, 3, 3)));
+        other.add_entity(Entity::new("minecraft:pig".to_string(), (0.5, 0.0, 0.5)));
+        other.add_entity(Entity::new("minecraft:cow".to_string(), (3.5, 0.0, 3.5)));
+
+        // Overlap of 2x2x2 between the cubes
+        let mut union = cube(&stone);
+        union.union(&other, (2, 2, 2), ConflictPolicy::Keep).unwrap();
+        assert_eq!(solid(&union), 2 * 64 - 8);
+        assert_eq!(union.get_bounding_box(), BoundingBox::new((0, 0, 0), (5, 5, 5)));
+        assert_eq!(name(&union, 2, 2, 2), "minecraft:stone");
+        assert_eq!(name(&union, 5, 5, 5), "minecraft:glass");
+        assert!(union.get_block_entity(BlockPosition { x: 5, y: 5, z: 5 }).is_some());
+        assert_eq!(union.default_region.entities.len(), 2);
+        assert_eq!(union.default_region.entities[0].position, (2.5, 2.0, 2.5));
+
+        let mut replaced = cube(&stone);
+        replaced.union(&other, (2, 2, 2), ConflictPolicy::Replace).unwrap();
+        assert_eq!(name(&replaced, 2, 2, 2), "minecraft:glass");
+        assert!(cube(&stone).union(&other, (2, 2, 2), ConflictPolicy::Fail).is_err());
+        assert!(cube(&glass).union(&other, (2, 2, 2), ConflictPolicy::Fail).is_ok());
+
+        let mut intersection = cube(&stone);
+        intersection.intersection(&other, (2, 2, 2), ConflictPolicy::Replace).unwrap();
+        assert_eq!(solid(&intersection), 8);
+        assert_eq!(name(&intersection, 3, 3, 3), "minecraft:glass");
+        assert_eq!(name(&intersection, 1, 1, 1), "minecraft:air");
+        assert!(intersection.get_block_entity(BlockPosition { x: 3, y: 3, z: 3 }).is_none());
+        // The pig stands on the overlap, the cow outside of it
+        assert_eq!(intersection.default_region.entities.len(), 1);
+
+        let mut difference = cube(&stone);
+        difference.add_entity(Entity::new("minecraft:sheep".to_string(), (3.5, 3.0, 3.5)));
+        difference.difference(&other, (2, 2, 2));
+        assert_eq!(solid(&difference), 64 - 8);
+        assert_eq!(name(&difference, 3, 3, 3), "minecraft:air");
+        assert_eq!(difference.get_bounding_box(), BoundingBox::new((0, 0, 0), (3, 3, 3)));
+        assert!(difference.default_region.entities.is_empty());
+
+        let mut xor = cube(&stone);
+        xor.xor(&other, (2, 2, 2));
+        assert_eq!(solid(&xor), 2 * 64 - 2 * 8);
+        assert_eq!(name(&xor, 2, 2, 2), "minecraft:air");
+        assert_eq!(name(&xor, 4, 4, 4), "minecraft:glass");
+        assert_eq!(xor.default_region.entities.len(), 1);
+    }
+
+    #[test]
+    fn test_boolean_operations_keep_regions() {
+        let stone = Pattern::parse("stone").unwrap();
+        let glass = Pattern::parse("glass").unwrap();
+        let mut schematic = UniversalSchematic::new("Regions".to_string());
+        schematic.fill(&BoundingBox::new((0, 0, 0), (3, 0, 0)), &stone);
+        let mut tower = Region::new("Tower".to_string(), (0, 1, 0), (1, 4, 1));
+        tower.set_block(0, 1, 0, BlockState::new("minecraft:stone".to_string()));
+        schematic.add_region(tower);
+
+        let mut other = UniversalSchematic::new("Other".to_string());
+        other.fill(&BoundingBox::new((0, 0, 0), (3, 0, 0)), &glass);
+        let mut bridge = Region::new("Bridge".to_string(), (0, 0, 1), (4, 1, 1));
+        bridge.set_block(0, 0, 1, BlockState::new("minecraft:glass".to_string()));
+        other.add_region(bridge);
+
+        let mut union = schematic.clone();
+        union.union(&other, (2, 0, 0), ConflictPolicy::Keep).unwrap();
+        assert_eq!(union.get_region_names(), ["Main", "Tower", "Bridge"]);
+        assert_eq!(union.get_region("Tower").unwrap().get_bounding_box(), BoundingBox::new((0, 1, 0), (0, 4, 0)));
+        assert_eq!(union.get_region("Bridge").unwrap().get_bounding_box(), BoundingBox::new((2, 0, 1), (5, 0, 1)));
+        assert_eq!(union.get_block(3, 0, 0).unwrap().name, "minecraft:stone");
+        assert_eq!(union.get_block(5, 0, 0).unwrap().name, "minecraft:glass");
+
+        let mut difference = schematic.clone();
+        difference.difference(&other, (0, 1, 0));
+        assert_eq!(difference.get_region_names(), ["Main", "Tower"]);
+        assert_eq!(difference.get_block(0, 1, 0).unwrap().name, "minecraft:air");
+        assert_eq!(difference.get_block(1, 0, 0).unwrap().name, "minecraft:stone");
+    }
+
+    #[test]
+    fn test_translate_and_normalize() {
+        let mut schematic = UniversalSchematic::new_sparse("Move".to_string());
+        schematic.set_block_str(5, 10, -20, "minecraft:stone");
+        schematic.set_block_str(8, 12, -18, "minecraft:glass");
+        schematic.set_block_in_region("Other", 30, 10, -20, BlockState::new("minecraft:dirt".to_string()));
+        schematic.set_block_entity(BlockPosition { x: 5, y: 10, z: -20 }, BlockEntity::new("minecraft:chest".to_string(), (5, 10, -20)));
+        schematic.add_entity(Entity::new("minecraft:pig".to_string(), (6.5, 11.0, -19.5)));
+        schematic.set_anchor(6, 10, -20);
+
+        schematic.translate(1, -2, 3);
+        assert_eq!(schematic.get_block(6, 8, -17).unwrap().name, "minecraft:stone");
+        assert_eq!(schematic.get_block(31, 8, -17).unwrap().name, "minecraft:dirt");
+        assert!(schematic.get_block_entity(BlockPosition { x: 6, y: 8, z: -17 }).is_some());
+        assert_eq!(schematic.default_region.entities[0].position, (7.5, 9.0, -16.5));
+        assert_eq!(schematic.get_anchor(), Some((7, 8, -17)));
+
+        assert_eq!(schematic.normalize(), (-6, -8, 17));
+        assert_eq!(schematic.get_bounding_box().min, (0, 0, 0));
+        assert_eq!(schematic.get_block(3, 2, 2).unwrap().name, "minecraft:glass");
+
+        assert_eq!(schematic.reorigin(), (-1, 0, 0));
+        assert_eq!(schematic.get_anchor(), Some((0, 0, 0)));
+        assert_eq!(schematic.get_block(-1, 0, 0).unwrap().name, "minecraft:stone");
+    }
+
+    #[test]
+    fn test_sparse_schematic() {
+        let mut schematic = UniversalSchematic::new_sparse("Sparse".to_string());
+        let stone = BlockState::new("minecraft:stone".to_string());
+        schematic.set_block(-100, 0, 0, stone.clone());
+        schematic.set_block(99, 40, 7, BlockState::new("minecraft:glass".to_string()));
+        schematic.set_block_in_region("Other", 3, 3, 3, stone.clone());
+
+        let region = &schematic.default_region;
+        assert!(region.is_sparse());
+        assert!(schematic.other_regions["Other"].is_sparse());
+        assert_eq!(region.get_bounding_box(), BoundingBox::new((-100, 0, 0), (99, 40, 7)));
+        let crate::BlockStorage::Sectioned(storage) = &region.blocks else { panic!("expected sectioned storage") };
+        assert_eq!(storage.section_count(), 2);
+        assert_eq!(region.count_blocks(), 2);
+
+        assert_eq!(schematic.get_block(-100, 0, 0), Some(&stone));
+        assert_eq!(schematic.get_block(0, 20, 3).map(|b| b.name.as_str()), Some("minecraft:air"));
+        assert_eq!(schematic.iter_blocks_indices().count(), 3);
+
+        // Exports the same blocks as the dense equivalent
+        let mut dense = UniversalSchematic::new("Sparse".to_string());
+        for (position, block) in schematic.iter_blocks().filter(|(_, block)| block.name != "minecraft:air") {
+            dense.set_block(position.x, position.y, position.z, block.clone());
+        }
+        let non_air = |schematic: &UniversalSchematic| {
+            let bytes = crate::schematic::to_schematic(schematic).unwrap();
+            let loaded = crate::schematic::from_schematic(&bytes).unwrap();
+            let mut blocks: Vec<_> = loaded.iter_blocks()
+                .filter(|(_, block)| block.name != "minecraft:air")
+                .map(|(position, block)| ((position.x, position.y, position.z), block.to_string()))
+                .collect();
+            blocks.sort();
+            blocks
+        };
+        let exported = non_air(&schematic);
+        assert_eq!(exported.len(), 3);
+        assert_eq!(exported, non_air(&dense));
+    }
+
     #[test]
     fn test_schematic_operations() {
         let mut schematic = UniversalSchematic::new("Test Schematic".to_string());
@@ -1962,6 +2611,12 @@ mod tests {
         } else {
             panic!("Expected list of items");
         }
+
+        assert_eq!(schematic.get_block_string(0, 0, 0).unwrap(), "minecraft:stone");
+        assert_eq!(
+            schematic.get_block_string(2, 0, 0).unwrap(),
+            r#"minecraft:barrel[facing=up]{CustomName:Storage,Items:[{Count:64b,Slot:0b,id:"minecraft:redstone"}]}"#
+        );
     }
 
     #[test]
@@ -1,217 +1,136 @@
 use std::collections::HashMap;
 use serde_json::Value;
-use crate::BlockState;
+use crate::{BlockState, NucleationError};
 use crate::utils::{NbtValue, NbtMap};
+use crate::utils::snbt::{parse_snbt, parse_snbt_compound, parse_snbt_compound_at};
 
-pub fn parse_block_string(block_string: &str) -> Result<(BlockState, Option<NbtMap>), String> {
-    let mut parts = block_string.splitn(2, '{');
-    let block_state_str = parts.next().unwrap().trim();
-    let nbt_str = parts.next().map(|s| s.trim_end_matches('}'));
+pub fn parse_block_string(block_string: &str) -> Result<(BlockState, Option<NbtMap>), NucleationError> {
+    let (block_state_str, nbt_str) = split_block_string(block_string);
+    let block_state = parse_block_state(block_string, block_state_str)?;
 
-    // Parse block state
-    let block_state = if block_state_str.contains('[') {
-        let mut state_parts = block_state_str.splitn(2, '[');
-        let block_name = state_parts.next().unwrap();
-        let properties_str = state_parts.next()
-            .ok_or("Missing properties closing bracket")?
-            .trim_end_matches(']');
-
-        let mut properties = HashMap::new();
-        for prop in properties_str.split(',') {
-            let mut kv = prop.split('=');
-            let key = kv.next().ok_or("Missing property key")?.trim();
-            let value = kv.next().ok_or("Missing property value")?.trim()
-                .trim_matches(|c| c == '\'' || c == '"');
-            properties.insert(key.to_string(), value.to_string());
-        }
-
-        BlockState::new(block_name.to_string()).with_properties(properties)
-    } else {
-        BlockState::new(block_state_str.to_string())
-    };
-
-    // Parse NBT data if present
-    let nbt_data = if let Some(nbt_str) = nbt_str {
-        let mut nbt_map = NbtMap::new();
-
-        // Parse Items array if present
-        if nbt_str.contains("Items:[") {
-            let items = parse_items_array(nbt_str)?;
-            nbt_map.insert("Items".to_string(), NbtValue::List(items));
-        }
-
-        // Parse CustomName if present
-        if nbt_str.contains("CustomName:") {
-            let name = parse_custom_name(nbt_str)?;
-            nbt_map.insert("CustomName".to_string(), NbtValue::String(name));
-        }
-
-        Some(nbt_map)
-    } else {
-        None
+    let nbt_data = match nbt_str {
+        Some((nbt_start, nbt_str)) => Some(parse_block_nbt(nbt_start, nbt_str)?),
+        None => None,
     };
 
     Ok((block_state, nbt_data))
 }
 
-pub fn parse_items_array(nbt_str: &str) -> Result<Vec<NbtValue>, String> {
-
-    // Find the Items array
-    let items_start = nbt_str.find("Items:[")
-        .ok_or("Missing Items array")?;
-    let array_start = items_start + "Items:".len();
-
-    // Extract the array content
-    let array_str = extract_balanced_substring(&nbt_str[array_start..], '[', ']')
-        .ok_or("Malformed Items array")?;
-
-    // Remove outer brackets
-    let items_content = array_str[1..array_str.len()-1].trim();
-
-    let mut items = Vec::new();
-    for item_str in split_items(items_content) {
-        let mut item_nbt = NbtMap::new();
-
-        // Parse each property
-        let clean_item_str = item_str.trim_matches(|c| c == '{' || c == '}');
-
-        for prop in clean_item_str.split(',') {
-            let prop = prop.trim();
-
-            let (key_part, value_part) = prop.split_once(':')
-                .ok_or_else(|| format!("Invalid property format: '{}'", prop))?;
-            // Trim whitespace and quotes from the key
-            let key = key_part.trim().trim_matches(|c| c == '"' || c == '\'');
-            let value = value_part.trim();
+/// Parses the SNBT compound of a block string, reporting errors at their byte offset in the
+/// block string. Item counts and slots written as strings and JSON custom names are
+/// normalized the way older block strings used them.
+pub(crate) fn parse_block_nbt(nbt_start: usize, nbt_str: &str) -> Result<NbtMap, NucleationError> {
+    let mut nbt_map = parse_snbt_compound_at(nbt_str)
+        .map_err(|(pos, message)| block_string_syntax(nbt_start + pos, message))?;
+    normalize_block_nbt(&mut nbt_map).map_err(|error| block_string_syntax(nbt_start, error.to_string()))?;
+    Ok(nbt_map)
+}
 
-            match key {
-                "Count" => {
-                    let count_str = value.trim_matches(|c| c == '"' || c == '\'').trim_end_matches('b');
-                    let count: i8 = count_str.parse()
-                        .map_err(|_| format!("Invalid Count value: {}", count_str))?;
-                    item_nbt.insert("Count".to_string(), NbtValue::Byte(count));
-                },
-                "Slot" => {
-                    let slot_str = value.trim_matches(|c| c == '"' || c == '\'').trim_end_matches('b');
-                    let slot: i8 = slot_str.parse()
-                        .map_err(|_| format!("Invalid Slot value: {}", slot_str))?;
-                    item_nbt.insert("Slot".to_string(), NbtValue::Byte(slot));
-                },
-                "id" => {
-                    let id = value.trim_matches(|c| c == '"' || c == '\'').to_string();
-                    item_nbt.insert("id".to_string(), NbtValue::String(id));
-                },
-                _ => {
-                }
+fn normalize_block_nbt(nbt_map: &mut NbtMap) -> Result<(), NucleationError> {
+    if let Some(NbtValue::List(items)) = nbt_map.get_mut("Items") {
+        for item in items.iter_mut() {
+            if let NbtValue::Compound(item) = item {
+                normalize_item(item)?;
             }
         }
-
-
-        // Verify required fields
-        if item_nbt.get("Count").is_none() ||
-            item_nbt.get("Slot").is_none() ||
-            item_nbt.get("id").is_none() {
-            return Err("Missing required item properties".to_string());
-        }
-
-        items.push(NbtValue::Compound(item_nbt));
     }
-
-    Ok(items)
+    if let Some(NbtValue::String(name)) = nbt_map.get_mut("CustomName") {
+        *name = custom_name_text(name)?;
+    }
+    Ok(())
 }
 
-// Helper function to split items, handling nested braces
-fn split_items(items_str: &str) -> Vec<String> {
-    let mut items = Vec::new();
-    let mut current_item = String::new();
-    let mut brace_count = 0;
-
-    for c in items_str.chars() {
-        match c {
-            '{' => {
-                brace_count += 1;
-                current_item.push(c);
-            }
-            '}' => {
-                brace_count -= 1;
-                current_item.push(c);
-                if brace_count == 0 {
-                    let item = current_item.trim();
-                    if !item.is_empty() {
-                        items.push(item.to_string());
-                    }
-                    current_item.clear();
-                }
-            }
-            ',' if brace_count == 0 => {},
-            _ => {
-                if brace_count > 0 {
-                    current_item.push(c);
-                }
-            }
+fn normalize_item(item: &mut NbtMap) -> Result<(), NucleationError> {
+    for key in ["Count", "Slot"] {
+        if let Some(NbtValue::String(value)) = item.get(key) {
+            let byte = match parse_snbt(value.trim_end_matches('b')) {
+                Ok(NbtValue::Int(value)) => i8::try_from(value).ok(),
+                _ => None,
+            };
+            let byte = byte.ok_or_else(|| format!("Invalid {} value: {}", key, value))?;
+            item.insert(key.to_string(), NbtValue::Byte(byte));
         }
     }
+    Ok(())
+}
 
-    items
+fn custom_name_text(name: &str) -> Result<String, NucleationError> {
+    if !name.trim_start().starts_with('{') {
+        return Ok(name.to_string());
+    }
+    let json: Value = serde_json::from_str(name)
+        .map_err(|e| format!("Invalid JSON in CustomName: {}", e))?;
+    json.get("text")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| "Missing or invalid 'text' field in CustomName JSON".into())
 }
 
-pub fn parse_custom_name(nbt_str: &str) -> Result<String, String> {
-    let name_start = nbt_str.find("CustomName:")
-        .ok_or("No CustomName field found")?
-        + "CustomName:".len();
+/// Splits a block string into its trimmed block state part and, if present, the NBT compound
+/// (including its braces) together with its byte offset in the original string.
+pub(crate) fn split_block_string(block_string: &str) -> (&str, Option<(usize, &str)>) {
+    match block_string.find('{') {
+        Some(open) => (block_string[..open].trim(), Some((open, block_string[open..].trim_end()))),
+        None => (block_string.trim(), None),
+    }
+}
 
-    // Find the end of the CustomName value (either at a comma or end of string)
-    let name_content = nbt_str[name_start..].trim_start();
+/// Parses the `name[key=value,...]` part of a block string. Positions in errors are byte
+/// offsets into `block_string`, of which `block_state_str` must be a substring.
+pub(crate) fn parse_block_state(block_string: &str, block_state_str: &str) -> Result<BlockState, NucleationError> {
+    let start = block_state_str.as_ptr() as usize - block_string.as_ptr() as usize;
+    let Some(open) = block_state_str.find('[') else {
+        return Ok(BlockState::new(block_state_str.to_string()));
+    };
 
-    let name_content = name_content.trim_start_matches('\'');
+    let block_name = &block_state_str[..open];
+    let properties_str = block_state_str[open + 1..].strip_suffix(']')
+        .ok_or_else(|| block_string_syntax(start + block_state_str.len(), "Missing properties closing bracket"))?;
+
+    let mut properties = HashMap::new();
+    let mut pos = start + open + 1;
+    for prop in properties_str.split(',') {
+        let (key, value) = prop.split_once('=')
+            .ok_or_else(|| block_string_syntax(pos + prop.len(), "Missing property value"))?;
+        let value = value.trim().trim_matches(|c| c == '\'' || c == '"');
+        properties.insert(key.trim().to_string(), value.to_string());
+        pos += prop.len() + 1;
+    }
 
-    let name_content = name_content
-        .split_once(',')
-        .map(|(name, _)| name)
-        .unwrap_or_else(|| name_content);
+    Ok(BlockState::new(block_name.to_string()).with_properties(properties))
+}
 
-    let name_content = name_content.trim_end_matches('\'').trim();
+pub(crate) fn block_string_syntax(pos: usize, message: impl Into<String>) -> NucleationError {
+    NucleationError::BlockStringSyntax { pos, message: message.into() }
+}
 
-    // If it's a JSON object
-    if name_content.starts_with('{') {
-        // Parse the JSON string
-        match serde_json::from_str::<Value>(name_content) {
-            Ok(json) => {
-                json.get("text")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .ok_or_else(|| "Missing or invalid 'text' field in CustomName JSON".to_string())
+/// Parses the `Items` list out of the inside of a block entity compound, e.g. `Items:[...],Lock:""`.
+pub fn parse_items_array(nbt_str: &str) -> Result<Vec<NbtValue>, NucleationError> {
+    let mut nbt_map = parse_snbt_compound(&format!("{{{}}}", nbt_str))?;
+    match nbt_map.remove("Items") {
+        Some(NbtValue::List(mut items)) => {
+            for item in items.iter_mut() {
+                match item {
+                    NbtValue::Compound(item) => normalize_item(item)?,
+                    _ => return Err("Items must be compounds".into()),
+                }
             }
-            Err(e) => Err(format!("Invalid JSON in CustomName: {}", e))
+            Ok(items)
         }
-    } else {
-        // If it's a plain string, return it as is
-        Ok(name_content.to_string())
+        Some(_) => Err("Items must be a list".into()),
+        None => Err("Missing Items array".into()),
     }
 }
 
-fn extract_balanced_substring(s: &str, open: char, close: char) -> Option<&str> {
-    let mut depth = 0;
-    let mut start = None;
-
-    for (i, c) in s.chars().enumerate() {
-        match c {
-            c if c == open => {
-                if depth == 0 {
-                    start = Some(i);
-                }
-                depth += 1;
-            }
-            c if c == close => {
-                depth -= 1;
-                if depth == 0 && start.is_some() {
-                    return Some(&s[start.unwrap()..=i]);
-                }
-            }
-            _ => {}
-        }
+/// Parses the `CustomName` out of the inside of a block entity compound, returning the `text`
+/// of JSON names.
+pub fn parse_custom_name(nbt_str: &str) -> Result<String, NucleationError> {
+    let nbt_map = parse_snbt_compound(&format!("{{{}}}", nbt_str))?;
+    match nbt_map.get("CustomName") {
+        Some(NbtValue::String(name)) => custom_name_text(name),
+        Some(_) => Err("CustomName must be a string".into()),
+        None => Err("No CustomName field found".into()),
     }
-    None
 }
 
 #[cfg(test)]
@@ -252,6 +171,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_block_string_syntax_errors() {
+        let error = parse_block_string("minecraft:lever[face=floor,powered").unwrap_err();
+        assert!(matches!(error, NucleationError::BlockStringSyntax { pos: 34, .. }));
+
+        let error = parse_block_string("  minecraft:lever[face=floor,powered]").unwrap_err();
+        assert!(matches!(error, NucleationError::BlockStringSyntax { pos: 36, .. }));
+
+        let error = parse_block_string(r#"minecraft:chest{Items:[{"Count":"xb","Slot":"0b","id":"minecraft:stone"}]}"#).unwrap_err();
+        assert!(matches!(error, NucleationError::BlockStringSyntax { pos: 15, .. }));
+
+        let error = parse_block_string(r#"minecraft:chest{Items:[{Count:1b id:"minecraft:stone"}]}"#).unwrap_err();
+        assert!(matches!(error, NucleationError::BlockStringSyntax { pos: 33, .. }));
+    }
+
+    #[test]
+    fn test_parse_block_string_keeps_full_snbt() {
+        let block_str = r#"minecraft:chest[facing=west]{Lock:"key",Items:[{Slot:3b,id:"minecraft:diamond_sword",count:1,components:{"minecraft:damage":5}}]}"#;
+        let (_, nbt_data) = parse_block_string(block_str).unwrap();
+        let nbt_data = nbt_data.unwrap();
+
+        assert_eq!(nbt_data.keys().collect::<Vec<_>>(), ["Lock", "Items"]);
+        let Some(NbtValue::List(items)) = nbt_data.get("Items") else { panic!("Expected list of items") };
+        let item = items[0].as_compound().unwrap();
+        assert_eq!(item.get("count"), Some(&NbtValue::Int(1)));
+        let components = item.get("components").and_then(|c| c.as_compound()).unwrap();
+        assert_eq!(components.get("minecraft:damage"), Some(&NbtValue::Int(5)));
+    }
+
     #[test]
     fn test_parse_custom_name() {
         let test_cases = [
@@ -1,5 +1,8 @@
 mod nbt;
 mod block_string;
+pub mod snbt;
 
-pub use nbt::{NbtValue, NbtMap};
-pub use block_string::{parse_items_array, parse_custom_name};
\ No newline at end of file
+pub use nbt::{FromNbtValue, NbtValue, NbtMap};
+pub use block_string::{parse_items_array, parse_custom_name};
+pub use snbt::{parse_snbt, parse_snbt_compound, to_snbt, to_snbt_pretty, compound_to_snbt};
+pub(crate) use block_string::{block_string_syntax, parse_block_nbt, parse_block_state, split_block_string};
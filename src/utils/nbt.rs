@@ -1,8 +1,9 @@
+use crate::NucleationError;
+use indexmap::IndexMap;
 use quartz_nbt::{self, NbtCompound, NbtTag};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum NbtValue {
     Byte(i8),
     Short(i16),
@@ -16,10 +17,40 @@ pub enum NbtValue {
     Compound(NbtMap),
     IntArray(Vec<i32>),
     LongArray(Vec<i64>),
+    /// An empty list that remembers the tag id of its element type. `List` with no
+    /// values is an empty list of `TAG_End`. quartz_nbt cannot carry the element type of
+    /// an empty list, so both become the same untyped list there; [`NbtMap::read_nbt`]
+    /// and [`NbtMap::write_nbt`] keep it.
+    EmptyList(u8),
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct NbtMap(HashMap<String, NbtValue>);
+// Floats compare by their bits, so values round trip exactly and NBT can be `Eq`
+impl PartialEq for NbtValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (NbtValue::Byte(a), NbtValue::Byte(b)) => a == b,
+            (NbtValue::Short(a), NbtValue::Short(b)) => a == b,
+            (NbtValue::Int(a), NbtValue::Int(b)) => a == b,
+            (NbtValue::Long(a), NbtValue::Long(b)) => a == b,
+            (NbtValue::Float(a), NbtValue::Float(b)) => a.to_bits() == b.to_bits(),
+            (NbtValue::Double(a), NbtValue::Double(b)) => a.to_bits() == b.to_bits(),
+            (NbtValue::ByteArray(a), NbtValue::ByteArray(b)) => a == b,
+            (NbtValue::String(a), NbtValue::String(b)) => a == b,
+            (NbtValue::List(a), NbtValue::List(b)) => a == b,
+            (NbtValue::Compound(a), NbtValue::Compound(b)) => a == b,
+            (NbtValue::IntArray(a), NbtValue::IntArray(b)) => a == b,
+            (NbtValue::LongArray(a), NbtValue::LongArray(b)) => a == b,
+            (NbtValue::EmptyList(a), NbtValue::EmptyList(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for NbtValue {}
+
+/// Compound contents, kept in insertion order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NbtMap(IndexMap<String, NbtValue>);
 
 impl Default for NbtMap {
     fn default() -> Self {
@@ -29,30 +60,53 @@ impl Default for NbtMap {
 
 impl NbtMap {
     pub fn new() -> Self {
-        NbtMap(HashMap::new())
+        NbtMap(IndexMap::new())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
     }
 
-    pub fn insert(&mut self, key: String, value: NbtValue) -> Option<NbtValue> {
-        self.0.insert(key, value)
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, value: NbtValue) -> Option<NbtValue> {
+        self.0.insert(key.into(), value)
     }
 
     pub fn get(&self, key: &str) -> Option<&NbtValue> {
         self.0.get(key)
     }
 
+    /// The value of `key` as `T`, e.g. `map.get_as::<i32>("Version")`. Fails when the tag is
+    /// missing or has another type, numbers are not converted.
+    pub fn get_as<'a, T: FromNbtValue<'a>>(&'a self, key: &str) -> Result<T, NucleationError> {
+        let value = self.get(key).ok_or_else(|| NucleationError::missing_tag(key))?;
+        T::from_nbt_value(value).ok_or_else(|| format!("Tag \"{}\" has an unexpected type", key).into())
+    }
+
     pub fn get_mut(&mut self, key: &str) -> Option<&mut NbtValue> {
         self.0.get_mut(key)
     }
 
     pub fn remove(&mut self, key: &str) -> Option<NbtValue> {
-        self.0.remove(key)
+        self.0.shift_remove(key)
     }
 
-    pub fn iter(&self) -> std::collections::hash_map::Iter<String, NbtValue> {
+    pub fn keys(&self) -> indexmap::map::Keys<'_, String, NbtValue> {
+        self.0.keys()
+    }
+
+    pub fn iter(&self) -> indexmap::map::Iter<'_, String, NbtValue> {
         self.0.iter()
     }
 
-    pub fn iter_mut(&mut self) -> std::collections::hash_map::IterMut<String, NbtValue> {
+    pub fn iter_mut(&mut self) -> indexmap::map::IterMut<'_, String, NbtValue> {
         self.0.iter_mut()
     }
 
@@ -73,11 +127,186 @@ impl NbtMap {
         compound
     }
 
+    /// Reads an uncompressed NBT root compound, returning its name and contents.
+    /// Unlike going through quartz_nbt, empty lists keep their element type.
+    pub fn read_nbt(bytes: &[u8]) -> Result<(String, NbtMap), NucleationError> {
+        let mut reader = NbtReader { bytes, pos: 0 };
+        if reader.u8()? != 10 {
+            return Err("NBT root is not a compound".into());
+        }
+        let name = reader.string()?;
+        let map = reader.compound(0)?;
+        Ok((name, map))
+    }
+
+    /// Writes this compound as an uncompressed NBT root named `name`.
+    pub fn write_nbt(&self, name: &str) -> Vec<u8> {
+        let mut out = vec![10];
+        write_string(&mut out, name);
+        write_compound(&mut out, self);
+        out
+    }
+}
+
+// Guards against stack overflows on malicious input
+const MAX_DEPTH: usize = 512;
+
+struct NbtReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl NbtReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], NucleationError> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| NucleationError::from("Unexpected end of NBT data".to_string()))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], NucleationError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, NucleationError> {
+        Ok(self.take(1)?[0])
+    }
+
+    // Lengths that could not fit in the rest of the data are rejected before allocating
+    fn len(&mut self, element_size: usize) -> Result<usize, NucleationError> {
+        let len = i32::from_be_bytes(self.array()?);
+        match usize::try_from(len) {
+            Ok(len) if len.saturating_mul(element_size) <= self.bytes.len() - self.pos => Ok(len),
+            _ => Err(format!("Invalid NBT length {}", len).into()),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, NucleationError> {
+        let len = u16::from_be_bytes(self.array()?) as usize;
+        let bytes = self.take(len)?;
+        cesu8::from_java_cesu8(bytes)
+            .map(|s| s.into_owned())
+            .map_err(|_| "Invalid NBT string".into())
+    }
+
+    fn compound(&mut self, depth: usize) -> Result<NbtMap, NucleationError> {
+        let mut map = NbtMap::new();
+        loop {
+            let id = self.u8()?;
+            if id == 0 {
+                return Ok(map);
+            }
+            let key = self.string()?;
+            let value = self.value(id, depth + 1)?;
+            map.insert(key, value);
+        }
+    }
+
+    fn value(&mut self, id: u8, depth: usize) -> Result<NbtValue, NucleationError> {
+        if depth > MAX_DEPTH {
+            return Err("NBT data is nested too deeply".into());
+        }
+        Ok(match id {
+            1 => NbtValue::Byte(self.u8()? as i8),
+            2 => NbtValue::Short(i16::from_be_bytes(self.array()?)),
+            3 => NbtValue::Int(i32::from_be_bytes(self.array()?)),
+            4 => NbtValue::Long(i64::from_be_bytes(self.array()?)),
+            5 => NbtValue::Float(f32::from_be_bytes(self.array()?)),
+            6 => NbtValue::Double(f64::from_be_bytes(self.array()?)),
+            7 => {
+                let len = self.len(1)?;
+                NbtValue::ByteArray(self.take(len)?.iter().map(|&b| b as i8).collect())
+            }
+            8 => NbtValue::String(self.string()?),
+            9 => {
+                let element = self.u8()?;
+                let len = self.len(1)?;
+                match (element, len) {
+                    (0, 0) => return Ok(NbtValue::List(Vec::new())),
+                    (0, _) => return Err("List of end tags".into()),
+                    (_, 0) => return Ok(NbtValue::EmptyList(element)),
+                    _ => {}
+                }
+                let values = (0..len).map(|_| self.value(element, depth + 1)).collect::<Result<_, _>>()?;
+                NbtValue::List(values)
+            }
+            10 => NbtValue::Compound(self.compound(depth)?),
+            11 => {
+                let len = self.len(4)?;
+                let values = (0..len).map(|_| Ok(i32::from_be_bytes(self.array()?)));
+                NbtValue::IntArray(values.collect::<Result<_, NucleationError>>()?)
+            }
+            12 => {
+                let len = self.len(8)?;
+                let values = (0..len).map(|_| Ok(i64::from_be_bytes(self.array()?)));
+                NbtValue::LongArray(values.collect::<Result<_, NucleationError>>()?)
+            }
+            _ => return Err(format!("Invalid NBT tag id {}", id).into()),
+        })
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    let bytes = cesu8::to_java_cesu8(value);
+    out.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    out.extend_from_slice(&bytes);
+}
+
+fn write_compound(out: &mut Vec<u8>, map: &NbtMap) {
+    for (key, value) in map {
+        out.push(value.tag_id());
+        write_string(out, key);
+        write_value(out, value);
+    }
+    out.push(0);
+}
+
+fn write_value(out: &mut Vec<u8>, value: &NbtValue) {
+    match value {
+        NbtValue::Byte(v) => out.push(*v as u8),
+        NbtValue::Short(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtValue::Int(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtValue::Long(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtValue::Float(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtValue::Double(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtValue::ByteArray(v) => {
+            out.extend_from_slice(&(v.len() as i32).to_be_bytes());
+            out.extend(v.iter().map(|&b| b as u8));
+        }
+        NbtValue::String(v) => write_string(out, v),
+        NbtValue::List(values) => {
+            out.push(value.list_element_id().unwrap());
+            out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for value in values {
+                write_value(out, value);
+            }
+        }
+        NbtValue::Compound(map) => write_compound(out, map),
+        NbtValue::IntArray(v) => {
+            out.extend_from_slice(&(v.len() as i32).to_be_bytes());
+            v.iter().for_each(|x| out.extend_from_slice(&x.to_be_bytes()));
+        }
+        NbtValue::LongArray(v) => {
+            out.extend_from_slice(&(v.len() as i32).to_be_bytes());
+            v.iter().for_each(|x| out.extend_from_slice(&x.to_be_bytes()));
+        }
+        NbtValue::EmptyList(id) => {
+            out.push(*id);
+            out.extend_from_slice(&0i32.to_be_bytes());
+        }
+    }
+}
+
+impl FromIterator<(String, NbtValue)> for NbtMap {
+    fn from_iter<I: IntoIterator<Item = (String, NbtValue)>>(iter: I) -> Self {
+        NbtMap(iter.into_iter().collect())
+    }
 }
 
 impl IntoIterator for NbtMap {
     type Item = (String, NbtValue);
-    type IntoIter = std::collections::hash_map::IntoIter<String, NbtValue>;
+    type IntoIter = indexmap::map::IntoIter<String, NbtValue>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.into_iter()
@@ -86,7 +315,7 @@ impl IntoIterator for NbtMap {
 
 impl<'a> IntoIterator for &'a NbtMap {
     type Item = (&'a String, &'a NbtValue);
-    type IntoIter = std::collections::hash_map::Iter<'a, String, NbtValue>;
+    type IntoIter = indexmap::map::Iter<'a, String, NbtValue>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.iter()
@@ -95,7 +324,7 @@ impl<'a> IntoIterator for &'a NbtMap {
 
 impl<'a> IntoIterator for &'a mut NbtMap {
     type Item = (&'a String, &'a mut NbtValue);
-    type IntoIter = std::collections::hash_map::IterMut<'a, String, NbtValue>;
+    type IntoIter = indexmap::map::IterMut<'a, String, NbtValue>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.iter_mut()
@@ -137,6 +366,34 @@ impl NbtValue {
             NbtValue::Compound(v) => NbtTag::Compound(v.to_quartz_nbt()),
             NbtValue::IntArray(v) => NbtTag::IntArray(v.clone()),
             NbtValue::LongArray(v) => NbtTag::LongArray(v.clone()),
+            NbtValue::EmptyList(_) => NbtTag::List(quartz_nbt::NbtList::new()),
+        }
+    }
+
+    /// The NBT tag id of this value.
+    pub fn tag_id(&self) -> u8 {
+        match self {
+            NbtValue::Byte(_) => 1,
+            NbtValue::Short(_) => 2,
+            NbtValue::Int(_) => 3,
+            NbtValue::Long(_) => 4,
+            NbtValue::Float(_) => 5,
+            NbtValue::Double(_) => 6,
+            NbtValue::ByteArray(_) => 7,
+            NbtValue::String(_) => 8,
+            NbtValue::List(_) | NbtValue::EmptyList(_) => 9,
+            NbtValue::Compound(_) => 10,
+            NbtValue::IntArray(_) => 11,
+            NbtValue::LongArray(_) => 12,
+        }
+    }
+
+    /// Tag id of the list elements, 0 (`TAG_End`) for untyped empty lists. `None` for non-lists.
+    pub fn list_element_id(&self) -> Option<u8> {
+        match self {
+            NbtValue::List(values) => Some(values.first().map_or(0, NbtValue::tag_id)),
+            NbtValue::EmptyList(id) => Some(*id),
+            _ => None,
         }
     }
 
@@ -183,6 +440,51 @@ impl NbtValue {
     }
 }
 
+/// Types [`NbtMap::get_as`] reads, each from a single tag type. Slices of values also
+/// accept typed empty lists.
+pub trait FromNbtValue<'a>: Sized {
+    fn from_nbt_value(value: &'a NbtValue) -> Option<Self>;
+}
+
+macro_rules! from_nbt_value {
+    ($($type:ty => |$value:ident| $variant:ident => $convert:expr),* $(,)?) => {
+        $(
+            impl<'a> FromNbtValue<'a> for $type {
+                fn from_nbt_value(value: &'a NbtValue) -> Option<Self> {
+                    match value {
+                        NbtValue::$variant($value) => Some($convert),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_nbt_value! {
+    i8 => |value| Byte => *value,
+    i16 => |value| Short => *value,
+    i32 => |value| Int => *value,
+    i64 => |value| Long => *value,
+    f32 => |value| Float => *value,
+    f64 => |value| Double => *value,
+    &'a [i8] => |value| ByteArray => value.as_slice(),
+    &'a str => |value| String => value.as_str(),
+    &'a NbtMap => |value| Compound => value,
+    &'a [i32] => |value| IntArray => value.as_slice(),
+    &'a [i64] => |value| LongArray => value.as_slice(),
+}
+
+impl<'a> FromNbtValue<'a> for &'a [NbtValue] {
+    fn from_nbt_value(value: &'a NbtValue) -> Option<Self> {
+        match value {
+            NbtValue::List(values) => Some(values.as_slice()),
+            NbtValue::EmptyList(_) => Some(&[]),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(feature = "wasm")]
 mod wasm {
     use super::*;
@@ -238,7 +540,85 @@ mod wasm {
                     }
                     arr.into()
                 }
+                NbtValue::EmptyList(_) => Array::new().into(),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> NbtMap {
+        let mut nested = NbtMap::new();
+        nested.insert("z".to_string(), NbtValue::Long(-5));
+        nested.insert("a".to_string(), NbtValue::Float(0.5));
+
+        let mut map = NbtMap::new();
+        map.insert("Byte".to_string(), NbtValue::Byte(1));
+        map.insert("Short".to_string(), NbtValue::Short(2));
+        map.insert("Int".to_string(), NbtValue::Int(3));
+        map.insert("Double".to_string(), NbtValue::Double(4.5));
+        map.insert("String".to_string(), NbtValue::String("text".to_string()));
+        map.insert("ByteArray".to_string(), NbtValue::ByteArray(vec![1, -1]));
+        map.insert("IntArray".to_string(), NbtValue::IntArray(vec![1, 2]));
+        map.insert("LongArray".to_string(), NbtValue::LongArray(vec![i64::MAX]));
+        map.insert("List".to_string(), NbtValue::List(vec![NbtValue::Compound(nested.clone())]));
+        map.insert("Compound".to_string(), NbtValue::Compound(nested));
+        map
+    }
+
+    #[test]
+    fn test_quartz_round_trip_keeps_values_and_order() {
+        let map = sample();
+        let back = NbtMap::from_quartz_nbt(&map.to_quartz_nbt());
+        assert_eq!(back, map);
+        assert!(back.keys().eq(map.keys()));
+    }
+
+    #[test]
+    fn test_floats_compare_by_bits() {
+        assert_eq!(NbtValue::Double(f64::NAN), NbtValue::Double(f64::NAN));
+        assert_ne!(NbtValue::Float(0.0), NbtValue::Float(-0.0));
+        assert_ne!(NbtValue::Int(1), NbtValue::Long(1));
+
+        fn is_eq<T: Eq>(_: &T) {}
+        is_eq(&crate::metadata::Metadata::default());
+    }
+
+    #[test]
+    fn test_typed_empty_list() {
+        let list = NbtValue::EmptyList(10);
+        assert_eq!(list.tag_id(), 9);
+        assert_eq!(list.list_element_id(), Some(10));
+        assert_eq!(NbtValue::List(vec![]).list_element_id(), Some(0));
+
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(serde_json::from_str::<NbtValue>(&json).unwrap(), list);
+
+        // quartz_nbt has no element type for empty lists
+        assert_eq!(NbtValue::from_quartz_nbt(&list.to_quartz_nbt()), NbtValue::List(vec![]));
+    }
+
+    #[test]
+    fn test_binary_round_trip_keeps_empty_list_types() {
+        let mut map = sample();
+        map.insert("Items".to_string(), NbtValue::EmptyList(10));
+        map.insert("Lore".to_string(), NbtValue::EmptyList(8));
+        map.insert("Untyped".to_string(), NbtValue::List(vec![]));
+
+        let bytes = map.write_nbt("root");
+        let (name, back) = NbtMap::read_nbt(&bytes).unwrap();
+        assert_eq!(name, "root");
+        assert_eq!(back.get("Items"), Some(&NbtValue::EmptyList(10)));
+        assert_eq!(back.get("Lore"), Some(&NbtValue::EmptyList(8)));
+        assert_eq!(back.get("Untyped"), Some(&NbtValue::List(vec![])));
+        assert_eq!(back.write_nbt("root"), bytes);
+
+        // Same layout as quartz_nbt writes
+        let quartz = quartz_nbt::io::read_nbt(&mut &bytes[..], quartz_nbt::io::Flavor::Uncompressed).unwrap();
+        assert_eq!(quartz.0, map.to_quartz_nbt());
+        assert!(NbtMap::read_nbt(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,458 @@
+// Stringified NBT as used in commands: `{id:"minecraft:stone",Count:1b,Tags:[I;1,2]}`.
+use crate::utils::{NbtMap, NbtValue};
+use crate::NucleationError;
+
+const MAX_DEPTH: usize = 512;
+
+/// Parses any SNBT value.
+pub fn parse_snbt(input: &str) -> Result<NbtValue, NucleationError> {
+    parse_complete(input, |parser| parser.parse_value(0)).map_err(|error| error.into_nucleation(input))
+}
+
+/// Parses an SNBT compound, e.g. `{Items:[],Lock:""}`.
+pub fn parse_snbt_compound(input: &str) -> Result<NbtMap, NucleationError> {
+    parse_complete(input, |parser| {
+        parser.skip_whitespace();
+        parser.parse_compound(0)
+    })
+    .map_err(|error| error.into_nucleation(input))
+}
+
+/// Parses an SNBT compound and reports errors as a byte offset into `input`.
+pub(crate) fn parse_snbt_compound_at(input: &str) -> Result<NbtMap, (usize, String)> {
+    parse_complete(input, |parser| {
+        parser.skip_whitespace();
+        parser.parse_compound(0)
+    })
+    .map_err(|error| (error.pos, error.message))
+}
+
+/// Writes a value as compact SNBT, suitable for commands.
+pub fn to_snbt(value: &NbtValue) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, None, 0);
+    out
+}
+
+/// Writes a compound as compact SNBT.
+pub fn compound_to_snbt(map: &NbtMap) -> String {
+    let mut out = String::new();
+    write_compound(&mut out, map, None, 0);
+    out
+}
+
+/// Writes a value as indented SNBT for debugging.
+pub fn to_snbt_pretty(value: &NbtValue) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, Some("    "), 0);
+    out
+}
+
+struct SnbtError {
+    pos: usize,
+    message: String,
+}
+
+impl SnbtError {
+    fn into_nucleation(self, input: &str) -> NucleationError {
+        let before = &input[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        NucleationError::Snbt { line, column, message: self.message }
+    }
+}
+
+fn parse_complete<T>(input: &str, parse: impl FnOnce(&mut Parser) -> Result<T, SnbtError>) -> Result<T, SnbtError> {
+    let mut parser = Parser { input, pos: 0 };
+    let value = parse(&mut parser)?;
+    parser.skip_whitespace();
+    if parser.pos < input.len() {
+        return Err(parser.error("Unexpected trailing data"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> SnbtError {
+        SnbtError { pos: self.pos, message: message.into() }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SnbtError> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(format!("Expected '{}'", expected)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<NbtValue, SnbtError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("SNBT is nested too deeply"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => Ok(NbtValue::Compound(self.parse_compound(depth)?)),
+            Some('[') => self.parse_list(depth),
+            Some('"') | Some('\'') => Ok(NbtValue::String(self.parse_quoted()?)),
+            _ => {
+                let start = self.pos;
+                let token = self.parse_unquoted();
+                if token.is_empty() {
+                    self.pos = start;
+                    return Err(self.error("Expected value"));
+                }
+                Ok(unquoted_value(token))
+            }
+        }
+    }
+
+    fn parse_compound(&mut self, depth: usize) -> Result<NbtMap, SnbtError> {
+        self.expect('{')?;
+        let mut map = NbtMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(map);
+        }
+        loop {
+            self.skip_whitespace();
+            let key = match self.peek() {
+                Some('"') | Some('\'') => self.parse_quoted()?,
+                _ => self.parse_unquoted().to_string(),
+            };
+            if key.is_empty() {
+                return Err(self.error("Expected key"));
+            }
+            self.expect(':')?;
+            let value = self.parse_value(depth + 1)?;
+            map.insert(key, value);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(map);
+                }
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn parse_list(&mut self, depth: usize) -> Result<NbtValue, SnbtError> {
+        self.expect('[')?;
+        let rest = &self.input[self.pos..];
+        let array_type = match rest.as_bytes() {
+            [kind @ (b'B' | b'I' | b'L'), b';', ..] => Some(*kind),
+            _ => None,
+        };
+        if array_type.is_some() {
+            self.pos += 2;
+        }
+
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+        } else {
+            loop {
+                let start = self.pos;
+                let value = self.parse_value(depth + 1)?;
+                if array_type.is_none() && values.first().is_some_and(|(_, first): &(usize, NbtValue)| first.tag_id() != value.tag_id()) {
+                    self.pos = start;
+                    self.skip_whitespace();
+                    return Err(self.error("List elements must all have the same type"));
+                }
+                values.push((start, value));
+
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(',') => self.pos += 1,
+                    Some(']') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return Err(self.error("Expected ',' or ']'")),
+                }
+            }
+        }
+
+        let mismatch = |start: usize, expected: &str| SnbtError {
+            pos: start + self.input[start..].len() - self.input[start..].trim_start().len(),
+            message: format!("Expected {} in array", expected),
+        };
+        match array_type {
+            Some(b'B') => values.into_iter()
+                .map(|(start, value)| match value {
+                    NbtValue::Byte(v) => Ok(v),
+                    _ => Err(mismatch(start, "byte")),
+                })
+                .collect::<Result<_, _>>()
+                .map(NbtValue::ByteArray),
+            Some(b'I') => values.into_iter()
+                .map(|(start, value)| match value {
+                    NbtValue::Int(v) => Ok(v),
+                    _ => Err(mismatch(start, "int")),
+                })
+                .collect::<Result<_, _>>()
+                .map(NbtValue::IntArray),
+            Some(_) => values.into_iter()
+                .map(|(start, value)| match value {
+                    NbtValue::Long(v) => Ok(v),
+                    _ => Err(mismatch(start, "long")),
+                })
+                .collect::<Result<_, _>>()
+                .map(NbtValue::LongArray),
+            None => Ok(NbtValue::List(values.into_iter().map(|(_, value)| value).collect())),
+        }
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, SnbtError> {
+        let quote = self.peek().unwrap();
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("Unterminated string"));
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '\\' => {
+                    let escaped = match self.peek() {
+                        Some(c @ ('\\' | '"' | '\'')) => c,
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        _ => return Err(self.error("Invalid escape sequence")),
+                    };
+                    self.pos += 1;
+                    value.push(escaped);
+                }
+                c if c == quote => return Ok(value),
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn parse_unquoted(&mut self) -> &str {
+        let start = self.pos;
+        while let Some(c) = self.peek().filter(|&c| is_unquoted_char(c)) {
+            self.pos += c.len_utf8();
+        }
+        &self.input[start..self.pos]
+    }
+}
+
+fn is_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+// Numbers take a type suffix, unsuffixed integers are ints and decimals doubles.
+// Anything that does not parse as a number is a string, like Minecraft does.
+fn unquoted_value(token: &str) -> NbtValue {
+    match token {
+        "true" => return NbtValue::Byte(1),
+        "false" => return NbtValue::Byte(0),
+        _ => {}
+    }
+
+    let is_integer = |s: &str| {
+        let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+        !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+    };
+    let is_decimal = |s: &str| {
+        let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+        digits.bytes().next().is_some_and(|b| b.is_ascii_digit() || b == b'.')
+            && digits.bytes().any(|b| b.is_ascii_digit())
+            && digits.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'-' | b'+'))
+    };
+
+    let (body, suffix) = token.split_at(token.len() - 1);
+    let parsed = match suffix {
+        "b" | "B" if is_integer(body) => body.parse().ok().map(NbtValue::Byte),
+        "s" | "S" if is_integer(body) => body.parse().ok().map(NbtValue::Short),
+        "l" | "L" if is_integer(body) => body.parse().ok().map(NbtValue::Long),
+        "f" | "F" if is_decimal(body) => body.parse().ok().map(NbtValue::Float),
+        "d" | "D" if is_decimal(body) => body.parse().ok().map(NbtValue::Double),
+        _ if is_integer(token) => token.parse().ok().map(NbtValue::Int),
+        _ if is_decimal(token) => token.parse().ok().map(NbtValue::Double),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| NbtValue::String(token.to_string()))
+}
+
+fn write_value(out: &mut String, value: &NbtValue, indent: Option<&str>, depth: usize) {
+    match value {
+        NbtValue::Byte(v) => out.push_str(&format!("{}b", v)),
+        NbtValue::Short(v) => out.push_str(&format!("{}s", v)),
+        NbtValue::Int(v) => out.push_str(&v.to_string()),
+        NbtValue::Long(v) => out.push_str(&format!("{}L", v)),
+        NbtValue::Float(v) => out.push_str(&format!("{}f", v)),
+        NbtValue::Double(v) => out.push_str(&format!("{}d", v)),
+        NbtValue::String(v) => write_string(out, v),
+        NbtValue::ByteArray(v) => write_array(out, "B;", v.iter().map(|v| format!("{}b", v))),
+        NbtValue::IntArray(v) => write_array(out, "I;", v.iter().map(|v| v.to_string())),
+        NbtValue::LongArray(v) => write_array(out, "L;", v.iter().map(|v| format!("{}L", v))),
+        NbtValue::EmptyList(_) => out.push_str("[]"),
+        NbtValue::List(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_newline(out, indent, depth + 1);
+                write_value(out, value, indent, depth + 1);
+            }
+            if !values.is_empty() {
+                write_newline(out, indent, depth);
+            }
+            out.push(']');
+        }
+        NbtValue::Compound(map) => write_compound(out, map, indent, depth),
+    }
+}
+
+fn write_compound(out: &mut String, map: &NbtMap, indent: Option<&str>, depth: usize) {
+    out.push('{');
+    for (i, (key, value)) in map.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_newline(out, indent, depth + 1);
+        if !key.is_empty() && key.chars().all(is_unquoted_char) {
+            out.push_str(key);
+        } else {
+            write_string(out, key);
+        }
+        out.push(':');
+        if indent.is_some() {
+            out.push(' ');
+        }
+        write_value(out, value, indent, depth + 1);
+    }
+    if !map.is_empty() {
+        write_newline(out, indent, depth);
+    }
+    out.push('}');
+}
+
+fn write_array(out: &mut String, prefix: &str, values: impl Iterator<Item = String>) {
+    out.push('[');
+    out.push_str(prefix);
+    out.push_str(&values.collect::<Vec<_>>().join(","));
+    out.push(']');
+}
+
+// Unquoted when it would read back as the same string, otherwise quoted with whichever
+// quote character needs no escaping.
+fn write_string(out: &mut String, value: &str) {
+    if !value.is_empty()
+        && value.chars().all(is_unquoted_char)
+        && unquoted_value(value) == NbtValue::String(value.to_string())
+    {
+        out.push_str(value);
+        return;
+    }
+
+    let quote = if value.contains('"') && !value.contains('\'') { '\'' } else { '"' };
+    out.push(quote);
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+}
+
+fn write_newline(out: &mut String, indent: Option<&str>, depth: usize) {
+    if let Some(indent) = indent {
+        out.push('\n');
+        for _ in 0..depth {
+            out.push_str(indent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_typed_values() {
+        let map = parse_snbt_compound(r#"{b:1b,s:-2s,i:3,l:4L,f:0.5f,d:1.5,d2:2d,t:true,name:stone,"quoted key":'say "hi"',big:99999999999}"#).unwrap();
+        assert_eq!(map.get("b"), Some(&NbtValue::Byte(1)));
+        assert_eq!(map.get("s"), Some(&NbtValue::Short(-2)));
+        assert_eq!(map.get("i"), Some(&NbtValue::Int(3)));
+        assert_eq!(map.get("l"), Some(&NbtValue::Long(4)));
+        assert_eq!(map.get("f"), Some(&NbtValue::Float(0.5)));
+        assert_eq!(map.get("d"), Some(&NbtValue::Double(1.5)));
+        assert_eq!(map.get("d2"), Some(&NbtValue::Double(2.0)));
+        assert_eq!(map.get("t"), Some(&NbtValue::Byte(1)));
+        assert_eq!(map.get("name"), Some(&NbtValue::String("stone".to_string())));
+        assert_eq!(map.get("quoted key"), Some(&NbtValue::String("say \"hi\"".to_string())));
+        assert_eq!(map.get("big"), Some(&NbtValue::String("99999999999".to_string())));
+    }
+
+    #[test]
+    fn test_parse_lists_and_arrays() {
+        let value = parse_snbt("[I; 1, -2 ,3]").unwrap();
+        assert_eq!(value, NbtValue::IntArray(vec![1, -2, 3]));
+        assert_eq!(parse_snbt("[B;1b,2b]").unwrap(), NbtValue::ByteArray(vec![1, 2]));
+        assert_eq!(parse_snbt("[L;]").unwrap(), NbtValue::LongArray(vec![]));
+
+        let value = parse_snbt("[{id:\"minecraft:stone\",Count:1b},{}]").unwrap();
+        let NbtValue::List(items) = value else { panic!("Expected list") };
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_compound().unwrap().get("Count"), Some(&NbtValue::Byte(1)));
+
+        assert!(parse_snbt("[1,2b]").is_err());
+        assert!(parse_snbt("[I;1,2L]").is_err());
+    }
+
+    #[test]
+    fn test_error_line_and_column() {
+        let error = parse_snbt("{\n  a: 1,\n  b: [1, 2\n}").unwrap_err();
+        match error {
+            NucleationError::Snbt { line, column, .. } => assert_eq!((line, column), (4, 1)),
+            other => panic!("Unexpected error {:?}", other),
+        }
+
+        let error = parse_snbt("{a:\"open}").unwrap_err();
+        assert!(matches!(error, NucleationError::Snbt { line: 1, column: 10, .. }));
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let input = r#"{Items:[{Slot:0b,id:"minecraft:stone",count:64}],Lock:"",Name:'{"text":"x"}',Pos:[I;1,2,3],UUID:[L;1L],Motion:[0.5d,1.0d],Weird:"true",Num:"12"}"#;
+        let value = parse_snbt(input).unwrap();
+        let compact = to_snbt(&value);
+        assert_eq!(parse_snbt(&compact).unwrap(), value);
+        assert_eq!(parse_snbt(&to_snbt_pretty(&value)).unwrap(), value);
+        assert!(compact.starts_with("{Items:[{Slot:0b,id:\"minecraft:stone\",count:64}],Lock:\"\",Name:'{\"text\":\"x\"}'"));
+        assert!(compact.contains("Weird:\"true\",Num:\"12\""));
+    }
+}
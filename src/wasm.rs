@@ -6,11 +6,18 @@ use web_sys::console;
 use crate::{
     UniversalSchematic,
     BlockState,
-    formats::{litematic, schematic},
+    NucleationError,
+    Axis,
+    ConflictPolicy,
+    Mask,
+    Pattern,
+    SchematicDiff,
+    diff_with,
+    formats::{litematic, schematic, structure, mcedit, mcstructure, axiom, vox},
     print_utils::{format_schematic as print_schematic, format_json_schematic as print_json_schematic},
     block_position::BlockPosition,
 };
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use crate::bounding_box::BoundingBox;
 use crate::schematic::SchematicVersion;
 use crate::universal_schematic::ChunkLoadingStrategy;
@@ -29,6 +36,13 @@ pub struct LazyChunkIterator {
 }
 
 
+// Errors surface as JS `Error`s whose `name` is the error kind, e.g. `MissingTag`.
+fn js_error(context: &str, error: NucleationError) -> JsValue {
+    let js_error = js_sys::Error::new(&format!("{}: {}", context, error));
+    js_error.set_name(error.kind());
+    js_error.into()
+}
+
 #[wasm_bindgen(start)]
 pub fn start() {
     console::log_1(&"Initializing schematic utilities".into());
@@ -60,38 +74,99 @@ impl SchematicWrapper {
         } else if schematic::is_schematic(data) {
             console::log_1(&"Parsing schematic data".into());
             self.from_schematic(data)
+        } else if structure::is_structure(data) {
+            console::log_1(&"Parsing structure data".into());
+            self.from_structure(data)
+        } else if mcstructure::is_mcstructure(data) {
+            console::log_1(&"Parsing mcstructure data".into());
+            self.from_mcstructure(data)
+        } else if axiom::is_axiom(data) {
+            console::log_1(&"Parsing Axiom blueprint data".into());
+            self.from_axiom(data)
+        } else if vox::is_vox(data) {
+            console::log_1(&"Parsing MagicaVoxel data".into());
+            self.from_vox(data)
         } else {
-            Err(JsValue::from_str("Unknown or unsupported schematic format"))
+            Err(js_error("Unknown or unsupported schematic format", NucleationError::UnsupportedFormat("unrecognized data".to_string())))
         }
     }
 
     pub fn from_litematic(&mut self, data: &[u8]) -> Result<(), JsValue> {
         self.0 = litematic::from_litematic(data)
-            .map_err(|e| JsValue::from_str(&format!("Litematic parsing error: {}", e)))?;
+            .map_err(|e| js_error("Litematic parsing error", e))?;
         Ok(())
     }
 
     pub fn to_litematic(&self) -> Result<Vec<u8>, JsValue> {
         litematic::to_litematic(&self.0)
-            .map_err(|e| JsValue::from_str(&format!("Litematic conversion error: {}", e)))
+            .map_err(|e| js_error("Litematic conversion error", e))
     }
 
     pub fn from_schematic(&mut self, data: &[u8]) -> Result<(), JsValue> {
         self.0 = schematic::from_schematic(data)
-            .map_err(|e| JsValue::from_str(&format!("Schematic parsing error: {}", e)))?;
+            .map_err(|e| js_error("Schematic parsing error", e))?;
         Ok(())
     }
 
     pub fn to_schematic(&self) -> Result<Vec<u8>, JsValue> {
         schematic::to_schematic(&self.0)
-            .map_err(|e| JsValue::from_str(&format!("Schematic conversion error: {}", e)))
+            .map_err(|e| js_error("Schematic conversion error", e))
+    }
+
+    pub fn from_structure(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        self.0 = structure::from_structure(data)
+            .map_err(|e| js_error("Structure parsing error", e))?;
+        Ok(())
+    }
+
+    pub fn to_structure(&self) -> Result<Vec<u8>, JsValue> {
+        structure::to_structure(&self.0)
+            .map_err(|e| js_error("Structure conversion error", e))
+    }
+
+    pub fn from_mcstructure(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        self.0 = mcstructure::from_mcstructure(data)
+            .map_err(|e| js_error("Bedrock structure parsing error", e))?;
+        Ok(())
+    }
+
+    pub fn to_mcstructure(&self) -> Result<Vec<u8>, JsValue> {
+        mcstructure::to_mcstructure(&self.0)
+            .map_err(|e| js_error("Bedrock structure conversion error", e))
+    }
+
+    pub fn from_axiom(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        self.0 = axiom::from_axiom(data)
+            .map_err(|e| js_error("Axiom blueprint parsing error", e))?;
+        Ok(())
+    }
+
+    pub fn to_axiom(&self) -> Result<Vec<u8>, JsValue> {
+        axiom::to_axiom(&self.0)
+            .map_err(|e| js_error("Axiom blueprint conversion error", e))
+    }
+
+    pub fn from_vox(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        self.0 = vox::from_vox(data)
+            .map_err(|e| js_error("MagicaVoxel parsing error", e))?;
+        Ok(())
+    }
+
+    pub fn to_vox(&self) -> Result<Vec<u8>, JsValue> {
+        vox::to_vox(&self.0)
+            .map_err(|e| js_error("MagicaVoxel conversion error", e))
+    }
+
+    pub fn to_mcedit(&self) -> Result<Vec<u8>, JsValue> {
+        mcedit::to_mcedit(&self.0)
+            .map_err(|e| js_error("Legacy schematic conversion error", e))
     }
 
     pub fn to_schematic_version(&self, version: &str) -> Result<Vec<u8>, JsValue> {
        let version =  schematic::to_schematic_version(&self.0, SchematicVersion::from_str(version).unwrap());
         match version {
             Ok(data) => Ok(data),
-            Err(e) => Err(JsValue::from_str(&format!("Schematic version conversion error: {}", e)))
+            Err(e) => Err(js_error("Schematic version conversion error", e))
         }
     }
 
@@ -215,6 +290,182 @@ impl SchematicWrapper {
         self.0.set_block_str(x, y, z, block_name);
     }
 
+    pub fn rotate_x(&mut self, degrees: i32) -> Result<(), JsValue> {
+        self.0.rotate_x(degrees).map_err(|e| js_error("Rotation error", e))
+    }
+
+    pub fn rotate_y(&mut self, degrees: i32) -> Result<(), JsValue> {
+        self.0.rotate_y(degrees).map_err(|e| js_error("Rotation error", e))
+    }
+
+    pub fn rotate_z(&mut self, degrees: i32) -> Result<(), JsValue> {
+        self.0.rotate_z(degrees).map_err(|e| js_error("Rotation error", e))
+    }
+
+    pub fn mirror_x(&mut self) {
+        self.0.mirror(Axis::X);
+    }
+
+    pub fn mirror_y(&mut self) {
+        self.0.mirror(Axis::Y);
+    }
+
+    pub fn mirror_z(&mut self) {
+        self.0.mirror(Axis::Z);
+    }
+
+    pub fn translate(&mut self, dx: i32, dy: i32, dz: i32) {
+        self.0.translate(dx, dy, dz);
+    }
+
+    pub fn normalize(&mut self) -> Vec<i32> {
+        let (x, y, z) = self.0.normalize();
+        vec![x, y, z]
+    }
+
+    pub fn trim(&mut self) {
+        self.0.trim();
+    }
+
+    pub fn set_anchor(&mut self, x: i32, y: i32, z: i32) {
+        self.0.set_anchor(x, y, z);
+    }
+
+    pub fn get_anchor(&self) -> Option<Vec<i32>> {
+        self.0.get_anchor().map(|(x, y, z)| vec![x, y, z])
+    }
+
+    pub fn fill(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        pattern: &str,
+        seed: Option<u64>,
+    ) -> Result<usize, JsValue> {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        let pattern = Pattern::parse(pattern).map_err(|e| js_error("Invalid pattern", e))?.with_seed(seed.unwrap_or_default());
+        Ok(self.0.fill(&bounds, &pattern))
+    }
+
+    pub fn replace(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        mask: &str,
+        pattern: &str,
+        seed: Option<u64>,
+    ) -> Result<usize, JsValue> {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        let mask = Mask::parse(mask).map_err(|e| js_error("Invalid mask", e))?;
+        let pattern = Pattern::parse(pattern).map_err(|e| js_error("Invalid pattern", e))?.with_seed(seed.unwrap_or_default());
+        Ok(self.0.replace(&bounds, &mask, &pattern))
+    }
+
+    pub fn walls(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        pattern: &str,
+        seed: Option<u64>,
+    ) -> Result<usize, JsValue> {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        let pattern = Pattern::parse(pattern).map_err(|e| js_error("Invalid pattern", e))?.with_seed(seed.unwrap_or_default());
+        Ok(self.0.walls(&bounds, &pattern))
+    }
+
+    pub fn outline(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        pattern: &str,
+        seed: Option<u64>,
+    ) -> Result<usize, JsValue> {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        let pattern = Pattern::parse(pattern).map_err(|e| js_error("Invalid pattern", e))?.with_seed(seed.unwrap_or_default());
+        Ok(self.0.outline(&bounds, &pattern))
+    }
+
+    pub fn hollow(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        thickness: u32,
+    ) -> usize {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        self.0.hollow(&bounds, thickness)
+    }
+
+    pub fn overlay(
+        &mut self,
+        min_x: i32, min_y: i32, min_z: i32,
+        max_x: i32, max_y: i32, max_z: i32,
+        pattern: &str,
+        seed: Option<u64>,
+    ) -> Result<usize, JsValue> {
+        let bounds = BoundingBox::new((min_x, min_y, min_z), (max_x, max_y, max_z));
+        let pattern = Pattern::parse(pattern).map_err(|e| js_error("Invalid pattern", e))?.with_seed(seed.unwrap_or_default());
+        Ok(self.0.overlay(&bounds, &pattern))
+    }
+
+    pub fn find_blocks(&self, mask: &str) -> Result<Array, JsValue> {
+        let mask = Mask::parse(mask).map_err(|e| js_error("Invalid mask", e))?;
+        let positions = Array::new();
+        for position in self.0.find_blocks(&mask) {
+            let js_position = Array::new();
+            js_position.push(&JsValue::from(position.x));
+            js_position.push(&JsValue::from(position.y));
+            js_position.push(&JsValue::from(position.z));
+            positions.push(&js_position);
+        }
+        Ok(positions)
+    }
+
+    pub fn count_matching(&self, mask: &str) -> Result<usize, JsValue> {
+        let mask = Mask::parse(mask).map_err(|e| js_error("Invalid mask", e))?;
+        Ok(self.0.count_matching(&mask))
+    }
+
+    /// `policy` is `keep`, `replace` or `fail` and picks the block where both schematics have one.
+    pub fn union(&mut self, other: &SchematicWrapper, dx: i32, dy: i32, dz: i32, policy: &str) -> Result<(), JsValue> {
+        let policy = ConflictPolicy::parse(policy).map_err(|e| js_error("Invalid conflict policy", e))?;
+        self.0.union(&other.0, (dx, dy, dz), policy).map_err(|e| js_error("Union error", e))
+    }
+
+    pub fn intersection(&mut self, other: &SchematicWrapper, dx: i32, dy: i32, dz: i32, policy: &str) -> Result<(), JsValue> {
+        let policy = ConflictPolicy::parse(policy).map_err(|e| js_error("Invalid conflict policy", e))?;
+        self.0.intersection(&other.0, (dx, dy, dz), policy).map_err(|e| js_error("Intersection error", e))
+    }
+
+    pub fn difference(&mut self, other: &SchematicWrapper, dx: i32, dy: i32, dz: i32) {
+        self.0.difference(&other.0, (dx, dy, dz));
+    }
+
+    pub fn xor(&mut self, other: &SchematicWrapper, dx: i32, dy: i32, dz: i32) {
+        self.0.xor(&other.0, (dx, dy, dz));
+    }
+
+    /// JSON patch from this schematic to `other`, blocks only differing in `ignored_properties` are left out.
+    pub fn diff(&self, other: &SchematicWrapper, ignored_properties: Vec<String>) -> Result<String, JsValue> {
+        let ignored_properties: Vec<&str> = ignored_properties.iter().map(String::as_str).collect();
+        diff_with(&self.0, &other.0, &ignored_properties).to_json().map_err(|e| js_error("Diff error", e))
+    }
+
+    /// Like `diff`, as gzipped NBT.
+    pub fn diff_bytes(&self, other: &SchematicWrapper, ignored_properties: Vec<String>) -> Result<Vec<u8>, JsValue> {
+        let ignored_properties: Vec<&str> = ignored_properties.iter().map(String::as_str).collect();
+        diff_with(&self.0, &other.0, &ignored_properties).to_bytes().map_err(|e| js_error("Diff error", e))
+    }
+
+    pub fn apply_patch(&mut self, patch: &str) -> Result<(), JsValue> {
+        let patch = SchematicDiff::from_json(patch).map_err(|e| js_error("Invalid patch", e))?;
+        patch.apply(&mut self.0).map_err(|e| js_error("Patch error", e))
+    }
+
+    pub fn apply_patch_bytes(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        let patch = SchematicDiff::from_bytes(data).map_err(|e| js_error("Invalid patch", e))?;
+        patch.apply(&mut self.0).map_err(|e| js_error("Patch error", e))
+    }
+
 
     pub fn copy_region(
         &mut self,
@@ -246,7 +497,7 @@ impl SchematicWrapper {
                     None => return Err(JsValue::from_str("Excluded blocks should be strings"))
                 };
                 let (block_state, _) = UniversalSchematic::parse_block_string(&block_string)
-                    .map_err(|e| JsValue::from_str(&format!("Invalid block state: {}", e)))?;
+                    .map_err(|e| js_error("Invalid block state", e))?;
                 rust_vec.push(block_state);
             }
 
@@ -260,7 +511,7 @@ impl SchematicWrapper {
             &bounds,
             (target_x, target_y, target_z),
             &excluded_blocks  // Now we can pass a direct reference to the Vec
-        ).map_err(|e| JsValue::from_str(&format!("Failed to copy region: {}", e)))
+        ).map_err(|e| js_error("Failed to copy region", e))
     }
 
 
@@ -274,7 +525,7 @@ impl SchematicWrapper {
         properties: &JsValue,
     ) -> Result<(), JsValue> {
         // Convert JsValue to HashMap<String, String>
-        let mut props = HashMap::new();
+        let mut props = BTreeMap::new();
 
         if !properties.is_undefined() && !properties.is_null() {
             let obj: Object = properties.clone().dyn_into().map_err(|_| {
@@ -691,7 +942,7 @@ impl SchematicWrapper {
     pub fn get_optimization_info(&self) -> JsValue {
         let default_region = &self.0.default_region;
         let total_blocks = default_region.blocks.len();
-        let non_air_blocks = default_region.blocks.iter().filter(|&&idx| idx != 0).count();
+        let non_air_blocks = default_region.blocks.iter().filter(|&idx| idx != 0).count();
         let palette_size = default_region.palette.len();
 
         let info_obj = Object::new();